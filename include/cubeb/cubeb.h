@@ -249,11 +249,103 @@ typedef enum {
                                        settings should persist across restarts
                                        of the stream and/or application. This is
                                        obsolete and ignored by all backends. */
-  CUBEB_STREAM_PREF_JACK_NO_AUTO_CONNECT = 0x20 /**< Don't automatically try to
-                                                   connect ports.  Only affects
-                                                   the jack backend. */
+  CUBEB_STREAM_PREF_JACK_NO_AUTO_CONNECT = 0x20, /**< Don't automatically try to
+                                                    connect ports.  Only affects
+                                                    the jack backend. */
+  CUBEB_STREAM_PREF_DRIFT_CORRECTION =
+      0x40 /**< Resample the input of a duplex stream to follow the
+                output clock, for input and output devices that do not
+                share a clock. Should be specified on the input params.
+                Ignored by backends that do not support it. */
 } cubeb_stream_prefs;
 
+/** Optional features of a backend, as reported by cubeb_get_capabilities. */
+typedef enum {
+  CUBEB_CAPABILITY_NONE = 0x00,
+  CUBEB_CAPABILITY_LOOPBACK = 0x01, /**< Loopback streams, see
+                                       CUBEB_STREAM_PREF_LOOPBACK. */
+  CUBEB_CAPABILITY_DEVICE_SWITCHING =
+      0x02, /**< Streams on the default device follow it when it changes. */
+  CUBEB_CAPABILITY_STREAM_VOLUME = 0x04, /**< cubeb_stream_set_volume. */
+  CUBEB_CAPABILITY_INPUT_LATENCY =
+      0x08, /**< cubeb_stream_get_input_latency. */
+  CUBEB_CAPABILITY_DEVICE_CHANGED_CALLBACK =
+      0x10, /**< cubeb_stream_register_device_changed_callback. */
+  CUBEB_CAPABILITY_VOICE_PROCESSING =
+      0x20, /**< CUBEB_STREAM_PREF_VOICE enables platform voice processing,
+                 such as echo cancellation. */
+  CUBEB_CAPABILITY_INPUT_CHANNEL_MAP =
+      0x40, /**< cubeb_stream_options::input_channel_map. */
+  CUBEB_CAPABILITY_MIX_MATRIX = 0x80, /**< cubeb_stream_options::mix_matrix. */
+  CUBEB_CAPABILITY_FORMAT_S16LE = 0x100, /**< CUBEB_SAMPLE_S16LE streams. */
+  CUBEB_CAPABILITY_FORMAT_S16BE = 0x200, /**< CUBEB_SAMPLE_S16BE streams. */
+  CUBEB_CAPABILITY_FORMAT_FLOAT32LE =
+      0x400, /**< CUBEB_SAMPLE_FLOAT32LE streams. */
+  CUBEB_CAPABILITY_FORMAT_FLOAT32BE =
+      0x800 /**< CUBEB_SAMPLE_FLOAT32BE streams. */
+} cubeb_capabilities;
+
+/** A key/value string pair. Used to describe audio devices and to pass
+ *  backend specific hints at stream creation. Keys are free-form; the
+ *  well-known device keys are the
+ *  `CUBEB_DEVICE_PROPERTY_*` macros. */
+typedef struct {
+  char const * key;   /**< Property name. */
+  char const * value; /**< Property value. */
+} cubeb_property;
+
+/** Routes a channel of an input device to a channel of an input stream.
+ *  Stream channels fed by several device channels get the average of their
+ *  contributions, stream channels fed by none are silent. */
+typedef struct {
+  uint32_t device_channel; /**< Index of the channel in the device frames. */
+  uint32_t stream_channel; /**< Index of the channel in the stream frames. */
+  float gain; /**< Linear gain applied to the device channel, 1.0 to keep
+                   it unchanged. */
+} cubeb_channel_route;
+
+/** Gain of a speaker of an output stream in a speaker of its device, for
+ *  custom mixing matrices. */
+typedef struct {
+  cubeb_channel output; /**< Speaker of the output device. */
+  cubeb_channel input;  /**< Speaker of the stream layout. */
+  float gain;           /**< Linear gain. */
+} cubeb_mix_coefficient;
+
+/** How an output stream is mixed to the speakers of its device, when they
+ *  differ from its layout. Speakers missing from the device are folded into
+ *  their neighbours: cubeb's default is
+ *  `{M_SQRT1_2, M_SQRT1_2, 1.0, 0, NULL, 0}`, ITU-R BS.775 downmixing
+ *  `{M_SQRT1_2, M_SQRT1_2, 0.0, 0, NULL, 0}`. */
+typedef struct {
+  float center_mix_level; /**< Gain of the front center channel in the front
+                               left and right speakers. */
+  float surround_mix_level; /**< Gain of the surround channels in the front
+                                 speakers. */
+  float lfe_mix_level; /**< Gain of the LFE channel in the front center
+                            speaker, 3 dB lower in the front left and right
+                            speakers. 0 to drop it on devices without LFE
+                            speaker. */
+  int normalize; /**< Non-zero to scale the matrix for no speaker to clip,
+                      whatever the sample format. Integer formats always
+                      are. */
+  cubeb_mix_coefficient const *
+      coefficients; /**< Optional custom matrix, replacing the levels above.
+                         Pairs of speakers not listed are not mixed. NULL
+                         for a matrix derived from the layouts. */
+  size_t coefficient_count; /**< Number of entries in `coefficients`. */
+} cubeb_mix_matrix;
+
+/** How float samples are quantized when a backend converts them to 16 bits,
+ *  e.g. after mixing them to the speakers of the device. */
+typedef enum {
+  CUBEB_DITHER_NONE,  /**< Round to the nearest value. */
+  CUBEB_DITHER_TPDF,  /**< Add triangular noise of +/-1 LSB before rounding,
+                           so the error is noise rather than distortion. */
+  CUBEB_DITHER_NOISE_SHAPED /**< TPDF dither, with the noise moved towards
+                                 high frequencies. */
+} cubeb_dither;
+
 /** Stream format initialization parameters. */
 typedef struct {
   cubeb_sample_format format; /**< Requested sample format.  One of
@@ -266,6 +358,41 @@ typedef struct {
   cubeb_stream_prefs prefs; /**< Requested preferences. */
 } cubeb_stream_params;
 
+/** Optional stream parameters, passed to `cubeb_stream_init_with_options`.
+ *  Set `size` to `sizeof(cubeb_stream_options)` and zero the fields that are
+ *  not used. Fields are only ever appended: the ones a caller built against
+ *  an older version of this header does not know of are treated as unset. */
+typedef struct {
+  size_t size; /**< Size of the structure, in bytes. */
+  cubeb_property const *
+      properties;        /**< Optional caller supplied properties, may be
+                            NULL. Backends copy what they need during
+                            stream initialization. Keys are backend
+                            specific, e.g. PulseAudio applies them to the
+                            stream proplist. */
+  size_t property_count; /**< Number of entries in `properties`. */
+  cubeb_channel_route const *
+      input_channel_map; /**< Optional channels of the input device to
+                              capture, and how, e.g. inputs 3 and 4 of an
+                              8 channel interface. Only valid for streams
+                              with an input, on backends reporting
+                              CUBEB_CAPABILITY_INPUT_CHANNEL_MAP. NULL for
+                              the backend's default, which keeps the
+                              leading device channels and averages stereo
+                              to mono. */
+  size_t input_channel_map_count; /**< Number of entries in
+                                       `input_channel_map`. */
+  cubeb_mix_matrix const *
+      mix_matrix; /**< Optional mixing of the stream to the speakers of the
+                       output device. Only valid for streams with an
+                       output, on backends reporting
+                       CUBEB_CAPABILITY_MIX_MATRIX. NULL for the backend's
+                       default. */
+  cubeb_dither dither; /**< Dither applied when the backend requantizes
+                            samples to 16 bits. Backends that never do
+                            ignore it. */
+} cubeb_stream_options;
+
 /** Audio device description */
 typedef struct {
   char * output_name; /**< The name of the output device */
@@ -358,6 +485,20 @@ typedef enum {
   CUBEB_DEVICE_PREF_ALL = 0x0F
 } cubeb_device_pref;
 
+/** Form factor of the device, e.g. "headset", "speaker" or "hdmi". */
+#define CUBEB_DEVICE_PROPERTY_FORM_FACTOR "form_factor"
+/** Bus the device is attached to, e.g. "usb", "bluetooth" or "pci". */
+#define CUBEB_DEVICE_PROPERTY_BUS "bus"
+/** Name of an icon suitable to represent the device in a UI. */
+#define CUBEB_DEVICE_PROPERTY_ICON_NAME "icon_name"
+/** Name of the port currently in use on the device. */
+#define CUBEB_DEVICE_PROPERTY_ACTIVE_PORT "active_port"
+/** Human readable description of the port currently in use. */
+#define CUBEB_DEVICE_PROPERTY_ACTIVE_PORT_DESCRIPTION "active_port_description"
+/** Prefix of the keys listing the available ports of the device. The rest of
+ *  the key is the port name, the value is its description. */
+#define CUBEB_DEVICE_PROPERTY_PORT_PREFIX "port."
+
 /** This structure holds the characteristics
  *  of an input or output audio device. It is obtained using
  *  `cubeb_enumerate_devices`, which returns these structures via
@@ -447,6 +588,16 @@ typedef void (*cubeb_device_collection_changed_callback)(cubeb * context,
 /** User supplied callback called when a message needs logging. */
 typedef void (*cubeb_log_callback)(char const * fmt, ...);
 
+/** User supplied callback called with an already formatted message, for
+    callers that can't implement a variadic function.
+    @param level Level the message was logged at.
+    @param file Name of the source file that logged the message, may be NULL.
+    @param line Line in `file`, 0 if unknown.
+    @param message The formatted message, without trailing newline. */
+typedef void (*cubeb_log_message_callback)(cubeb_log_level level,
+                                           char const * file, uint32_t line,
+                                           char const * message);
+
 /** Initialize an application context.  This will perform any library or
     application scoped initialization.
 
@@ -510,6 +661,17 @@ cubeb_get_min_latency(cubeb * context, cubeb_stream_params * params,
 CUBEB_EXPORT int
 cubeb_get_preferred_sample_rate(cubeb * context, uint32_t * rate);
 
+/** Get the optional features supported by this backend, so applications can
+    hide options that would fail.
+    @param context A pointer to the cubeb context.
+    @param capabilities The supported features, a combination of
+                        #cubeb_capabilities flags.
+    @retval CUBEB_OK
+    @retval CUBEB_ERROR_INVALID_PARAMETER
+    @retval CUBEB_ERROR_NOT_SUPPORTED */
+CUBEB_EXPORT int
+cubeb_get_capabilities(cubeb * context, cubeb_capabilities * capabilities);
+
 /** Destroy an application context. This must be called after all stream have
  *  been destroyed.
     @param context A pointer to the cubeb context.*/
@@ -557,6 +719,25 @@ cubeb_stream_init(cubeb * context, cubeb_stream ** stream,
                   uint32_t latency_frames, cubeb_data_callback data_callback,
                   cubeb_state_callback state_callback, void * user_ptr);
 
+/** Initialize a stream with optional parameters, see `cubeb_stream_init`.
+    @param options Optional parameters of the stream, or NULL. They are only
+                   read during the call.
+    @retval CUBEB_OK
+    @retval CUBEB_ERROR
+    @retval CUBEB_ERROR_INVALID_FORMAT
+    @retval CUBEB_ERROR_INVALID_PARAMETER if options is malformed.
+    @retval CUBEB_ERROR_DEVICE_UNAVAILABLE
+    @retval CUBEB_ERROR_NOT_SUPPORTED if options sets parameters and the
+                                      backend does not take any. */
+CUBEB_EXPORT int
+cubeb_stream_init_with_options(
+    cubeb * context, cubeb_stream ** stream, char const * stream_name,
+    cubeb_devid input_device, cubeb_stream_params * input_stream_params,
+    cubeb_devid output_device, cubeb_stream_params * output_stream_params,
+    cubeb_stream_options const * options, uint32_t latency_frames,
+    cubeb_data_callback data_callback, cubeb_state_callback state_callback,
+    void * user_ptr);
+
 /** Destroy a stream. `cubeb_stream_stop` MUST be called before destroying a
     stream.
     @param stream The stream to destroy. */
@@ -687,6 +868,25 @@ CUBEB_EXPORT int
 cubeb_device_collection_destroy(cubeb * context,
                                 cubeb_device_collection * collection);
 
+/** Get the backend specific properties of a device of a collection. The
+    well-known keys are the `CUBEB_DEVICE_PROPERTY_*` macros.
+    @param context
+    @param collection collection returned by cubeb_enumerate_devices
+    @param index index of the device in the collection
+    @param properties output properties, owned by the collection and valid
+                      until it is destroyed. NULL if the device has none.
+    @param count output number of entries in `properties`
+    @retval CUBEB_OK
+    @retval CUBEB_ERROR_INVALID_PARAMETER if index is out of the collection,
+                                          or a pointer is invalid
+    @retval CUBEB_ERROR_NOT_SUPPORTED */
+CUBEB_EXPORT int
+cubeb_device_collection_get_properties(cubeb * context,
+                                       cubeb_device_collection * collection,
+                                       size_t index,
+                                       cubeb_property const ** properties,
+                                       size_t * count);
+
 /** Registers a callback which is called when the system detects
     a new device or a device is removed.
     @param context
@@ -718,6 +918,22 @@ CUBEB_EXPORT int
 cubeb_set_log_callback(cubeb_log_level log_level,
                        cubeb_log_callback log_callback);
 
+/** Set a callback to be called with formatted messages. Only one callback,
+    set with this function or `cubeb_set_log_callback`, can be registered at
+    a time: unregister it with either function before setting another.
+    @param log_level CUBEB_LOG_VERBOSE, CUBEB_LOG_NORMAL.
+    @param log_callback A function called with a message when there is
+                        something to log. Pass NULL to unregister.
+    @retval CUBEB_OK in case of success.
+    @retval CUBEB_ERROR_INVALID_PARAMETER if log_callback is NULL while
+                                          log_level is not
+                                          CUBEB_LOG_DISABLED.
+    @retval CUBEB_ERROR_NOT_SUPPORTED if a callback, of either kind, is
+                                      already set. */
+CUBEB_EXPORT int
+cubeb_set_log_message_callback(cubeb_log_level log_level,
+                               cubeb_log_message_callback log_callback);
+
 #if defined(__cplusplus)
 }
 #endif
@@ -7,11 +7,9 @@
 
 extern crate coreaudio_sys_utils;
 extern crate libc;
-extern crate ringbuf;
 
 mod aggregate_device;
 mod auto_release;
-mod buffer_manager;
 mod device_property;
 mod mixer;
 mod resampler;
@@ -19,7 +17,6 @@ mod utils;
 
 use self::aggregate_device::*;
 use self::auto_release::*;
-use self::buffer_manager::*;
 use self::coreaudio_sys_utils::aggregate_device::*;
 use self::coreaudio_sys_utils::audio_object::*;
 use self::coreaudio_sys_utils::audio_unit::*;
@@ -32,15 +29,22 @@ use self::mixer::*;
 use self::resampler::*;
 use self::utils::*;
 use atomic;
+use cubeb_backend::byte_order::{self, ByteOrderConverter};
+use cubeb_backend::channel_map::ChannelMap;
+use cubeb_backend::duplex::DuplexBuffer;
+use cubeb_backend::latency::{LatencyController, LatencyPolicy};
+use cubeb_backend::rt_check::{self, enter_audio_callback};
+use cubeb_backend::unwind::catch;
 use cubeb_backend::{
-    ffi, Context, ContextOps, DeviceCollectionRef, DeviceId, DeviceRef, DeviceType, Error, Ops,
-    Result, SampleFormat, State, Stream, StreamOps, StreamParams, StreamParamsRef, StreamPrefs,
+    ffi, Capabilities, ChannelRoute, Context, ContextOps, DeviceCollectionRef, DeviceId, DeviceRef,
+    DeviceType, Dither, Error, MixMatrix, Ops, Result, SampleFormat, State, Stream, StreamOps,
+    StreamOptions, StreamOptionsRef, StreamParams, StreamParamsRef, StreamPrefs,
 };
 use mach::mach_time::{mach_absolute_time, mach_timebase_info};
 use std::cmp;
 use std::ffi::{CStr, CString};
 use std::mem;
-use std::os::raw::c_void;
+use std::os::raw::{c_long, c_void};
 use std::ptr;
 use std::slice;
 use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
@@ -182,10 +186,14 @@ fn set_notification_runloop() {
     }
 }
 
-fn clamp_latency(latency_frames: u32) -> u32 {
-    cmp::max(
-        cmp::min(latency_frames, SAFE_MAX_LATENCY_FRAMES),
+// The first stream decides the latency of the others. It is silently clamped down to the
+// platform default, because we synthetize the clock from the callbacks, and we want the clock
+// to update often.
+fn new_latency_controller() -> LatencyController {
+    LatencyController::new(
+        LatencyPolicy::First,
         SAFE_MIN_LATENCY_FRAMES,
+        SAFE_MAX_LATENCY_FRAMES,
     )
 }
 
@@ -310,6 +318,62 @@ fn audiounit_make_silent(io_data: &mut AudioBuffer) {
     }
 }
 
+// Runs `f`, the body of a callback from CoreAudio, which must not unwind
+// into the AudioUnit. A panic stops the stream and puts it in the error
+// state.
+fn catch_callback<F: FnOnce() -> OSStatus>(
+    what: &'static str,
+    user_ptr: *mut c_void,
+    f: F,
+) -> Option<OSStatus> {
+    let status = catch(what, f);
+    if status.is_none() && !user_ptr.is_null() {
+        let stm = unsafe { &mut *(user_ptr as *mut AudioUnitStream) };
+        stm.stopped.store(true, Ordering::SeqCst);
+        stm.core_stream_data.stop_audiounits();
+        stm.notify_state_changed(State::Error);
+    }
+    status
+}
+
+// The data callback the resampler calls for streams not in the native byte
+// order, with the stream as `user_ptr`. A panic is reported to the resampler
+// as an error, which the render callback turns into the error state.
+extern "C" fn byte_order_data_callback(
+    stream: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    input_buffer: *const c_void,
+    output_buffer: *mut c_void,
+    nframes: c_long,
+) -> c_long {
+    catch("byte_order_data_callback", || {
+        convert_byte_order(stream, user_ptr, input_buffer, output_buffer, nframes)
+    })
+    .unwrap_or(ffi::CUBEB_ERROR as c_long)
+}
+
+fn convert_byte_order(
+    stream: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    input_buffer: *const c_void,
+    output_buffer: *mut c_void,
+    nframes: c_long,
+) -> c_long {
+    assert!(!user_ptr.is_null());
+    let stm = unsafe { &mut *(user_ptr as *mut AudioUnitStream) };
+    let converter = stm.core_stream_data.byte_order.as_mut().unwrap();
+    unsafe {
+        converter.data_callback(
+            stm.data_callback,
+            stream,
+            stm.user_ptr,
+            input_buffer,
+            output_buffer,
+            nframes,
+        )
+    }
+}
+
 extern "C" fn audiounit_input_callback(
     user_ptr: *mut c_void,
     flags: *mut AudioUnitRenderActionFlags,
@@ -317,12 +381,26 @@ extern "C" fn audiounit_input_callback(
     bus: u32,
     input_frames: u32,
     _: *mut AudioBufferList,
+) -> OSStatus {
+    catch_callback("audiounit_input_callback", user_ptr, || {
+        read_input(user_ptr, flags, tstamp, bus, input_frames)
+    })
+    .unwrap_or(NO_ERR)
+}
+
+fn read_input(
+    user_ptr: *mut c_void,
+    flags: *mut AudioUnitRenderActionFlags,
+    tstamp: *const AudioTimeStamp,
+    bus: u32,
+    input_frames: u32,
 ) -> OSStatus {
     enum ErrorHandle {
         Return(OSStatus),
         Reinit,
     }
 
+    let _rt = enter_audio_callback("audiounit_input_callback");
     assert!(input_frames > 0);
     assert_eq!(bus, AU_IN_BUS);
 
@@ -397,8 +475,10 @@ extern "C" fn audiounit_input_callback(
             ErrorHandle::Reinit
         } else {
             assert_eq!(status, NO_ERR);
-            input_buffer_manager
-                .push_data(input_buffer_list.mBuffers[0].mData, input_frames as usize);
+            unsafe {
+                input_buffer_manager
+                    .push_raw(input_buffer_list.mBuffers[0].mData, input_frames as usize);
+            }
             ErrorHandle::Return(status)
         };
 
@@ -409,7 +489,7 @@ extern "C" fn audiounit_input_callback(
             input_buffer_list.mBuffers[0].mDataByteSize,
             input_buffer_list.mBuffers[0].mNumberChannels,
             input_frames,
-            input_buffer_manager.available_frames()
+            input_buffer_manager.buffered_frames()
         );
 
         // Full Duplex. We'll call data_callback in the AudioUnit output callback.
@@ -419,14 +499,13 @@ extern "C" fn audiounit_input_callback(
 
         // Input only. Call the user callback through resampler.
         // Resampler will deliver input buffer in the correct rate.
-        assert!(input_frames as usize <= input_buffer_manager.available_frames());
+        assert!(input_frames as usize <= input_buffer_manager.buffered_frames());
         stm.frames_read.fetch_add(
-            input_buffer_manager.available_frames(),
+            input_buffer_manager.buffered_frames(),
             atomic::Ordering::SeqCst,
         );
-        let mut total_input_frames = input_buffer_manager.available_frames() as i64;
-        let input_buffer =
-            input_buffer_manager.get_linear_data(input_buffer_manager.available_frames());
+        let mut total_input_frames = input_buffer_manager.buffered_frames() as i64;
+        let input_buffer = input_buffer_manager.pull_raw(input_buffer_manager.buffered_frames());
         let outframes = stm.core_stream_data.resampler.fill(
             input_buffer,
             &mut total_input_frames,
@@ -515,6 +594,30 @@ extern "C" fn audiounit_output_callback(
     output_frames: u32,
     out_buffer_list: *mut AudioBufferList,
 ) -> OSStatus {
+    catch_callback("audiounit_output_callback", user_ptr, || {
+        write_output(user_ptr, flags, tstamp, bus, output_frames, out_buffer_list)
+    })
+    .unwrap_or_else(|| {
+        // Whatever the callback left in the buffer must not be played.
+        let buffers = unsafe { out_buffer_list.as_mut() };
+        if let Some(buffer) = buffers.filter(|b| b.mNumberBuffers > 0) {
+            if !buffer.mBuffers[0].mData.is_null() {
+                audiounit_make_silent(&mut buffer.mBuffers[0]);
+            }
+        }
+        NO_ERR
+    })
+}
+
+fn write_output(
+    user_ptr: *mut c_void,
+    flags: *mut AudioUnitRenderActionFlags,
+    tstamp: *const AudioTimeStamp,
+    bus: u32,
+    output_frames: u32,
+    out_buffer_list: *mut AudioBufferList,
+) -> OSStatus {
+    let _rt = enter_audio_callback("audiounit_output_callback");
     assert_eq!(bus, AU_OUT_BUS);
     assert!(!out_buffer_list.is_null());
 
@@ -593,7 +696,7 @@ extern "C" fn audiounit_output_callback(
             f64::from(stm.core_stream_data.output_stream_params.rate()),
             output_frames as usize,
         );
-        let buffered_input_frames = input_buffer_manager.available_frames();
+        let buffered_input_frames = input_buffer_manager.buffered_frames();
         // Else if the input has buffered a lot already because the output started late, we
         // need to trim the input buffer
         if prev_frames_written == 0 && buffered_input_frames > input_frames_needed as usize {
@@ -607,7 +710,7 @@ extern "C" fn audiounit_output_callback(
                 || stm.reinit_pending.load(Ordering::SeqCst)
                 || stm.frames_read.load(Ordering::SeqCst) == 0)
         {
-            // The silent frames will be inserted in `get_linear_data` below.
+            // The silent frames will be inserted in `pull_raw` below.
             let silent_frames_to_push = input_frames_needed - buffered_input_frames;
             cubeb_alog!(
                 "({:p}) Missing Frames: {} will append {} frames of input silence.",
@@ -628,7 +731,7 @@ extern "C" fn audiounit_output_callback(
 
         stm.frames_read.fetch_add(input_frames, Ordering::SeqCst);
         (
-            input_buffer_manager.get_linear_data(input_frames),
+            input_buffer_manager.pull_raw(input_frames),
             input_frames as i64,
         )
     } else {
@@ -749,7 +852,7 @@ extern "C" fn audiounit_property_listener_callback(
         return NO_ERR;
     }
     {
-        let callback = stm.device_changed_callback.lock().unwrap();
+        let callback = rt_check::lock(&stm.device_changed_callback);
         if let Some(device_changed_callback) = *callback {
             unsafe {
                 device_changed_callback(stm.user_ptr);
@@ -1776,36 +1879,11 @@ struct SharedDevices {
     output: DevicesData,
 }
 
-#[derive(Debug, Default)]
-struct LatencyController {
-    streams: u32,
-    latency: Option<u32>,
-}
-
-impl LatencyController {
-    fn add_stream(&mut self, latency: u32) -> Option<u32> {
-        self.streams += 1;
-        // For the 1st stream set anything within safe min-max
-        if self.streams == 1 {
-            assert!(self.latency.is_none());
-            // Silently clamp the latency down to the platform default, because we
-            // synthetize the clock from the callbacks, and we want the clock to update often.
-            self.latency = Some(clamp_latency(latency));
-        }
-        self.latency
-    }
-
-    fn subtract_stream(&mut self) -> Option<u32> {
-        self.streams -= 1;
-        if self.streams == 0 {
-            assert!(self.latency.is_some());
-            self.latency = None;
-        }
-        self.latency
-    }
-}
-
-pub const OPS: Ops = capi_new!(AudioUnitContext, AudioUnitStream);
+pub const OPS: Ops = capi_new!(
+    AudioUnitContext,
+    AudioUnitStream,
+    unsupported: [stream_set_name]
+);
 
 // The fisrt member of the Cubeb context must be a pointer to a Ops struct. The Ops struct is an
 // interface to link to all the Cubeb APIs, and the Cubeb interface use this assumption to operate
@@ -1825,24 +1903,24 @@ impl AudioUnitContext {
         Self {
             _ops: &OPS as *const _,
             serial_queue: Queue::new(DISPATCH_QUEUE_LABEL),
-            latency_controller: Mutex::new(LatencyController::default()),
+            latency_controller: Mutex::new(new_latency_controller()),
             devices: Mutex::new(SharedDevices::default()),
         }
     }
 
     fn active_streams(&self) -> u32 {
         let controller = self.latency_controller.lock().unwrap();
-        controller.streams
+        controller.streams()
     }
 
     fn update_latency_by_adding_stream(&self, latency_frames: u32) -> Option<u32> {
         let mut controller = self.latency_controller.lock().unwrap();
-        controller.add_stream(latency_frames)
+        controller.add_stream(latency_frames).latency
     }
 
-    fn update_latency_by_removing_stream(&self) -> Option<u32> {
+    fn update_latency_by_removing_stream(&self, latency_frames: u32) -> Option<u32> {
         let mut controller = self.latency_controller.lock().unwrap();
-        controller.subtract_stream()
+        controller.remove_stream(latency_frames).latency
     }
 
     fn add_devices_changed_listener(
@@ -2025,6 +2103,23 @@ impl ContextOps for AudioUnitContext {
         })?;
         Ok(rate as u32)
     }
+    fn capabilities(&mut self) -> Result<Capabilities> {
+        // Loopback streams and voice processing are not implemented.
+        let capabilities = Capabilities::DEVICE_SWITCHING
+            | Capabilities::STREAM_VOLUME
+            | Capabilities::DEVICE_CHANGED_CALLBACK
+            | Capabilities::INPUT_CHANNEL_MAP
+            | Capabilities::MIX_MATRIX
+            | Capabilities::FORMAT_S16LE
+            | Capabilities::FORMAT_S16BE
+            | Capabilities::FORMAT_FLOAT32LE
+            | Capabilities::FORMAT_FLOAT32BE;
+        if cfg!(target_os = "ios") {
+            Ok(capabilities)
+        } else {
+            Ok(capabilities | Capabilities::INPUT_LATENCY)
+        }
+    }
     fn enumerate_devices(
         &mut self,
         devtype: DeviceType,
@@ -2072,12 +2167,39 @@ impl ContextOps for AudioUnitContext {
         Ok(())
     }
     fn stream_init(
+        &mut self,
+        stream_name: Option<&CStr>,
+        input_device: DeviceId,
+        input_stream_params: Option<&StreamParamsRef>,
+        output_device: DeviceId,
+        output_stream_params: Option<&StreamParamsRef>,
+        latency_frames: u32,
+        data_callback: ffi::cubeb_data_callback,
+        state_callback: ffi::cubeb_state_callback,
+        user_ptr: *mut c_void,
+    ) -> Result<Stream> {
+        self.stream_init_with_options(
+            stream_name,
+            input_device,
+            input_stream_params,
+            output_device,
+            output_stream_params,
+            &StreamOptions::default(),
+            latency_frames,
+            data_callback,
+            state_callback,
+            user_ptr,
+        )
+    }
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+    fn stream_init_with_options(
         &mut self,
         _stream_name: Option<&CStr>,
         input_device: DeviceId,
         input_stream_params: Option<&StreamParamsRef>,
         output_device: DeviceId,
         output_stream_params: Option<&StreamParamsRef>,
+        options: &StreamOptionsRef,
         latency_frames: u32,
         data_callback: ffi::cubeb_data_callback,
         state_callback: ffi::cubeb_state_callback,
@@ -2137,6 +2259,7 @@ impl ContextOps for AudioUnitContext {
             user_ptr,
             data_callback,
             state_callback,
+            latency_frames,
             global_latency_frames,
         ));
 
@@ -2144,8 +2267,12 @@ impl ContextOps for AudioUnitContext {
         let queue_label = format!("{}.{:p}", DISPATCH_QUEUE_LABEL, boxed_stream.as_ref());
         boxed_stream.queue = Queue::new(queue_label.as_str());
 
-        boxed_stream.core_stream_data =
-            CoreStreamData::new(boxed_stream.as_ref(), in_stm_settings, out_stm_settings);
+        boxed_stream.core_stream_data = CoreStreamData::new(
+            boxed_stream.as_ref(),
+            in_stm_settings,
+            out_stm_settings,
+            options,
+        );
 
         if let Err(r) = boxed_stream.core_stream_data.setup() {
             cubeb_log!(
@@ -2185,11 +2312,11 @@ impl Drop for AudioUnitContext {
             let controller = self.latency_controller.lock().unwrap();
             // Disabling this assert for bug 1083664 -- we seem to leak a stream
             // assert(controller.streams == 0);
-            if controller.streams > 0 {
+            if controller.streams() > 0 {
                 cubeb_log!(
                     "({:p}) API misuse, {} streams active when context destroyed!",
                     self as *const AudioUnitContext,
-                    controller.streams
+                    controller.streams()
                 );
             }
         }
@@ -2227,7 +2354,18 @@ struct CoreStreamData<'ctx> {
     // Info of the I/O devices.
     input_device: device_info,
     output_device: device_info,
-    input_buffer_manager: Option<BufferManager>,
+    input_buffer_manager: Option<DuplexBuffer>,
+    // Caller supplied routing of the input device channels, empty for the
+    // default.
+    input_channel_map: Vec<ChannelRoute>,
+    // Caller supplied mixing to the output device speakers, `None` for the
+    // default.
+    mix_matrix: Option<MixMatrix>,
+    // Caller supplied dither of the integer samples the mixer requantizes.
+    dither: Dither,
+    // The stream runs in the native byte order, the user's frames are
+    // converted if its format is not.
+    byte_order: Option<ByteOrderConverter>,
     // Listeners indicating what system events are monitored.
     default_input_listener: Option<device_property_listener>,
     default_output_listener: Option<device_property_listener>,
@@ -2249,6 +2387,7 @@ impl<'ctx> Default for CoreStreamData<'ctx> {
                 channels: 0,
                 layout: ffi::CUBEB_LAYOUT_UNDEFINED,
                 prefs: ffi::CUBEB_STREAM_PREF_NONE,
+                ..Default::default()
             }),
             output_stream_params: StreamParams::from(ffi::cubeb_stream_params {
                 format: ffi::CUBEB_SAMPLE_FLOAT32NE,
@@ -2256,6 +2395,7 @@ impl<'ctx> Default for CoreStreamData<'ctx> {
                 channels: 0,
                 layout: ffi::CUBEB_LAYOUT_UNDEFINED,
                 prefs: ffi::CUBEB_STREAM_PREF_NONE,
+                ..Default::default()
             }),
             input_dev_desc: AudioStreamBasicDescription::default(),
             output_dev_desc: AudioStreamBasicDescription::default(),
@@ -2264,6 +2404,10 @@ impl<'ctx> Default for CoreStreamData<'ctx> {
             input_device: device_info::default(),
             output_device: device_info::default(),
             input_buffer_manager: None,
+            input_channel_map: Vec::new(),
+            mix_matrix: None,
+            dither: Dither::None,
+            byte_order: None,
             default_input_listener: None,
             default_output_listener: None,
             input_alive_listener: None,
@@ -2278,6 +2422,7 @@ impl<'ctx> CoreStreamData<'ctx> {
         stm: &AudioUnitStream<'ctx>,
         input_stream_settings: Option<(StreamParams, device_info)>,
         output_stream_settings: Option<(StreamParams, device_info)>,
+        options: &StreamOptionsRef,
     ) -> Self {
         fn get_default_sttream_params() -> StreamParams {
             StreamParams::from(ffi::cubeb_stream_params {
@@ -2286,19 +2431,29 @@ impl<'ctx> CoreStreamData<'ctx> {
                 channels: 0,
                 layout: ffi::CUBEB_LAYOUT_UNDEFINED,
                 prefs: ffi::CUBEB_STREAM_PREF_NONE,
+                ..Default::default()
             })
         }
+        let byte_order = ByteOrderConverter::with_params(
+            input_stream_settings.as_ref().map(|(p, _)| p.as_ref()),
+            output_stream_settings.as_ref().map(|(p, _)| p.as_ref()),
+            SAFE_MAX_LATENCY_FRAMES as usize,
+        );
         let (in_stm_params, in_dev) =
             input_stream_settings.unwrap_or((get_default_sttream_params(), device_info::default()));
         let (out_stm_params, out_dev) = output_stream_settings
             .unwrap_or((get_default_sttream_params(), device_info::default()));
+        // The options and the parameters only point to the map and the
+        // matrix during the stream initialization.
+        let input_channel_map = options.input_channel_map();
+        let mix_matrix = options.mix_matrix();
         Self {
             stm_ptr: stm,
             aggregate_device: None,
             mixer: None,
             resampler: Resampler::default(),
-            input_stream_params: in_stm_params,
-            output_stream_params: out_stm_params,
+            input_stream_params: byte_order::native_params(&in_stm_params),
+            output_stream_params: byte_order::native_params(&out_stm_params),
             input_dev_desc: AudioStreamBasicDescription::default(),
             output_dev_desc: AudioStreamBasicDescription::default(),
             input_unit: ptr::null_mut(),
@@ -2306,6 +2461,10 @@ impl<'ctx> CoreStreamData<'ctx> {
             input_device: in_dev,
             output_device: out_dev,
             input_buffer_manager: None,
+            input_channel_map,
+            mix_matrix,
+            dither: options.dither(),
+            byte_order,
             default_input_listener: None,
             default_output_listener: None,
             input_alive_listener: None,
@@ -2484,7 +2643,9 @@ impl<'ctx> CoreStreamData<'ctx> {
 
             let device_channel_count =
                 get_device_channel_count(self.input_device.id, DeviceType::INPUT).unwrap_or(0);
-            if device_channel_count < self.input_stream_params.channels() {
+            if self.input_channel_map.is_empty()
+                && device_channel_count < self.input_stream_params.channels()
+            {
                 return Err(Error::invalid_parameter());
             }
 
@@ -2602,12 +2763,39 @@ impl<'ctx> CoreStreamData<'ctx> {
             // ignore some data captured by the audio input of the requested output device (e.g.,
             // the requested output device is a USB headset with built-in mic), in the beginning of
             // the raw data taken from input callback.
-            self.input_buffer_manager = Some(BufferManager::new(
+            // The caller supplied channel map indexes the channels of the requested input
+            // device, so it is offset the same way.
+            let ignored_channels =
+                (self.input_dev_desc.mChannelsPerFrame - device_channel_count) as usize;
+            let channels = self.input_stream_params.channels() as usize;
+            let map = if self.input_channel_map.is_empty() {
+                ChannelMap::leading(
+                    self.input_dev_desc.mChannelsPerFrame as usize,
+                    ignored_channels,
+                    channels,
+                )
+            } else {
+                ChannelMap::new(
+                    device_channel_count as usize,
+                    channels,
+                    &self.input_channel_map,
+                )
+                .map_err(|e| {
+                    cubeb_log!(
+                        "({:p}) Invalid input channel map for {} device channels.",
+                        self.stm_ptr,
+                        device_channel_count
+                    );
+                    e
+                })?
+                .offset(ignored_channels)
+            };
+            // 8 times the expected callback size, to handle the input callback being called
+            // multiple times in a row correctly.
+            self.input_buffer_manager = Some(DuplexBuffer::with_channel_map(
                 self.input_stream_params.format(),
-                SAFE_MAX_LATENCY_FRAMES as usize,
-                self.input_dev_desc.mChannelsPerFrame as usize,
-                (self.input_dev_desc.mChannelsPerFrame - device_channel_count) as usize,
-                self.input_stream_params.channels() as usize,
+                SAFE_MAX_LATENCY_FRAMES as usize * 8,
+                map,
             ));
 
             let aurcbs_in = AURenderCallbackStruct {
@@ -2720,9 +2908,15 @@ impl<'ctx> CoreStreamData<'ctx> {
             // 1. using aggregate device whose input device has output channels
             // 2. output device has more channels than we need
             // 3. output device has different layout than the one we have
+            // 4. the caller gave its own matrix
+            let custom_matrix = self
+                .mix_matrix
+                .as_ref()
+                .map_or(false, |m| m.coefficients.is_some());
             self.mixer = if self.output_dev_desc.mChannelsPerFrame
                 != self.output_stream_params.channels()
-                || device_layout != mixer::get_channel_order(self.output_stream_params.layout())
+                || device_layout != mixer::channel_order(self.output_stream_params.layout())
+                || custom_matrix
             {
                 cubeb_log!("Incompatible channel layouts detected, setting up remixer");
                 // We will be remixing the data before it reaches the output device.
@@ -2732,6 +2926,8 @@ impl<'ctx> CoreStreamData<'ctx> {
                     self.output_stream_params.layout(),
                     self.output_dev_desc.mChannelsPerFrame as usize,
                     device_layout,
+                    &self.mix_matrix.clone().unwrap_or_default(),
+                    self.dither,
                 ))
             } else {
                 None
@@ -2845,13 +3041,23 @@ impl<'ctx> CoreStreamData<'ctx> {
             ffi::CUBEB_RESAMPLER_RECLOCK_NONE
         };
 
+        // Frames not in the native byte order are converted around the user
+        // callback.
+        let (data_callback, user_ptr) = if self.byte_order.is_some() {
+            (
+                Some(byte_order_data_callback as _),
+                self.stm_ptr as *mut c_void,
+            )
+        } else {
+            (stream.data_callback, stream.user_ptr)
+        };
         self.resampler = Resampler::new(
             self.stm_ptr as *mut ffi::cubeb_stream,
             resampler_input_params,
             resampler_output_params,
             target_sample_rate,
-            stream.data_callback,
-            stream.user_ptr,
+            data_callback,
+            user_ptr,
             ffi::CUBEB_RESAMPLER_QUALITY_DESKTOP,
             reclock_policy,
         );
@@ -3218,6 +3424,8 @@ struct AudioUnitStream<'ctx> {
     reinit_pending: AtomicBool,
     destroy_pending: AtomicBool,
     // Latency requested by the user.
+    requested_latency_frames: u32,
+    // Latency shared with the other streams of the context.
     latency_frames: u32,
     // Fixed latency, characteristic of the device.
     output_device_latency_frames: AtomicU32,
@@ -3239,6 +3447,7 @@ impl<'ctx> AudioUnitStream<'ctx> {
         user_ptr: *mut c_void,
         data_callback: ffi::cubeb_data_callback,
         state_callback: ffi::cubeb_state_callback,
+        requested_latency_frames: u32,
         latency_frames: u32,
     ) -> Self {
         let output_callback_timing_data =
@@ -3263,6 +3472,7 @@ impl<'ctx> AudioUnitStream<'ctx> {
             draining: AtomicBool::new(false),
             reinit_pending: AtomicBool::new(false),
             destroy_pending: AtomicBool::new(false),
+            requested_latency_frames,
             latency_frames,
             output_device_latency_frames: AtomicU32::new(0),
             input_device_latency_frames: AtomicU32::new(0),
@@ -3449,7 +3659,8 @@ impl<'ctx> AudioUnitStream<'ctx> {
     fn destroy_internal(&mut self) {
         self.core_stream_data.close();
         assert!(self.context.active_streams() >= 1);
-        self.context.update_latency_by_removing_stream();
+        self.context
+            .update_latency_by_removing_stream(self.requested_latency_frames);
     }
 
     fn destroy(&mut self) {
@@ -3629,9 +3840,6 @@ impl<'ctx> StreamOps for AudioUnitStream<'ctx> {
         );
         Ok(())
     }
-    fn set_name(&mut self, _: &CStr) -> Result<()> {
-        Err(Error::not_supported())
-    }
     #[cfg(target_os = "ios")]
     fn current_device(&mut self) -> Result<&DeviceRef> {
         Err(not_supported())
@@ -3705,7 +3913,7 @@ impl<'ctx> StreamOps for AudioUnitStream<'ctx> {
         &mut self,
         device_changed_callback: ffi::cubeb_device_changed_callback,
     ) -> Result<()> {
-        let mut callback = self.device_changed_callback.lock().unwrap();
+        let mut callback = rt_check::lock(&self.device_changed_callback);
         // Note: second register without unregister first causes 'nope' error.
         // Current implementation requires unregister before register a new cb.
         if device_changed_callback.is_some() && callback.is_some() {
@@ -1,11 +1,22 @@
+// The resampler of libcubeb, unless `native-resampler` selects the one of
+// cubeb-backend.
+
+#[cfg(not(feature = "native-resampler"))]
 use super::auto_release::*;
 use cubeb_backend::ffi;
+#[cfg(feature = "native-resampler")]
+use cubeb_backend::resampler::RawResampler;
+#[cfg(feature = "native-resampler")]
+use cubeb_backend::StreamParams;
 use std::os::raw::{c_long, c_uint, c_void};
+#[cfg(not(feature = "native-resampler"))]
 use std::ptr;
 
+#[cfg(not(feature = "native-resampler"))]
 #[derive(Debug)]
 pub struct Resampler(AutoRelease<ffi::cubeb_resampler>);
 
+#[cfg(not(feature = "native-resampler"))]
 impl Resampler {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
@@ -70,12 +81,14 @@ impl Resampler {
     }
 }
 
+#[cfg(not(feature = "native-resampler"))]
 impl Drop for Resampler {
     fn drop(&mut self) {
         self.destroy();
     }
 }
 
+#[cfg(not(feature = "native-resampler"))]
 impl Default for Resampler {
     fn default() -> Self {
         Self(AutoRelease::new(
@@ -84,3 +97,56 @@ impl Default for Resampler {
         ))
     }
 }
+
+#[cfg(feature = "native-resampler")]
+#[derive(Debug, Default)]
+pub struct Resampler(Option<RawResampler>);
+
+#[cfg(feature = "native-resampler")]
+impl Resampler {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        stream: *mut ffi::cubeb_stream,
+        input_params: Option<ffi::cubeb_stream_params>,
+        output_params: Option<ffi::cubeb_stream_params>,
+        target_rate: c_uint,
+        data_callback: ffi::cubeb_data_callback,
+        user_ptr: *mut c_void,
+        quality: ffi::cubeb_resampler_quality,
+        _reclock: ffi::cubeb_resampler_reclock,
+    ) -> Self {
+        let input_params = input_params.map(StreamParams::from);
+        let output_params = output_params.map(StreamParams::from);
+        Self(Some(RawResampler::new(
+            stream,
+            input_params.as_ref().map(|p| p.as_ref()),
+            output_params.as_ref().map(|p| p.as_ref()),
+            target_rate,
+            data_callback,
+            user_ptr,
+            quality.into(),
+        )))
+    }
+
+    pub fn fill(
+        &mut self,
+        input_buffer: *mut c_void,
+        input_frame_count: *mut c_long,
+        output_buffer: *mut c_void,
+        output_frames_needed: c_long,
+    ) -> c_long {
+        let resampler = self.0.as_mut().expect("No resampler");
+        unsafe {
+            resampler.fill(
+                input_buffer,
+                input_frame_count,
+                output_buffer,
+                output_frames_needed,
+            )
+        }
+    }
+
+    pub fn destroy(&mut self) {
+        self.0 = None;
+    }
+}
@@ -62,10 +62,10 @@ fn test_increase_and_decrease_context_streams() {
     }
 
     let mut join_handles = vec![];
-    for _ in 0..STREAMS {
+    for i in 0..STREAMS {
         join_handles.push(thread::spawn(move || {
             let context = unsafe { &*(context_ptr_value as *const AudioUnitContext) };
-            context.update_latency_by_removing_stream();
+            context.update_latency_by_removing_stream(i);
         }));
     }
     for handle in join_handles {
@@ -78,12 +78,12 @@ fn test_increase_and_decrease_context_streams() {
 
 fn check_streams(context: &AudioUnitContext, number: u32) {
     let guard = context.latency_controller.lock().unwrap();
-    assert_eq!(guard.streams, number);
+    assert_eq!(guard.streams(), number);
 }
 
 fn check_latency(context: &AudioUnitContext, latency: Option<u32>) {
     let guard = context.latency_controller.lock().unwrap();
-    assert_eq!(guard.latency, latency);
+    assert_eq!(guard.latency(), latency);
 }
 
 // make_silent
@@ -885,7 +885,7 @@ fn test_create_audiounit_with_unknown_scope() {
     let _unit = create_audiounit(&device);
 }
 
-// clamp_latency
+// new_latency_controller
 // ------------------------------------
 #[test]
 fn test_clamp_latency() {
@@ -893,7 +893,8 @@ fn test_clamp_latency() {
     assert!(range.start < SAFE_MIN_LATENCY_FRAMES);
     // assert!(range.end < SAFE_MAX_LATENCY_FRAMES);
     for latency_frames in range {
-        let clamp = clamp_latency(latency_frames);
+        let mut controller = new_latency_controller();
+        let clamp = controller.add_stream(latency_frames).latency.unwrap();
         assert!(clamp >= SAFE_MIN_LATENCY_FRAMES);
         assert!(clamp <= SAFE_MAX_LATENCY_FRAMES);
     }
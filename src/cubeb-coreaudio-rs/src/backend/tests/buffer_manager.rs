@@ -0,0 +1,21 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+use super::*;
+use cubeb_backend::duplex::assert_rt_safe;
+
+// The buffer manager is used from the audio callbacks, so it must not
+// allocate once created.
+#[test]
+fn test_buffer_manager_rt_safe() {
+    for &(format, input_channels, ignored, output_channels) in &[
+        (SampleFormat::S16NE, 2, 0, 2),
+        (SampleFormat::Float32NE, 2, 0, 2),
+        (SampleFormat::Float32NE, 2, 0, 1),
+        (SampleFormat::Float32NE, 4, 1, 2),
+    ] {
+        let manager = DuplexBuffer::new(format, 256 * 8, input_channels, ignored, output_channels);
+        assert_rt_safe(manager, 256 * 8);
+    }
+}
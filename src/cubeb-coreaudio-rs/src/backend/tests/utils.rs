@@ -1149,9 +1149,10 @@ fn test_get_raw_stream<F>(
         user_ptr,
         data_callback,
         state_callback,
+        latency_frames,
         global_latency_frames.unwrap(),
     );
-    stream.core_stream_data = CoreStreamData::new(&stream, None, None);
+    stream.core_stream_data = CoreStreamData::new(&stream, None, None, &StreamOptions::default());
 
     operation(&mut stream);
 }
@@ -17,3 +17,14 @@ pub unsafe extern "C" fn audiounit_rust_init(
 ) -> c_int {
     capi::capi_init::<AudioUnitContext>(c, context_name)
 }
+
+/// Entry point from C code, keeping the logger of the backend in sync with
+/// `cubeb_set_log_callback` and `cubeb_set_log_message_callback`.
+#[no_mangle]
+pub extern "C" fn audiounit_rust_set_logging(
+    level: ffi::cubeb_log_level,
+    log_callback: ffi::cubeb_log_callback,
+    message_callback: ffi::cubeb_log_message_callback,
+) {
+    capi::capi_set_logging(level, log_callback, message_callback);
+}
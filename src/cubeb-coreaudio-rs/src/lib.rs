@@ -18,3 +18,8 @@ mod backend;
 mod capi;
 
 pub use crate::capi::audiounit_rust_init;
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOC: cubeb_backend::rt_check::RtCheckAllocator =
+    cubeb_backend::rt_check::RtCheckAllocator::new(std::alloc::System);
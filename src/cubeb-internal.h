@@ -69,6 +69,19 @@ struct cubeb_ops {
   int (*register_device_collection_changed)(
       cubeb * context, cubeb_device_type devtype,
       cubeb_device_collection_changed_callback callback, void * user_ptr);
+  int (*get_capabilities)(cubeb * context, cubeb_capabilities * capabilities);
+  int (*device_collection_get_properties)(cubeb * context,
+                                          cubeb_device_collection * collection,
+                                          size_t index,
+                                          cubeb_property const ** properties,
+                                          size_t * count);
+  int (*stream_init_with_options)(
+      cubeb * context, cubeb_stream ** stream, char const * stream_name,
+      cubeb_devid input_device, cubeb_stream_params * input_stream_params,
+      cubeb_devid output_device, cubeb_stream_params * output_stream_params,
+      cubeb_stream_options const * options, unsigned int latency,
+      cubeb_data_callback data_callback, cubeb_state_callback state_callback,
+      void * user_ptr);
 };
 
 #endif /* CUBEB_INTERNAL_0eb56756_4e20_4404_a76d_42bf88cd15a5 */
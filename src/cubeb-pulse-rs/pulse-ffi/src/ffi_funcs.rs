@@ -36,6 +36,11 @@ mod static_fns {
             cb: pa_context_notify_cb_t,
             userdata: *mut c_void,
         ) -> *mut pa_operation;
+        pub fn pa_context_get_card_info_list(
+            c: *mut pa_context,
+            cb: pa_card_info_cb_t,
+            userdata: *mut c_void,
+        ) -> *mut pa_operation;
         pub fn pa_context_get_server_info(
             c: *const pa_context,
             cb: pa_server_info_cb_t,
@@ -64,6 +69,13 @@ mod static_fns {
             userdata: *mut c_void,
         ) -> *mut pa_operation;
         pub fn pa_context_get_state(c: *const pa_context) -> pa_context_state_t;
+        pub fn pa_context_move_sink_input_by_name(
+            c: *mut pa_context,
+            idx: u32,
+            sink_name: *const c_char,
+            cb: pa_context_success_cb_t,
+            userdata: *mut c_void,
+        ) -> *mut pa_operation;
         pub fn pa_context_new(
             mainloop: *mut pa_mainloop_api,
             name: *const c_char,
@@ -74,6 +86,13 @@ mod static_fns {
             cb: pa_time_event_cb_t,
             userdata: *mut c_void,
         ) -> *mut pa_time_event;
+        pub fn pa_context_set_card_profile_by_index(
+            c: *mut pa_context,
+            idx: u32,
+            profile: *const c_char,
+            cb: pa_context_success_cb_t,
+            userdata: *mut c_void,
+        ) -> *mut pa_operation;
         pub fn pa_context_set_sink_input_volume(
             c: *mut pa_context,
             idx: u32,
@@ -81,6 +100,48 @@ mod static_fns {
             cb: pa_context_success_cb_t,
             userdata: *mut c_void,
         ) -> *mut pa_operation;
+        pub fn pa_context_set_sink_mute_by_name(
+            c: *mut pa_context,
+            name: *const c_char,
+            mute: c_int,
+            cb: pa_context_success_cb_t,
+            userdata: *mut c_void,
+        ) -> *mut pa_operation;
+        pub fn pa_context_set_sink_port_by_name(
+            c: *mut pa_context,
+            name: *const c_char,
+            port: *const c_char,
+            cb: pa_context_success_cb_t,
+            userdata: *mut c_void,
+        ) -> *mut pa_operation;
+        pub fn pa_context_set_source_mute_by_name(
+            c: *mut pa_context,
+            name: *const c_char,
+            mute: c_int,
+            cb: pa_context_success_cb_t,
+            userdata: *mut c_void,
+        ) -> *mut pa_operation;
+        pub fn pa_context_set_source_output_mute(
+            c: *mut pa_context,
+            idx: u32,
+            mute: c_int,
+            cb: pa_context_success_cb_t,
+            userdata: *mut c_void,
+        ) -> *mut pa_operation;
+        pub fn pa_context_set_source_output_volume(
+            c: *mut pa_context,
+            idx: u32,
+            volume: *const pa_cvolume,
+            cb: pa_context_success_cb_t,
+            userdata: *mut c_void,
+        ) -> *mut pa_operation;
+        pub fn pa_context_set_source_port_by_name(
+            c: *mut pa_context,
+            name: *const c_char,
+            port: *const c_char,
+            cb: pa_context_success_cb_t,
+            userdata: *mut c_void,
+        ) -> *mut pa_operation;
         pub fn pa_context_set_state_callback(
             c: *mut pa_context,
             cb: pa_context_notify_cb_t,
@@ -128,6 +189,13 @@ mod static_fns {
             userdata: *mut c_void,
         );
         pub fn pa_proplist_gets(p: *mut pa_proplist, key: *const c_char) -> *const c_char;
+        pub fn pa_proplist_new() -> *mut pa_proplist;
+        pub fn pa_proplist_free(p: *mut pa_proplist);
+        pub fn pa_proplist_sets(
+            p: *mut pa_proplist,
+            key: *const c_char,
+            value: *const c_char,
+        ) -> c_int;
         pub fn pa_rtclock_now() -> pa_usec_t;
         pub fn pa_stream_begin_write(
             p: *mut pa_stream,
@@ -178,6 +246,13 @@ mod static_fns {
             ss: *const pa_sample_spec,
             map: *const pa_channel_map,
         ) -> *mut pa_stream;
+        pub fn pa_stream_new_with_proplist(
+            c: *mut pa_context,
+            name: *const c_char,
+            ss: *const pa_sample_spec,
+            map: *const pa_channel_map,
+            p: *mut pa_proplist,
+        ) -> *mut pa_stream;
         pub fn pa_stream_peek(
             p: *mut pa_stream,
             data: *mut *const c_void,
@@ -189,6 +264,26 @@ mod static_fns {
             cb: pa_stream_notify_cb_t,
             userdata: *mut c_void,
         );
+        pub fn pa_stream_set_moved_callback(
+            s: *mut pa_stream,
+            cb: pa_stream_notify_cb_t,
+            userdata: *mut c_void,
+        );
+        pub fn pa_stream_set_overflow_callback(
+            s: *mut pa_stream,
+            cb: pa_stream_notify_cb_t,
+            userdata: *mut c_void,
+        );
+        pub fn pa_stream_set_suspended_callback(
+            s: *mut pa_stream,
+            cb: pa_stream_notify_cb_t,
+            userdata: *mut c_void,
+        );
+        pub fn pa_stream_set_underflow_callback(
+            s: *mut pa_stream,
+            cb: pa_stream_notify_cb_t,
+            userdata: *mut c_void,
+        );
         pub fn pa_stream_set_write_callback(
             p: *mut pa_stream,
             cb: pa_stream_request_cb_t,
@@ -221,6 +316,19 @@ mod static_fns {
             cb: pa_stream_success_cb_t,
             userdata: *mut c_void,
         ) -> *mut pa_operation;
+        pub fn pa_stream_proplist_update(
+            s: *mut pa_stream,
+            mode: pa_update_mode_t,
+            p: *mut pa_proplist,
+            cb: pa_stream_success_cb_t,
+            userdata: *mut c_void,
+        ) -> *mut pa_operation;
+        pub fn pa_stream_set_buffer_attr(
+            s: *mut pa_stream,
+            attr: *const pa_buffer_attr,
+            cb: pa_stream_success_cb_t,
+            userdata: *mut c_void,
+        ) -> *mut pa_operation;
         pub fn pa_sw_volume_from_linear(v: c_double) -> pa_volume_t;
         pub fn pa_threaded_mainloop_free(m: *mut pa_threaded_mainloop);
         pub fn pa_threaded_mainloop_get_api(m: *mut pa_threaded_mainloop) -> *mut pa_mainloop_api;
@@ -307,6 +415,13 @@ mod dynamic_fns {
                 }
                 fp
             };
+            PA_CONTEXT_GET_CARD_INFO_LIST = {
+                let fp = dlsym(h, cstr!("pa_context_get_card_info_list"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
             PA_CONTEXT_GET_SERVER_INFO = {
                 let fp = dlsym(h, cstr!("pa_context_get_server_info"));
                 if fp.is_null() {
@@ -349,6 +464,13 @@ mod dynamic_fns {
                 }
                 fp
             };
+            PA_CONTEXT_MOVE_SINK_INPUT_BY_NAME = {
+                let fp = dlsym(h, cstr!("pa_context_move_sink_input_by_name"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
             PA_CONTEXT_NEW = {
                 let fp = dlsym(h, cstr!("pa_context_new"));
                 if fp.is_null() {
@@ -363,6 +485,13 @@ mod dynamic_fns {
                 }
                 fp
             };
+            PA_CONTEXT_SET_CARD_PROFILE_BY_INDEX = {
+                let fp = dlsym(h, cstr!("pa_context_set_card_profile_by_index"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
             PA_CONTEXT_SET_SINK_INPUT_VOLUME = {
                 let fp = dlsym(h, cstr!("pa_context_set_sink_input_volume"));
                 if fp.is_null() {
@@ -370,6 +499,48 @@ mod dynamic_fns {
                 }
                 fp
             };
+            PA_CONTEXT_SET_SINK_MUTE_BY_NAME = {
+                let fp = dlsym(h, cstr!("pa_context_set_sink_mute_by_name"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
+            PA_CONTEXT_SET_SINK_PORT_BY_NAME = {
+                let fp = dlsym(h, cstr!("pa_context_set_sink_port_by_name"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
+            PA_CONTEXT_SET_SOURCE_MUTE_BY_NAME = {
+                let fp = dlsym(h, cstr!("pa_context_set_source_mute_by_name"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
+            PA_CONTEXT_SET_SOURCE_OUTPUT_MUTE = {
+                let fp = dlsym(h, cstr!("pa_context_set_source_output_mute"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
+            PA_CONTEXT_SET_SOURCE_OUTPUT_VOLUME = {
+                let fp = dlsym(h, cstr!("pa_context_set_source_output_volume"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
+            PA_CONTEXT_SET_SOURCE_PORT_BY_NAME = {
+                let fp = dlsym(h, cstr!("pa_context_set_source_port_by_name"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
             PA_CONTEXT_SET_STATE_CALLBACK = {
                 let fp = dlsym(h, cstr!("pa_context_set_state_callback"));
                 if fp.is_null() {
@@ -496,6 +667,27 @@ mod dynamic_fns {
                 }
                 fp
             };
+            PA_PROPLIST_NEW = {
+                let fp = dlsym(h, cstr!("pa_proplist_new"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
+            PA_PROPLIST_FREE = {
+                let fp = dlsym(h, cstr!("pa_proplist_free"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
+            PA_PROPLIST_SETS = {
+                let fp = dlsym(h, cstr!("pa_proplist_sets"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
             PA_RTCLOCK_NOW = {
                 let fp = dlsym(h, cstr!("pa_rtclock_now"));
                 if fp.is_null() {
@@ -636,6 +828,13 @@ mod dynamic_fns {
                 }
                 fp
             };
+            PA_STREAM_NEW_WITH_PROPLIST = {
+                let fp = dlsym(h, cstr!("pa_stream_new_with_proplist"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
             PA_STREAM_PEEK = {
                 let fp = dlsym(h, cstr!("pa_stream_peek"));
                 if fp.is_null() {
@@ -657,6 +856,34 @@ mod dynamic_fns {
                 }
                 fp
             };
+            PA_STREAM_SET_MOVED_CALLBACK = {
+                let fp = dlsym(h, cstr!("pa_stream_set_moved_callback"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
+            PA_STREAM_SET_OVERFLOW_CALLBACK = {
+                let fp = dlsym(h, cstr!("pa_stream_set_overflow_callback"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
+            PA_STREAM_SET_SUSPENDED_CALLBACK = {
+                let fp = dlsym(h, cstr!("pa_stream_set_suspended_callback"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
+            PA_STREAM_SET_UNDERFLOW_CALLBACK = {
+                let fp = dlsym(h, cstr!("pa_stream_set_underflow_callback"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
             PA_STREAM_SET_WRITE_CALLBACK = {
                 let fp = dlsym(h, cstr!("pa_stream_set_write_callback"));
                 if fp.is_null() {
@@ -713,6 +940,20 @@ mod dynamic_fns {
                 }
                 fp
             };
+            PA_STREAM_PROPLIST_UPDATE = {
+                let fp = dlsym(h, cstr!("pa_stream_proplist_update"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
+            PA_STREAM_SET_BUFFER_ATTR = {
+                let fp = dlsym(h, cstr!("pa_stream_set_buffer_attr"));
+                if fp.is_null() {
+                    return None;
+                }
+                fp
+            };
             PA_SW_VOLUME_FROM_LINEAR = {
                 let fp = dlsym(h, cstr!("pa_sw_volume_from_linear"));
                 if fp.is_null() {
@@ -895,6 +1136,19 @@ mod dynamic_fns {
         >(PA_CONTEXT_DRAIN))(c, cb, userdata)
     }
 
+    static mut PA_CONTEXT_GET_CARD_INFO_LIST: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_context_get_card_info_list(
+        c: *mut pa_context,
+        cb: pa_card_info_cb_t,
+        userdata: *mut c_void,
+    ) -> *mut pa_operation {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(*mut pa_context, pa_card_info_cb_t, *mut c_void) -> *mut pa_operation,
+        >(PA_CONTEXT_GET_CARD_INFO_LIST))(c, cb, userdata)
+    }
+
     static mut PA_CONTEXT_GET_SERVER_INFO: *mut ::libc::c_void = 0 as *mut _;
     #[inline]
     pub unsafe fn pa_context_get_server_info(
@@ -980,6 +1234,27 @@ mod dynamic_fns {
         ))(c)
     }
 
+    static mut PA_CONTEXT_MOVE_SINK_INPUT_BY_NAME: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_context_move_sink_input_by_name(
+        c: *mut pa_context,
+        idx: u32,
+        sink_name: *const c_char,
+        cb: pa_context_success_cb_t,
+        userdata: *mut c_void,
+    ) -> *mut pa_operation {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(
+                *mut pa_context,
+                u32,
+                *const c_char,
+                pa_context_success_cb_t,
+                *mut c_void,
+            ) -> *mut pa_operation,
+        >(PA_CONTEXT_MOVE_SINK_INPUT_BY_NAME))(c, idx, sink_name, cb, userdata)
+    }
+
     static mut PA_CONTEXT_NEW: *mut ::libc::c_void = 0 as *mut _;
     #[inline]
     pub unsafe fn pa_context_new(
@@ -1011,6 +1286,27 @@ mod dynamic_fns {
         >(PA_CONTEXT_RTTIME_NEW))(c, usec, cb, userdata)
     }
 
+    static mut PA_CONTEXT_SET_CARD_PROFILE_BY_INDEX: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_context_set_card_profile_by_index(
+        c: *mut pa_context,
+        idx: u32,
+        profile: *const c_char,
+        cb: pa_context_success_cb_t,
+        userdata: *mut c_void,
+    ) -> *mut pa_operation {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(
+                *mut pa_context,
+                u32,
+                *const c_char,
+                pa_context_success_cb_t,
+                *mut c_void,
+            ) -> *mut pa_operation,
+        >(PA_CONTEXT_SET_CARD_PROFILE_BY_INDEX))(c, idx, profile, cb, userdata)
+    }
+
     static mut PA_CONTEXT_SET_SINK_INPUT_VOLUME: *mut ::libc::c_void = 0 as *mut _;
     #[inline]
     pub unsafe fn pa_context_set_sink_input_volume(
@@ -1032,6 +1328,132 @@ mod dynamic_fns {
         >(PA_CONTEXT_SET_SINK_INPUT_VOLUME))(c, idx, volume, cb, userdata)
     }
 
+    static mut PA_CONTEXT_SET_SINK_MUTE_BY_NAME: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_context_set_sink_mute_by_name(
+        c: *mut pa_context,
+        name: *const c_char,
+        mute: c_int,
+        cb: pa_context_success_cb_t,
+        userdata: *mut c_void,
+    ) -> *mut pa_operation {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(
+                *mut pa_context,
+                *const c_char,
+                c_int,
+                pa_context_success_cb_t,
+                *mut c_void,
+            ) -> *mut pa_operation,
+        >(PA_CONTEXT_SET_SINK_MUTE_BY_NAME))(c, name, mute, cb, userdata)
+    }
+
+    static mut PA_CONTEXT_SET_SINK_PORT_BY_NAME: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_context_set_sink_port_by_name(
+        c: *mut pa_context,
+        name: *const c_char,
+        port: *const c_char,
+        cb: pa_context_success_cb_t,
+        userdata: *mut c_void,
+    ) -> *mut pa_operation {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(
+                *mut pa_context,
+                *const c_char,
+                *const c_char,
+                pa_context_success_cb_t,
+                *mut c_void,
+            ) -> *mut pa_operation,
+        >(PA_CONTEXT_SET_SINK_PORT_BY_NAME))(c, name, port, cb, userdata)
+    }
+
+    static mut PA_CONTEXT_SET_SOURCE_MUTE_BY_NAME: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_context_set_source_mute_by_name(
+        c: *mut pa_context,
+        name: *const c_char,
+        mute: c_int,
+        cb: pa_context_success_cb_t,
+        userdata: *mut c_void,
+    ) -> *mut pa_operation {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(
+                *mut pa_context,
+                *const c_char,
+                c_int,
+                pa_context_success_cb_t,
+                *mut c_void,
+            ) -> *mut pa_operation,
+        >(PA_CONTEXT_SET_SOURCE_MUTE_BY_NAME))(c, name, mute, cb, userdata)
+    }
+
+    static mut PA_CONTEXT_SET_SOURCE_OUTPUT_MUTE: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_context_set_source_output_mute(
+        c: *mut pa_context,
+        idx: u32,
+        mute: c_int,
+        cb: pa_context_success_cb_t,
+        userdata: *mut c_void,
+    ) -> *mut pa_operation {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(
+                *mut pa_context,
+                u32,
+                c_int,
+                pa_context_success_cb_t,
+                *mut c_void,
+            ) -> *mut pa_operation,
+        >(PA_CONTEXT_SET_SOURCE_OUTPUT_MUTE))(c, idx, mute, cb, userdata)
+    }
+
+    static mut PA_CONTEXT_SET_SOURCE_OUTPUT_VOLUME: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_context_set_source_output_volume(
+        c: *mut pa_context,
+        idx: u32,
+        volume: *const pa_cvolume,
+        cb: pa_context_success_cb_t,
+        userdata: *mut c_void,
+    ) -> *mut pa_operation {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(
+                *mut pa_context,
+                u32,
+                *const pa_cvolume,
+                pa_context_success_cb_t,
+                *mut c_void,
+            ) -> *mut pa_operation,
+        >(PA_CONTEXT_SET_SOURCE_OUTPUT_VOLUME))(c, idx, volume, cb, userdata)
+    }
+
+    static mut PA_CONTEXT_SET_SOURCE_PORT_BY_NAME: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_context_set_source_port_by_name(
+        c: *mut pa_context,
+        name: *const c_char,
+        port: *const c_char,
+        cb: pa_context_success_cb_t,
+        userdata: *mut c_void,
+    ) -> *mut pa_operation {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(
+                *mut pa_context,
+                *const c_char,
+                *const c_char,
+                pa_context_success_cb_t,
+                *mut c_void,
+            ) -> *mut pa_operation,
+        >(PA_CONTEXT_SET_SOURCE_PORT_BY_NAME))(c, name, port, cb, userdata)
+    }
+
     static mut PA_CONTEXT_SET_STATE_CALLBACK: *mut ::libc::c_void = 0 as *mut _;
     #[inline]
     pub unsafe fn pa_context_set_state_callback(
@@ -1209,6 +1631,31 @@ mod dynamic_fns {
         ))(p, key)
     }
 
+    static mut PA_PROPLIST_NEW: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_proplist_new() -> *mut pa_proplist {
+        (::std::mem::transmute::<_, extern "C" fn() -> *mut pa_proplist>(PA_PROPLIST_NEW))()
+    }
+
+    static mut PA_PROPLIST_FREE: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_proplist_free(p: *mut pa_proplist) {
+        (::std::mem::transmute::<_, extern "C" fn(*mut pa_proplist)>(PA_PROPLIST_FREE))(p)
+    }
+
+    static mut PA_PROPLIST_SETS: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_proplist_sets(
+        p: *mut pa_proplist,
+        key: *const c_char,
+        value: *const c_char,
+    ) -> c_int {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(*mut pa_proplist, *const c_char, *const c_char) -> c_int,
+        >(PA_PROPLIST_SETS))(p, key, value)
+    }
+
     static mut PA_RTCLOCK_NOW: *mut ::libc::c_void = 0 as *mut _;
     #[inline]
     pub unsafe fn pa_rtclock_now() -> pa_usec_t {
@@ -1421,6 +1868,27 @@ mod dynamic_fns {
         >(PA_STREAM_NEW))(c, name, ss, map)
     }
 
+    static mut PA_STREAM_NEW_WITH_PROPLIST: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_stream_new_with_proplist(
+        c: *mut pa_context,
+        name: *const c_char,
+        ss: *const pa_sample_spec,
+        map: *const pa_channel_map,
+        p: *mut pa_proplist,
+    ) -> *mut pa_stream {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(
+                *mut pa_context,
+                *const c_char,
+                *const pa_sample_spec,
+                *const pa_channel_map,
+                *mut pa_proplist,
+            ) -> *mut pa_stream,
+        >(PA_STREAM_NEW_WITH_PROPLIST))(c, name, ss, map, p)
+    }
+
     static mut PA_STREAM_PEEK: *mut ::libc::c_void = 0 as *mut _;
     #[inline]
     pub unsafe fn pa_stream_peek(
@@ -1455,6 +1923,58 @@ mod dynamic_fns {
         >(PA_STREAM_SET_STATE_CALLBACK))(s, cb, userdata)
     }
 
+    static mut PA_STREAM_SET_MOVED_CALLBACK: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_stream_set_moved_callback(
+        s: *mut pa_stream,
+        cb: pa_stream_notify_cb_t,
+        userdata: *mut c_void,
+    ) {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(*mut pa_stream, pa_stream_notify_cb_t, *mut c_void),
+        >(PA_STREAM_SET_MOVED_CALLBACK))(s, cb, userdata)
+    }
+
+    static mut PA_STREAM_SET_OVERFLOW_CALLBACK: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_stream_set_overflow_callback(
+        s: *mut pa_stream,
+        cb: pa_stream_notify_cb_t,
+        userdata: *mut c_void,
+    ) {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(*mut pa_stream, pa_stream_notify_cb_t, *mut c_void),
+        >(PA_STREAM_SET_OVERFLOW_CALLBACK))(s, cb, userdata)
+    }
+
+    static mut PA_STREAM_SET_SUSPENDED_CALLBACK: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_stream_set_suspended_callback(
+        s: *mut pa_stream,
+        cb: pa_stream_notify_cb_t,
+        userdata: *mut c_void,
+    ) {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(*mut pa_stream, pa_stream_notify_cb_t, *mut c_void),
+        >(PA_STREAM_SET_SUSPENDED_CALLBACK))(s, cb, userdata)
+    }
+
+    static mut PA_STREAM_SET_UNDERFLOW_CALLBACK: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_stream_set_underflow_callback(
+        s: *mut pa_stream,
+        cb: pa_stream_notify_cb_t,
+        userdata: *mut c_void,
+    ) {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(*mut pa_stream, pa_stream_notify_cb_t, *mut c_void),
+        >(PA_STREAM_SET_UNDERFLOW_CALLBACK))(s, cb, userdata)
+    }
+
     static mut PA_STREAM_SET_WRITE_CALLBACK: *mut ::libc::c_void = 0 as *mut _;
     #[inline]
     pub unsafe fn pa_stream_set_write_callback(
@@ -1558,6 +2078,46 @@ mod dynamic_fns {
         >(PA_STREAM_SET_NAME))(s, name, cb, userdata)
     }
 
+    static mut PA_STREAM_PROPLIST_UPDATE: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_stream_proplist_update(
+        s: *mut pa_stream,
+        mode: pa_update_mode_t,
+        p: *mut pa_proplist,
+        cb: pa_stream_success_cb_t,
+        userdata: *mut c_void,
+    ) -> *mut pa_operation {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(
+                *mut pa_stream,
+                pa_update_mode_t,
+                *mut pa_proplist,
+                pa_stream_success_cb_t,
+                *mut c_void,
+            ) -> *mut pa_operation,
+        >(PA_STREAM_PROPLIST_UPDATE))(s, mode, p, cb, userdata)
+    }
+
+    static mut PA_STREAM_SET_BUFFER_ATTR: *mut ::libc::c_void = 0 as *mut _;
+    #[inline]
+    pub unsafe fn pa_stream_set_buffer_attr(
+        s: *mut pa_stream,
+        attr: *const pa_buffer_attr,
+        cb: pa_stream_success_cb_t,
+        userdata: *mut c_void,
+    ) -> *mut pa_operation {
+        (::std::mem::transmute::<
+            _,
+            extern "C" fn(
+                *mut pa_stream,
+                *const pa_buffer_attr,
+                pa_stream_success_cb_t,
+                *mut c_void,
+            ) -> *mut pa_operation,
+        >(PA_STREAM_SET_BUFFER_ATTR))(s, attr, cb, userdata)
+    }
+
     static mut PA_SW_VOLUME_FROM_LINEAR: *mut ::libc::c_void = 0 as *mut _;
     #[inline]
     pub unsafe fn pa_sw_volume_from_linear(v: c_double) -> pa_volume_t {
@@ -210,6 +210,35 @@ impl Context {
         unsafe { ffi::pa_context_rttime_new(self.raw_mut(), usec, Some(wrapped::<CB>), userdata) }
     }
 
+    pub fn get_card_info_list<CB>(&self, _: CB, userdata: *mut c_void) -> Result<Operation>
+    where
+        CB: Fn(&Context, *const CardInfo, i32, *mut c_void),
+    {
+        assert_eq!(::std::mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(
+            c: *mut ffi::pa_context,
+            info: *const ffi::pa_card_info,
+            eol: c_int,
+            userdata: *mut c_void,
+        ) where
+            F: Fn(&Context, *const CardInfo, i32, *mut c_void),
+        {
+            let ctx = context::from_raw_ptr(c);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&ctx, info, eol, userdata);
+            forget(ctx);
+
+            result
+        }
+
+        op_or_err!(
+            self,
+            ffi::pa_context_get_card_info_list(self.raw_mut(), Some(wrapped::<CB>), userdata)
+        )
+    }
+
     pub fn get_server_info<CB>(&self, _: CB, userdata: *mut c_void) -> Result<Operation>
     where
         CB: Fn(&Context, Option<&ServerInfo>, *mut c_void),
@@ -371,6 +400,86 @@ impl Context {
         )
     }
 
+    pub fn set_card_profile_by_index<'str, CB>(
+        &self,
+        idx: u32,
+        profile: &'str CStr,
+        _: CB,
+        userdata: *mut c_void,
+    ) -> Result<Operation>
+    where
+        CB: Fn(&Context, i32, *mut c_void),
+    {
+        assert_eq!(::std::mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(
+            c: *mut ffi::pa_context,
+            success: c_int,
+            userdata: *mut c_void,
+        ) where
+            F: Fn(&Context, i32, *mut c_void),
+        {
+            let ctx = context::from_raw_ptr(c);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&ctx, success, userdata);
+            forget(ctx);
+
+            result
+        }
+
+        op_or_err!(
+            self,
+            ffi::pa_context_set_card_profile_by_index(
+                self.raw_mut(),
+                idx,
+                profile.as_ptr(),
+                Some(wrapped::<CB>),
+                userdata
+            )
+        )
+    }
+
+    pub fn move_sink_input_by_name<'str, CB>(
+        &self,
+        idx: u32,
+        sink_name: &'str CStr,
+        _: CB,
+        userdata: *mut c_void,
+    ) -> Result<Operation>
+    where
+        CB: Fn(&Context, i32, *mut c_void),
+    {
+        assert_eq!(::std::mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(
+            c: *mut ffi::pa_context,
+            success: c_int,
+            userdata: *mut c_void,
+        ) where
+            F: Fn(&Context, i32, *mut c_void),
+        {
+            let ctx = context::from_raw_ptr(c);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&ctx, success, userdata);
+            forget(ctx);
+
+            result
+        }
+
+        op_or_err!(
+            self,
+            ffi::pa_context_move_sink_input_by_name(
+                self.raw_mut(),
+                idx,
+                sink_name.as_ptr(),
+                Some(wrapped::<CB>),
+                userdata
+            )
+        )
+    }
+
     pub fn set_sink_input_volume<CB>(
         &self,
         idx: u32,
@@ -411,6 +520,246 @@ impl Context {
         )
     }
 
+    pub fn set_sink_mute_by_name<'str, CB>(
+        &self,
+        name: &'str CStr,
+        mute: i32,
+        _: CB,
+        userdata: *mut c_void,
+    ) -> Result<Operation>
+    where
+        CB: Fn(&Context, i32, *mut c_void),
+    {
+        assert_eq!(::std::mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(
+            c: *mut ffi::pa_context,
+            success: c_int,
+            userdata: *mut c_void,
+        ) where
+            F: Fn(&Context, i32, *mut c_void),
+        {
+            let ctx = context::from_raw_ptr(c);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&ctx, success, userdata);
+            forget(ctx);
+
+            result
+        }
+
+        op_or_err!(
+            self,
+            ffi::pa_context_set_sink_mute_by_name(
+                self.raw_mut(),
+                name.as_ptr(),
+                mute,
+                Some(wrapped::<CB>),
+                userdata
+            )
+        )
+    }
+
+    pub fn set_sink_port_by_name<'str, CB>(
+        &self,
+        name: &'str CStr,
+        port: &'str CStr,
+        _: CB,
+        userdata: *mut c_void,
+    ) -> Result<Operation>
+    where
+        CB: Fn(&Context, i32, *mut c_void),
+    {
+        assert_eq!(::std::mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(
+            c: *mut ffi::pa_context,
+            success: c_int,
+            userdata: *mut c_void,
+        ) where
+            F: Fn(&Context, i32, *mut c_void),
+        {
+            let ctx = context::from_raw_ptr(c);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&ctx, success, userdata);
+            forget(ctx);
+
+            result
+        }
+
+        op_or_err!(
+            self,
+            ffi::pa_context_set_sink_port_by_name(
+                self.raw_mut(),
+                name.as_ptr(),
+                port.as_ptr(),
+                Some(wrapped::<CB>),
+                userdata
+            )
+        )
+    }
+
+    pub fn set_source_mute_by_name<'str, CB>(
+        &self,
+        name: &'str CStr,
+        mute: i32,
+        _: CB,
+        userdata: *mut c_void,
+    ) -> Result<Operation>
+    where
+        CB: Fn(&Context, i32, *mut c_void),
+    {
+        assert_eq!(::std::mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(
+            c: *mut ffi::pa_context,
+            success: c_int,
+            userdata: *mut c_void,
+        ) where
+            F: Fn(&Context, i32, *mut c_void),
+        {
+            let ctx = context::from_raw_ptr(c);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&ctx, success, userdata);
+            forget(ctx);
+
+            result
+        }
+
+        op_or_err!(
+            self,
+            ffi::pa_context_set_source_mute_by_name(
+                self.raw_mut(),
+                name.as_ptr(),
+                mute,
+                Some(wrapped::<CB>),
+                userdata
+            )
+        )
+    }
+
+    pub fn set_source_output_mute<CB>(
+        &self,
+        idx: u32,
+        mute: i32,
+        _: CB,
+        userdata: *mut c_void,
+    ) -> Result<Operation>
+    where
+        CB: Fn(&Context, i32, *mut c_void),
+    {
+        assert_eq!(::std::mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(
+            c: *mut ffi::pa_context,
+            success: c_int,
+            userdata: *mut c_void,
+        ) where
+            F: Fn(&Context, i32, *mut c_void),
+        {
+            let ctx = context::from_raw_ptr(c);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&ctx, success, userdata);
+            forget(ctx);
+
+            result
+        }
+
+        op_or_err!(
+            self,
+            ffi::pa_context_set_source_output_mute(
+                self.raw_mut(),
+                idx,
+                mute,
+                Some(wrapped::<CB>),
+                userdata
+            )
+        )
+    }
+
+    pub fn set_source_output_volume<CB>(
+        &self,
+        idx: u32,
+        volume: &CVolume,
+        _: CB,
+        userdata: *mut c_void,
+    ) -> Result<Operation>
+    where
+        CB: Fn(&Context, i32, *mut c_void),
+    {
+        assert_eq!(::std::mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(
+            c: *mut ffi::pa_context,
+            success: c_int,
+            userdata: *mut c_void,
+        ) where
+            F: Fn(&Context, i32, *mut c_void),
+        {
+            let ctx = context::from_raw_ptr(c);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&ctx, success, userdata);
+            forget(ctx);
+
+            result
+        }
+
+        op_or_err!(
+            self,
+            ffi::pa_context_set_source_output_volume(
+                self.raw_mut(),
+                idx,
+                volume,
+                Some(wrapped::<CB>),
+                userdata
+            )
+        )
+    }
+
+    pub fn set_source_port_by_name<'str, CB>(
+        &self,
+        name: &'str CStr,
+        port: &'str CStr,
+        _: CB,
+        userdata: *mut c_void,
+    ) -> Result<Operation>
+    where
+        CB: Fn(&Context, i32, *mut c_void),
+    {
+        assert_eq!(::std::mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(
+            c: *mut ffi::pa_context,
+            success: c_int,
+            userdata: *mut c_void,
+        ) where
+            F: Fn(&Context, i32, *mut c_void),
+        {
+            let ctx = context::from_raw_ptr(c);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&ctx, success, userdata);
+            forget(ctx);
+
+            result
+        }
+
+        op_or_err!(
+            self,
+            ffi::pa_context_set_source_port_by_name(
+                self.raw_mut(),
+                name.as_ptr(),
+                port.as_ptr(),
+                Some(wrapped::<CB>),
+                userdata
+            )
+        )
+    }
+
     pub fn subscribe<CB>(
         &self,
         m: SubscriptionMask,
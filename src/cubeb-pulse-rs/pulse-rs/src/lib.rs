@@ -20,8 +20,12 @@ mod util;
 pub use context::Context;
 pub use error::ErrorCode;
 pub use ffi::pa_buffer_attr as BufferAttr;
+pub use ffi::pa_card_info as CardInfo;
+pub use ffi::pa_card_port_info as CardPortInfo;
+pub use ffi::pa_card_profile_info2 as CardProfileInfo;
 pub use ffi::pa_channel_map as ChannelMap;
 pub use ffi::pa_cvolume as CVolume;
+pub use ffi::pa_port_info as PortInfo;
 pub use ffi::pa_sample_spec as SampleSpec;
 pub use ffi::pa_server_info as ServerInfo;
 pub use ffi::pa_sink_info as SinkInfo;
@@ -33,6 +37,7 @@ pub use ffi::timeval as TimeVal;
 pub use mainloop_api::MainloopApi;
 pub use operation::Operation;
 pub use proplist::Proplist;
+use std::mem::ManuallyDrop;
 use std::os::raw::{c_char, c_uint};
 pub use stream::Stream;
 pub use threaded_mainloop::ThreadedMainloop;
@@ -69,6 +74,20 @@ impl Into<ffi::pa_sample_format_t> for SampleFormat {
     }
 }
 
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdateMode {
+    Set = ffi::PA_UPDATE_SET,
+    Merge = ffi::PA_UPDATE_MERGE,
+    Replace = ffi::PA_UPDATE_REPLACE,
+}
+
+impl Into<ffi::pa_update_mode_t> for UpdateMode {
+    fn into(self) -> ffi::pa_update_mode_t {
+        self as ffi::pa_update_mode_t
+    }
+}
+
 #[repr(i32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ContextState {
@@ -640,21 +659,83 @@ impl ChannelMapExt for ChannelMap {
 }
 
 pub trait ProplistExt {
-    fn proplist(&self) -> Proplist;
+    fn proplist(&self) -> ManuallyDrop<Proplist>;
 }
 
 impl ProplistExt for SinkInfo {
-    fn proplist(&self) -> Proplist {
+    fn proplist(&self) -> ManuallyDrop<Proplist> {
         unsafe { proplist::from_raw_ptr(self.proplist) }
     }
 }
 
 impl ProplistExt for SourceInfo {
-    fn proplist(&self) -> Proplist {
+    fn proplist(&self) -> ManuallyDrop<Proplist> {
         unsafe { proplist::from_raw_ptr(self.proplist) }
     }
 }
 
+impl ProplistExt for CardInfo {
+    fn proplist(&self) -> ManuallyDrop<Proplist> {
+        unsafe { proplist::from_raw_ptr(self.proplist) }
+    }
+}
+
+pub trait CardProfilesExt {
+    fn profiles(&self) -> Vec<&CardProfileInfo>;
+    fn active_profile(&self) -> Option<&CardProfileInfo>;
+}
+
+impl CardProfilesExt for CardInfo {
+    fn profiles(&self) -> Vec<&CardProfileInfo> {
+        if self.profiles2.is_null() {
+            return Vec::new();
+        }
+        unsafe { ::std::slice::from_raw_parts(self.profiles2, self.n_profiles as usize) }
+            .iter()
+            .filter_map(|p| unsafe { p.as_ref() })
+            .collect()
+    }
+
+    fn active_profile(&self) -> Option<&CardProfileInfo> {
+        unsafe { self.active_profile2.as_ref() }
+    }
+}
+
+pub trait PortsExt {
+    fn ports(&self) -> Vec<&PortInfo>;
+    fn active_port(&self) -> Option<&PortInfo>;
+}
+
+unsafe fn ports_from_raw<'a>(ports: *mut *mut PortInfo, n_ports: u32) -> Vec<&'a PortInfo> {
+    if ports.is_null() {
+        return Vec::new();
+    }
+    ::std::slice::from_raw_parts(ports, n_ports as usize)
+        .iter()
+        .filter_map(|p| p.as_ref())
+        .collect()
+}
+
+impl PortsExt for SinkInfo {
+    fn ports(&self) -> Vec<&PortInfo> {
+        unsafe { ports_from_raw(self.ports, self.n_ports) }
+    }
+
+    fn active_port(&self) -> Option<&PortInfo> {
+        unsafe { self.active_port.as_ref() }
+    }
+}
+
+impl PortsExt for SourceInfo {
+    fn ports(&self) -> Vec<&PortInfo> {
+        unsafe { ports_from_raw(self.ports, self.n_ports) }
+    }
+
+    fn active_port(&self) -> Option<&PortInfo> {
+        unsafe { self.active_port.as_ref() }
+    }
+}
+
 pub trait SampleSpecExt {
     fn frame_size(&self) -> usize;
     fn sample_size(&self) -> usize;
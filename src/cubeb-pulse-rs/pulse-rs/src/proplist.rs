@@ -3,13 +3,46 @@
 // This program is made available under an ISC-style license.  See the
 // accompanying file LICENSE for details.
 
+use error::ErrorCode;
 use ffi;
 use std::ffi::{CStr, CString};
+use std::mem::ManuallyDrop;
+use Result;
 
 #[derive(Debug)]
 pub struct Proplist(*mut ffi::pa_proplist);
 
 impl Proplist {
+    pub fn new() -> Option<Self> {
+        let p = unsafe { ffi::pa_proplist_new() };
+        if p.is_null() {
+            None
+        } else {
+            Some(Proplist(p))
+        }
+    }
+
+    pub fn raw_mut(&self) -> &mut ffi::pa_proplist {
+        unsafe { &mut *self.0 }
+    }
+
+    pub fn sets<K, V>(&self, key: K, value: V) -> Result<()>
+    where
+        K: Into<Vec<u8>>,
+        V: Into<Vec<u8>>,
+    {
+        let (key, value) = match (CString::new(key), CString::new(value)) {
+            (Ok(k), Ok(v)) => (k, v),
+            _ => return Err(ErrorCode::from_error_code(ffi::PA_ERR_INVALID)),
+        };
+        let r = unsafe { ffi::pa_proplist_sets(self.0, key.as_ptr(), value.as_ptr()) };
+        if r < 0 {
+            Err(ErrorCode::from_error_code(ffi::PA_ERR_INVALID))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn gets<T>(&self, key: T) -> Option<&CStr>
     where
         T: Into<Vec<u8>>,
@@ -27,6 +60,14 @@ impl Proplist {
     }
 }
 
-pub unsafe fn from_raw_ptr(raw: *mut ffi::pa_proplist) -> Proplist {
-    return Proplist(raw);
+impl Drop for Proplist {
+    fn drop(&mut self) {
+        unsafe { ffi::pa_proplist_free(self.0) }
+    }
+}
+
+// The proplists of introspection info structures are owned by PulseAudio,
+// and must not be freed.
+pub unsafe fn from_raw_ptr(raw: *mut ffi::pa_proplist) -> ManuallyDrop<Proplist> {
+    ManuallyDrop::new(Proplist(raw))
 }
@@ -41,6 +41,32 @@ impl Stream {
         }
     }
 
+    pub fn new_with_proplist<'a, CM>(
+        c: &Context,
+        name: &::std::ffi::CStr,
+        ss: &SampleSpec,
+        map: CM,
+        p: &Proplist,
+    ) -> Option<Self>
+    where
+        CM: Into<Option<&'a ChannelMap>>,
+    {
+        let ptr = unsafe {
+            ffi::pa_stream_new_with_proplist(
+                c.raw_mut(),
+                name.as_ptr(),
+                ss as *const _,
+                to_ptr(map.into()),
+                p.raw_mut(),
+            )
+        };
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Stream(ptr))
+        }
+    }
+
     #[doc(hidden)]
     pub fn raw_mut(&self) -> &mut ffi::pa_stream {
         unsafe { &mut *self.0 }
@@ -269,6 +295,126 @@ impl Stream {
         }
     }
 
+    pub fn clear_moved_callback(&self) {
+        unsafe {
+            ffi::pa_stream_set_moved_callback(self.raw_mut(), None, ptr::null_mut());
+        }
+    }
+
+    pub fn set_moved_callback<CB>(&self, _: CB, userdata: *mut c_void)
+    where
+        CB: Fn(&Stream, *mut c_void),
+    {
+        assert_eq!(mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(s: *mut ffi::pa_stream, userdata: *mut c_void)
+        where
+            F: Fn(&Stream, *mut c_void),
+        {
+            let mut stm = stream::from_raw_ptr(s);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&mut stm, userdata);
+            forget(stm);
+
+            result
+        }
+
+        unsafe {
+            ffi::pa_stream_set_moved_callback(self.raw_mut(), Some(wrapped::<CB>), userdata);
+        }
+    }
+
+    pub fn clear_underflow_callback(&self) {
+        unsafe {
+            ffi::pa_stream_set_underflow_callback(self.raw_mut(), None, ptr::null_mut());
+        }
+    }
+
+    pub fn set_underflow_callback<CB>(&self, _: CB, userdata: *mut c_void)
+    where
+        CB: Fn(&Stream, *mut c_void),
+    {
+        assert_eq!(mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(s: *mut ffi::pa_stream, userdata: *mut c_void)
+        where
+            F: Fn(&Stream, *mut c_void),
+        {
+            let mut stm = stream::from_raw_ptr(s);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&mut stm, userdata);
+            forget(stm);
+
+            result
+        }
+
+        unsafe {
+            ffi::pa_stream_set_underflow_callback(self.raw_mut(), Some(wrapped::<CB>), userdata);
+        }
+    }
+
+    pub fn clear_overflow_callback(&self) {
+        unsafe {
+            ffi::pa_stream_set_overflow_callback(self.raw_mut(), None, ptr::null_mut());
+        }
+    }
+
+    pub fn set_overflow_callback<CB>(&self, _: CB, userdata: *mut c_void)
+    where
+        CB: Fn(&Stream, *mut c_void),
+    {
+        assert_eq!(mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(s: *mut ffi::pa_stream, userdata: *mut c_void)
+        where
+            F: Fn(&Stream, *mut c_void),
+        {
+            let mut stm = stream::from_raw_ptr(s);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&mut stm, userdata);
+            forget(stm);
+
+            result
+        }
+
+        unsafe {
+            ffi::pa_stream_set_overflow_callback(self.raw_mut(), Some(wrapped::<CB>), userdata);
+        }
+    }
+
+    pub fn clear_suspended_callback(&self) {
+        unsafe {
+            ffi::pa_stream_set_suspended_callback(self.raw_mut(), None, ptr::null_mut());
+        }
+    }
+
+    pub fn set_suspended_callback<CB>(&self, _: CB, userdata: *mut c_void)
+    where
+        CB: Fn(&Stream, *mut c_void),
+    {
+        assert_eq!(mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(s: *mut ffi::pa_stream, userdata: *mut c_void)
+        where
+            F: Fn(&Stream, *mut c_void),
+        {
+            let mut stm = stream::from_raw_ptr(s);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&mut stm, userdata);
+            forget(stm);
+
+            result
+        }
+
+        unsafe {
+            ffi::pa_stream_set_suspended_callback(self.raw_mut(), Some(wrapped::<CB>), userdata);
+        }
+    }
+
     pub fn clear_write_callback(&self) {
         unsafe {
             ffi::pa_stream_set_write_callback(self.raw_mut(), None, ptr::null_mut());
@@ -448,6 +594,95 @@ impl Stream {
         }
         Ok(unsafe { operation::from_raw_ptr(r) })
     }
+
+    pub fn set_buffer_attr<CB>(
+        &self,
+        attr: &BufferAttr,
+        _: CB,
+        userdata: *mut c_void,
+    ) -> Result<Operation>
+    where
+        CB: Fn(&Stream, i32, *mut c_void),
+    {
+        assert_eq!(mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(
+            s: *mut ffi::pa_stream,
+            success: c_int,
+            userdata: *mut c_void,
+        ) where
+            F: Fn(&Stream, i32, *mut c_void),
+        {
+            let mut stm = stream::from_raw_ptr(s);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&mut stm, success, userdata);
+            forget(stm);
+
+            result
+        }
+
+        let r = unsafe {
+            ffi::pa_stream_set_buffer_attr(self.raw_mut(), attr, Some(wrapped::<CB>), userdata)
+        };
+        if r.is_null() {
+            let err = if let Some(c) = self.get_context() {
+                c.errno()
+            } else {
+                ffi::PA_ERR_UNKNOWN
+            };
+            return Err(ErrorCode::from_error_code(err));
+        }
+        Ok(unsafe { operation::from_raw_ptr(r) })
+    }
+
+    pub fn proplist_update<CB>(
+        &self,
+        mode: UpdateMode,
+        p: &Proplist,
+        _: CB,
+        userdata: *mut c_void,
+    ) -> Result<Operation>
+    where
+        CB: Fn(&Stream, i32, *mut c_void),
+    {
+        assert_eq!(mem::size_of::<CB>(), 0);
+
+        // See: A note about `wrapped` functions
+        unsafe extern "C" fn wrapped<F>(
+            s: *mut ffi::pa_stream,
+            success: c_int,
+            userdata: *mut c_void,
+        ) where
+            F: Fn(&Stream, i32, *mut c_void),
+        {
+            let mut stm = stream::from_raw_ptr(s);
+            let cb = MaybeUninit::<F>::uninit();
+            let result = (*cb.as_ptr())(&mut stm, success, userdata);
+            forget(stm);
+
+            result
+        }
+
+        let r = unsafe {
+            ffi::pa_stream_proplist_update(
+                self.raw_mut(),
+                mode.into(),
+                p.raw_mut(),
+                Some(wrapped::<CB>),
+                userdata,
+            )
+        };
+        if r.is_null() {
+            let err = if let Some(c) = self.get_context() {
+                c.errno()
+            } else {
+                ffi::PA_ERR_UNKNOWN
+            };
+            return Err(ErrorCode::from_error_code(err));
+        }
+        Ok(unsafe { operation::from_raw_ptr(r) })
+    }
 }
 
 #[doc(hidden)]
@@ -4,20 +4,35 @@
 // accompanying file LICENSE for details.
 
 use backend::*;
+use cubeb_backend::latency::{LatencyController, LatencyPolicy};
 use cubeb_backend::{
-    ffi, log_enabled, Context, ContextOps, DeviceCollectionRef, DeviceId, DeviceType, Error, Ops,
-    Result, Stream, StreamParams, StreamParamsRef,
+    ffi, log_enabled, Capabilities, Context, ContextOps, DeviceCollectionRef, DeviceId, DeviceType,
+    Error, Ops, Result, Stream, StreamOptions, StreamOptionsRef, StreamParams, StreamParamsRef,
 };
-use pulse::{self, ProplistExt};
+use pulse::{self, PortsExt, ProplistExt};
 use pulse_ffi::*;
 use semver;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::default::Default;
 use std::ffi::{CStr, CString};
 use std::mem;
-use std::os::raw::c_void;
+use std::os::raw::{c_char, c_void};
 use std::ptr;
 
+// Range of the latency shared by the streams, in microseconds. Below a
+// millisecond the buffer attributes round to nothing, and libcubeb takes at
+// most 96000 frames, 2s at 48kHz.
+const MIN_LATENCY_USEC: u32 = 1_000;
+const MAX_LATENCY_USEC: u32 = 2_000_000;
+
+// The server runs a sink at the smallest latency its streams request, so
+// do the streams: none runs with more latency than it asked for, and they
+// all wake up together.
+pub fn new_latency_controller() -> LatencyController {
+    LatencyController::new(LatencyPolicy::Smallest, MIN_LATENCY_USEC, MAX_LATENCY_USEC)
+}
+
 #[derive(Debug)]
 pub struct DefaultInfo {
     pub sample_spec: pulse::SampleSpec,
@@ -25,7 +40,11 @@ pub struct DefaultInfo {
     pub flags: pulse::SinkFlags,
 }
 
-pub const PULSE_OPS: Ops = capi_new!(PulseContext, PulseStream);
+pub const PULSE_OPS: Ops = capi_new!(
+    PulseContext,
+    PulseStream,
+    unsupported: [stream_register_device_changed_callback]
+);
 
 #[repr(C)]
 #[derive(Debug)]
@@ -45,6 +64,13 @@ pub struct PulseContext {
     #[cfg(feature = "pulse-dlopen")]
     pub libpulse: LibLoader,
     devids: RefCell<Intern>,
+    // Properties of the devices of each non-empty collection handed out by
+    // `enumerate_devices`, keyed by its array of devices.
+    device_properties: HashMap<*const ffi::cubeb_device_info, Vec<DeviceProperties>>,
+    // Latency of the streams, in microseconds, and the streams following
+    // it. Used with the mainloop lock held.
+    pub latency_controller: RefCell<LatencyController>,
+    pub streams: RefCell<Vec<*mut c_void>>,
 }
 
 impl PulseContext {
@@ -71,6 +97,9 @@ impl PulseContext {
             version_0_9_8: false,
             version_2_0_0: false,
             devids: RefCell::new(Intern::new()),
+            device_properties: HashMap::new(),
+            latency_controller: RefCell::new(new_latency_controller()),
+            streams: RefCell::new(Vec::new()),
         });
 
         Ok(ctx)
@@ -92,6 +121,9 @@ impl PulseContext {
             version_0_9_8: false,
             version_2_0_0: false,
             devids: RefCell::new(Intern::new()),
+            device_properties: HashMap::new(),
+            latency_controller: RefCell::new(new_latency_controller()),
+            streams: RefCell::new(Vec::new()),
         }))
     }
 
@@ -293,6 +325,19 @@ impl ContextOps for PulseContext {
         }
     }
 
+    fn capabilities(&mut self) -> Result<Capabilities> {
+        // Loopback streams and device changed callbacks are not
+        // implemented. Voice streams ask the server for echo cancellation.
+        Ok(Capabilities::DEVICE_SWITCHING
+            | Capabilities::STREAM_VOLUME
+            | Capabilities::INPUT_LATENCY
+            | Capabilities::VOICE_PROCESSING
+            | Capabilities::FORMAT_S16LE
+            | Capabilities::FORMAT_S16BE
+            | Capabilities::FORMAT_FLOAT32LE
+            | Capabilities::FORMAT_FLOAT32BE)
+    }
+
     fn enumerate_devices(
         &mut self,
         devtype: DeviceType,
@@ -328,7 +373,7 @@ impl ContextOps for PulseContext {
             };
 
             let info_name = unsafe { CStr::from_ptr(info.name) };
-            let info_description = unsafe { CStr::from_ptr(info.description) }.to_owned();
+            let info_description = unsafe { CStr::from_ptr(info.description) };
 
             let preferred = if *info_name == *list_data.default_sink_name {
                 ffi::CUBEB_DEVICE_PREF_ALL
@@ -336,8 +381,20 @@ impl ContextOps for PulseContext {
                 ffi::CUBEB_DEVICE_PREF_NONE
             };
 
+            let (latency_lo, latency_hi) = latency_range_in_frames(
+                info.latency,
+                info.configured_latency,
+                info.flags & PA_SINK_DYNAMIC_LATENCY != 0,
+                info.sample_spec.rate,
+            );
+
+            let proplist = info.proplist();
+            let ports = info.ports();
+            let properties =
+                DeviceProperties::new(device_properties(&proplist, &ports, info.active_port()));
+
             let device_id = ctx.devids.borrow_mut().add(info_name);
-            let friendly_name = info_description.into_raw();
+            let friendly_name = info_description.to_owned().into_raw();
             let devinfo = ffi::cubeb_device_info {
                 device_id,
                 devid: device_id as ffi::cubeb_devid,
@@ -350,13 +407,24 @@ impl ContextOps for PulseContext {
                 format: ffi::CUBEB_DEVICE_FMT_ALL,
                 default_format: pulse_format_to_cubeb_format(info.sample_spec.format),
                 max_channels: u32::from(info.channel_map.channels),
-                min_rate: 1,
+                min_rate: min_rate(info.sample_spec.rate),
                 max_rate: PA_RATE_MAX,
                 default_rate: info.sample_spec.rate,
-                latency_lo: 0,
-                latency_hi: 0,
+                latency_lo,
+                latency_hi,
             };
-            list_data.devinfo.push(devinfo);
+            let port_devices = port_devices(
+                ctx,
+                &devinfo,
+                info_name,
+                info_description,
+                &proplist,
+                &ports,
+            );
+            list_data.push(devinfo, properties);
+            for (devinfo, properties) in port_devices {
+                list_data.push(devinfo, properties);
+            }
         }
 
         fn add_input_device(
@@ -389,7 +457,7 @@ impl ContextOps for PulseContext {
             };
 
             let info_name = unsafe { CStr::from_ptr(info.name) };
-            let info_description = unsafe { CStr::from_ptr(info.description) }.to_owned();
+            let info_description = unsafe { CStr::from_ptr(info.description) };
 
             let preferred = if *info_name == *list_data.default_source_name {
                 ffi::CUBEB_DEVICE_PREF_ALL
@@ -397,8 +465,20 @@ impl ContextOps for PulseContext {
                 ffi::CUBEB_DEVICE_PREF_NONE
             };
 
+            let (latency_lo, latency_hi) = latency_range_in_frames(
+                info.latency,
+                info.configured_latency,
+                info.flags & PA_SOURCE_DYNAMIC_LATENCY != 0,
+                info.sample_spec.rate,
+            );
+
+            let proplist = info.proplist();
+            let ports = info.ports();
+            let properties =
+                DeviceProperties::new(device_properties(&proplist, &ports, info.active_port()));
+
             let device_id = ctx.devids.borrow_mut().add(info_name);
-            let friendly_name = info_description.into_raw();
+            let friendly_name = info_description.to_owned().into_raw();
             let devinfo = ffi::cubeb_device_info {
                 device_id,
                 devid: device_id as ffi::cubeb_devid,
@@ -411,14 +491,24 @@ impl ContextOps for PulseContext {
                 format: ffi::CUBEB_DEVICE_FMT_ALL,
                 default_format: pulse_format_to_cubeb_format(info.sample_spec.format),
                 max_channels: u32::from(info.channel_map.channels),
-                min_rate: 1,
+                min_rate: min_rate(info.sample_spec.rate),
                 max_rate: PA_RATE_MAX,
                 default_rate: info.sample_spec.rate,
-                latency_lo: 0,
-                latency_hi: 0,
+                latency_lo,
+                latency_hi,
             };
-
-            list_data.devinfo.push(devinfo);
+            let port_devices = port_devices(
+                ctx,
+                &devinfo,
+                info_name,
+                info_description,
+                &proplist,
+                &ports,
+            );
+            list_data.push(devinfo, properties);
+            for (devinfo, properties) in port_devices {
+                list_data.push(devinfo, properties);
+            }
         }
 
         fn default_device_names(
@@ -474,10 +564,18 @@ impl ContextOps for PulseContext {
         // PulseDevListData and convert it into C representation.
         let mut tmp = Vec::new();
         mem::swap(&mut user_data.devinfo, &mut tmp);
+        let properties = mem::take(&mut user_data.properties);
+        drop(user_data);
         let mut devices = tmp.into_boxed_slice();
         let coll = unsafe { &mut *collection.as_ptr() };
         coll.device = devices.as_mut_ptr();
         coll.count = devices.len();
+        // Every empty collection has the same dangling pointer, and no
+        // properties to look up.
+        if !devices.is_empty() {
+            self.device_properties
+                .insert(coll.device as *const _, properties);
+        }
 
         // Giving away the memory owned by devices.  Don't free it!
         mem::forget(devices);
@@ -504,12 +602,26 @@ impl ContextOps for PulseContext {
                     let _ = CString::from_raw(dev.friendly_name as *mut _);
                 }
             }
+            self.device_properties.remove(&(coll.device as *const _));
             coll.device = ptr::null_mut();
             coll.count = 0;
         }
         Ok(())
     }
 
+    fn device_collection_properties(
+        &mut self,
+        collection: &DeviceCollectionRef,
+        index: usize,
+    ) -> Result<&[ffi::cubeb_property]> {
+        let coll = unsafe { &*collection.as_ptr() };
+        self.device_properties
+            .get(&(coll.device as *const _))
+            .and_then(|devices| devices.get(index))
+            .map(|props| &props.raw[..])
+            .ok_or_else(Error::invalid_parameter)
+    }
+
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
     fn stream_init(
         &mut self,
@@ -522,6 +634,34 @@ impl ContextOps for PulseContext {
         data_callback: ffi::cubeb_data_callback,
         state_callback: ffi::cubeb_state_callback,
         user_ptr: *mut c_void,
+    ) -> Result<Stream> {
+        self.stream_init_with_options(
+            stream_name,
+            input_device,
+            input_stream_params,
+            output_device,
+            output_stream_params,
+            &StreamOptions::default(),
+            latency_frames,
+            data_callback,
+            state_callback,
+            user_ptr,
+        )
+    }
+
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+    fn stream_init_with_options(
+        &mut self,
+        stream_name: Option<&CStr>,
+        input_device: DeviceId,
+        input_stream_params: Option<&StreamParamsRef>,
+        output_device: DeviceId,
+        output_stream_params: Option<&StreamParamsRef>,
+        options: &StreamOptionsRef,
+        latency_frames: u32,
+        data_callback: ffi::cubeb_data_callback,
+        state_callback: ffi::cubeb_state_callback,
+        user_ptr: *mut c_void,
     ) -> Result<Stream> {
         if self.error {
             self.context_init()?;
@@ -534,6 +674,7 @@ impl ContextOps for PulseContext {
             input_stream_params,
             output_device,
             output_stream_params,
+            options,
             latency_frames,
             data_callback,
             state_callback,
@@ -714,6 +855,8 @@ struct PulseDevListData<'a> {
     default_sink_name: CString,
     default_source_name: CString,
     devinfo: Vec<ffi::cubeb_device_info>,
+    // The properties of each entry of `devinfo`.
+    properties: Vec<DeviceProperties>,
     context: &'a PulseContext,
 }
 
@@ -726,9 +869,15 @@ impl<'a> PulseDevListData<'a> {
             default_sink_name: CString::default(),
             default_source_name: CString::default(),
             devinfo: Vec::new(),
+            properties: Vec::new(),
             context,
         }
     }
+
+    fn push(&mut self, devinfo: ffi::cubeb_device_info, properties: DeviceProperties) {
+        self.devinfo.push(devinfo);
+        self.properties.push(properties);
+    }
 }
 
 impl<'a> Drop for PulseDevListData<'a> {
@@ -749,3 +898,358 @@ fn pulse_format_to_cubeb_format(format: pa_sample_format_t) -> ffi::cubeb_device
         _ => ffi::CUBEB_DEVICE_FMT_F32NE,
     }
 }
+
+// Metadata exposed through `cubeb_device_collection_get_properties`, gathered
+// from the sink/source proplist and port list.
+fn device_properties(
+    proplist: &pulse::Proplist,
+    ports: &[&pulse::PortInfo],
+    active_port: Option<&pulse::PortInfo>,
+) -> Vec<(CString, CString)> {
+    let mut props = proplist_properties(proplist);
+    props.extend(port_properties(ports, active_port));
+    props
+}
+
+fn proplist_properties(proplist: &pulse::Proplist) -> Vec<(CString, CString)> {
+    [
+        (ffi::CUBEB_DEVICE_PROPERTY_FORM_FACTOR, "device.form_factor"),
+        (ffi::CUBEB_DEVICE_PROPERTY_BUS, "device.bus"),
+        (ffi::CUBEB_DEVICE_PROPERTY_ICON_NAME, "device.icon_name"),
+    ]
+    .iter()
+    .filter_map(|&(key, pa_key)| {
+        proplist
+            .gets(pa_key)
+            .map(|value| (CString::new(key).unwrap(), value.to_owned()))
+    })
+    .collect()
+}
+
+fn port_properties(
+    ports: &[&pulse::PortInfo],
+    active_port: Option<&pulse::PortInfo>,
+) -> Vec<(CString, CString)> {
+    let mut props = Vec::new();
+
+    if let Some(port) = active_port {
+        if let Some(name) = super::try_cstr_from(port.name) {
+            props.push((
+                CString::new(ffi::CUBEB_DEVICE_PROPERTY_ACTIVE_PORT).unwrap(),
+                name.to_owned(),
+            ));
+        }
+        if let Some(desc) = super::try_cstr_from(port.description) {
+            props.push((
+                CString::new(ffi::CUBEB_DEVICE_PROPERTY_ACTIVE_PORT_DESCRIPTION).unwrap(),
+                desc.to_owned(),
+            ));
+        }
+    }
+
+    for port in ports {
+        if port.available == PA_PORT_AVAILABLE_NO {
+            continue;
+        }
+        let name = match super::try_cstr_from(port.name) {
+            Some(name) => name,
+            None => continue,
+        };
+        let mut key = ffi::CUBEB_DEVICE_PROPERTY_PORT_PREFIX.as_bytes().to_vec();
+        key.extend_from_slice(name.to_bytes());
+        let desc = super::try_cstr_from(port.description).unwrap_or(name);
+        props.push((CString::new(key).unwrap(), desc.to_owned()));
+    }
+
+    props
+}
+
+// Exposes each port of a sink or source that has several of them as a device
+// of its own, so that streams can be routed to a given port. The entries
+// share everything but their name, state and preference with `device`.
+fn port_devices(
+    ctx: &PulseContext,
+    device: &ffi::cubeb_device_info,
+    name: &CStr,
+    description: &CStr,
+    proplist: &pulse::Proplist,
+    ports: &[&pulse::PortInfo],
+) -> Vec<(ffi::cubeb_device_info, DeviceProperties)> {
+    let mut devices = Vec::new();
+    if ports.len() < 2 {
+        return devices;
+    }
+
+    for port in ports {
+        let port_name = match super::try_cstr_from(port.name) {
+            Some(name) => name,
+            None => continue,
+        };
+        let port_description = super::try_cstr_from(port.description).unwrap_or(port_name);
+        let friendly_name = format!(
+            "{}: {}",
+            description.to_string_lossy(),
+            port_description.to_string_lossy()
+        );
+        let properties = DeviceProperties::new(proplist_properties(proplist));
+
+        let device_id = ctx
+            .devids
+            .borrow_mut()
+            .add(&super::port_device_name(name, port_name));
+        let devinfo = ffi::cubeb_device_info {
+            device_id,
+            devid: device_id as ffi::cubeb_devid,
+            friendly_name: CString::new(friendly_name).unwrap().into_raw(),
+            group_id: dup_cstr(device.group_id),
+            vendor_name: dup_cstr(device.vendor_name),
+            state: ctx.state_from_port(*port),
+            preferred: ffi::CUBEB_DEVICE_PREF_NONE,
+            ..*device
+        };
+        devices.push((devinfo, properties));
+    }
+
+    devices
+}
+
+fn dup_cstr(s: *const c_char) -> *const c_char {
+    match super::try_cstr_from(s) {
+        Some(s) => s.to_owned().into_raw(),
+        None => ptr::null(),
+    }
+}
+
+// Properties of a device, in the C representation handed out by
+// `device_collection_properties`.
+#[derive(Debug, Default)]
+struct DeviceProperties {
+    raw: Vec<ffi::cubeb_property>,
+    // The strings `raw` points to.
+    _strings: Vec<(CString, CString)>,
+}
+
+impl DeviceProperties {
+    fn new(strings: Vec<(CString, CString)>) -> Self {
+        let raw = strings
+            .iter()
+            .map(|(key, value)| ffi::cubeb_property {
+                key: key.as_ptr(),
+                value: value.as_ptr(),
+            })
+            .collect();
+        DeviceProperties {
+            raw,
+            _strings: strings,
+        }
+    }
+}
+
+// Lowest rate reported for a device. PulseAudio resamples anything down to
+// 1Hz, but nothing below telephony rates is useful to clients.
+const MIN_RATE: u32 = 8000;
+
+fn min_rate(default_rate: u32) -> u32 {
+    default_rate.min(MIN_RATE)
+}
+
+fn usec_to_frames(usec: pa_usec_t, rate: u32) -> u32 {
+    let frames = usec * u64::from(rate) / PA_USEC_PER_SEC;
+    frames.min(u64::from(u32::MAX)) as u32
+}
+
+// Sinks and sources report the latency they currently run at and, when
+// they support dynamic latency, the latency requested by their clients.
+// Fixed latency devices have a single value; dynamic latency devices can
+// be driven anywhere between the two. A dynamic latency device that is
+// idle reports a configured latency of zero.
+fn latency_range_in_frames(
+    latency: pa_usec_t,
+    configured_latency: pa_usec_t,
+    dynamic: bool,
+    rate: u32,
+) -> (u32, u32) {
+    let (lo, hi) = if dynamic && configured_latency != 0 {
+        (
+            latency.min(configured_latency),
+            latency.max(configured_latency),
+        )
+    } else {
+        (latency, latency)
+    };
+    (usec_to_frames(lo, rate), usec_to_frames(hi, rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pulse_format_to_cubeb_format_known() {
+        assert_eq!(
+            pulse_format_to_cubeb_format(PA_SAMPLE_S16LE),
+            ffi::CUBEB_DEVICE_FMT_S16LE
+        );
+        assert_eq!(
+            pulse_format_to_cubeb_format(PA_SAMPLE_S16BE),
+            ffi::CUBEB_DEVICE_FMT_S16BE
+        );
+        assert_eq!(
+            pulse_format_to_cubeb_format(PA_SAMPLE_FLOAT32LE),
+            ffi::CUBEB_DEVICE_FMT_F32LE
+        );
+        assert_eq!(
+            pulse_format_to_cubeb_format(PA_SAMPLE_FLOAT32BE),
+            ffi::CUBEB_DEVICE_FMT_F32BE
+        );
+    }
+
+    #[test]
+    fn pulse_format_to_cubeb_format_unsupported() {
+        assert_eq!(
+            pulse_format_to_cubeb_format(PA_SAMPLE_U8),
+            ffi::CUBEB_DEVICE_FMT_F32NE
+        );
+        assert_eq!(
+            pulse_format_to_cubeb_format(PA_SAMPLE_S32LE),
+            ffi::CUBEB_DEVICE_FMT_F32NE
+        );
+    }
+
+    #[test]
+    fn min_rate_clamps_to_default_rate() {
+        assert_eq!(min_rate(48000), MIN_RATE);
+        assert_eq!(min_rate(4000), 4000);
+    }
+
+    #[test]
+    fn usec_to_frames_rounds_down() {
+        assert_eq!(usec_to_frames(0, 48000), 0);
+        assert_eq!(usec_to_frames(PA_USEC_PER_SEC, 48000), 48000);
+        assert_eq!(usec_to_frames(10_000, 44100), 441);
+        assert_eq!(usec_to_frames(1, 44100), 0);
+    }
+
+    #[test]
+    fn latency_range_fixed() {
+        assert_eq!(latency_range_in_frames(20_000, 0, false, 48000), (960, 960));
+        // Configured latency is ignored without the dynamic latency flag.
+        assert_eq!(
+            latency_range_in_frames(20_000, 5_000, false, 48000),
+            (960, 960)
+        );
+    }
+
+    fn port(name: &CStr, description: &CStr, available: i32) -> pa_port_info {
+        pa_port_info {
+            name: name.as_ptr(),
+            description: description.as_ptr(),
+            priority: 0,
+            available,
+        }
+    }
+
+    fn to_strings(props: &[(CString, CString)]) -> Vec<(&str, &str)> {
+        props
+            .iter()
+            .map(|(k, v)| (k.to_str().unwrap(), v.to_str().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn port_properties_none() {
+        assert!(port_properties(&[], None).is_empty());
+    }
+
+    #[test]
+    fn port_properties_lists_available_ports() {
+        let speaker = CString::new("analog-output-speaker").unwrap();
+        let speaker_desc = CString::new("Speakers").unwrap();
+        let headphones = CString::new("analog-output-headphones").unwrap();
+        let headphones_desc = CString::new("Headphones").unwrap();
+        let hdmi = CString::new("hdmi-output-0").unwrap();
+        let hdmi_desc = CString::new("HDMI").unwrap();
+
+        let infos = [
+            port(&speaker, &speaker_desc, PA_PORT_AVAILABLE_UNKNOWN),
+            port(&headphones, &headphones_desc, PA_PORT_AVAILABLE_YES),
+            port(&hdmi, &hdmi_desc, PA_PORT_AVAILABLE_NO),
+        ];
+        let ports: Vec<&pa_port_info> = infos.iter().collect();
+
+        let props = port_properties(&ports, Some(&infos[1]));
+        assert_eq!(
+            to_strings(&props),
+            vec![
+                ("active_port", "analog-output-headphones"),
+                ("active_port_description", "Headphones"),
+                ("port.analog-output-speaker", "Speakers"),
+                ("port.analog-output-headphones", "Headphones"),
+            ]
+        );
+    }
+
+    #[test]
+    fn port_device_name_round_trip() {
+        let sink = CString::new("alsa_output.pci-0000_00_1f.3.analog-stereo").unwrap();
+        let port = CString::new("analog-output-headphones").unwrap();
+        let name = super::super::port_device_name(&sink, &port);
+        assert_eq!(
+            name.to_bytes(),
+            &b"alsa_output.pci-0000_00_1f.3.analog-stereo#analog-output-headphones"[..]
+        );
+        assert_eq!(
+            super::super::split_port_device_name(&name),
+            Some((sink.clone(), port))
+        );
+        assert_eq!(super::super::split_port_device_name(&sink), None);
+    }
+
+    #[test]
+    fn device_properties_point_to_strings() {
+        assert!(DeviceProperties::new(Vec::new()).raw.is_empty());
+
+        let props = DeviceProperties::new(vec![(
+            CString::new(ffi::CUBEB_DEVICE_PROPERTY_BUS).unwrap(),
+            CString::new("usb").unwrap(),
+        )]);
+        assert_eq!(props.raw.len(), 1);
+        unsafe {
+            assert_eq!(CStr::from_ptr(props.raw[0].key).to_bytes(), b"bus");
+            assert_eq!(CStr::from_ptr(props.raw[0].value).to_bytes(), b"usb");
+        }
+    }
+
+    #[test]
+    fn latency_range_dynamic() {
+        assert_eq!(
+            latency_range_in_frames(20_000, 5_000, true, 48000),
+            (240, 960)
+        );
+        assert_eq!(
+            latency_range_in_frames(5_000, 20_000, true, 48000),
+            (240, 960)
+        );
+        // Idle dynamic latency device.
+        assert_eq!(latency_range_in_frames(20_000, 0, true, 48000), (960, 960));
+    }
+
+    // Needs a running server, e.g. `pulseaudio -n --load=module-null-sink`
+    // with the null sink's monitor as default source. Run it with
+    // `cargo test conformance_on_server -- --ignored`.
+    #[test]
+    #[ignore]
+    fn conformance_on_server() {
+        use cubeb_backend::conformance::{self, Config};
+        if PulseContext::init(None).is_err() {
+            panic!("no PulseAudio server to run the conformance suite on");
+        }
+        let config = Config {
+            duplex: true,
+            ..Config::default()
+        };
+        if let Err(failures) = conformance::run::<PulseContext>(&config) {
+            panic!("{:#?}", failures);
+        }
+    }
+}
@@ -7,14 +7,20 @@ mod context;
 mod cork_state;
 mod intern;
 mod stream;
+mod stream_proplist;
 
 pub use self::context::PulseContext;
 use self::intern::Intern;
 pub use self::stream::Device;
 pub use self::stream::PulseStream;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 
+// Ports of sinks and sources with several of them are exposed as devices of
+// their own, named `<device>#<port>`. PulseAudio object names never contain
+// the separator.
+const PORT_SEPARATOR: u8 = b'#';
+
 // helper to convert *const c_char to Option<CStr>
 fn try_cstr_from<'str>(s: *const c_char) -> Option<&'str CStr> {
     if s.is_null() {
@@ -23,3 +29,21 @@ fn try_cstr_from<'str>(s: *const c_char) -> Option<&'str CStr> {
         Some(unsafe { CStr::from_ptr(s) })
     }
 }
+
+fn port_device_name(device: &CStr, port: &CStr) -> CString {
+    let mut name = device.to_bytes().to_vec();
+    name.push(PORT_SEPARATOR);
+    name.extend_from_slice(port.to_bytes());
+    CString::new(name).unwrap()
+}
+
+// Splits a device name built by `port_device_name` into the sink or source
+// name and the port name.
+fn split_port_device_name(name: &CStr) -> Option<(CString, CString)> {
+    let name = name.to_bytes();
+    let pos = name.iter().position(|&b| b == PORT_SEPARATOR)?;
+    Some((
+        CString::new(&name[..pos]).unwrap(),
+        CString::new(&name[pos + 1..]).unwrap(),
+    ))
+}
@@ -5,23 +5,25 @@
 
 use backend::cork_state::CorkState;
 use backend::*;
+use cubeb_backend::byte_order::{self, ByteOrderConverter};
+use cubeb_backend::duplex::DuplexBuffer;
+use cubeb_backend::latency::LatencyController;
+use cubeb_backend::log::enter_log_id;
+use cubeb_backend::rt_check::enter_audio_callback;
+use cubeb_backend::unwind::catch;
 use cubeb_backend::{
-    ffi, log_enabled, ChannelLayout, DeviceId, DeviceRef, Error, Result, SampleFormat, StreamOps,
-    StreamParamsRef, StreamPrefs,
+    ffi, log_enabled, CallbackResult, ChannelLayout, DeviceId, DeviceRef, DeviceType, Error, LogId,
+    Result, SampleFormat, StreamOps, StreamOptionsRef, StreamParamsRef, StreamPrefs,
 };
 use pulse::{self, CVolumeExt, ChannelMapExt, SampleSpecExt, StreamLatency, USecExt};
 use pulse_ffi::*;
-use ringbuf::RingBuffer;
+use std::cell::RefCell;
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_long, c_void};
 use std::slice;
 use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
 use std::{mem, ptr};
 
-use self::LinearInputBuffer::*;
-use self::RingBufferConsumer::*;
-use self::RingBufferProducer::*;
-
 const PULSE_NO_GAIN: f32 = -1.0;
 
 /// Iterator interface to `ChannelLayout`.
@@ -125,143 +127,6 @@ impl Drop for Device {
     }
 }
 
-enum RingBufferConsumer {
-    IntegerRingBufferConsumer(ringbuf::Consumer<i16>),
-    FloatRingBufferConsumer(ringbuf::Consumer<f32>),
-}
-
-enum RingBufferProducer {
-    IntegerRingBufferProducer(ringbuf::Producer<i16>),
-    FloatRingBufferProducer(ringbuf::Producer<f32>),
-}
-
-enum LinearInputBuffer {
-    IntegerLinearInputBuffer(Vec<i16>),
-    FloatLinearInputBuffer(Vec<f32>),
-}
-
-struct BufferManager {
-    consumer: RingBufferConsumer,
-    producer: RingBufferProducer,
-    linear_input_buffer: LinearInputBuffer,
-}
-
-impl BufferManager {
-    // When opening a duplex stream, the sample-spec are guaranteed to match. It's ok to have
-    // either the input or output sample-spec here.
-    fn new(input_buffer_size: usize, sample_spec: &pulse::SampleSpec) -> BufferManager {
-        if sample_spec.format == PA_SAMPLE_S16BE || sample_spec.format == PA_SAMPLE_S16LE {
-            let ring = RingBuffer::<i16>::new(input_buffer_size);
-            let (prod, cons) = ring.split();
-            BufferManager {
-                producer: IntegerRingBufferProducer(prod),
-                consumer: IntegerRingBufferConsumer(cons),
-                linear_input_buffer: IntegerLinearInputBuffer(Vec::<i16>::with_capacity(
-                    input_buffer_size,
-                )),
-            }
-        } else {
-            let ring = RingBuffer::<f32>::new(input_buffer_size);
-            let (prod, cons) = ring.split();
-            BufferManager {
-                producer: FloatRingBufferProducer(prod),
-                consumer: FloatRingBufferConsumer(cons),
-                linear_input_buffer: FloatLinearInputBuffer(Vec::<f32>::with_capacity(
-                    input_buffer_size,
-                )),
-            }
-        }
-    }
-
-    fn push_input_data(&mut self, input_data: *const c_void, read_samples: usize) {
-        match &mut self.producer {
-            RingBufferProducer::FloatRingBufferProducer(p) => {
-                let input_data =
-                    unsafe { slice::from_raw_parts::<f32>(input_data as *const f32, read_samples) };
-                // we don't do anything in particular if we can't push everything
-                p.push_slice(input_data);
-            }
-            RingBufferProducer::IntegerRingBufferProducer(p) => {
-                let input_data =
-                    unsafe { slice::from_raw_parts::<i16>(input_data as *const i16, read_samples) };
-                p.push_slice(input_data);
-            }
-        }
-    }
-
-    fn pull_input_data(&mut self, input_data: *mut c_void, needed_samples: usize) {
-        match &mut self.consumer {
-            IntegerRingBufferConsumer(p) => {
-                let input: &mut [i16] = unsafe {
-                    slice::from_raw_parts_mut::<i16>(input_data as *mut i16, needed_samples)
-                };
-                let read = p.pop_slice(input);
-                if read < needed_samples {
-                    for i in 0..(needed_samples - read) {
-                        input[read + i] = 0;
-                    }
-                }
-            }
-            FloatRingBufferConsumer(p) => {
-                let input: &mut [f32] = unsafe {
-                    slice::from_raw_parts_mut::<f32>(input_data as *mut f32, needed_samples)
-                };
-                let read = p.pop_slice(input);
-                if read < needed_samples {
-                    for i in 0..(needed_samples - read) {
-                        input[read + i] = 0.;
-                    }
-                }
-            }
-        }
-    }
-
-    fn get_linear_input_data(&mut self, nsamples: usize) -> *const c_void {
-        let p = match &mut self.linear_input_buffer {
-            LinearInputBuffer::IntegerLinearInputBuffer(b) => {
-                b.resize(nsamples, 0);
-                b.as_mut_ptr() as *mut c_void
-            }
-            LinearInputBuffer::FloatLinearInputBuffer(b) => {
-                b.resize(nsamples, 0.);
-                b.as_mut_ptr() as *mut c_void
-            }
-        };
-        self.pull_input_data(p, nsamples);
-
-        p
-    }
-
-    pub fn trim(&mut self, final_size: usize) {
-        match &self.linear_input_buffer {
-            LinearInputBuffer::IntegerLinearInputBuffer(b) => {
-                let length = b.len();
-                assert!(final_size <= length);
-                let nframes_to_pop = length - final_size;
-                self.get_linear_input_data(nframes_to_pop);
-            }
-            LinearInputBuffer::FloatLinearInputBuffer(b) => {
-                let length = b.len();
-                assert!(final_size <= length);
-                let nframes_to_pop = length - final_size;
-                self.get_linear_input_data(nframes_to_pop);
-            }
-        }
-    }
-    pub fn available_samples(&mut self) -> usize {
-        match &self.linear_input_buffer {
-            LinearInputBuffer::IntegerLinearInputBuffer(b) => b.len(),
-            LinearInputBuffer::FloatLinearInputBuffer(b) => b.len(),
-        }
-    }
-}
-
-impl std::fmt::Debug for BufferManager {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "")
-    }
-}
-
 #[repr(C)]
 #[derive(Debug)]
 pub struct PulseStream<'ctx> {
@@ -279,7 +144,13 @@ pub struct PulseStream<'ctx> {
     shutdown: bool,
     volume: f32,
     state: ffi::cubeb_state,
-    input_buffer_manager: Option<BufferManager>,
+    input_buffer_manager: Option<DuplexBuffer>,
+    // The PulseAudio streams are in the native byte order, the user's
+    // frames are converted if the stream format is not.
+    byte_order: Option<ByteOrderConverter>,
+    // Latency requested, in microseconds, while the stream counts in the
+    // latency of the context.
+    requested_latency_usec: Option<u32>,
 }
 
 impl<'ctx> PulseStream<'ctx> {
@@ -291,6 +162,7 @@ impl<'ctx> PulseStream<'ctx> {
         input_stream_params: Option<&StreamParamsRef>,
         output_device: DeviceId,
         output_stream_params: Option<&StreamParamsRef>,
+        options: &StreamOptionsRef,
         latency_frames: u32,
         data_callback: ffi::cubeb_data_callback,
         state_callback: ffi::cubeb_state_callback,
@@ -298,6 +170,7 @@ impl<'ctx> PulseStream<'ctx> {
     ) -> Result<Box<Self>> {
         fn check_error(s: &pulse::Stream, u: *mut c_void) {
             let stm = unsafe { &mut *(u as *mut PulseStream) };
+            let _log_id = enter_log_id(stm.log_id());
             if !s.get_state().is_good() {
                 cubeb_log!("Calling error callback");
                 stm.state_change_callback(ffi::CUBEB_STATE_ERROR);
@@ -305,7 +178,22 @@ impl<'ctx> PulseStream<'ctx> {
             stm.context.mainloop.signal();
         }
 
+        // Runs `f`, the body of a callback from the PulseAudio mainloop,
+        // which must not unwind into libpulse. A panic puts the stream in the
+        // error state.
+        fn catch_callback<F: FnOnce()>(what: &'static str, u: *mut c_void, f: F) {
+            if catch(what, f).is_none() {
+                let stm = unsafe { &mut *(u as *mut PulseStream) };
+                stm.shutdown = true;
+                stm.state_change_callback(ffi::CUBEB_STATE_ERROR);
+            }
+        }
+
         fn read_data(s: &pulse::Stream, nbytes: usize, u: *mut c_void) {
+            catch_callback("pulse read_data", u, || read_input_data(s, nbytes, u));
+        }
+
+        fn read_input_data(s: &pulse::Stream, nbytes: usize, u: *mut c_void) {
             fn read_from_input(
                 s: &pulse::Stream,
                 buffer: *mut *const c_void,
@@ -319,8 +207,10 @@ impl<'ctx> PulseStream<'ctx> {
                 readable_size
             }
 
-            cubeb_alogv!("Input callback buffer size {}", nbytes);
+            let _rt = enter_audio_callback("pulse read_data");
             let stm = unsafe { &mut *(u as *mut PulseStream) };
+            let _log_id = enter_log_id(stm.log_id());
+            cubeb_alogv!("Input callback buffer size {}", nbytes);
             if stm.shutdown {
                 return;
             }
@@ -332,19 +222,23 @@ impl<'ctx> PulseStream<'ctx> {
                 if !read_data.is_null() {
                     let in_frame_size = stm.input_sample_spec.frame_size();
                     let read_frames = read_size / in_frame_size;
-                    let read_samples = read_size / stm.input_sample_spec.sample_size();
 
                     if stm.output_stream.is_some() {
                         // duplex stream: push the input data to the ring buffer.
-                        stm.input_buffer_manager
-                            .as_mut()
-                            .unwrap()
-                            .push_input_data(read_data, read_samples);
+                        unsafe {
+                            stm.input_buffer_manager
+                                .as_mut()
+                                .unwrap()
+                                .push_raw(read_data, read_frames);
+                        }
                     } else {
                         // input/capture only operation. Call callback directly
+                        let stream = stm as *mut _ as *mut _;
                         let got = unsafe {
-                            stm.data_callback.unwrap()(
-                                stm as *mut _ as *mut _,
+                            call_data_callback(
+                                stm.byte_order.as_mut(),
+                                stm.data_callback,
+                                stream,
                                 stm.user_ptr,
                                 read_data,
                                 ptr::null_mut(),
@@ -352,18 +246,17 @@ impl<'ctx> PulseStream<'ctx> {
                             )
                         };
 
-                        if got < 0 || got as usize != read_frames {
+                        let result = CallbackResult::from_raw(got, read_frames as c_long);
+                        if result != CallbackResult::Continue(read_frames) {
                             let _ = s.cancel_write();
                             stm.shutdown = true;
-                            if got < 0 {
-                                unsafe {
-                                    stm.state_callback.unwrap()(
-                                        stm as *mut _ as *mut _,
-                                        stm.user_ptr,
-                                        ffi::CUBEB_STATE_ERROR,
-                                    );
-                                }
-                            }
+                            // Nothing to play out: an input-only stream
+                            // drains as soon as the callback asks to.
+                            stm.state_change_callback(if result == CallbackResult::Error {
+                                ffi::CUBEB_STATE_ERROR
+                            } else {
+                                ffi::CUBEB_STATE_DRAINED
+                            });
                             break;
                         }
                     }
@@ -380,8 +273,14 @@ impl<'ctx> PulseStream<'ctx> {
         }
 
         fn write_data(_: &pulse::Stream, nbytes: usize, u: *mut c_void) {
-            cubeb_alogv!("Output callback to be written buffer size {}", nbytes);
+            catch_callback("pulse write_data", u, || write_output_data(nbytes, u));
+        }
+
+        fn write_output_data(nbytes: usize, u: *mut c_void) {
+            let _rt = enter_audio_callback("pulse write_data");
             let stm = unsafe { &mut *(u as *mut PulseStream) };
+            let _log_id = enter_log_id(stm.log_id());
+            cubeb_alogv!("Output callback to be written buffer size {}", nbytes);
             if stm.shutdown || stm.state != ffi::CUBEB_STATE_STARTED {
                 return;
             }
@@ -389,22 +288,19 @@ impl<'ctx> PulseStream<'ctx> {
             let nframes = nbytes / stm.output_sample_spec.frame_size();
             let first_callback = stm.output_frame_count.fetch_add(nframes, Ordering::SeqCst) == 0;
             if stm.input_stream.is_some() {
-                let nsamples_input = nframes * stm.input_sample_spec.channels as usize;
                 let input_buffer_manager = stm.input_buffer_manager.as_mut().unwrap();
 
                 if first_callback {
-                    let buffered_input_frames = input_buffer_manager.available_samples()
-                        / stm.input_sample_spec.channels as usize;
+                    let buffered_input_frames = input_buffer_manager.buffered_frames();
                     if buffered_input_frames > nframes {
                         // Trim the buffer to ensure minimal roundtrip latency
                         let popped_frames = buffered_input_frames - nframes;
-                        input_buffer_manager
-                            .trim(nframes * stm.input_sample_spec.channels as usize);
+                        input_buffer_manager.trim(nframes);
                         cubeb_alog!("Dropping {} frames in input buffer.", popped_frames);
                     }
                 }
 
-                let p = input_buffer_manager.get_linear_input_data(nsamples_input);
+                let p = input_buffer_manager.pull_raw(nframes);
                 stm.trigger_user_callback(p, nbytes);
             } else {
                 // Output/playback only operation.
@@ -429,31 +325,47 @@ impl<'ctx> PulseStream<'ctx> {
             volume: PULSE_NO_GAIN,
             state: ffi::CUBEB_STATE_ERROR,
             input_buffer_manager: None,
+            byte_order: ByteOrderConverter::with_params(
+                input_stream_params,
+                output_stream_params,
+                8 * latency_frames as usize,
+            ),
+            requested_latency_usec: None,
         });
 
         if let Some(ref context) = stm.context.context {
             stm.context.mainloop.lock();
 
+            let rate = output_stream_params
+                .or(input_stream_params)
+                .map_or(0, |p| p.rate());
+            let latency_usec = stm.add_to_latency_controller(frames_to_usec(latency_frames, rate));
+
             // Setup output stream
             if let Some(stream_params) = output_stream_params {
-                match PulseStream::stream_init(context, stream_params, stream_name) {
+                match PulseStream::stream_init(
+                    context,
+                    stream_params,
+                    options,
+                    stream_name,
+                    stm.context.context_name.as_deref(),
+                ) {
                     Ok(s) => {
                         stm.output_sample_spec = *s.get_sample_spec();
 
                         s.set_state_callback(check_error, stm.as_mut() as *mut _ as *mut _);
                         s.set_write_callback(write_data, stm.as_mut() as *mut _ as *mut _);
 
-                        let buffer_size_bytes =
-                            latency_frames * stm.output_sample_spec.frame_size() as u32;
-
-                        let battr = pa_buffer_attr {
-                            maxlength: u32::max_value(),
-                            prebuf: u32::max_value(),
-                            fragsize: u32::max_value(),
-                            tlength: buffer_size_bytes * 2,
-                            minreq: buffer_size_bytes / 4,
-                        };
+                        let battr = output_buffer_attr(&stm.output_sample_spec, latency_usec);
                         let device_name = super::try_cstr_from(output_device as *const _);
+                        let device_port = device_name.and_then(super::split_port_device_name);
+                        if let Some((ref device, ref port)) = device_port {
+                            select_port(stm.context, context, device, port, DeviceType::OUTPUT);
+                        }
+                        let device_name = match device_port {
+                            Some((ref device, _)) => Some(device.as_c_str()),
+                            None => device_name,
+                        };
                         let mut stream_flags = pulse::StreamFlags::AUTO_TIMING_UPDATE
                             | pulse::StreamFlags::INTERPOLATE_TIMING
                             | pulse::StreamFlags::START_CORKED
@@ -480,23 +392,29 @@ impl<'ctx> PulseStream<'ctx> {
 
             // Set up input stream
             if let Some(stream_params) = input_stream_params {
-                match PulseStream::stream_init(context, stream_params, stream_name) {
+                match PulseStream::stream_init(
+                    context,
+                    stream_params,
+                    options,
+                    stream_name,
+                    stm.context.context_name.as_deref(),
+                ) {
                     Ok(s) => {
                         stm.input_sample_spec = *s.get_sample_spec();
 
                         s.set_state_callback(check_error, stm.as_mut() as *mut _ as *mut _);
                         s.set_read_callback(read_data, stm.as_mut() as *mut _ as *mut _);
 
-                        let buffer_size_bytes =
-                            latency_frames * stm.input_sample_spec.frame_size() as u32;
-                        let battr = pa_buffer_attr {
-                            maxlength: u32::max_value(),
-                            prebuf: u32::max_value(),
-                            fragsize: buffer_size_bytes,
-                            tlength: buffer_size_bytes,
-                            minreq: buffer_size_bytes,
-                        };
+                        let battr = input_buffer_attr(&stm.input_sample_spec, latency_usec);
                         let device_name = super::try_cstr_from(input_device as *const _);
+                        let device_port = device_name.and_then(super::split_port_device_name);
+                        if let Some((ref device, ref port)) = device_port {
+                            select_port(stm.context, context, device, port, DeviceType::INPUT);
+                        }
+                        let device_name = match device_port {
+                            Some((ref device, _)) => Some(device.as_c_str()),
+                            None => device_name,
+                        };
                         let mut stream_flags = pulse::StreamFlags::AUTO_TIMING_UPDATE
                             | pulse::StreamFlags::INTERPOLATE_TIMING
                             | pulse::StreamFlags::START_CORKED
@@ -522,14 +440,14 @@ impl<'ctx> PulseStream<'ctx> {
             }
 
             // Duplex, set up the ringbuffer
-            if input_stream_params.is_some() && output_stream_params.is_some() {
-                // A bit more room in case of output underrun.
-                let buffer_size_bytes =
-                    2 * latency_frames * stm.input_sample_spec.frame_size() as u32;
-                stm.input_buffer_manager = Some(BufferManager::new(
-                    buffer_size_bytes as usize,
-                    &stm.input_sample_spec,
-                ))
+            if let (Some(params), Some(_)) = (input_stream_params, output_stream_params) {
+                let mut input_buffer = input_buffer(&stm.input_sample_spec, latency_frames);
+                // Only the caller knows whether the input and output devices
+                // run off different clocks.
+                if params.prefs().contains(StreamPrefs::DRIFT_CORRECTION) {
+                    input_buffer.correct_drift(stm.input_sample_spec.rate);
+                }
+                stm.input_buffer_manager = Some(input_buffer);
             }
 
             let r = if stm.wait_until_ready() {
@@ -581,11 +499,59 @@ impl<'ctx> PulseStream<'ctx> {
         Ok(stm)
     }
 
+    // Adds the stream, requesting `latency_usec`, to the latency controller
+    // of the context, and returns the latency to run at. The other streams
+    // move to it if it changed. Called with the mainloop lock held.
+    fn add_to_latency_controller(&mut self, latency_usec: u32) -> u32 {
+        let context = self.context;
+        let latency = add_latency_request(&context.latency_controller, latency_usec, |latency| {
+            set_streams_latency(context, latency)
+        });
+        self.requested_latency_usec = Some(latency_usec);
+        self.context
+            .streams
+            .borrow_mut()
+            .push(self as *mut _ as *mut c_void);
+        latency
+    }
+
+    // Removes the stream from the latency controller of the context, if it
+    // was added. Called with the mainloop lock held.
+    fn remove_from_latency_controller(&mut self) {
+        if let Some(latency_usec) = self.requested_latency_usec.take() {
+            let stm = self as *mut _ as *mut c_void;
+            self.context.streams.borrow_mut().retain(|&s| s != stm);
+            let context = self.context;
+            remove_latency_request(&context.latency_controller, latency_usec, |latency| {
+                set_streams_latency(context, latency)
+            });
+        }
+    }
+
+    // Moves the buffer attributes of the stream to `latency_usec`. Called
+    // with the mainloop lock held.
+    fn set_latency(&self, latency_usec: u32) {
+        let u = self as *const _ as *mut c_void;
+        if let Some(ref stm) = self.output_stream {
+            let battr = output_buffer_attr(&self.output_sample_spec, latency_usec);
+            if stm.set_buffer_attr(&battr, stream_success, u).is_err() {
+                cubeb_log!("Error: couldn't set the output buffer attributes");
+            }
+        }
+        if let Some(ref stm) = self.input_stream {
+            let battr = input_buffer_attr(&self.input_sample_spec, latency_usec);
+            if stm.set_buffer_attr(&battr, stream_success, u).is_err() {
+                cubeb_log!("Error: couldn't set the input buffer attributes");
+            }
+        }
+    }
+
     fn destroy(&mut self) {
         self.cork(CorkState::cork());
 
         self.context.mainloop.lock();
         {
+            self.remove_from_latency_controller();
             if let Some(stm) = self.output_stream.take() {
                 let drain_timer = self.drain_timer.load(Ordering::Acquire);
                 if !drain_timer.is_null() {
@@ -850,21 +816,15 @@ impl<'ctx> StreamOps for PulseStream<'ctx> {
             Ok(())
         }
     }
-
-    fn register_device_changed_callback(
-        &mut self,
-        _: ffi::cubeb_device_changed_callback,
-    ) -> Result<()> {
-        cubeb_log!("Error: register_device_change_callback unimplemented");
-        Err(Error::error())
-    }
 }
 
 impl<'ctx> PulseStream<'ctx> {
     fn stream_init(
         context: &pulse::Context,
         stream_params: &StreamParamsRef,
+        options: &StreamOptionsRef,
         stream_name: Option<&CStr>,
+        application_name: Option<&CStr>,
     ) -> Result<pulse::Stream> {
         if stream_params.prefs() == StreamPrefs::LOOPBACK {
             cubeb_log!("Error: StreamPref::LOOPBACK unimplemented");
@@ -881,7 +841,7 @@ impl<'ctx> PulseStream<'ctx> {
             }
         }
 
-        let fmt = to_pulse_format(stream_params.format());
+        let fmt = to_pulse_format(byte_order::native(stream_params.format()));
         if fmt == pulse::SampleFormat::Invalid {
             cubeb_log!("Error: invalid sample format");
             return Err(invalid_format());
@@ -914,7 +874,29 @@ impl<'ctx> PulseStream<'ctx> {
             _ => Some(layout_to_channel_map(stream_params.layout())),
         };
 
-        let stream = pulse::Stream::new(context, stream_name.unwrap(), &ss, cm.as_ref());
+        let proplist = match pulse::Proplist::new() {
+            Some(p) => p,
+            None => {
+                cubeb_log!("Error: pulse::Proplist::new failure");
+                return Err(Error::error());
+            }
+        };
+        let application_name = application_name.and_then(|s| s.to_str().ok());
+        for (key, value) in
+            stream_proplist::stream_properties(stream_params, options, application_name)
+        {
+            if proplist.sets(key, value).is_err() {
+                cubeb_log!("Ignoring invalid stream property {}={}", key, value);
+            }
+        }
+
+        let stream = pulse::Stream::new_with_proplist(
+            context,
+            stream_name.unwrap(),
+            &ss,
+            cm.as_ref(),
+            &proplist,
+        );
 
         match stream {
             None => {
@@ -976,6 +958,12 @@ impl<'ctx> PulseStream<'ctx> {
         r
     }
 
+    // Tags log messages from PulseAudio's threads with the address handed
+    // out as the `cubeb_stream`, as `capi` does for calls into the stream.
+    fn log_id(&self) -> LogId {
+        LogId::new("stream", self)
+    }
+
     pub fn state_change_callback(&mut self, s: ffi::cubeb_state) {
         self.state = s;
         unsafe {
@@ -1066,27 +1054,35 @@ impl<'ctx> PulseStream<'ctx> {
                             read_offset
                         );
                         let read_ptr = unsafe { (input_data as *const u8).add(read_offset) };
-                        let mut got = unsafe {
-                            self.data_callback.unwrap()(
-                                self as *const _ as *mut _,
+                        let nframes = (size / frame_size) as c_long;
+                        let stream = self as *const _ as *mut _;
+                        let ret = unsafe {
+                            call_data_callback(
+                                self.byte_order.as_mut(),
+                                self.data_callback,
+                                stream,
                                 self.user_ptr,
-                                read_ptr as *const _ as *mut _,
+                                read_ptr as *const _,
                                 buffer,
-                                (size / frame_size) as c_long,
-                            ) as i64
+                                nframes,
+                            )
                         };
-                        if got < 0 {
-                            let _ = stm.cancel_write();
-                            self.shutdown = true;
-                            unsafe {
-                                self.state_callback.unwrap()(
-                                    self as *const _ as *mut _,
-                                    self.user_ptr,
-                                    ffi::CUBEB_STATE_ERROR,
-                                );
+                        let (mut got, should_drain) = match CallbackResult::from_raw(ret, nframes) {
+                            CallbackResult::Continue(n) => (n as i64, false),
+                            CallbackResult::Drain(n) => (n as i64, true),
+                            CallbackResult::Error => {
+                                let _ = stm.cancel_write();
+                                self.shutdown = true;
+                                unsafe {
+                                    self.state_callback.unwrap()(
+                                        self as *const _ as *mut _,
+                                        self.user_ptr,
+                                        ffi::CUBEB_STATE_ERROR,
+                                    );
+                                }
+                                return;
                             }
-                            return;
-                        }
+                        };
 
                         // If more iterations move offset of read buffer
                         if !input_data.is_null() {
@@ -1113,8 +1109,6 @@ impl<'ctx> PulseStream<'ctx> {
                             }
                         }
 
-                        let should_drain = (got as usize) < size / frame_size;
-
                         if should_drain && self.output_frame_count.load(Ordering::SeqCst) == 0 {
                             // Draining during preroll, ensure `prebuf` frames are written so
                             // the stream starts. If not, pad with a bit of silence.
@@ -1210,14 +1204,225 @@ fn context_success(_: &pulse::Context, success: i32, u: *mut c_void) {
     ctx.mainloop.signal();
 }
 
+// Makes `port` the active port of the sink or source `device`, so that a
+// stream connected to `device` ends up on that port.
+fn select_port(
+    ctx: &PulseContext,
+    context: &pulse::Context,
+    device: &CStr,
+    port: &CStr,
+    devtype: DeviceType,
+) {
+    let context_ptr = ctx as *const _ as *mut _;
+    let o = if devtype == DeviceType::OUTPUT {
+        context.set_sink_port_by_name(device, port, context_success, context_ptr)
+    } else {
+        context.set_source_port_by_name(device, port, context_success, context_ptr)
+    };
+    match o {
+        Ok(o) => {
+            ctx.operation_wait(None, &o);
+        }
+        Err(_) => {
+            cubeb_log!("Error: failed to select port {:?} on {:?}", port, device);
+        }
+    }
+}
+
+// Calls the data callback, with frames converted by `byte_order` if the
+// stream is not in the native byte order.
+unsafe fn call_data_callback(
+    byte_order: Option<&mut ByteOrderConverter>,
+    data_callback: ffi::cubeb_data_callback,
+    stream: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    input: *const c_void,
+    output: *mut c_void,
+    frames: c_long,
+) -> c_long {
+    match byte_order {
+        Some(converter) => {
+            converter.data_callback(data_callback, stream, user_ptr, input, output, frames)
+        }
+        None => data_callback.unwrap()(stream, user_ptr, input, output, frames),
+    }
+}
+
 fn invalid_format() -> Error {
     Error::from_raw(ffi::CUBEB_ERROR_INVALID_FORMAT)
 }
 
+// When opening a duplex stream, the sample-spec are guaranteed to match. It's
+// ok to have either the input or output sample-spec here.
+fn input_buffer(sample_spec: &pulse::SampleSpec, latency_frames: u32) -> DuplexBuffer {
+    let format = if sample_spec.format == PA_SAMPLE_S16BE || sample_spec.format == PA_SAMPLE_S16LE {
+        SampleFormat::S16NE
+    } else {
+        SampleFormat::Float32NE
+    };
+    let channels = sample_spec.channels as usize;
+    // Room for a few callbacks in a row, in case of output underrun.
+    DuplexBuffer::new(format, 8 * latency_frames as usize, channels, 0, channels)
+}
+
+// Adds a stream requesting `latency_usec` to `controller`, and returns the
+// latency to run at. `move_streams` is called with it if the streams already
+// added have to move.
+fn add_latency_request<F: FnOnce(u32)>(
+    controller: &RefCell<LatencyController>,
+    latency_usec: u32,
+    move_streams: F,
+) -> u32 {
+    let update = controller.borrow_mut().add_stream(latency_usec);
+    let latency = update.latency.unwrap();
+    if update.changed {
+        move_streams(latency);
+    }
+    latency
+}
+
+// Removes a stream that requested `latency_usec` from `controller`.
+// `move_streams` is called with the new latency if the remaining streams
+// have to move.
+fn remove_latency_request<F: FnOnce(u32)>(
+    controller: &RefCell<LatencyController>,
+    latency_usec: u32,
+    move_streams: F,
+) {
+    let update = controller.borrow_mut().remove_stream(latency_usec);
+    if update.changed {
+        move_streams(update.latency.unwrap());
+    }
+}
+
+// Moves the streams of `ctx` to `latency_usec`.
+fn set_streams_latency(ctx: &PulseContext, latency_usec: u32) {
+    let streams = ctx.streams.borrow();
+    cubeb_log!(
+        "Moving {} streams to a latency of {}us",
+        streams.len(),
+        latency_usec
+    );
+    for &stm in streams.iter() {
+        let stm = unsafe { &*(stm as *const PulseStream) };
+        stm.set_latency(latency_usec);
+    }
+}
+
+// Rounds up, so that converting back at the same rate gives `frames`.
+fn frames_to_usec(frames: u32, rate: u32) -> u32 {
+    if rate == 0 {
+        return 0;
+    }
+    (u64::from(frames) * 1_000_000).div_ceil(u64::from(rate)) as u32
+}
+
+fn usec_to_frames(usec: u32, rate: u32) -> u32 {
+    (u64::from(usec) * u64::from(rate) / 1_000_000) as u32
+}
+
+fn output_buffer_attr(spec: &pulse::SampleSpec, latency_usec: u32) -> pa_buffer_attr {
+    let buffer_size_bytes = usec_to_frames(latency_usec, spec.rate) * spec.frame_size() as u32;
+    pa_buffer_attr {
+        maxlength: u32::max_value(),
+        prebuf: u32::max_value(),
+        fragsize: u32::max_value(),
+        tlength: buffer_size_bytes * 2,
+        minreq: buffer_size_bytes / 4,
+    }
+}
+
+fn input_buffer_attr(spec: &pulse::SampleSpec, latency_usec: u32) -> pa_buffer_attr {
+    let buffer_size_bytes = usec_to_frames(latency_usec, spec.rate) * spec.frame_size() as u32;
+    pa_buffer_attr {
+        maxlength: u32::max_value(),
+        prebuf: u32::max_value(),
+        fragsize: buffer_size_bytes,
+        tlength: buffer_size_bytes,
+        minreq: buffer_size_bytes,
+    }
+}
+
 fn not_supported() -> Error {
     Error::from_raw(ffi::CUBEB_ERROR_NOT_SUPPORTED)
 }
 
+#[cfg(all(test, debug_assertions))]
+mod buffer_manager_tests {
+    use super::input_buffer;
+    use cubeb_backend::duplex::assert_rt_safe;
+    use pulse_ffi::*;
+
+    fn sample_spec(format: pa_sample_format_t) -> pa_sample_spec {
+        pa_sample_spec {
+            format,
+            rate: 48000,
+            channels: 2,
+        }
+    }
+
+    // The buffer has room for 8 times `latency_frames`.
+    #[test]
+    fn test_buffer_manager_rt_safe() {
+        for format in [PA_SAMPLE_S16LE, PA_SAMPLE_FLOAT32LE] {
+            assert_rt_safe(input_buffer(&sample_spec(format), 128), 8 * 128);
+        }
+    }
+}
+
+#[cfg(test)]
+mod latency_tests {
+    use super::{add_latency_request, frames_to_usec, remove_latency_request, usec_to_frames};
+    use backend::context::new_latency_controller;
+    use std::cell::RefCell;
+
+    // A stream on its own runs at the latency it asked for, through the
+    // microseconds the context shares.
+    #[test]
+    fn test_latency_keeps_frames() {
+        for &rate in &[8000, 22050, 44100, 48000, 96000, 192000] {
+            for &frames in &[1, 127, 256, 441, 512, 4096, 96000] {
+                let usec = frames_to_usec(frames, rate);
+                assert_eq!(
+                    usec_to_frames(usec, rate),
+                    frames,
+                    "{} at {}Hz",
+                    frames,
+                    rate
+                );
+            }
+        }
+    }
+
+    // Streams joining and leaving the context move the others to the
+    // smallest latency requested, and only when it changes.
+    #[test]
+    fn test_streams_latency() {
+        let controller = RefCell::new(new_latency_controller());
+        let mut moves = Vec::new();
+        assert_eq!(
+            add_latency_request(&controller, 40_000, |l| moves.push(l)),
+            40_000
+        );
+        assert_eq!(
+            add_latency_request(&controller, 10_000, |l| moves.push(l)),
+            10_000
+        );
+        assert_eq!(
+            add_latency_request(&controller, 20_000, |l| moves.push(l)),
+            10_000
+        );
+        assert_eq!(moves, [10_000]);
+
+        remove_latency_request(&controller, 10_000, |l| moves.push(l));
+        assert_eq!(moves, [10_000, 20_000]);
+        remove_latency_request(&controller, 40_000, |l| moves.push(l));
+        assert_eq!(moves, [10_000, 20_000]);
+        remove_latency_request(&controller, 20_000, |l| moves.push(l));
+        assert_eq!(moves, [10_000, 20_000]);
+    }
+}
+
 #[cfg(all(test, not(feature = "pulse-dlopen")))]
 mod test {
     use super::layout_to_channel_map;
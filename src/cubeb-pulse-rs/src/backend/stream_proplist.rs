@@ -0,0 +1,96 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+use cubeb_backend::{StreamOptionsRef, StreamParamsRef, StreamPrefs};
+
+// Properties to set on the proplist of a new stream. Voice streams get the
+// `phone` media role, which role based policies (ducking, routing,
+// module-role-cork) key off. Caller supplied properties, e.g.
+// `application.icon_name`, come last so they override the ones derived from
+// the stream parameters.
+pub fn stream_properties<'a>(
+    params: &StreamParamsRef,
+    options: &'a StreamOptionsRef,
+    application_name: Option<&'a str>,
+) -> Vec<(&'a str, &'a str)> {
+    let mut props = Vec::new();
+    if let Some(name) = application_name {
+        props.push(("application.name", name));
+    }
+    if params.prefs().contains(StreamPrefs::VOICE) {
+        props.push(("media.role", "phone"));
+        props.push(("filter.want", "echo-cancel"));
+    }
+    props.extend(options.properties());
+    props
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cubeb_backend::ffi;
+    use cubeb_backend::{StreamOptions, StreamParams};
+
+    fn params(prefs: StreamPrefs) -> StreamParams {
+        StreamParams::from(ffi::cubeb_stream_params {
+            prefs: prefs.bits(),
+            ..Default::default()
+        })
+    }
+
+    fn options(properties: &[ffi::cubeb_property]) -> StreamOptions {
+        StreamOptions::from(ffi::cubeb_stream_options {
+            properties: properties.as_ptr(),
+            property_count: properties.len(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_stream_properties_default() {
+        let params = params(StreamPrefs::NONE);
+        let options = options(&[]);
+        assert_eq!(stream_properties(&params, &options, None), vec![]);
+        assert_eq!(
+            stream_properties(&params, &options, Some("Firefox")),
+            vec![("application.name", "Firefox")]
+        );
+    }
+
+    #[test]
+    fn test_stream_properties_voice() {
+        let params = params(StreamPrefs::VOICE | StreamPrefs::DISABLE_DEVICE_SWITCHING);
+        assert_eq!(
+            stream_properties(&params, &options(&[]), None),
+            vec![("media.role", "phone"), ("filter.want", "echo-cancel")]
+        );
+    }
+
+    #[test]
+    fn test_stream_properties_caller_last() {
+        let props = [
+            ffi::cubeb_property {
+                key: b"media.role\0".as_ptr() as *const _,
+                value: b"video\0".as_ptr() as *const _,
+            },
+            ffi::cubeb_property {
+                key: b"application.icon_name\0".as_ptr() as *const _,
+                value: b"firefox\0".as_ptr() as *const _,
+            },
+        ];
+        let params = params(StreamPrefs::VOICE);
+        let options = options(&props);
+        assert_eq!(
+            stream_properties(&params, &options, Some("Firefox")),
+            vec![
+                ("application.name", "Firefox"),
+                ("media.role", "phone"),
+                ("filter.want", "echo-cancel"),
+                ("media.role", "video"),
+                ("application.icon_name", "firefox"),
+            ]
+        );
+    }
+}
@@ -9,10 +9,14 @@
 extern crate cubeb_backend;
 extern crate pulse;
 extern crate pulse_ffi;
-extern crate ringbuf;
 extern crate semver;
 
 mod backend;
 mod capi;
 
 pub use capi::pulse_rust_init;
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOC: cubeb_backend::rt_check::RtCheckAllocator =
+    cubeb_backend::rt_check::RtCheckAllocator::new(std::alloc::System);
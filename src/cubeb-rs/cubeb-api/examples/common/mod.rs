@@ -1,32 +1,13 @@
-use cubeb::{Context, Result};
-use std::env;
-use std::ffi::CString;
-use std::io::{self, Write};
+use cubeb::{Context, ContextBuilder, Result};
 
 pub fn init<T: Into<Vec<u8>>>(ctx_name: T) -> Result<Context> {
-    let backend = match env::var("CUBEB_BACKEND") {
-        Ok(s) => Some(s),
-        Err(_) => None,
-    };
-
-    let ctx_name = CString::new(ctx_name).unwrap();
-    let ctx = Context::init(Some(ctx_name.as_c_str()), None);
-    if let Ok(ref ctx) = ctx {
-        if let Some(ref backend) = backend {
-            let ctx_backend = ctx.backend_id();
-            if backend != ctx_backend {
-                let stderr = io::stderr();
-                let mut handle = stderr.lock();
-
-                writeln!(
-                    handle,
-                    "Requested backend `{}', got `{}'",
-                    backend, ctx_backend
-                )
-                .unwrap();
-            }
-        }
+    // Backends listed in CUBEB_BACKEND are tried first.
+    let selection = ContextBuilder::new(ctx_name).select();
+    for failure in &selection.failures {
+        eprintln!(
+            "Requested backend `{}' not used: {:?}",
+            failure.backend, failure.reason
+        );
     }
-
-    ctx
+    selection.context
 }
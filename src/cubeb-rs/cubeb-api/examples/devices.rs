@@ -88,6 +88,14 @@ fn print_device_info(info: &cubeb::DeviceInfo) {
     );
 }
 
+fn print_device_properties(devices: &cubeb::DeviceCollection, index: usize) {
+    if let Ok(properties) = devices.properties(index) {
+        for (key, value) in properties {
+            println!("\t{}: \"{}\"", key, value);
+        }
+    }
+}
+
 fn main() {
     let ctx = common::init("Cubeb audio test").expect("Failed to create cubeb context");
 
@@ -106,8 +114,9 @@ fn main() {
     };
 
     println!("Found {} input devices", devices.len());
-    for d in devices.iter() {
+    for (i, d) in devices.iter().enumerate() {
         print_device_info(d);
+        print_device_properties(&devices, i);
     }
 
     println!(
@@ -124,7 +133,8 @@ fn main() {
     };
 
     println!("Found {} output devices", devices.len());
-    for d in devices.iter() {
+    for (i, d) in devices.iter().enumerate() {
         print_device_info(d);
+        print_device_properties(&devices, i);
     }
 }
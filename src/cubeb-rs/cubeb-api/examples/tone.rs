@@ -46,7 +46,7 @@ fn main() {
 
                 position += 1;
             }
-            output.len() as isize
+            cubeb::CallbackResult::Continue(output.len())
         })
         .state_callback(|state| {
             println!("stream {:?}", state);
@@ -1,5 +1,6 @@
-use std::ffi::CString;
-use {Context, Result};
+use std::env;
+use std::ffi::{CStr, CString};
+use {Context, Error, Result};
 
 /// Initialize a new cubeb [`Context`]
 pub fn init<T: Into<Vec<u8>>>(name: T) -> Result<Context> {
@@ -7,3 +8,244 @@ pub fn init<T: Into<Vec<u8>>>(name: T) -> Result<Context> {
 
     Context::init(Some(name.as_c_str()), None)
 }
+
+/// Environment variable overriding the backend preferences of a
+/// [`ContextBuilder`], as a comma-separated list of backend names.
+pub const BACKEND_ENV_VAR: &str = "CUBEB_BACKEND";
+
+/// Why a backend was not chosen by [`ContextBuilder::select`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BackendFailureReason {
+    /// The backend is not built in, or failed to initialize: libcubeb fell
+    /// back to the given backend instead.
+    Unavailable(String),
+    /// No backend could be initialized.
+    Error(Error),
+}
+
+/// A backend [`ContextBuilder::select`] tried and did not choose.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackendFailure {
+    pub backend: String,
+    pub reason: BackendFailureReason,
+}
+
+/// Outcome of [`ContextBuilder::select`].
+pub struct BackendSelection {
+    /// The context of the chosen backend, or the error of the last attempt.
+    pub context: Result<Context>,
+    /// The backends tried and not chosen, in order.
+    pub failures: Vec<BackendFailure>,
+}
+
+/// Initialize a cubeb [`Context`] on the first of a list of preferred
+/// backends that works.
+///
+/// The list is overridden by the [`BACKEND_ENV_VAR`] environment variable
+/// when it is set. Once the list is exhausted, libcubeb's own choice is
+/// used, unless `fallback(false)` is given.
+///
+/// ```no_run
+/// let selection = cubeb::ContextBuilder::new("Cubeb example")
+///     .backends(&["pulse-rust", "pulse", "alsa"])
+///     .select();
+/// for failure in &selection.failures {
+///     println!("{} not used: {:?}", failure.backend, failure.reason);
+/// }
+/// let ctx = selection.context.expect("Failed to create cubeb context");
+/// println!("Using backend {}", ctx.backend_id());
+/// ```
+pub struct ContextBuilder {
+    name: CString,
+    backends: Vec<String>,
+    env_var: Option<String>,
+    fallback: bool,
+}
+
+impl ContextBuilder {
+    /// # Panics
+    ///
+    /// If `name` contains a nul byte.
+    pub fn new<T: Into<Vec<u8>>>(name: T) -> ContextBuilder {
+        ContextBuilder {
+            name: CString::new(name).expect("context name contains a nul byte"),
+            backends: Vec::new(),
+            env_var: Some(BACKEND_ENV_VAR.to_owned()),
+            fallback: true,
+        }
+    }
+
+    /// Backends to try, in order of preference, by their `backend_id`.
+    pub fn backends(&mut self, backends: &[&str]) -> &mut Self {
+        self.backends = backends.iter().map(|&b| b.to_owned()).collect();
+        self
+    }
+
+    /// Environment variable overriding the preferred backends, `None` to
+    /// ignore the environment. Defaults to [`BACKEND_ENV_VAR`].
+    pub fn env_var(&mut self, var: Option<&str>) -> &mut Self {
+        self.env_var = var.map(str::to_owned);
+        self
+    }
+
+    /// Whether to let libcubeb choose a backend when none of the preferred
+    /// ones can be initialized. Defaults to `true`.
+    pub fn fallback(&mut self, fallback: bool) -> &mut Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Initialize a context on the first preferred backend that works.
+    pub fn init(&self) -> Result<Context> {
+        self.select().context
+    }
+
+    /// Same as [`init`](Self::init), also reporting why the backends
+    /// before the chosen one were not used.
+    pub fn select(&self) -> BackendSelection {
+        self.select_with(Context::init)
+    }
+
+    // The preferred backends, from the environment if set there.
+    fn preferences(&self) -> Vec<String> {
+        let from_env = self.env_var.as_ref().and_then(|var| env::var(var).ok());
+        match from_env {
+            Some(list) => list
+                .split(',')
+                .map(str::trim)
+                .filter(|b| !b.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            None => self.backends.clone(),
+        }
+    }
+
+    fn select_with<I>(&self, mut init: I) -> BackendSelection
+    where
+        I: FnMut(Option<&CStr>, Option<&CStr>) -> Result<Context>,
+    {
+        let name = Some(self.name.as_c_str());
+        let mut failures = Vec::new();
+        let mut last_error = Error::error();
+        for backend in self.preferences() {
+            let reason = match CString::new(backend.as_str()) {
+                Err(_) => BackendFailureReason::Error(Error::invalid_parameter()),
+                // libcubeb falls back to its own choice when the backend
+                // asked for does not work, which is only visible from the
+                // backend of the context.
+                Ok(backend_name) => match init(name, Some(backend_name.as_c_str())) {
+                    Ok(context) => {
+                        if context.backend_id() == backend {
+                            return BackendSelection {
+                                context: Ok(context),
+                                failures,
+                            };
+                        }
+                        BackendFailureReason::Unavailable(context.backend_id().to_owned())
+                    }
+                    Err(e) => BackendFailureReason::Error(e),
+                },
+            };
+            if let BackendFailureReason::Error(e) = reason {
+                last_error = e;
+            }
+            failures.push(BackendFailure { backend, reason });
+        }
+        let context = if self.fallback {
+            init(name, None)
+        } else {
+            Err(last_error)
+        };
+        BackendSelection { context, failures }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate cubeb_backend;
+
+    use self::cubeb_backend::mock::MockContext;
+    use super::{BackendFailure, BackendFailureReason, ContextBuilder};
+    use std::env;
+    use std::ffi::CStr;
+    use {Context, Error, Result};
+
+    // Behaves like `cubeb_init` with only the mock backend working. Asking
+    // for "none" fails as if no backend worked.
+    fn init(_name: Option<&CStr>, backend: Option<&CStr>) -> Result<Context> {
+        if backend.map(|b| b.to_bytes()) == Some(&b"none"[..]) {
+            return Err(Error::error());
+        }
+        MockContext::init_with_script(Default::default())
+    }
+
+    fn failure(backend: &str, reason: BackendFailureReason) -> BackendFailure {
+        BackendFailure {
+            backend: backend.to_owned(),
+            reason,
+        }
+    }
+
+    #[test]
+    fn select_first_working() {
+        let selection = ContextBuilder::new("test")
+            .env_var(None)
+            .backends(&["broken", "mock", "alsa"])
+            .select_with(init);
+        assert_eq!(selection.context.unwrap().backend_id(), "mock");
+        assert_eq!(
+            selection.failures,
+            [failure(
+                "broken",
+                BackendFailureReason::Unavailable("mock".to_owned())
+            )]
+        );
+    }
+
+    #[test]
+    fn select_fallback() {
+        let selection = ContextBuilder::new("test")
+            .env_var(None)
+            .backends(&["none", "broken"])
+            .select_with(init);
+        assert_eq!(selection.context.unwrap().backend_id(), "mock");
+        assert_eq!(
+            selection.failures,
+            [
+                failure("none", BackendFailureReason::Error(Error::error())),
+                failure(
+                    "broken",
+                    BackendFailureReason::Unavailable("mock".to_owned())
+                ),
+            ]
+        );
+
+        let selection = ContextBuilder::new("test")
+            .env_var(None)
+            .backends(&["broken", "none"])
+            .fallback(false)
+            .select_with(init);
+        assert_eq!(selection.context.err(), Some(Error::error()));
+        assert_eq!(selection.failures.len(), 2);
+    }
+
+    #[test]
+    fn select_env_override() {
+        let var = "CUBEB_TEST_SELECT_ENV_OVERRIDE";
+        env::set_var(var, "broken, mock");
+        let selection = ContextBuilder::new("test")
+            .env_var(Some(var))
+            .backends(&["none"])
+            .fallback(false)
+            .select_with(init);
+        env::remove_var(var);
+        assert_eq!(selection.context.unwrap().backend_id(), "mock");
+        assert_eq!(
+            selection.failures,
+            [failure(
+                "broken",
+                BackendFailureReason::Unavailable("mock".to_owned())
+            )]
+        );
+    }
+}
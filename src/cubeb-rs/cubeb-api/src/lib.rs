@@ -27,11 +27,16 @@ mod stream;
 
 pub use context::*;
 // Re-export cubeb_core types
+#[cfg(feature = "log")]
+pub use cubeb_core::forward_to_log;
+#[cfg(feature = "tracing")]
+pub use cubeb_core::forward_to_tracing;
 pub use cubeb_core::{
-    ffi, ChannelLayout, Context, ContextRef, Device, DeviceCollection, DeviceCollectionRef,
-    DeviceFormat, DeviceId, DeviceInfo, DeviceInfoRef, DeviceRef, DeviceState, DeviceType, Error,
-    ErrorCode, LogLevel, Result, SampleFormat, State, StreamParams, StreamParamsBuilder,
-    StreamParamsRef, StreamPrefs, StreamRef,
+    ffi, CallbackResult, Capabilities, ChannelLayout, ChannelRoute, Context, ContextRef, Device,
+    DeviceCollection, DeviceCollectionRef, DeviceFormat, DeviceId, DeviceInfo, DeviceInfoRef,
+    DeviceRef, DeviceState, DeviceType, Dither, Error, ErrorCode, LogLevel, MixCoefficient,
+    MixMatrix, Properties, Quantizer, Result, SampleFormat, State, StreamOptions, StreamOptionsRef,
+    StreamParams, StreamParamsBuilder, StreamParamsRef, StreamPrefs, StreamRef,
 };
 pub use frame::*;
 pub use sample::*;
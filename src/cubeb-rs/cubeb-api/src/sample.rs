@@ -3,17 +3,30 @@
 // This program is made available under an ISC-style license.  See the
 // accompanying file LICENSE for details.
 
+use cubeb_core::{float_to_i16, Quantizer};
+
 /// An extension trait which allows the implementation of converting
 /// void* buffers from libcubeb-sys into rust slices of the appropriate
 /// type.
 pub trait Sample: Send + Copy {
-    /// Map f32 in range [-1,1] to sample type
+    /// Map f32 in range [-1,1] to sample type. Values out of range
+    /// saturate.
     fn from_float(_: f32) -> Self;
+
+    /// As `from_float`, for sample `channel` of a frame, quantized by
+    /// `quantizer` when the sample type is an integer.
+    fn from_float_dithered(x: f32, _quantizer: &mut Quantizer, _channel: usize) -> Self {
+        Self::from_float(x)
+    }
 }
 
 impl Sample for i16 {
     fn from_float(x: f32) -> i16 {
-        (x * f32::from(i16::max_value())) as i16
+        float_to_i16(x)
+    }
+
+    fn from_float_dithered(x: f32, quantizer: &mut Quantizer, channel: usize) -> i16 {
+        quantizer.quantize_float(channel, x)
     }
 }
 
@@ -22,3 +35,30 @@ impl Sample for f32 {
         x
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cubeb_core::Dither;
+
+    #[test]
+    fn from_float_saturates() {
+        assert_eq!(i16::from_float(1.0), i16::MAX);
+        assert_eq!(i16::from_float(-1.0), -i16::MAX);
+        assert_eq!(i16::from_float(2.0), i16::MAX);
+        assert_eq!(i16::from_float(-2.0), -i16::MAX);
+        assert_eq!(i16::from_float(0.25), 8192);
+        assert_eq!(f32::from_float(2.0), 2.0);
+    }
+
+    #[test]
+    fn from_float_dithered() {
+        let mut q = Quantizer::new(Dither::None, 2);
+        assert_eq!(i16::from_float_dithered(0.25, &mut q, 1), 8192);
+        assert_eq!(i16::from_float_dithered(-3.0, &mut q, 0), -i16::MAX);
+        assert_eq!(f32::from_float_dithered(0.1, &mut q, 0), 0.1);
+        let mut q = Quantizer::new(Dither::Tpdf, 1);
+        let s = i16::from_float_dithered(0.25, &mut q, 0);
+        assert!((8191..=8193).contains(&s));
+    }
+}
@@ -8,19 +8,18 @@ use ffi;
 use std::ffi::CString;
 use std::marker::PhantomData;
 use std::mem::ManuallyDrop;
-use std::os::raw::{c_long, c_void};
+use std::os::raw::{c_int, c_long, c_void};
 use std::slice::{from_raw_parts, from_raw_parts_mut};
 use std::{ops, panic, ptr};
-use {ContextRef, DeviceId, Error, Result, State, StreamParamsRef};
+use {
+    CallbackResult, ChannelRoute, ContextRef, DeviceId, Dither, Error, MixMatrix, Result, State,
+    StreamOptions, StreamParamsRef,
+};
 
 /// User supplied data callback.
 ///
 /// - Calling other cubeb functions from this callback is unsafe.
 /// - The code in the callback should be non-blocking.
-/// - Returning less than the number of frames this callback asks for or
-///   provides puts the stream in drain mode. This callback will not be called
-///   again, and the state callback will be called with CUBEB_STATE_DRAINED when
-///   all the frames have been output.
 ///
 /// # Arguments
 ///
@@ -29,13 +28,16 @@ use {ContextRef, DeviceId, Error, Result, State, StreamParamsRef};
 ///
 /// # Return value
 ///
-/// If the stream has output, this is the number of frames written to the output buffer. In this
-/// case, if this number is less than the length of the output buffer, then the stream will start to
-/// drain.
-///
-/// If the stream is input only, then returning the length of the input buffer indicates data has
-/// been read.  In this case, a value less than that will result in the stream being stopped.
-pub type DataCallback<F> = dyn FnMut(&[F], &mut [F]) -> isize + Send + Sync + 'static;
+/// - `CallbackResult::Continue(n)`: `n` frames were written to the output
+///   buffer, the rest of which is filled with silence, or the input was
+///   consumed. The stream keeps running.
+/// - `CallbackResult::Drain(n)`: `n` frames were written, or the input was
+///   consumed, and this callback will not be called again. The state
+///   callback is called with `State::Drained` once the output has played
+///   out, or right away for input-only streams.
+/// - `CallbackResult::Error`: the state callback is called with
+///   `State::Error` and the stream stops.
+pub type DataCallback<F> = dyn FnMut(&[F], &mut [F]) -> CallbackResult + Send + Sync + 'static;
 
 /// User supplied state callback.
 ///
@@ -51,6 +53,8 @@ pub struct StreamCallbacks<F> {
     pub(crate) data: Box<DataCallback<F>>,
     pub(crate) state: Box<StateCallback>,
     pub(crate) device_changed: Option<Box<DeviceChangedCallback>>,
+    // Set once the data callback asked to drain after a full buffer.
+    pub(crate) draining: bool,
 }
 
 /// Audio input/output stream
@@ -90,7 +94,7 @@ pub struct StreamCallbacks<F> {
 ///                 phase = (phase + phase_inc) % 1.0;
 ///             }
 ///
-///             output.len() as isize
+///             cubeb::CallbackResult::Continue(output.len())
 ///         })
 ///         .state_callback(|state| {
 ///             println!("stream {:?}", state);
@@ -170,7 +174,7 @@ impl<F> ops::Deref for Stream<F> {
 ///
 ///             position += 1;
 ///         }
-///         output.len() as isize
+///         cubeb::CallbackResult::Continue(output.len())
 ///     })
 ///     .state_callback(|state| {
 ///         println!("stream {:?}", state);
@@ -186,6 +190,10 @@ pub struct StreamBuilder<'a, F> {
     data_cb: Option<Box<DataCallback<F>>>,
     state_cb: Option<Box<StateCallback>>,
     device_changed_cb: Option<Box<DeviceChangedCallback>>,
+    properties: Vec<(CString, CString)>,
+    input_channel_map: Vec<ffi::cubeb_channel_route>,
+    mix_matrix: Option<MixMatrix>,
+    dither: Dither,
 }
 
 impl<'a, F> StreamBuilder<'a, F> {
@@ -196,7 +204,7 @@ impl<'a, F> StreamBuilder<'a, F> {
     /// User supplied data callback, see [`DataCallback`]
     pub fn data_callback<D>(&mut self, cb: D) -> &mut Self
     where
-        D: FnMut(&[F], &mut [F]) -> isize + Send + Sync + 'static,
+        D: FnMut(&[F], &mut [F]) -> CallbackResult + Send + Sync + 'static,
     {
         self.data_cb = Some(Box::new(cb) as Box<DataCallback<F>>);
         self
@@ -257,6 +265,47 @@ impl<'a, F> StreamBuilder<'a, F> {
         self
     }
 
+    /// Attach a backend specific property to the stream, e.g.
+    /// `media.role` on PulseAudio. The properties are passed in the
+    /// stream's `StreamOptions`; backends that take no options fail to
+    /// initialize the stream.
+    pub fn property<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: Into<Vec<u8>>,
+        V: Into<Vec<u8>>,
+    {
+        self.properties
+            .push((CString::new(key).unwrap(), CString::new(value).unwrap()));
+        self
+    }
+
+    /// Capture the input device channels given by `routes` instead of the
+    /// backend's default, e.g. inputs 3 and 4 of an 8 channel interface.
+    /// The routes are passed in the stream's `StreamOptions`; backends
+    /// without `Capabilities::INPUT_CHANNEL_MAP` fail to initialize the
+    /// stream.
+    pub fn input_channel_map(&mut self, routes: &[ChannelRoute]) -> &mut Self {
+        self.input_channel_map = routes.iter().map(|&r| r.into()).collect();
+        self
+    }
+
+    /// Mix the output stream to the speakers of the device following
+    /// `matrix` instead of the backend's default. The matrix is passed in
+    /// the stream's `StreamOptions`; backends without
+    /// `Capabilities::MIX_MATRIX` fail to initialize the stream.
+    pub fn mix_matrix(&mut self, matrix: &MixMatrix) -> &mut Self {
+        self.mix_matrix = Some(matrix.clone());
+        self
+    }
+
+    /// Dither applied when the backend requantizes the samples to 16 bits,
+    /// e.g. after mixing them to the speakers of the device. None by
+    /// default. Passed in the stream's `StreamOptions`, like the mix matrix.
+    pub fn dither(&mut self, dither: Dither) -> &mut Self {
+        self.dither = dither;
+        self
+    }
+
     /// User supplied callback called when the underlying device changed.
     ///
     /// See [`StateCallback`]
@@ -281,25 +330,67 @@ impl<'a, F> StreamBuilder<'a, F> {
             data: self.data_cb.unwrap(),
             state: self.state_cb.unwrap(),
             device_changed: self.device_changed_cb,
+            draining: false,
         }));
 
         let stream_name = self.name.as_deref();
+        let properties: Vec<_> = self
+            .properties
+            .iter()
+            .map(|(key, value)| ffi::cubeb_property {
+                key: key.as_ptr(),
+                value: value.as_ptr(),
+            })
+            .collect();
+        let mix_coefficients: Vec<ffi::cubeb_mix_coefficient> = self
+            .mix_matrix
+            .iter()
+            .flat_map(|m| m.coefficients.iter().flatten())
+            .map(|&c| c.into())
+            .collect();
+        let mix_matrix = self.mix_matrix.as_ref().map(|m| ffi::cubeb_mix_matrix {
+            center_mix_level: m.center_mix_level,
+            surround_mix_level: m.surround_mix_level,
+            lfe_mix_level: m.lfe_mix_level,
+            normalize: m.normalize as c_int,
+            coefficients: match m.coefficients {
+                Some(_) => mix_coefficients.as_ptr(),
+                None => ptr::null(),
+            },
+            coefficient_count: mix_coefficients.len(),
+        });
         let (input_device, input_stream_params) =
             self.input.map_or((ptr::null(), None), |x| (x.0, Some(x.1)));
         let (output_device, output_stream_params) = self
             .output
             .map_or((ptr::null(), None), |x| (x.0, Some(x.1)));
+        let options = StreamOptions::from(ffi::cubeb_stream_options {
+            properties: properties.as_ptr(),
+            property_count: properties.len(),
+            input_channel_map: if self.input_channel_map.is_empty() {
+                ptr::null()
+            } else {
+                self.input_channel_map.as_ptr()
+            },
+            input_channel_map_count: self.input_channel_map.len(),
+            mix_matrix: mix_matrix
+                .as_ref()
+                .map_or(ptr::null(), |m| m as *const ffi::cubeb_mix_matrix),
+            dither: self.dither.into(),
+            ..Default::default()
+        });
         let latency = self.latency.unwrap_or(1);
         let data_callback: ffi::cubeb_data_callback = Some(data_cb_c::<F>);
         let state_callback: ffi::cubeb_state_callback = Some(state_cb_c::<F>);
 
         let stream = unsafe {
-            ctx.stream_init(
+            ctx.stream_init_with_options(
                 stream_name,
                 input_device,
                 input_stream_params,
                 output_device,
                 output_stream_params,
+                &options,
                 latency,
                 data_callback,
                 state_callback,
@@ -325,6 +416,10 @@ impl<'a, F> Default for StreamBuilder<'a, F> {
             data_cb: None,
             state_cb: None,
             device_changed_cb: None,
+            properties: Vec::new(),
+            input_channel_map: Vec::new(),
+            mix_matrix: None,
+            dither: Dither::None,
         }
     }
 }
@@ -349,9 +444,29 @@ unsafe extern "C" fn data_cb_c<F>(
         } else {
             from_raw_parts_mut(output_buffer as *mut _, nframes as usize)
         };
-        (cbs.data)(input, output) as c_long
+        if cbs.draining {
+            return 0;
+        }
+        match (cbs.data)(input, output) {
+            CallbackResult::Continue(n) => {
+                if n < output.len() {
+                    // Frames are plain samples, for which all zeroes is silence.
+                    ptr::write_bytes(output[n..].as_mut_ptr(), 0, output.len() - n);
+                }
+                nframes
+            }
+            CallbackResult::Drain(n) if n as c_long >= nframes => {
+                // The backend can only be told to drain by a short
+                // buffer, so return an empty one next time.
+                cbs.draining = true;
+                nframes
+            }
+            CallbackResult::Drain(n) => n as c_long,
+            CallbackResult::Error => c_long::from(ffi::CUBEB_ERROR),
+        }
     });
-    ok.unwrap_or(0)
+    // A panic is reported like `CallbackResult::Error`.
+    ok.unwrap_or(c_long::from(ffi::CUBEB_ERROR))
 }
 
 unsafe extern "C" fn state_cb_c<F>(
@@ -0,0 +1,174 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details
+
+extern crate cubeb;
+extern crate cubeb_backend;
+
+use cubeb::{
+    CallbackResult, ChannelLayout, Context, MonoFrame, SampleFormat, State, StreamBuilder,
+    StreamParams, StreamParamsBuilder,
+};
+use cubeb_backend::mock::{self, MockContext, Script};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Frame = MonoFrame<f32>;
+
+const PERIOD: u32 = 256;
+const RATE: u32 = 48000;
+
+#[derive(Default)]
+struct Calls {
+    callbacks: usize,
+    // Length of the input and output buffers of each callback.
+    buffers: Vec<(usize, usize)>,
+    states: Vec<State>,
+}
+
+fn params() -> StreamParams {
+    StreamParamsBuilder::new()
+        .format(SampleFormat::Float32NE)
+        .rate(RATE)
+        .channels(1)
+        .layout(ChannelLayout::MONO)
+        .take()
+}
+
+// Runs a stream for `periods` periods of the mock backend, the data callback
+// returning `results` in turn, then `Continue`.
+fn run(input: bool, output: bool, results: Vec<CallbackResult>, periods: u32) -> (Calls, u64) {
+    let mut results = results.into_iter();
+    run_with(input, output, periods, move |frames| {
+        results.next().unwrap_or(CallbackResult::Continue(frames))
+    })
+}
+
+// Same as `run`, with `result` giving the data callback's return value from
+// the number of frames it was asked for.
+fn run_with<R>(input: bool, output: bool, periods: u32, mut result: R) -> (Calls, u64)
+where
+    R: FnMut(usize) -> CallbackResult + Send + Sync + 'static,
+{
+    let context: Context = MockContext::init_with_script(Script {
+        period: PERIOD,
+        ..Script::default()
+    })
+    .unwrap();
+    let params = params();
+    let calls = Arc::new(Mutex::new(Calls::default()));
+    let mut builder = StreamBuilder::<Frame>::new();
+    if input {
+        builder.default_input(&params);
+    }
+    if output {
+        builder.default_output(&params);
+    }
+    let data_calls = calls.clone();
+    let state_calls = calls.clone();
+    builder
+        .latency(PERIOD)
+        .data_callback(move |input, output| {
+            for f in output.iter_mut() {
+                f.m = 1.0;
+            }
+            {
+                let mut calls = data_calls.lock().unwrap();
+                calls.callbacks += 1;
+                calls.buffers.push((input.len(), output.len()));
+            }
+            result(input.len().max(output.len()))
+        })
+        .state_callback(move |state| state_calls.lock().unwrap().states.push(state));
+    let stream = builder.init(&context).unwrap();
+    stream.start().unwrap();
+    mock::advance(
+        &context,
+        Duration::from_nanos(u64::from(periods * PERIOD) * 1_000_000_000 / u64::from(RATE)),
+    );
+    let position = stream.position().unwrap();
+    stream.stop().unwrap();
+    drop(stream);
+    let calls = Arc::try_unwrap(calls).ok().unwrap().into_inner().unwrap();
+    (calls, position)
+}
+
+#[test]
+fn test_output_continue_short() {
+    // Frames past the count returned are silenced, and the stream goes on.
+    let (calls, position) = run(false, true, vec![CallbackResult::Continue(10)], 4);
+    assert_eq!(calls.callbacks, 4);
+    assert_eq!(position, u64::from(4 * PERIOD));
+    assert_eq!(calls.states, [State::Started, State::Stopped]);
+}
+
+#[test]
+fn test_output_drain_short() {
+    let (calls, position) = run(false, true, vec![CallbackResult::Drain(100)], 4);
+    assert_eq!(calls.callbacks, 1);
+    assert_eq!(position, 100);
+    assert_eq!(calls.states, [State::Started, State::Drained]);
+}
+
+#[test]
+fn test_output_drain_full_buffer() {
+    let results = vec![CallbackResult::Drain(PERIOD as usize)];
+    let (calls, position) = run(false, true, results, 4);
+    assert_eq!(calls.callbacks, 1);
+    assert_eq!(position, u64::from(PERIOD));
+    assert_eq!(calls.states, [State::Started, State::Drained]);
+}
+
+#[test]
+fn test_output_error() {
+    let results = vec![
+        CallbackResult::Continue(PERIOD as usize),
+        CallbackResult::Error,
+    ];
+    let (calls, position) = run(false, true, results, 4);
+    assert_eq!(calls.callbacks, 2);
+    assert_eq!(position, u64::from(PERIOD));
+    assert_eq!(calls.states, [State::Started, State::Error]);
+}
+
+#[test]
+fn test_input_drain() {
+    let results = vec![
+        CallbackResult::Continue(PERIOD as usize),
+        CallbackResult::Drain(0),
+    ];
+    let (calls, _) = run(true, false, results, 4);
+    assert_eq!(calls.callbacks, 2);
+    assert_eq!(calls.buffers, [(PERIOD as usize, 0); 2]);
+    assert_eq!(calls.states, [State::Started, State::Drained]);
+}
+
+#[test]
+fn test_input_error() {
+    let (calls, _) = run(true, false, vec![CallbackResult::Error], 4);
+    assert_eq!(calls.callbacks, 1);
+    assert_eq!(calls.states, [State::Started, State::Error]);
+}
+
+#[test]
+fn test_duplex_continue_and_drain() {
+    let results = vec![
+        CallbackResult::Continue(PERIOD as usize),
+        CallbackResult::Continue(PERIOD as usize),
+        CallbackResult::Drain(PERIOD as usize / 2),
+    ];
+    let (calls, position) = run(true, true, results, 8);
+    assert_eq!(calls.callbacks, 3);
+    assert_eq!(calls.buffers, [(PERIOD as usize, PERIOD as usize); 3]);
+    assert_eq!(position, u64::from(2 * PERIOD + PERIOD / 2));
+    assert_eq!(calls.states, [State::Started, State::Drained]);
+}
+
+#[test]
+fn test_panic_is_error() {
+    let (calls, position) = run_with(false, true, 4, |_| panic!("data callback panic"));
+    assert_eq!(calls.callbacks, 1);
+    assert_eq!(position, 0);
+    assert_eq!(calls.states, [State::Started, State::Error]);
+}
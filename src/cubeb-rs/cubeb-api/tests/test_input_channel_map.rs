@@ -0,0 +1,102 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details
+
+extern crate cubeb;
+extern crate cubeb_backend;
+
+use cubeb::{
+    Capabilities, ChannelLayout, ChannelRoute, Context, Error, MonoFrame, Result, SampleFormat,
+    StreamBuilder, StreamParamsBuilder,
+};
+use cubeb_backend::mock::{MockContext, Script};
+
+// Initializes an input stream, or an output stream if `output`, of
+// `channels` on the mock backend, with `capabilities` and routes `routes`.
+fn init_stream(
+    capabilities: Capabilities,
+    output: bool,
+    channels: u32,
+    routes: &[ChannelRoute],
+) -> Result<()> {
+    let context: Context = MockContext::init_with_script(Script {
+        capabilities,
+        ..Script::default()
+    })?;
+    let layout = if channels == 1 {
+        ChannelLayout::MONO
+    } else {
+        ChannelLayout::STEREO
+    };
+    let params = StreamParamsBuilder::new()
+        .format(SampleFormat::Float32NE)
+        .rate(48000)
+        .channels(channels)
+        .layout(layout)
+        .take();
+    let mut builder = StreamBuilder::<MonoFrame<f32>>::new();
+    if output {
+        builder.default_output(&params);
+    } else {
+        builder.default_input(&params);
+    }
+    builder
+        .input_channel_map(routes)
+        .latency(256)
+        .data_callback(|input, _| cubeb::CallbackResult::Continue(input.len()))
+        .state_callback(|_| {});
+    builder.init(&context).map(|_| ())
+}
+
+fn init(capabilities: Capabilities, channels: u32, routes: &[ChannelRoute]) -> Result<()> {
+    init_stream(capabilities, false, channels, routes)
+}
+
+fn supported() -> Capabilities {
+    Script::default().capabilities | Capabilities::INPUT_CHANNEL_MAP
+}
+
+#[test]
+fn test_input_channel_map_supported() {
+    let routes = [ChannelRoute::new(2, 0), ChannelRoute::new(3, 1)];
+    assert_eq!(init(supported(), 2, &routes), Ok(()));
+}
+
+#[test]
+fn test_input_channel_map_not_supported() {
+    let routes = [ChannelRoute::new(2, 0)];
+    assert_eq!(
+        init(Script::default().capabilities, 1, &routes),
+        Err(Error::not_supported())
+    );
+    // The default channels work everywhere.
+    assert_eq!(init(Script::default().capabilities, 1, &[]), Ok(()));
+}
+
+#[test]
+fn test_input_channel_map_invalid() {
+    // Stream channel past those of the stream.
+    let routes = [ChannelRoute::new(0, 1)];
+    assert_eq!(
+        init(supported(), 1, &routes),
+        Err(Error::invalid_parameter())
+    );
+    // Gain that is not finite.
+    for &gain in &[f32::NAN, f32::INFINITY] {
+        let routes = [ChannelRoute {
+            gain,
+            ..ChannelRoute::new(0, 0)
+        }];
+        assert_eq!(
+            init(supported(), 1, &routes),
+            Err(Error::invalid_parameter())
+        );
+    }
+    // No input to route.
+    let routes = [ChannelRoute::new(0, 0)];
+    assert_eq!(
+        init_stream(supported(), true, 1, &routes),
+        Err(Error::invalid_parameter())
+    );
+}
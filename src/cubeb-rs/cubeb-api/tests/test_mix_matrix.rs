@@ -0,0 +1,107 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details
+
+extern crate cubeb;
+extern crate cubeb_backend;
+
+use cubeb::{
+    Capabilities, ChannelLayout, Context, Error, MixCoefficient, MixMatrix, Result, SampleFormat,
+    StereoFrame, StreamBuilder, StreamParamsBuilder,
+};
+use cubeb_backend::mock::{MockContext, Script};
+
+// Initializes a stereo output stream, or an input stream if `input`, on the
+// mock backend, with `capabilities` and `matrix`.
+fn init_stream(capabilities: Capabilities, input: bool, matrix: Option<&MixMatrix>) -> Result<()> {
+    let context: Context = MockContext::init_with_script(Script {
+        capabilities,
+        ..Script::default()
+    })?;
+    let params = StreamParamsBuilder::new()
+        .format(SampleFormat::Float32NE)
+        .rate(48000)
+        .channels(2)
+        .layout(ChannelLayout::STEREO)
+        .take();
+    let mut builder = StreamBuilder::<StereoFrame<f32>>::new();
+    if input {
+        builder.default_input(&params);
+    } else {
+        builder.default_output(&params);
+    }
+    builder
+        .latency(256)
+        .data_callback(|input, output| {
+            cubeb::CallbackResult::Continue(input.len().max(output.len()))
+        })
+        .state_callback(|_| {});
+    if let Some(matrix) = matrix {
+        builder.mix_matrix(matrix);
+    }
+    builder.init(&context).map(|_| ())
+}
+
+fn init(capabilities: Capabilities, matrix: Option<&MixMatrix>) -> Result<()> {
+    init_stream(capabilities, false, matrix)
+}
+
+fn supported() -> Capabilities {
+    Script::default().capabilities | Capabilities::MIX_MATRIX
+}
+
+#[test]
+fn test_mix_matrix_supported() {
+    assert_eq!(init(supported(), Some(&MixMatrix::itu_bs775())), Ok(()));
+    let matrix = MixMatrix::custom(vec![MixCoefficient::new(
+        ChannelLayout::FRONT_LEFT,
+        ChannelLayout::FRONT_RIGHT,
+        1.0,
+    )]);
+    assert_eq!(init(supported(), Some(&matrix)), Ok(()));
+}
+
+#[test]
+fn test_mix_matrix_not_supported() {
+    assert_eq!(
+        init(
+            Script::default().capabilities,
+            Some(&MixMatrix::default().normalized())
+        ),
+        Err(Error::not_supported())
+    );
+    // The default mixing works everywhere.
+    assert_eq!(init(Script::default().capabilities, None), Ok(()));
+}
+
+#[test]
+fn test_mix_matrix_invalid() {
+    let matrix = MixMatrix {
+        surround_mix_level: -1.0,
+        ..MixMatrix::default()
+    };
+    assert_eq!(
+        init(supported(), Some(&matrix)),
+        Err(Error::invalid_parameter())
+    );
+    // Coefficients are between single speakers.
+    let matrix = MixMatrix::custom(vec![MixCoefficient::new(
+        ChannelLayout::STEREO,
+        ChannelLayout::FRONT_LEFT,
+        1.0,
+    )]);
+    assert_eq!(
+        init(supported(), Some(&matrix)),
+        Err(Error::invalid_parameter())
+    );
+    assert_eq!(
+        init(supported(), Some(&MixMatrix::custom(Vec::new()))),
+        Err(Error::invalid_parameter())
+    );
+    // No output to mix.
+    assert_eq!(
+        init_stream(supported(), true, Some(&MixMatrix::default())),
+        Err(Error::invalid_parameter())
+    );
+}
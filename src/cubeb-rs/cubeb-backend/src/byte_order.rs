@@ -0,0 +1,278 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Byte order conversion for streams asking for a format that is not in the
+//! native byte order.
+//!
+//! Mixing, resampling and buffering work on native samples, so backends run
+//! such streams in the native byte order, see [`native`], and only convert
+//! at the boundary with the user: [`ByteOrderConverter`] swaps the input
+//! frames handed to the data callback, and the output frames it returns.
+
+use cubeb_core::{ffi, SampleFormat, StreamParams, StreamParamsRef};
+use std::os::raw::{c_long, c_void};
+use std::{mem, ptr, slice};
+
+/// Whether the samples of `format` are in the native byte order.
+pub fn is_native(format: SampleFormat) -> bool {
+    match format {
+        SampleFormat::S16NE | SampleFormat::Float32NE => true,
+        SampleFormat::S16LE | SampleFormat::Float32LE => cfg!(target_endian = "little"),
+        SampleFormat::S16BE | SampleFormat::Float32BE => cfg!(target_endian = "big"),
+    }
+}
+
+/// The format with the samples of `format`, in the native byte order. The
+/// byte order is explicit, as backends map it to the one of their server or
+/// device.
+pub fn native(format: SampleFormat) -> SampleFormat {
+    let little = cfg!(target_endian = "little");
+    match format {
+        SampleFormat::S16LE | SampleFormat::S16BE | SampleFormat::S16NE => {
+            if little {
+                SampleFormat::S16LE
+            } else {
+                SampleFormat::S16BE
+            }
+        }
+        SampleFormat::Float32LE | SampleFormat::Float32BE | SampleFormat::Float32NE => {
+            if little {
+                SampleFormat::Float32LE
+            } else {
+                SampleFormat::Float32BE
+            }
+        }
+    }
+}
+
+/// `params`, with their format in the native byte order.
+pub fn native_params(params: &StreamParamsRef) -> StreamParams {
+    let mut raw = unsafe { *params.as_ptr() };
+    raw.format = native(params.format()).into();
+    StreamParams::from(raw)
+}
+
+fn sample_size(format: SampleFormat) -> usize {
+    match format {
+        SampleFormat::S16LE | SampleFormat::S16BE | SampleFormat::S16NE => mem::size_of::<i16>(),
+        _ => mem::size_of::<f32>(),
+    }
+}
+
+/// Reverses the bytes of each sample of `format` in `data`, converting it
+/// from or to the native byte order.
+pub fn swap_bytes(format: SampleFormat, data: &mut [u8]) {
+    let size = sample_size(format);
+    assert_eq!(data.len() % size, 0);
+    for sample in data.chunks_mut(size) {
+        sample.reverse();
+    }
+}
+
+/// Converts the frames exchanged with the data callback of a stream between
+/// the native byte order, that the backend runs it in, and the one of its
+/// format.
+#[derive(Debug)]
+pub struct ByteOrderConverter {
+    format: SampleFormat,
+    input_frame_size: usize,
+    output_frame_size: usize,
+    // Input frames in the byte order of the stream.
+    input: Vec<u8>,
+}
+
+impl ByteOrderConverter {
+    /// A converter for a stream of `format`, unless it is native. The input
+    /// frames of up to `max_frames` long callbacks are converted without
+    /// allocating.
+    pub fn new(
+        format: SampleFormat,
+        input_channels: usize,
+        output_channels: usize,
+        max_frames: usize,
+    ) -> Option<ByteOrderConverter> {
+        if is_native(format) {
+            return None;
+        }
+        let size = sample_size(format);
+        Some(ByteOrderConverter {
+            format,
+            input_frame_size: input_channels * size,
+            output_frame_size: output_channels * size,
+            input: Vec::with_capacity(max_frames * input_channels * size),
+        })
+    }
+
+    /// A converter for a stream with `input_params` and `output_params`,
+    /// unless their format is native.
+    pub fn with_params(
+        input_params: Option<&StreamParamsRef>,
+        output_params: Option<&StreamParamsRef>,
+        max_frames: usize,
+    ) -> Option<ByteOrderConverter> {
+        let format = input_params.or(output_params)?.format();
+        let input_channels = input_params.map_or(0, |p| p.channels() as usize);
+        let output_channels = output_params.map_or(0, |p| p.channels() as usize);
+        ByteOrderConverter::new(format, input_channels, output_channels, max_frames)
+    }
+
+    /// The format of the stream.
+    pub fn format(&self) -> SampleFormat {
+        self.format
+    }
+
+    /// Calls `data_callback` with the native `input` frames, if any,
+    /// converted to the byte order of the stream, then converts the frames
+    /// it wrote to `output` to the native byte order. Returns what the
+    /// callback returned.
+    ///
+    /// # Safety
+    ///
+    /// As calling `data_callback` with `input` and `output`, which hold
+    /// `frames` frames.
+    pub unsafe fn data_callback(
+        &mut self,
+        data_callback: ffi::cubeb_data_callback,
+        stream: *mut ffi::cubeb_stream,
+        user_ptr: *mut c_void,
+        input: *const c_void,
+        output: *mut c_void,
+        frames: c_long,
+    ) -> c_long {
+        let input = if input.is_null() {
+            ptr::null()
+        } else {
+            let len = frames as usize * self.input_frame_size;
+            self.input.clear();
+            self.input
+                .extend_from_slice(slice::from_raw_parts(input as *const u8, len));
+            swap_bytes(self.format, &mut self.input);
+            self.input.as_ptr() as *const c_void
+        };
+        let got = data_callback.unwrap()(stream, user_ptr, input, output, frames);
+        if !output.is_null() && got > 0 {
+            let len = got.min(frames) as usize * self.output_frame_size;
+            swap_bytes(
+                self.format,
+                slice::from_raw_parts_mut(output as *mut u8, len),
+            );
+        }
+        got
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The formats in the other byte order, emulated on this host.
+    const NON_NATIVE_S16: SampleFormat = if cfg!(target_endian = "little") {
+        SampleFormat::S16BE
+    } else {
+        SampleFormat::S16LE
+    };
+    const NON_NATIVE_FLOAT32: SampleFormat = if cfg!(target_endian = "little") {
+        SampleFormat::Float32BE
+    } else {
+        SampleFormat::Float32LE
+    };
+
+    #[test]
+    fn test_native() {
+        assert!(is_native(SampleFormat::S16NE));
+        assert!(is_native(SampleFormat::Float32NE));
+        assert!(!is_native(NON_NATIVE_S16));
+        assert!(!is_native(NON_NATIVE_FLOAT32));
+        for &format in &[SampleFormat::S16NE, NON_NATIVE_S16] {
+            let native = native(format);
+            assert!(is_native(native));
+            assert_ne!(native, SampleFormat::S16NE);
+            assert_eq!(sample_size(native), 2);
+        }
+        for &format in &[SampleFormat::Float32NE, NON_NATIVE_FLOAT32] {
+            let native = native(format);
+            assert!(is_native(native));
+            assert_ne!(native, SampleFormat::Float32NE);
+            assert_eq!(sample_size(native), 4);
+        }
+    }
+
+    #[test]
+    fn test_swap_bytes() {
+        let mut data = [0x01, 0x02, 0x03, 0x04];
+        swap_bytes(SampleFormat::S16BE, &mut data);
+        assert_eq!(data, [0x02, 0x01, 0x04, 0x03]);
+        swap_bytes(SampleFormat::Float32LE, &mut data);
+        assert_eq!(data, [0x03, 0x04, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn test_no_converter_when_native() {
+        assert!(ByteOrderConverter::new(SampleFormat::S16NE, 1, 1, 16).is_none());
+        assert!(ByteOrderConverter::new(native(NON_NATIVE_FLOAT32), 1, 1, 16).is_none());
+    }
+
+    // Doubles the input frames into the output, in the non-native format.
+    unsafe extern "C" fn double_callback(
+        _stream: *mut ffi::cubeb_stream,
+        _user_ptr: *mut c_void,
+        input: *const c_void,
+        output: *mut c_void,
+        frames: c_long,
+    ) -> c_long {
+        let input = slice::from_raw_parts(input as *const [u8; 2], frames as usize);
+        let output = slice::from_raw_parts_mut(output as *mut [u8; 2], frames as usize);
+        for (i, o) in input.iter().zip(output) {
+            let sample = if cfg!(target_endian = "little") {
+                i16::from_be_bytes(*i)
+            } else {
+                i16::from_le_bytes(*i)
+            };
+            *o = if cfg!(target_endian = "little") {
+                (sample * 2).to_be_bytes()
+            } else {
+                (sample * 2).to_le_bytes()
+            };
+        }
+        frames - 1
+    }
+
+    #[test]
+    fn test_data_callback() {
+        let mut converter = ByteOrderConverter::new(NON_NATIVE_S16, 1, 1, 4).unwrap();
+        let input: [i16; 4] = [1, -2, 300, -400];
+        let mut output = [0i16; 4];
+        let got = unsafe {
+            converter.data_callback(
+                Some(double_callback),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                input.as_ptr() as *const c_void,
+                output.as_mut_ptr() as *mut c_void,
+                4,
+            )
+        };
+        // Only the frames the callback returned are converted.
+        assert_eq!(got, 3);
+        assert_eq!(output[..3], [2, -4, 600]);
+        assert_eq!(output[3], (-800i16).swap_bytes());
+    }
+
+    #[test]
+    fn test_native_params() {
+        let params = StreamParams::from(ffi::cubeb_stream_params {
+            format: NON_NATIVE_FLOAT32.into(),
+            rate: 44100,
+            channels: 2,
+            ..Default::default()
+        });
+        let native = native_params(&params);
+        assert_eq!(native.format(), super::native(NON_NATIVE_FLOAT32));
+        assert_eq!(native.rate(), 44100);
+        assert_eq!(native.channels(), 2);
+        let converter = ByteOrderConverter::with_params(None, Some(&params), 16).unwrap();
+        assert_eq!(converter.format(), NON_NATIVE_FLOAT32);
+    }
+}
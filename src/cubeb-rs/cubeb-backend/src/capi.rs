@@ -3,10 +3,15 @@
 // This program is made available under an ISC-style license.  See the
 // accompanying file LICENSE for details
 
-use cubeb_core::{ffi, DeviceCollectionRef, DeviceRef, DeviceType, StreamParams, StreamParamsRef};
+use cubeb_core::{
+    ffi, DeviceCollectionRef, DeviceRef, DeviceType, LogId, LogLevel, StreamOptionsRef,
+    StreamParams, StreamParamsRef,
+};
+use log::{async_log_reset_threads, enter_log_id, set_logging};
 use std::ffi::CStr;
-use std::mem;
 use std::os::raw::{c_char, c_int, c_void};
+use std::{mem, ptr};
+use unwind::{catch, install_panic_hook};
 use {ContextOps, StreamOps};
 
 // Helper macro for unwrapping `Result` values from rust-api calls
@@ -29,8 +34,25 @@ macro_rules! as_opt_ref {
     };
 }
 
+/// Builds the `Ops` of a backend from its `ContextOps` and `StreamOps`
+/// implementations.
+///
+/// Optional operations the backend leaves to the traits' default
+/// implementations can be listed after `unsupported:` by their `Ops` field
+/// name. They are then `None`, which `cubeb.c` reports as
+/// `CUBEB_ERROR_NOT_SUPPORTED` and C callers can test for. The operations
+/// `cubeb_init` requires (`get_backend_id`, `destroy`, `stream_init`,
+/// `stream_destroy`, `stream_start`, `stream_stop` and `stream_get_position`)
+/// must not be listed:
+///
+/// `capi_new!(MyContext, MyStream, unsupported: [stream_set_name])`
 #[macro_export]
 macro_rules! capi_new(
+    ($ctx:ident, $stm:ident, unsupported: [$($op:ident),* $(,)*]) => (
+        Ops {
+            $($op: None,)*
+            ..$crate::capi_new!($ctx, $stm)
+        });
     ($ctx:ident, $stm:ident) => (
         Ops {
             init: Some($crate::capi::capi_init::<$ctx>),
@@ -55,9 +77,24 @@ macro_rules! capi_new(
             stream_register_device_changed_callback:
                 Some($crate::capi::capi_stream_register_device_changed_callback::<$stm>),
             register_device_collection_changed:
-                Some($crate::capi::capi_register_device_collection_changed::<$ctx>)
+                Some($crate::capi::capi_register_device_collection_changed::<$ctx>),
+            get_capabilities: Some($crate::capi::capi_get_capabilities::<$ctx>),
+            device_collection_get_properties:
+                Some($crate::capi::capi_device_collection_get_properties::<$ctx>),
+            stream_init_with_options: Some($crate::capi::capi_stream_init_with_options::<$ctx>)
         }));
 
+/// Entry point from C code, behind each backend's `*_rust_set_logging`.
+/// `cubeb.c` calls it with the level and callbacks of the C logger
+/// whenever they change, see `log::set_logging`.
+pub extern "C" fn capi_set_logging(
+    level: ffi::cubeb_log_level,
+    log_callback: ffi::cubeb_log_callback,
+    message_callback: ffi::cubeb_log_message_callback,
+) {
+    set_logging(LogLevel::from(level), log_callback, message_callback);
+}
+
 /// # Safety
 ///
 /// Entry point from C code.
@@ -68,13 +105,18 @@ pub unsafe extern "C" fn capi_init<CTX: ContextOps>(
     c: *mut *mut ffi::cubeb,
     context_name: *const c_char,
 ) -> c_int {
-    let anchor = &();
-    let context_name = opt_cstr(anchor, context_name);
-    let context = _try!(CTX::init(context_name));
-    *c = context.as_ptr();
-    // Leaking pointer across C FFI
-    mem::forget(context);
-    ffi::CUBEB_OK
+    catch("capi_init", || {
+        install_panic_hook();
+        async_log_reset_threads();
+        let anchor = &();
+        let context_name = opt_cstr(anchor, context_name);
+        let context = _try!(CTX::init(context_name));
+        *c = context.as_ptr();
+        // Leaking pointer across C FFI
+        mem::forget(context);
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -84,8 +126,12 @@ pub unsafe extern "C" fn capi_init<CTX: ContextOps>(
 /// This function is unsafe because it dereferences the given `c` pointer.
 /// The caller should ensure that pointer is valid.
 pub unsafe extern "C" fn capi_get_backend_id<CTX: ContextOps>(c: *mut ffi::cubeb) -> *const c_char {
-    let ctx = &mut *(c as *mut CTX);
-    ctx.backend_id().as_ptr()
+    catch("capi_get_backend_id", || {
+        let ctx = &mut *(c as *mut CTX);
+        let _log_id = enter_log_id(LogId::new("context", c));
+        ctx.backend_id().as_ptr()
+    })
+    .unwrap_or(b"\0".as_ptr() as *const c_char)
 }
 
 /// # Safety
@@ -98,10 +144,14 @@ pub unsafe extern "C" fn capi_get_max_channel_count<CTX: ContextOps>(
     c: *mut ffi::cubeb,
     max_channels: *mut u32,
 ) -> c_int {
-    let ctx = &mut *(c as *mut CTX);
+    catch("capi_get_max_channel_count", || {
+        let ctx = &mut *(c as *mut CTX);
+        let _log_id = enter_log_id(LogId::new("context", c));
 
-    *max_channels = _try!(ctx.max_channel_count());
-    ffi::CUBEB_OK
+        *max_channels = _try!(ctx.max_channel_count());
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -115,10 +165,14 @@ pub unsafe extern "C" fn capi_get_min_latency<CTX: ContextOps>(
     param: ffi::cubeb_stream_params,
     latency_frames: *mut u32,
 ) -> c_int {
-    let ctx = &mut *(c as *mut CTX);
-    let param = StreamParams::from(param);
-    *latency_frames = _try!(ctx.min_latency(param));
-    ffi::CUBEB_OK
+    catch("capi_get_min_latency", || {
+        let ctx = &mut *(c as *mut CTX);
+        let _log_id = enter_log_id(LogId::new("context", c));
+        let param = StreamParams::from(param);
+        *latency_frames = _try!(ctx.min_latency(param));
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -131,10 +185,34 @@ pub unsafe extern "C" fn capi_get_preferred_sample_rate<CTX: ContextOps>(
     c: *mut ffi::cubeb,
     rate: *mut u32,
 ) -> c_int {
-    let ctx = &mut *(c as *mut CTX);
+    catch("capi_get_preferred_sample_rate", || {
+        let ctx = &mut *(c as *mut CTX);
+        let _log_id = enter_log_id(LogId::new("context", c));
 
-    *rate = _try!(ctx.preferred_sample_rate());
-    ffi::CUBEB_OK
+        *rate = _try!(ctx.preferred_sample_rate());
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
+}
+
+/// # Safety
+///
+/// Entry point from C code.
+///
+/// This function is unsafe because it dereferences the given `c` and `capabilities` pointers.
+/// The caller should ensure those pointers are valid.
+pub unsafe extern "C" fn capi_get_capabilities<CTX: ContextOps>(
+    c: *mut ffi::cubeb,
+    capabilities: *mut ffi::cubeb_capabilities,
+) -> c_int {
+    catch("capi_get_capabilities", || {
+        let ctx = &mut *(c as *mut CTX);
+        let _log_id = enter_log_id(LogId::new("context", c));
+
+        *capabilities = _try!(ctx.capabilities()).bits();
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -148,11 +226,15 @@ pub unsafe extern "C" fn capi_enumerate_devices<CTX: ContextOps>(
     devtype: ffi::cubeb_device_type,
     collection: *mut ffi::cubeb_device_collection,
 ) -> c_int {
-    let ctx = &mut *(c as *mut CTX);
-    let devtype = DeviceType::from_bits_truncate(devtype);
-    let collection = DeviceCollectionRef::from_ptr(collection);
-    _try!(ctx.enumerate_devices(devtype, collection));
-    ffi::CUBEB_OK
+    catch("capi_enumerate_devices", || {
+        let ctx = &mut *(c as *mut CTX);
+        let _log_id = enter_log_id(LogId::new("context", c));
+        let devtype = DeviceType::from_bits_truncate(devtype);
+        let collection = DeviceCollectionRef::from_ptr(collection);
+        _try!(ctx.enumerate_devices(devtype, collection));
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -165,10 +247,44 @@ pub unsafe extern "C" fn capi_device_collection_destroy<CTX: ContextOps>(
     c: *mut ffi::cubeb,
     collection: *mut ffi::cubeb_device_collection,
 ) -> c_int {
-    let ctx = &mut *(c as *mut CTX);
-    let collection = DeviceCollectionRef::from_ptr_mut(collection);
-    _try!(ctx.device_collection_destroy(collection));
-    ffi::CUBEB_OK
+    catch("capi_device_collection_destroy", || {
+        let ctx = &mut *(c as *mut CTX);
+        let _log_id = enter_log_id(LogId::new("context", c));
+        let collection = DeviceCollectionRef::from_ptr_mut(collection);
+        _try!(ctx.device_collection_destroy(collection));
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
+}
+
+/// # Safety
+///
+/// Entry point from C code.
+///
+/// This function is unsafe because it dereferences the given `c`, `collection`, `properties`
+/// and `count` pointers.
+/// The caller should ensure those pointers are valid.
+pub unsafe extern "C" fn capi_device_collection_get_properties<CTX: ContextOps>(
+    c: *mut ffi::cubeb,
+    collection: *mut ffi::cubeb_device_collection,
+    index: usize,
+    properties: *mut *const ffi::cubeb_property,
+    count: *mut usize,
+) -> c_int {
+    catch("capi_device_collection_get_properties", || {
+        let ctx = &mut *(c as *mut CTX);
+        let _log_id = enter_log_id(LogId::new("context", c));
+        let collection = DeviceCollectionRef::from_ptr(collection);
+        let props = _try!(ctx.device_collection_properties(collection, index));
+        *properties = if props.is_empty() {
+            ptr::null()
+        } else {
+            props.as_ptr()
+        };
+        *count = props.len();
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -178,7 +294,10 @@ pub unsafe extern "C" fn capi_device_collection_destroy<CTX: ContextOps>(
 /// This function is unsafe because it dereferences the given `c` pointer.
 /// The caller should ensure that pointer is valid.
 pub unsafe extern "C" fn capi_destroy<CTX>(c: *mut ffi::cubeb) {
-    let _: Box<CTX> = Box::from_raw(c as *mut _);
+    let _ = catch("capi_destroy", || {
+        let _log_id = enter_log_id(LogId::new("context", c));
+        let _: Box<CTX> = Box::from_raw(c as *mut _);
+    });
 }
 
 /// # Safety
@@ -201,27 +320,81 @@ pub unsafe extern "C" fn capi_stream_init<CTX: ContextOps>(
     state_callback: ffi::cubeb_state_callback,
     user_ptr: *mut c_void,
 ) -> c_int {
-    let ctx = &mut *(c as *mut CTX);
-    let anchor = &(); // for lifetime of stream_name as CStr
-
-    let input_stream_params = as_opt_ref!(input_stream_params);
-    let output_stream_params = as_opt_ref!(output_stream_params);
-
-    let stream = _try!(ctx.stream_init(
-        opt_cstr(anchor, stream_name),
-        input_device,
-        input_stream_params,
-        output_device,
-        output_stream_params,
-        latency_frames,
-        data_callback,
-        state_callback,
-        user_ptr
-    ));
-    *s = stream.as_ptr();
-    // Leaking pointer across C FFI
-    mem::forget(stream);
-    ffi::CUBEB_OK
+    catch("capi_stream_init", || {
+        let ctx = &mut *(c as *mut CTX);
+        let _log_id = enter_log_id(LogId::new("context", c));
+        let anchor = &(); // for lifetime of stream_name as CStr
+
+        let input_stream_params = as_opt_ref!(input_stream_params);
+        let output_stream_params = as_opt_ref!(output_stream_params);
+
+        let stream = _try!(ctx.stream_init(
+            opt_cstr(anchor, stream_name),
+            input_device,
+            input_stream_params,
+            output_device,
+            output_stream_params,
+            latency_frames,
+            data_callback,
+            state_callback,
+            user_ptr
+        ));
+        *s = stream.as_ptr();
+        // Leaking pointer across C FFI
+        mem::forget(stream);
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
+}
+
+/// # Safety
+///
+/// Entry point from C code.
+///
+/// This function is unsafe because it dereferences the given `c`, `s`, `stream_name`, `input_stream_params`,
+/// `output_stream_params`, `options`, `data_callback`, `state_callback`, and `user_ptr` pointers.
+/// The caller should ensure those pointers are valid.
+pub unsafe extern "C" fn capi_stream_init_with_options<CTX: ContextOps>(
+    c: *mut ffi::cubeb,
+    s: *mut *mut ffi::cubeb_stream,
+    stream_name: *const c_char,
+    input_device: ffi::cubeb_devid,
+    input_stream_params: *mut ffi::cubeb_stream_params,
+    output_device: ffi::cubeb_devid,
+    output_stream_params: *mut ffi::cubeb_stream_params,
+    options: *const ffi::cubeb_stream_options,
+    latency_frames: u32,
+    data_callback: ffi::cubeb_data_callback,
+    state_callback: ffi::cubeb_state_callback,
+    user_ptr: *mut c_void,
+) -> c_int {
+    catch("capi_stream_init_with_options", || {
+        let ctx = &mut *(c as *mut CTX);
+        let _log_id = enter_log_id(LogId::new("context", c));
+        let anchor = &(); // for lifetime of stream_name as CStr
+
+        let input_stream_params = as_opt_ref!(input_stream_params);
+        let output_stream_params = as_opt_ref!(output_stream_params);
+        let options = StreamOptionsRef::from_ptr(options as *mut _);
+
+        let stream = _try!(ctx.stream_init_with_options(
+            opt_cstr(anchor, stream_name),
+            input_device,
+            input_stream_params,
+            output_device,
+            output_stream_params,
+            options,
+            latency_frames,
+            data_callback,
+            state_callback,
+            user_ptr
+        ));
+        *s = stream.as_ptr();
+        // Leaking pointer across C FFI
+        mem::forget(stream);
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -231,7 +404,10 @@ pub unsafe extern "C" fn capi_stream_init<CTX: ContextOps>(
 /// This function is unsafe because it dereferences the given `s` pointer.
 /// The caller should ensure that pointer is valid.
 pub unsafe extern "C" fn capi_stream_destroy<STM>(s: *mut ffi::cubeb_stream) {
-    let _ = Box::from_raw(s as *mut STM);
+    let _ = catch("capi_stream_destroy", || {
+        let _log_id = enter_log_id(LogId::new("stream", s));
+        let _ = Box::from_raw(s as *mut STM);
+    });
 }
 
 /// # Safety
@@ -241,10 +417,14 @@ pub unsafe extern "C" fn capi_stream_destroy<STM>(s: *mut ffi::cubeb_stream) {
 /// This function is unsafe because it dereferences the given `s` pointer.
 /// The caller should ensure that pointer is valid.
 pub unsafe extern "C" fn capi_stream_start<STM: StreamOps>(s: *mut ffi::cubeb_stream) -> c_int {
-    let stm = &mut *(s as *mut STM);
+    catch("capi_stream_start", || {
+        let stm = &mut *(s as *mut STM);
+        let _log_id = enter_log_id(LogId::new("stream", s));
 
-    _try!(stm.start());
-    ffi::CUBEB_OK
+        _try!(stm.start());
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -254,10 +434,14 @@ pub unsafe extern "C" fn capi_stream_start<STM: StreamOps>(s: *mut ffi::cubeb_st
 /// This function is unsafe because it dereferences the given `s` pointer.
 /// The caller should ensure that pointer is valid.
 pub unsafe extern "C" fn capi_stream_stop<STM: StreamOps>(s: *mut ffi::cubeb_stream) -> c_int {
-    let stm = &mut *(s as *mut STM);
+    catch("capi_stream_stop", || {
+        let stm = &mut *(s as *mut STM);
+        let _log_id = enter_log_id(LogId::new("stream", s));
 
-    _try!(stm.stop());
-    ffi::CUBEB_OK
+        _try!(stm.stop());
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -270,10 +454,14 @@ pub unsafe extern "C" fn capi_stream_get_position<STM: StreamOps>(
     s: *mut ffi::cubeb_stream,
     position: *mut u64,
 ) -> c_int {
-    let stm = &mut *(s as *mut STM);
+    catch("capi_stream_get_position", || {
+        let stm = &mut *(s as *mut STM);
+        let _log_id = enter_log_id(LogId::new("stream", s));
 
-    *position = _try!(stm.position());
-    ffi::CUBEB_OK
+        *position = _try!(stm.position());
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -286,10 +474,14 @@ pub unsafe extern "C" fn capi_stream_get_latency<STM: StreamOps>(
     s: *mut ffi::cubeb_stream,
     latency: *mut u32,
 ) -> c_int {
-    let stm = &mut *(s as *mut STM);
+    catch("capi_stream_get_latency", || {
+        let stm = &mut *(s as *mut STM);
+        let _log_id = enter_log_id(LogId::new("stream", s));
 
-    *latency = _try!(stm.latency());
-    ffi::CUBEB_OK
+        *latency = _try!(stm.latency());
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -302,10 +494,14 @@ pub unsafe extern "C" fn capi_stream_get_input_latency<STM: StreamOps>(
     s: *mut ffi::cubeb_stream,
     latency: *mut u32,
 ) -> c_int {
-    let stm = &mut *(s as *mut STM);
+    catch("capi_stream_get_input_latency", || {
+        let stm = &mut *(s as *mut STM);
+        let _log_id = enter_log_id(LogId::new("stream", s));
 
-    *latency = _try!(stm.input_latency());
-    ffi::CUBEB_OK
+        *latency = _try!(stm.input_latency());
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -318,10 +514,14 @@ pub unsafe extern "C" fn capi_stream_set_volume<STM: StreamOps>(
     s: *mut ffi::cubeb_stream,
     volume: f32,
 ) -> c_int {
-    let stm = &mut *(s as *mut STM);
+    catch("capi_stream_set_volume", || {
+        let stm = &mut *(s as *mut STM);
+        let _log_id = enter_log_id(LogId::new("stream", s));
 
-    _try!(stm.set_volume(volume));
-    ffi::CUBEB_OK
+        _try!(stm.set_volume(volume));
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -334,14 +534,18 @@ pub unsafe extern "C" fn capi_stream_set_name<STM: StreamOps>(
     s: *mut ffi::cubeb_stream,
     name: *const c_char,
 ) -> c_int {
-    let stm = &mut *(s as *mut STM);
-    let anchor = &();
-    if let Some(name) = opt_cstr(anchor, name) {
-        _try!(stm.set_name(name));
-        ffi::CUBEB_OK
-    } else {
-        ffi::CUBEB_ERROR_INVALID_PARAMETER
-    }
+    catch("capi_stream_set_name", || {
+        let stm = &mut *(s as *mut STM);
+        let _log_id = enter_log_id(LogId::new("stream", s));
+        let anchor = &();
+        if let Some(name) = opt_cstr(anchor, name) {
+            _try!(stm.set_name(name));
+            ffi::CUBEB_OK
+        } else {
+            ffi::CUBEB_ERROR_INVALID_PARAMETER
+        }
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -354,10 +558,14 @@ pub unsafe extern "C" fn capi_stream_get_current_device<STM: StreamOps>(
     s: *mut ffi::cubeb_stream,
     device: *mut *mut ffi::cubeb_device,
 ) -> i32 {
-    let stm = &mut *(s as *mut STM);
+    catch("capi_stream_get_current_device", || {
+        let stm = &mut *(s as *mut STM);
+        let _log_id = enter_log_id(LogId::new("stream", s));
 
-    *device = _try!(stm.current_device()).as_ptr();
-    ffi::CUBEB_OK
+        *device = _try!(stm.current_device()).as_ptr();
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -370,10 +578,14 @@ pub unsafe extern "C" fn capi_stream_device_destroy<STM: StreamOps>(
     s: *mut ffi::cubeb_stream,
     device: *mut ffi::cubeb_device,
 ) -> c_int {
-    let stm = &mut *(s as *mut STM);
-    let device = DeviceRef::from_ptr(device);
-    let _ = stm.device_destroy(device);
-    ffi::CUBEB_OK
+    catch("capi_stream_device_destroy", || {
+        let stm = &mut *(s as *mut STM);
+        let _log_id = enter_log_id(LogId::new("stream", s));
+        let device = DeviceRef::from_ptr(device);
+        let _ = stm.device_destroy(device);
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -386,10 +598,14 @@ pub unsafe extern "C" fn capi_stream_register_device_changed_callback<STM: Strea
     s: *mut ffi::cubeb_stream,
     device_changed_callback: ffi::cubeb_device_changed_callback,
 ) -> c_int {
-    let stm = &mut *(s as *mut STM);
+    catch("capi_stream_register_device_changed_callback", || {
+        let stm = &mut *(s as *mut STM);
+        let _log_id = enter_log_id(LogId::new("stream", s));
 
-    _try!(stm.register_device_changed_callback(device_changed_callback));
-    ffi::CUBEB_OK
+        _try!(stm.register_device_changed_callback(device_changed_callback));
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 /// # Safety
@@ -405,10 +621,18 @@ pub unsafe extern "C" fn capi_register_device_collection_changed<CTX: ContextOps
     collection_changed_callback: ffi::cubeb_device_collection_changed_callback,
     user_ptr: *mut c_void,
 ) -> i32 {
-    let ctx = &mut *(c as *mut CTX);
-    let devtype = DeviceType::from_bits_truncate(devtype);
-    _try!(ctx.register_device_collection_changed(devtype, collection_changed_callback, user_ptr));
-    ffi::CUBEB_OK
+    catch("capi_register_device_collection_changed", || {
+        let ctx = &mut *(c as *mut CTX);
+        let _log_id = enter_log_id(LogId::new("context", c));
+        let devtype = DeviceType::from_bits_truncate(devtype);
+        _try!(ctx.register_device_collection_changed(
+            devtype,
+            collection_changed_callback,
+            user_ptr
+        ));
+        ffi::CUBEB_OK
+    })
+    .unwrap_or(ffi::CUBEB_ERROR)
 }
 
 fn opt_cstr<T>(_anchor: &T, ptr: *const c_char) -> Option<&CStr> {
@@ -0,0 +1,217 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Selection and mixing of the channels of an input device into the
+//! channels of an input stream.
+//!
+//! A [`ChannelMap`] is built from the [`ChannelRoute`]s a caller passes in
+//! `StreamOptionsRef::input_channel_map`, or from the default policy when
+//! none are given. Each stream channel is the average of the device
+//! channels routed to it, each scaled by the gain of its route. Averaging
+//! rather than summing means mixing full scale channels does not clip.
+
+use cubeb_core::{ChannelRoute, Error, Result};
+use duplex::Sample;
+
+/// How to compute the frames of a stream from the frames of a device.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelMap {
+    device_channels: usize,
+    // Device channels feeding each stream channel, with their gain divided
+    // by their count.
+    sources: Vec<Vec<(usize, f32)>>,
+}
+
+impl ChannelMap {
+    /// A map from frames of `device_channels` to frames of `channels`,
+    /// following `routes`. Stream channels without a route are silent.
+    pub fn new(
+        device_channels: usize,
+        channels: usize,
+        routes: &[ChannelRoute],
+    ) -> Result<ChannelMap> {
+        if channels == 0 {
+            return Err(Error::invalid_parameter());
+        }
+        let mut sources = vec![Vec::new(); channels];
+        for route in routes {
+            let device_channel = route.device_channel as usize;
+            let stream_channel = route.stream_channel as usize;
+            if device_channel >= device_channels || stream_channel >= channels {
+                return Err(Error::invalid_parameter());
+            }
+            sources[stream_channel].push((device_channel, route.gain));
+        }
+        for channel in &mut sources {
+            let count = channel.len() as f32;
+            for source in channel.iter_mut() {
+                source.1 /= count;
+            }
+        }
+        Ok(ChannelMap {
+            device_channels,
+            sources,
+        })
+    }
+
+    /// Stream channel `i` is device channel `selected[i]`.
+    pub fn select(device_channels: usize, selected: &[usize]) -> Result<ChannelMap> {
+        let routes: Vec<_> = selected
+            .iter()
+            .enumerate()
+            .map(|(i, &d)| ChannelRoute::new(d as u32, i as u32))
+            .collect();
+        ChannelMap::new(device_channels, selected.len(), &routes)
+    }
+
+    /// The default policy: the first `ignored_channels` of the device are
+    /// dropped, and the following ones are kept in order. Two channels
+    /// captured as mono are averaged. Stream channels past those of the
+    /// device are silent.
+    pub fn leading(device_channels: usize, ignored_channels: usize, channels: usize) -> ChannelMap {
+        assert!(channels > 0 && device_channels >= ignored_channels);
+        let available = device_channels - ignored_channels;
+        let selected: Vec<_> = if channels == 1 && available == 2 {
+            vec![vec![0, 1]]
+        } else {
+            (0..channels.min(available)).map(|c| vec![c]).collect()
+        };
+        let routes: Vec<_> = selected
+            .iter()
+            .enumerate()
+            .flat_map(|(i, sources)| {
+                sources
+                    .iter()
+                    .map(move |&d| ChannelRoute::new((ignored_channels + d) as u32, i as u32))
+            })
+            .collect();
+        ChannelMap::new(device_channels, channels, &routes).unwrap()
+    }
+
+    /// The same map, on device frames with `channels` more channels at
+    /// their start, for instance when the device is part of an aggregate
+    /// device.
+    pub fn offset(mut self, channels: usize) -> ChannelMap {
+        self.device_channels += channels;
+        for source in self.sources.iter_mut().flat_map(|s| s.iter_mut()) {
+            source.0 += channels;
+        }
+        self
+    }
+
+    /// Channels of the device frames.
+    pub fn device_channels(&self) -> usize {
+        self.device_channels
+    }
+
+    /// Channels of the stream frames.
+    pub fn channels(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Whether stream frames are the device frames unchanged.
+    pub fn is_identity(&self) -> bool {
+        self.device_channels == self.sources.len()
+            && self
+                .sources
+                .iter()
+                .enumerate()
+                .all(|(i, s)| s.len() == 1 && s[0] == (i, 1.0))
+    }
+
+    /// Computes the stream frame `frame` from the device frame
+    /// `device_frame`.
+    pub fn apply<T: Sample>(&self, device_frame: &[T], frame: &mut [T]) {
+        debug_assert_eq!(device_frame.len(), self.device_channels);
+        debug_assert_eq!(frame.len(), self.sources.len());
+        for (out, sources) in frame.iter_mut().zip(&self.sources) {
+            let sum: f32 = sources
+                .iter()
+                .map(|&(d, gain)| device_frame[d].to_f32() * gain)
+                .sum();
+            *out = T::from_f32(sum);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChannelMap;
+    use cubeb_core::{ChannelRoute, Error};
+
+    fn map<T: ::duplex::Sample>(map: &ChannelMap, input: &[T]) -> Vec<T> {
+        let mut output = vec![T::default(); map.channels()];
+        map.apply(input, &mut output);
+        output
+    }
+
+    #[test]
+    fn select() {
+        // Inputs 3 and 4 of an 8 channel interface.
+        let m = ChannelMap::select(8, &[2, 3]).unwrap();
+        assert_eq!(map(&m, &[1i16, 2, 3, 4, 5, 6, 7, 8]), [3, 4]);
+        assert!(!m.is_identity());
+        assert!(ChannelMap::select(2, &[0, 1]).unwrap().is_identity());
+        assert_eq!(
+            ChannelMap::select(2, &[2]).unwrap_err(),
+            Error::invalid_parameter()
+        );
+    }
+
+    #[test]
+    fn average_and_gain() {
+        let routes = [
+            ChannelRoute::new(0, 0),
+            ChannelRoute::new(1, 0),
+            ChannelRoute {
+                device_channel: 2,
+                stream_channel: 1,
+                gain: 0.5,
+            },
+        ];
+        let m = ChannelMap::new(3, 3, &routes).unwrap();
+        // Averaged, the unrouted channel is silent.
+        assert_eq!(map(&m, &[0.5f32, 0.25, 0.5]), [0.375, 0.25, 0.]);
+        // Full scale channels do not clip.
+        assert_eq!(map(&m, &[i16::MAX, i16::MAX, 0]), [i16::MAX, 0, 0]);
+        assert_eq!(map(&m, &[i16::MIN, i16::MIN, 0]), [i16::MIN, 0, 0]);
+    }
+
+    #[test]
+    fn gain_saturates() {
+        let routes = [ChannelRoute {
+            device_channel: 0,
+            stream_channel: 0,
+            gain: 4.0,
+        }];
+        let m = ChannelMap::new(1, 1, &routes).unwrap();
+        assert_eq!(map(&m, &[20000i16]), [i16::MAX]);
+        assert_eq!(map(&m, &[-20000i16]), [i16::MIN]);
+    }
+
+    #[test]
+    fn leading() {
+        assert!(ChannelMap::leading(2, 0, 2).is_identity());
+        // Stereo to mono is averaged.
+        let m = ChannelMap::leading(2, 0, 1);
+        assert_eq!(map(&m, &[30000i16, 30000]), [30000]);
+        // Otherwise trailing channels are dropped.
+        assert_eq!(map(&ChannelMap::leading(4, 0, 2), &[1i16, 2, 3, 4]), [1, 2]);
+        // Ignored channels come first.
+        let m = ChannelMap::leading(4, 1, 2);
+        assert_eq!(map(&m, &[1i16, 2, 3, 4]), [2, 3]);
+        let m = ChannelMap::leading(3, 1, 1);
+        assert_eq!(map(&m, &[1f32, 2., 4.]), [3.]);
+        // Missing channels are silent.
+        assert_eq!(map(&ChannelMap::leading(1, 0, 2), &[1i16]), [1, 0]);
+    }
+
+    #[test]
+    fn offset() {
+        let m = ChannelMap::select(2, &[1]).unwrap().offset(2);
+        assert_eq!(m.device_channels(), 4);
+        assert_eq!(map(&m, &[1i16, 2, 3, 4]), [4]);
+    }
+}
@@ -0,0 +1,436 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Behaviour every backend is expected to share, checked through the public
+//! cubeb API so that `cubeb.c`'s validation is exercised as well.
+//!
+//! `run::<MyContext>(&config)` returns the failed checks. Backends driven by
+//! real hardware or a sound server wait in real time; the mock backend
+//! advances its virtual clock instead, see `Config::wait`.
+
+use cubeb_core::{
+    ffi, Capabilities, ChannelLayout, Context, ContextRef, ErrorCode, Result, SampleFormat, State,
+    Stream, StreamParams, StreamParamsBuilder,
+};
+use std::ffi::CStr;
+use std::os::raw::{c_long, c_void};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use std::{ptr, slice, thread};
+use ContextOps;
+
+/// How the suite creates streams and lets time pass.
+pub struct Config {
+    pub rate: u32,
+    pub channels: u32,
+    pub latency_frames: u32,
+    /// Whether to check duplex streams.
+    pub duplex: bool,
+    /// Lets `duration` pass on `context`.
+    pub wait: fn(&ContextRef, Duration),
+    /// How long to wait for a callback before failing.
+    pub timeout: Duration,
+}
+
+impl Default for Config {
+    /// Real-time configuration.
+    fn default() -> Self {
+        Config {
+            rate: 48000,
+            channels: 2,
+            latency_frames: 4096,
+            duplex: false,
+            wait: |_, duration| thread::sleep(duration),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+const STEP: Duration = Duration::from_millis(10);
+
+/// Runs every check against a context created by `CTX::init`. Returns the
+/// description of each failed check.
+pub fn run<CTX: ContextOps>(config: &Config) -> std::result::Result<(), Vec<String>> {
+    let name = CStr::from_bytes_with_nul(b"cubeb conformance\0").unwrap();
+    let context = match CTX::init(Some(name)) {
+        Ok(context) => context,
+        Err(e) => return Err(vec![format!("init: {}", e)]),
+    };
+    run_with_context(&context, config)
+}
+
+/// Runs every check against `context`.
+pub fn run_with_context(
+    context: &Context,
+    config: &Config,
+) -> std::result::Result<(), Vec<String>> {
+    let mut checks: Vec<(&str, CheckFn)> = vec![
+        ("start_stop", start_stop),
+        ("drain", drain),
+        ("position", position),
+        ("volume", volume),
+        ("destroy_while_running", destroy_while_running),
+    ];
+    if config.duplex {
+        checks.push(("duplex", duplex));
+    }
+    if let Ok(capabilities) = context.capabilities() {
+        checks.push(("formats", formats));
+        if !capabilities.contains(Capabilities::STREAM_VOLUME) {
+            checks.retain(|&(name, _)| name != "volume");
+        }
+    }
+    let failures: Vec<_> = checks
+        .into_iter()
+        .filter_map(|(name, check)| {
+            check(context, config)
+                .err()
+                .map(|e| format!("{}: {}", name, e))
+        })
+        .collect();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures)
+    }
+}
+
+type Check = std::result::Result<(), String>;
+type CheckFn = fn(&Context, &Config) -> Check;
+
+macro_rules! ensure {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            return Err(format!($($arg)+));
+        }
+    };
+}
+
+// State shared with the callbacks of the stream under test.
+struct Recorder {
+    channels: usize,
+    states: Mutex<Vec<State>>,
+    callbacks: AtomicUsize,
+    frames: AtomicU64,
+    // Stop providing data, draining the stream, after this many frames.
+    drain_after: u64,
+    missing_input: AtomicUsize,
+    missing_output: AtomicUsize,
+    destroyed: AtomicBool,
+    callbacks_after_destroy: AtomicUsize,
+}
+
+impl Recorder {
+    fn new(config: &Config) -> Box<Self> {
+        Box::new(Recorder {
+            channels: config.channels as usize,
+            states: Mutex::new(Vec::new()),
+            callbacks: AtomicUsize::new(0),
+            frames: AtomicU64::new(0),
+            drain_after: u64::MAX,
+            missing_input: AtomicUsize::new(0),
+            missing_output: AtomicUsize::new(0),
+            destroyed: AtomicBool::new(false),
+            callbacks_after_destroy: AtomicUsize::new(0),
+        })
+    }
+
+    fn states(&self) -> Vec<State> {
+        self.states.lock().unwrap().clone()
+    }
+
+    fn callbacks(&self) -> usize {
+        self.callbacks.load(Ordering::SeqCst)
+    }
+}
+
+unsafe extern "C" fn data_cb(
+    _: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    input: *const c_void,
+    output: *mut c_void,
+    nframes: c_long,
+) -> c_long {
+    let recorder = &*(user_ptr as *const Recorder);
+    if recorder.destroyed.load(Ordering::SeqCst) {
+        recorder
+            .callbacks_after_destroy
+            .fetch_add(1, Ordering::SeqCst);
+        return 0;
+    }
+    recorder.callbacks.fetch_add(1, Ordering::SeqCst);
+    if input.is_null() {
+        recorder.missing_input.fetch_add(1, Ordering::SeqCst);
+    }
+    if output.is_null() {
+        recorder.missing_output.fetch_add(1, Ordering::SeqCst);
+    } else {
+        let samples = nframes as usize * recorder.channels;
+        slice::from_raw_parts_mut(output as *mut f32, samples)
+            .iter_mut()
+            .for_each(|s| *s = 0.0);
+    }
+    let done = recorder.frames.load(Ordering::SeqCst);
+    let frames = (nframes as u64).min(recorder.drain_after.saturating_sub(done));
+    recorder.frames.fetch_add(frames, Ordering::SeqCst);
+    frames as c_long
+}
+
+unsafe extern "C" fn state_cb(
+    _: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    state: ffi::cubeb_state,
+) {
+    let recorder = &*(user_ptr as *const Recorder);
+    recorder.states.lock().unwrap().push(State::from(state));
+}
+
+fn params(config: &Config, format: SampleFormat) -> StreamParams {
+    StreamParamsBuilder::new()
+        .format(format)
+        .rate(config.rate)
+        .channels(config.channels)
+        .layout(if config.channels == 2 {
+            ChannelLayout::STEREO
+        } else {
+            ChannelLayout::UNDEFINED
+        })
+        .take()
+}
+
+fn stream_init(
+    context: &Context,
+    config: &Config,
+    recorder: &Recorder,
+    input: bool,
+) -> Result<Stream> {
+    stream_init_with_format(context, config, recorder, input, SampleFormat::Float32NE)
+}
+
+fn stream_init_with_format(
+    context: &Context,
+    config: &Config,
+    recorder: &Recorder,
+    input: bool,
+    format: SampleFormat,
+) -> Result<Stream> {
+    let params = params(config, format);
+    let input_params = if input { Some(&*params) } else { None };
+    unsafe {
+        context.stream_init(
+            None,
+            ptr::null(),
+            input_params,
+            ptr::null(),
+            Some(&params),
+            config.latency_frames,
+            Some(data_cb),
+            Some(state_cb),
+            recorder as *const Recorder as *mut c_void,
+        )
+    }
+}
+
+// Waits until `cond` holds, for at most `config.timeout`.
+fn wait_for<F: Fn() -> bool>(context: &Context, config: &Config, cond: F) -> bool {
+    let mut waited = Duration::from_secs(0);
+    while !cond() {
+        if waited >= config.timeout {
+            return false;
+        }
+        (config.wait)(context, STEP);
+        waited += STEP;
+    }
+    true
+}
+
+// Data callbacks start after `start` and end once `stop` returns. The
+// state callback reports both, in order, and the stream can be restarted.
+fn start_stop(context: &Context, config: &Config) -> Check {
+    let recorder = Recorder::new(config);
+    let stream = stream_init(context, config, &recorder, false).map_err(|e| e.to_string())?;
+    (config.wait)(context, STEP * 5);
+    ensure!(recorder.callbacks() == 0, "data callback before start");
+    for _ in 0..2 {
+        stream.start().map_err(|e| format!("start: {}", e))?;
+        let before = recorder.callbacks();
+        ensure!(
+            wait_for(context, config, || recorder.callbacks() > before),
+            "no data callback after start"
+        );
+        stream.stop().map_err(|e| format!("stop: {}", e))?;
+        let stopped = recorder.callbacks();
+        (config.wait)(context, STEP * 5);
+        ensure!(
+            recorder.callbacks() == stopped,
+            "data callback after stop returned"
+        );
+    }
+    ensure!(
+        wait_for(context, config, || recorder.states().len() >= 4),
+        "missing state callbacks: {:?}",
+        recorder.states()
+    );
+    let states = recorder.states();
+    ensure!(
+        states
+            == [
+                State::Started,
+                State::Stopped,
+                State::Started,
+                State::Stopped
+            ],
+        "unexpected state sequence {:?}",
+        states
+    );
+    Ok(())
+}
+
+// A data callback returning less than requested drains the stream: the
+// state callback reports `Drained` and data callbacks end.
+fn drain(context: &Context, config: &Config) -> Check {
+    let mut recorder = Recorder::new(config);
+    recorder.drain_after = u64::from(config.rate / 10);
+    let stream = stream_init(context, config, &recorder, false).map_err(|e| e.to_string())?;
+    stream.start().map_err(|e| format!("start: {}", e))?;
+    ensure!(
+        wait_for(context, config, || recorder
+            .states()
+            .contains(&State::Drained)),
+        "not drained: {:?}",
+        recorder.states()
+    );
+    let drained = recorder.callbacks();
+    (config.wait)(context, STEP * 5);
+    ensure!(recorder.callbacks() == drained, "data callback after drain");
+    ensure!(
+        recorder.frames.load(Ordering::SeqCst) == recorder.drain_after,
+        "{} frames rendered, {} expected",
+        recorder.frames.load(Ordering::SeqCst),
+        recorder.drain_after
+    );
+    let states = recorder.states();
+    ensure!(
+        states == [State::Started, State::Drained],
+        "unexpected state sequence {:?}",
+        states
+    );
+    stream
+        .stop()
+        .map_err(|e| format!("stop after drain: {}", e))?;
+    Ok(())
+}
+
+// The position never goes backwards, advances while playing and does not
+// run ahead of the frames the data callback provided.
+fn position(context: &Context, config: &Config) -> Check {
+    let recorder = Recorder::new(config);
+    let stream = stream_init(context, config, &recorder, false).map_err(|e| e.to_string())?;
+    let initial = stream.position().map_err(|e| format!("position: {}", e))?;
+    ensure!(initial == 0, "position {} before start", initial);
+    stream.start().map_err(|e| format!("start: {}", e))?;
+    let mut last = 0;
+    for _ in 0..20 {
+        (config.wait)(context, STEP);
+        let position = stream.position().map_err(|e| format!("position: {}", e))?;
+        let frames = recorder.frames.load(Ordering::SeqCst);
+        ensure!(
+            position >= last,
+            "position went from {} to {}",
+            last,
+            position
+        );
+        ensure!(
+            position <= frames,
+            "position {} ahead of {} frames rendered",
+            position,
+            frames
+        );
+        last = position;
+    }
+    let advanced = wait_for(context, config, || {
+        stream.position().map(|p| p > 0).unwrap_or(false)
+    });
+    stream.stop().map_err(|e| format!("stop: {}", e))?;
+    ensure!(advanced, "position did not advance");
+    Ok(())
+}
+
+// Volumes outside of [0, 1] are rejected.
+fn volume(context: &Context, config: &Config) -> Check {
+    let recorder = Recorder::new(config);
+    let stream = stream_init(context, config, &recorder, false).map_err(|e| e.to_string())?;
+    for &v in &[0.0, 0.5, 1.0] {
+        stream
+            .set_volume(v)
+            .map_err(|e| format!("set_volume({}): {}", v, e))?;
+    }
+    for &v in &[-0.1, 1.1] {
+        match stream.set_volume(v) {
+            Err(ref e) if e.code() == ErrorCode::InvalidParameter => {}
+            r => return Err(format!("set_volume({}) returned {:?}", v, r)),
+        }
+    }
+    Ok(())
+}
+
+// Destroying a started stream stops it: no callback runs afterwards.
+fn destroy_while_running(context: &Context, config: &Config) -> Check {
+    let recorder = Recorder::new(config);
+    let stream = stream_init(context, config, &recorder, false).map_err(|e| e.to_string())?;
+    stream.start().map_err(|e| format!("start: {}", e))?;
+    ensure!(
+        wait_for(context, config, || recorder.callbacks() > 0),
+        "no data callback after start"
+    );
+    drop(stream);
+    recorder.destroyed.store(true, Ordering::SeqCst);
+    (config.wait)(context, STEP * 5);
+    let late = recorder.callbacks_after_destroy.load(Ordering::SeqCst);
+    ensure!(late == 0, "{} data callbacks after destroy", late);
+    Ok(())
+}
+
+// Every callback of a duplex stream gets both an input and an output
+// buffer of the same number of frames.
+fn duplex(context: &Context, config: &Config) -> Check {
+    let recorder = Recorder::new(config);
+    let stream = stream_init(context, config, &recorder, true).map_err(|e| e.to_string())?;
+    stream.start().map_err(|e| format!("start: {}", e))?;
+    let started = wait_for(context, config, || recorder.callbacks() >= 10);
+    stream.stop().map_err(|e| format!("stop: {}", e))?;
+    ensure!(started, "no data callback after start");
+    let missing_input = recorder.missing_input.load(Ordering::SeqCst);
+    let missing_output = recorder.missing_output.load(Ordering::SeqCst);
+    ensure!(
+        missing_input == 0 && missing_output == 0,
+        "{} callbacks without input, {} without output",
+        missing_input,
+        missing_output
+    );
+    Ok(())
+}
+
+// Streams can be created in every sample format the backend claims to
+// support.
+fn formats(context: &Context, config: &Config) -> Check {
+    let capabilities = context
+        .capabilities()
+        .map_err(|e| format!("capabilities: {}", e))?;
+    let recorder = Recorder::new(config);
+    for &format in &[
+        SampleFormat::S16LE,
+        SampleFormat::S16BE,
+        SampleFormat::Float32LE,
+        SampleFormat::Float32BE,
+    ] {
+        if capabilities.supports_format(format) {
+            stream_init_with_format(context, config, &recorder, false, format)
+                .map_err(|e| format!("{:?} stream: {}", format, e))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Compensation of the drift between the clocks of the input and output
+//! devices of a duplex stream.
+//!
+//! Input frames are buffered between the input and the output callbacks.
+//! When both devices run off different clocks, the buffer slowly fills or
+//! drains, until frames are dropped or silence is inserted. `DriftCorrector`
+//! instead measures the number of frames buffered each time the output
+//! pulls from the buffer, and computes how many input frames to consume per
+//! output frame to keep it at the level reached after a warm-up period.
+//!
+//! The level is low-pass filtered, to ignore the jitter of the callbacks,
+//! and fed to a PI controller. The resulting ratio stays within
+//! `MAX_CORRECTION` of 1, far beyond the drift of real devices, and changes
+//! slowly enough not to be heard.
+
+/// Time constant of the filter of the buffer level, in seconds.
+const FILTER_SECONDS: f64 = 1.0;
+/// Time after the first update at which the target level is taken, for the
+/// filter to have settled.
+const WARMUP_SECONDS: f64 = 3.0;
+/// Correction per second of latency error.
+const KP: f64 = 0.1;
+/// Correction per second of accumulated latency error, per second. Gives
+/// a damping ratio of 1/√2 with `KP`.
+const KI: f64 = KP * KP / 2.0;
+
+/// Largest deviation of the ratio from 1.
+pub const MAX_CORRECTION: f64 = 0.001;
+
+/// Computes the ratio at which to consume a buffer for its level to stay
+/// constant.
+#[derive(Debug)]
+pub struct DriftCorrector {
+    rate: f64,
+    elapsed: f64,
+    filtered: Option<f64>,
+    target: Option<f64>,
+    // Integral of the latency error, in seconds squared.
+    integral: f64,
+    ratio: f64,
+}
+
+impl DriftCorrector {
+    /// A corrector for a buffer of frames at `rate`.
+    pub fn new(rate: u32) -> DriftCorrector {
+        assert!(rate > 0);
+        DriftCorrector {
+            rate: f64::from(rate),
+            elapsed: 0.0,
+            filtered: None,
+            target: None,
+            integral: 0.0,
+            ratio: 1.0,
+        }
+    }
+
+    /// Takes the number of frames `buffered` before the output consumes
+    /// `frames` output frames, and returns the number of input frames to
+    /// consume per output frame.
+    pub fn update(&mut self, buffered: f64, frames: usize) -> f64 {
+        let dt = frames as f64 / self.rate;
+        let filtered = match self.filtered {
+            Some(f) => f + (buffered - f) * dt / (FILTER_SECONDS + dt),
+            None => buffered,
+        };
+        self.filtered = Some(filtered);
+        self.elapsed += dt;
+        let target = match self.target {
+            Some(target) => target,
+            None if self.elapsed >= WARMUP_SECONDS => {
+                self.target = Some(filtered);
+                filtered
+            }
+            None => return self.ratio,
+        };
+        let error = (filtered - target) / self.rate;
+        let integral = self.integral + error * dt;
+        let correction = KP * error + KI * integral;
+        // Stop integrating while saturated, not to overshoot once back.
+        if correction.abs() < MAX_CORRECTION {
+            self.integral = integral;
+        }
+        self.ratio = 1.0 + correction.clamp(-MAX_CORRECTION, MAX_CORRECTION);
+        self.ratio
+    }
+
+    /// The ratio returned by the last `update`.
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// The level kept, in frames, once the warm-up period is over.
+    pub fn target_frames(&self) -> Option<f64> {
+        self.target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DriftCorrector, MAX_CORRECTION};
+
+    // Updates of a quarter of a second, the warm-up lasting 12 of them.
+    fn warmed_up(level: f64) -> DriftCorrector {
+        let mut corrector = DriftCorrector::new(1000);
+        for _ in 0..12 {
+            assert_eq!(corrector.update(level, 250), 1.0);
+        }
+        assert_eq!(corrector.target_frames(), Some(level));
+        corrector
+    }
+
+    #[test]
+    fn warmup() {
+        let mut corrector = DriftCorrector::new(1000);
+        // Far from the level seen so far, but still warming up.
+        assert_eq!(corrector.update(10.0, 250), 1.0);
+        for _ in 0..10 {
+            assert_eq!(corrector.update(500.0, 250), 1.0);
+        }
+        assert_eq!(corrector.target_frames(), None);
+        corrector.update(500.0, 250);
+        assert!(corrector.target_frames().is_some());
+    }
+
+    #[test]
+    fn direction_and_bounds() {
+        // Too many frames buffered: consume faster.
+        let mut corrector = warmed_up(100.0);
+        assert!(corrector.update(200.0, 250) > 1.0);
+        for _ in 0..100 {
+            corrector.update(100_000.0, 250);
+        }
+        assert_eq!(corrector.ratio(), 1.0 + MAX_CORRECTION);
+        // Too few: consume slower.
+        let mut corrector = warmed_up(100.0);
+        assert!(corrector.update(0.0, 250) < 1.0);
+    }
+}
@@ -0,0 +1,625 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Buffering of the input of duplex streams, between the input callback of
+//! a backend and the output callback that calls the user with both.
+//!
+//! Input frames are stored in a ring buffer of fixed capacity, and handed
+//! out as a linear buffer. The buffer is meant to be used from the audio
+//! callbacks: once created, it does not allocate as long as no more than
+//! its capacity is pulled at once.
+//!
+//! The following policy applies, and is accounted for in [`Stats`]:
+//!
+//! - Channels: input frames are mapped to buffered frames by a
+//!   [`ChannelMap`], by default `ChannelMap::leading`.
+//! - Overflow: input frames that do not fit are dropped, the buffered ones
+//!   are kept.
+//! - Underrun: pulling more frames than are buffered pads the linear buffer
+//!   with silence after the buffered frames.
+//! - Latency: the frames buffered are the input latency added on top of the
+//!   device's. `trim` drops the oldest frames down to a target, for
+//!   instance when the output starts after the input. With
+//!   `correct_drift`, the frames are resampled as they are pulled, to keep
+//!   the latency constant when the input and output clocks drift apart.
+
+use channel_map::ChannelMap;
+use cubeb_core::{Quantizer, SampleFormat};
+use drift::DriftCorrector;
+use ringbuf::{Consumer, Producer, RingBuffer};
+use rt_check::enter_audio_callback;
+use std::os::raw::c_void;
+use std::slice;
+use std::{fmt, mem};
+
+/// A sample type the buffer can hold. `Default` is silence.
+pub trait Sample: Copy + Default + Send + 'static {
+    /// Whether values past full scale saturate, rather than being kept.
+    const SATURATES: bool;
+
+    /// Conversions for mixing and interpolation, without scaling. Integer
+    /// samples saturate.
+    fn to_f32(self) -> f32;
+    fn from_f32(value: f32) -> Self;
+
+    /// As `from_f32`, for sample `channel` of a frame, quantized by
+    /// `quantizer` for integer samples.
+    fn quantize(value: f32, _quantizer: &mut Quantizer, _channel: usize) -> Self {
+        Self::from_f32(value)
+    }
+}
+
+impl Sample for i16 {
+    const SATURATES: bool = true;
+
+    fn to_f32(self) -> f32 {
+        f32::from(self)
+    }
+    fn from_f32(value: f32) -> i16 {
+        value
+            .round()
+            .clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16
+    }
+    fn quantize(value: f32, quantizer: &mut Quantizer, channel: usize) -> i16 {
+        quantizer.quantize(channel, value)
+    }
+}
+
+impl Sample for f32 {
+    const SATURATES: bool = false;
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+    fn from_f32(value: f32) -> f32 {
+        value
+    }
+}
+
+/// Frame counts since the buffer was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Frames stored by `push`.
+    pub pushed_frames: u64,
+    /// Frames `push` dropped because the buffer was full.
+    pub overflow_frames: u64,
+    /// Buffered frames handed out by `pull`.
+    pub pulled_frames: u64,
+    /// Silent frames `pull` padded its buffer with.
+    pub underrun_frames: u64,
+    /// Frames `trim` dropped.
+    pub trimmed_frames: u64,
+}
+
+/// Input buffer of a duplex stream, for samples of type `T`.
+pub struct InputBuffer<T> {
+    producer: Producer<T>,
+    consumer: Consumer<T>,
+    linear: Vec<T>,
+    // From the frames given to `push` to the buffered frames.
+    map: ChannelMap,
+    // A mapped frame, when the map is not the identity.
+    frame: Vec<T>,
+    // Channels of the frames given to `push`.
+    input_channels: usize,
+    // Channels of the buffered frames.
+    channels: usize,
+    stats: Stats,
+    drift: Option<Drift<T>>,
+}
+
+// State of the resampling of the frames pulled to correct drift. Output
+// frames are interpolated between `prev` and `next`, at `frac`.
+struct Drift<T> {
+    corrector: DriftCorrector,
+    prev: Vec<T>,
+    next: Vec<T>,
+    frac: f64,
+}
+
+impl<T: Sample> InputBuffer<T> {
+    /// A buffer holding up to `capacity_frames` frames of `channels`, taken
+    /// from frames of `input_channels` of which the first
+    /// `ignored_channels` are dropped, see `ChannelMap::leading`.
+    pub fn new(
+        capacity_frames: usize,
+        input_channels: usize,
+        ignored_channels: usize,
+        channels: usize,
+    ) -> InputBuffer<T> {
+        let map = ChannelMap::leading(input_channels, ignored_channels, channels);
+        InputBuffer::with_channel_map(capacity_frames, map)
+    }
+
+    /// A buffer holding up to `capacity_frames` frames, computed by `map`
+    /// from the frames given to `push`.
+    pub fn with_channel_map(capacity_frames: usize, map: ChannelMap) -> InputBuffer<T> {
+        let input_channels = map.device_channels();
+        let channels = map.channels();
+        let (producer, consumer) = RingBuffer::new(capacity_frames * channels).split();
+        InputBuffer {
+            producer,
+            consumer,
+            linear: Vec::with_capacity(capacity_frames * channels),
+            frame: vec![T::default(); channels],
+            map,
+            input_channels,
+            channels,
+            stats: Stats::default(),
+            drift: None,
+        }
+    }
+
+    /// Resamples the frames pulled from now on, for the number of frames
+    /// buffered to stay where it is after a warm-up period, see `drift`.
+    /// `rate` is the rate of the frames.
+    pub fn correct_drift(&mut self, rate: u32) {
+        self.drift = Some(Drift {
+            corrector: DriftCorrector::new(rate),
+            prev: vec![T::default(); self.channels],
+            next: vec![T::default(); self.channels],
+            frac: 0.0,
+        });
+    }
+
+    /// Input frames consumed per output frame by the last `pull`, if drift
+    /// is corrected.
+    pub fn drift_ratio(&self) -> Option<f64> {
+        self.drift.as_ref().map(|d| d.corrector.ratio())
+    }
+
+    /// Channels of the buffered frames.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Frames buffered.
+    pub fn buffered_frames(&self) -> usize {
+        self.consumer.len() / self.channels
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Stores the interleaved frames of `input_channels` in `data`.
+    /// Returns the number of frames stored, the others are dropped.
+    pub fn push(&mut self, data: &[T]) -> usize {
+        debug_assert_eq!(data.len() % self.input_channels, 0);
+        let frames = data.len() / self.input_channels;
+        let stored = frames.min(self.producer.remaining() / self.channels);
+        if self.map.is_identity() {
+            self.producer.push_slice(&data[..stored * self.channels]);
+        } else {
+            for input in data.chunks(self.input_channels).take(stored) {
+                self.map.apply(input, &mut self.frame);
+                self.producer.push_slice(&self.frame);
+            }
+        }
+        if stored < frames {
+            cubeb_alog!(
+                "Input buffer full, could only push {} frames instead of {}",
+                stored,
+                frames
+            );
+        }
+        self.stats.pushed_frames += stored as u64;
+        self.stats.overflow_frames += (frames - stored) as u64;
+        stored
+    }
+
+    /// The `frames` oldest buffered frames, padded with silence if fewer
+    /// are buffered. When correcting drift, the frames are resampled from
+    /// the oldest buffered ones instead.
+    pub fn pull(&mut self, frames: usize) -> &[T] {
+        let samples = frames * self.channels;
+        self.linear.resize(samples, T::default());
+        let (read, missing) = match self.drift {
+            Some(ref mut drift) => pull_resampled(drift, &mut self.consumer, &mut self.linear),
+            None => {
+                let read = self.consumer.pop_slice(&mut self.linear);
+                for s in &mut self.linear[read..] {
+                    *s = T::default();
+                }
+                (read, samples - read)
+            }
+        };
+        if missing > 0 {
+            cubeb_alog!(
+                "Underrun during input data pull: (needed: {}, missing: {})",
+                samples,
+                missing
+            );
+        }
+        self.stats.pulled_frames += (read / self.channels) as u64;
+        self.stats.underrun_frames += (missing / self.channels) as u64;
+        &self.linear
+    }
+
+    /// Drops the oldest buffered frames to keep at most `frames`.
+    pub fn trim(&mut self, frames: usize) {
+        let buffered = self.buffered_frames();
+        if buffered > frames {
+            self.consumer.discard((buffered - frames) * self.channels);
+            self.stats.trimmed_frames += (buffered - frames) as u64;
+        }
+    }
+}
+
+// Interpolates `output` from the frames in `consumer`, at the ratio given by
+// the drift corrector. Returns the number of samples consumed and of samples
+// missing, which are replaced by silence.
+fn pull_resampled<T: Sample>(
+    drift: &mut Drift<T>,
+    consumer: &mut Consumer<T>,
+    output: &mut [T],
+) -> (usize, usize) {
+    let channels = drift.prev.len();
+    let buffered = (consumer.len() / channels) as f64;
+    let ratio = drift.corrector.update(buffered, output.len() / channels);
+    let mut read = 0;
+    let mut missing = 0;
+    for frame in output.chunks_mut(channels) {
+        let frac = drift.frac as f32;
+        for ((out, prev), next) in frame.iter_mut().zip(&drift.prev).zip(&drift.next) {
+            let prev = prev.to_f32();
+            *out = T::from_f32(prev + (next.to_f32() - prev) * frac);
+        }
+        drift.frac += ratio;
+        while drift.frac >= 1.0 {
+            drift.frac -= 1.0;
+            mem::swap(&mut drift.prev, &mut drift.next);
+            if consumer.pop_slice(&mut drift.next) == channels {
+                read += channels;
+            } else {
+                for s in &mut drift.next {
+                    *s = T::default();
+                }
+                missing += channels;
+            }
+        }
+    }
+    (read, missing)
+}
+
+impl<T> fmt::Debug for InputBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InputBuffer")
+            .field("map", &self.map)
+            .field("buffered_samples", &self.consumer.len())
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+/// An `InputBuffer` for the sample type of a `SampleFormat`, chosen at
+/// runtime, with a raw pointer interface for backends.
+#[derive(Debug)]
+pub enum DuplexBuffer {
+    S16(InputBuffer<i16>),
+    Float32(InputBuffer<f32>),
+}
+
+impl DuplexBuffer {
+    /// See `InputBuffer::new`.
+    pub fn new(
+        format: SampleFormat,
+        capacity_frames: usize,
+        input_channels: usize,
+        ignored_channels: usize,
+        channels: usize,
+    ) -> DuplexBuffer {
+        let map = ChannelMap::leading(input_channels, ignored_channels, channels);
+        DuplexBuffer::with_channel_map(format, capacity_frames, map)
+    }
+
+    /// See `InputBuffer::with_channel_map`.
+    pub fn with_channel_map(
+        format: SampleFormat,
+        capacity_frames: usize,
+        map: ChannelMap,
+    ) -> DuplexBuffer {
+        match format {
+            SampleFormat::S16LE | SampleFormat::S16BE | SampleFormat::S16NE => {
+                DuplexBuffer::S16(InputBuffer::with_channel_map(capacity_frames, map))
+            }
+            SampleFormat::Float32LE | SampleFormat::Float32BE | SampleFormat::Float32NE => {
+                DuplexBuffer::Float32(InputBuffer::with_channel_map(capacity_frames, map))
+            }
+        }
+    }
+
+    pub fn buffered_frames(&self) -> usize {
+        match self {
+            DuplexBuffer::S16(b) => b.buffered_frames(),
+            DuplexBuffer::Float32(b) => b.buffered_frames(),
+        }
+    }
+
+    pub fn stats(&self) -> Stats {
+        match self {
+            DuplexBuffer::S16(b) => b.stats(),
+            DuplexBuffer::Float32(b) => b.stats(),
+        }
+    }
+
+    /// See `InputBuffer::push`.
+    ///
+    /// # Safety
+    ///
+    /// `data` must point to `frames` interleaved frames of
+    /// `input_channels` samples of the buffer's format.
+    pub unsafe fn push_raw(&mut self, data: *const c_void, frames: usize) -> usize {
+        match self {
+            DuplexBuffer::S16(b) => {
+                let len = frames * b.input_channels;
+                b.push(slice::from_raw_parts(data as *const i16, len))
+            }
+            DuplexBuffer::Float32(b) => {
+                let len = frames * b.input_channels;
+                b.push(slice::from_raw_parts(data as *const f32, len))
+            }
+        }
+    }
+
+    /// See `InputBuffer::pull`. The frames are valid until the next call.
+    pub fn pull_raw(&mut self, frames: usize) -> *mut c_void {
+        match self {
+            DuplexBuffer::S16(b) => b.pull(frames).as_ptr() as *mut c_void,
+            DuplexBuffer::Float32(b) => b.pull(frames).as_ptr() as *mut c_void,
+        }
+    }
+
+    /// See `InputBuffer::trim`.
+    pub fn trim(&mut self, frames: usize) {
+        match self {
+            DuplexBuffer::S16(b) => b.trim(frames),
+            DuplexBuffer::Float32(b) => b.trim(frames),
+        }
+    }
+
+    /// See `InputBuffer::correct_drift`.
+    pub fn correct_drift(&mut self, rate: u32) {
+        match self {
+            DuplexBuffer::S16(b) => b.correct_drift(rate),
+            DuplexBuffer::Float32(b) => b.correct_drift(rate),
+        }
+    }
+}
+
+/// Test helper for backends: checks that `buffer`, created for
+/// `capacity_frames` frames, can be used from the audio callbacks. Pushing,
+/// trimming and pulling up to `capacity_frames` must not allocate, free or
+/// block, and pulling more, which grows the buffer, must be flagged. Panics
+/// otherwise. The test binary must install `rt_check::RtCheckAllocator`;
+/// release builds, where `rt_check` does nothing, only check the buffer.
+pub fn assert_rt_safe(mut buffer: DuplexBuffer, capacity_frames: usize) {
+    let input_channels = match buffer {
+        DuplexBuffer::S16(ref b) => b.input_channels,
+        DuplexBuffer::Float32(ref b) => b.input_channels,
+    };
+    // Large enough for either sample type.
+    let input = vec![0f32; capacity_frames * input_channels];
+    {
+        let scope = enter_audio_callback("assert_rt_safe");
+        unsafe { buffer.push_raw(input.as_ptr() as *const c_void, capacity_frames) };
+        assert_eq!(buffer.buffered_frames(), capacity_frames);
+        buffer.trim(capacity_frames / 2);
+        assert_eq!(buffer.buffered_frames(), capacity_frames / 2);
+        buffer.pull_raw(capacity_frames);
+        assert_eq!(buffer.buffered_frames(), 0);
+        assert_eq!(scope.violations(), 0);
+    }
+    if cfg!(debug_assertions) {
+        let scope = enter_audio_callback("assert_rt_safe");
+        buffer.pull_raw(capacity_frames + 1);
+        assert!(scope.violations() > 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assert_rt_safe, DuplexBuffer, InputBuffer, Stats};
+    use channel_map::ChannelMap;
+    use std::os::raw::c_void;
+    use SampleFormat;
+
+    #[test]
+    fn push_pull() {
+        let mut buffer = InputBuffer::<i16>::new(4, 2, 0, 2);
+        assert_eq!(buffer.push(&[1, 2, 3, 4]), 2);
+        assert_eq!(buffer.buffered_frames(), 2);
+        assert_eq!(buffer.pull(1), [1, 2]);
+        assert_eq!(buffer.pull(1), [3, 4]);
+        assert_eq!(buffer.buffered_frames(), 0);
+    }
+
+    #[test]
+    fn overflow_drops_new_frames() {
+        let mut buffer = InputBuffer::<i16>::new(2, 1, 0, 1);
+        assert_eq!(buffer.push(&[1, 2, 3]), 2);
+        assert_eq!(buffer.pull(2), [1, 2]);
+        assert_eq!(
+            buffer.stats(),
+            Stats {
+                pushed_frames: 2,
+                overflow_frames: 1,
+                pulled_frames: 2,
+                ..Stats::default()
+            }
+        );
+    }
+
+    #[test]
+    fn overflow_keeps_whole_frames() {
+        let mut buffer = InputBuffer::<i16>::new(2, 2, 0, 2);
+        assert_eq!(buffer.push(&[1, 2]), 1);
+        assert_eq!(buffer.push(&[3, 4, 5, 6]), 1);
+        assert_eq!(buffer.pull(2), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn underrun_pads_with_silence() {
+        let mut buffer = InputBuffer::<f32>::new(4, 1, 0, 1);
+        buffer.push(&[0.5, 0.25]);
+        assert_eq!(buffer.pull(4), [0.5, 0.25, 0., 0.]);
+        assert_eq!(buffer.stats().pulled_frames, 2);
+        assert_eq!(buffer.stats().underrun_frames, 2);
+    }
+
+    #[test]
+    fn trim_drops_oldest() {
+        let mut buffer = InputBuffer::<i16>::new(8, 1, 0, 1);
+        buffer.push(&[1, 2, 3, 4, 5]);
+        buffer.trim(8);
+        assert_eq!(buffer.buffered_frames(), 5);
+        buffer.trim(2);
+        assert_eq!(buffer.buffered_frames(), 2);
+        assert_eq!(buffer.stats().trimmed_frames, 3);
+        assert_eq!(buffer.pull(2), [4, 5]);
+    }
+
+    #[test]
+    fn downmix_stereo() {
+        let mut buffer = InputBuffer::<i16>::new(4, 2, 0, 1);
+        buffer.push(&[1, 2, 30000, 30000]);
+        // Averaged, not to clip.
+        assert_eq!(buffer.pull(2), [2, 30000]);
+    }
+
+    #[test]
+    fn drop_channels() {
+        // One ignored channel, then the first two of three kept.
+        let mut buffer = InputBuffer::<i16>::new(4, 4, 1, 2);
+        buffer.push(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(buffer.pull(2), [2, 3, 6, 7]);
+        // One ignored channel, then a stereo pair downmixed.
+        let mut buffer = InputBuffer::<f32>::new(4, 3, 1, 1);
+        buffer.push(&[1., 2., 3., 4., 5., 6.]);
+        assert_eq!(buffer.pull(2), [2.5, 5.5]);
+    }
+
+    #[test]
+    fn channel_map() {
+        // Inputs 3 and 4 of an 8 channel interface, swapped.
+        let map = ChannelMap::select(8, &[3, 2]).unwrap();
+        let mut buffer = InputBuffer::<i16>::with_channel_map(4, map);
+        let input: Vec<i16> = (0..16).collect();
+        assert_eq!(buffer.push(&input), 2);
+        assert_eq!(buffer.pull(2), [3, 2, 11, 10]);
+    }
+
+    #[test]
+    fn raw_interface() {
+        let mut buffer = DuplexBuffer::new(SampleFormat::Float32NE, 4, 2, 0, 2);
+        let input = [1f32, 2., 3., 4.];
+        let stored = unsafe { buffer.push_raw(input.as_ptr() as *const c_void, 2) };
+        assert_eq!(stored, 2);
+        let output = buffer.pull_raw(3) as *const f32;
+        let output = unsafe { std::slice::from_raw_parts(output, 6) };
+        assert_eq!(output, [1., 2., 3., 4., 0., 0.]);
+        assert!(matches!(
+            DuplexBuffer::new(SampleFormat::S16LE, 4, 1, 0, 1),
+            DuplexBuffer::S16(_)
+        ));
+    }
+
+    #[test]
+    fn drift_interpolates() {
+        let mut buffer = InputBuffer::<i16>::new(8, 1, 0, 1);
+        buffer.correct_drift(1000);
+        buffer.push(&[10, 20, 30, 40]);
+        // Unchanged until warmed up, behind the two frames interpolated
+        // between.
+        assert_eq!(buffer.pull(4), [0, 0, 10, 20]);
+        assert_eq!(buffer.drift_ratio(), Some(1.0));
+        assert_eq!(buffer.stats().pulled_frames, 4);
+        assert_eq!(buffer.stats().underrun_frames, 0);
+    }
+
+    // Simulates an input device whose clock runs `drift` faster than the
+    // output's. Returns the stats past the warm-up, the mean ratio over the
+    // last minute and the largest distance from the level after warm-up.
+    fn run_drift(drift: f64) -> (Stats, f64, f64) {
+        const RATE: u32 = 16000;
+        const INPUT_FRAMES: usize = 160;
+        const OUTPUT_FRAMES: usize = 256;
+        // Times in nanoseconds, not to accumulate rounding errors.
+        const SECOND: u64 = 1_000_000_000;
+        let input_period = (INPUT_FRAMES as f64 * 1e9 / (f64::from(RATE) * (1.0 + drift))) as u64;
+        let output_period = OUTPUT_FRAMES as u64 * SECOND / u64::from(RATE);
+        let mut buffer = InputBuffer::<f32>::new(RATE as usize, 1, 0, 1);
+        buffer.correct_drift(RATE);
+        let input = [1f32; INPUT_FRAMES];
+        // The output starts once two of its periods are buffered.
+        let (mut input_time, mut output_time) = (0, 2 * output_period);
+        let mut warm = None;
+        let mut target = 0.0;
+        let mut max_distance = 0f64;
+        let (mut ratio_sum, mut ratios) = (0.0, 0);
+        while output_time < 180 * SECOND {
+            if input_time <= output_time {
+                buffer.push(&input);
+                input_time += input_period;
+                continue;
+            }
+            let buffered = buffer.buffered_frames() as f64;
+            let output = buffer.pull(OUTPUT_FRAMES);
+            output_time += output_period;
+            if output_time < 10 * SECOND {
+                continue;
+            }
+            assert!(output.iter().all(|&s| s == 1.0));
+            if output_time >= 120 * SECOND {
+                ratio_sum += buffer.drift_ratio().unwrap();
+                ratios += 1;
+            }
+            match warm {
+                None => {
+                    warm = Some(buffer.stats());
+                    target = buffered;
+                }
+                Some(_) => max_distance = max_distance.max((buffered - target).abs()),
+            }
+        }
+        let warm = warm.unwrap();
+        let stats = buffer.stats();
+        let stats = Stats {
+            pushed_frames: stats.pushed_frames - warm.pushed_frames,
+            overflow_frames: stats.overflow_frames - warm.overflow_frames,
+            pulled_frames: stats.pulled_frames - warm.pulled_frames,
+            underrun_frames: stats.underrun_frames - warm.underrun_frames,
+            trimmed_frames: stats.trimmed_frames - warm.trimmed_frames,
+        };
+        (stats, ratio_sum / f64::from(ratios), max_distance)
+    }
+
+    #[test]
+    fn drift_corrected() {
+        for &drift in &[200e-6, -200e-6, 0.0] {
+            let (stats, ratio, max_distance) = run_drift(drift);
+            assert_eq!(stats.overflow_frames, 0);
+            assert_eq!(stats.underrun_frames, 0);
+            // Without correction, the level would have moved by 544 frames.
+            assert!(max_distance < 2.0 * 256.0, "{}: {}", drift, max_distance);
+            assert!((ratio - 1.0 - drift).abs() < 10e-6, "{}: {}", drift, ratio);
+        }
+    }
+
+    // The buffer is used from the audio callbacks, so it must not allocate
+    // once created.
+    #[test]
+    fn rt_safe() {
+        for &(format, input_channels, ignored, channels) in &[
+            (SampleFormat::S16NE, 2, 0, 2),
+            (SampleFormat::Float32NE, 2, 0, 2),
+            (SampleFormat::Float32NE, 2, 0, 1),
+            (SampleFormat::Float32NE, 4, 1, 2),
+        ] {
+            let buffer = DuplexBuffer::new(format, 256, input_channels, ignored, channels);
+            assert_rt_safe(buffer, 256);
+        }
+    }
+}
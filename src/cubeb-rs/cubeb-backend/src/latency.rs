@@ -0,0 +1,169 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! A latency shared by the streams of a context.
+//!
+//! Streams of a context often end up on the same device or server, which
+//! runs at a single buffer size. `LatencyController` decides it from the
+//! latencies the streams request, following a `LatencyPolicy`, within the
+//! range the backend supports. Backends add each stream as it is created,
+//! and remove it as it is destroyed; `LatencyUpdate::changed` tells them
+//! when the streams already running must follow a new latency.
+//!
+//! The unit of the latencies is up to the backend: frames for a device
+//! running at a single rate, time when the streams have different rates.
+
+/// How the latency of the streams is decided from their requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LatencyPolicy {
+    /// The latency requested by the first stream, kept until none is left.
+    First,
+    /// The largest latency requested, for the fewest wake-ups.
+    Largest,
+    /// The smallest latency requested, so no stream runs with more latency
+    /// than it asked for.
+    Smallest,
+}
+
+/// What adding or removing a stream did to the shared latency.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LatencyUpdate {
+    /// The latency of the streams, `None` once there are none.
+    pub latency: Option<u32>,
+    /// Whether the streams that were already running must move to
+    /// `latency`.
+    pub changed: bool,
+}
+
+/// Decides the latency shared by the streams of a context.
+#[derive(Clone, Debug)]
+pub struct LatencyController {
+    policy: LatencyPolicy,
+    min: u32,
+    max: u32,
+    // The latency requested by each stream, in the order they were added.
+    requests: Vec<u32>,
+    latency: Option<u32>,
+}
+
+impl LatencyController {
+    /// A controller following `policy`, clamping latencies between `min`
+    /// and `max`.
+    pub fn new(policy: LatencyPolicy, min: u32, max: u32) -> LatencyController {
+        assert!(min <= max);
+        LatencyController {
+            policy,
+            min,
+            max,
+            requests: Vec::new(),
+            latency: None,
+        }
+    }
+
+    pub fn policy(&self) -> LatencyPolicy {
+        self.policy
+    }
+
+    /// The number of streams added and not removed.
+    pub fn streams(&self) -> u32 {
+        self.requests.len() as u32
+    }
+
+    /// The latency of the streams, `None` without streams.
+    pub fn latency(&self) -> Option<u32> {
+        self.latency
+    }
+
+    /// Adds a stream requesting `latency`. The stream runs at the returned
+    /// latency.
+    pub fn add_stream(&mut self, latency: u32) -> LatencyUpdate {
+        self.requests.push(latency);
+        self.update()
+    }
+
+    /// Removes a stream that requested `latency`.
+    pub fn remove_stream(&mut self, latency: u32) -> LatencyUpdate {
+        let i = self
+            .requests
+            .iter()
+            .position(|&l| l == latency)
+            .expect("removing a stream that was not added");
+        self.requests.remove(i);
+        self.update()
+    }
+
+    fn update(&mut self) -> LatencyUpdate {
+        let requested = match self.policy {
+            LatencyPolicy::First => self.latency.or_else(|| self.requests.first().cloned()),
+            LatencyPolicy::Largest => self.requests.iter().max().cloned(),
+            LatencyPolicy::Smallest => self.requests.iter().min().cloned(),
+        };
+        let latency = if self.requests.is_empty() {
+            None
+        } else {
+            requested.map(|l| l.clamp(self.min, self.max))
+        };
+        let changed = self.latency.is_some() && latency.is_some() && latency != self.latency;
+        self.latency = latency;
+        LatencyUpdate { latency, changed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(latency: Option<u32>, changed: bool) -> LatencyUpdate {
+        LatencyUpdate { latency, changed }
+    }
+
+    #[test]
+    fn test_first_wins() {
+        let mut c = LatencyController::new(LatencyPolicy::First, 128, 512);
+        assert_eq!(c.latency(), None);
+        assert_eq!(c.add_stream(1024), update(Some(512), false));
+        assert_eq!(c.add_stream(256), update(Some(512), false));
+        // Kept after the first stream is gone.
+        assert_eq!(c.remove_stream(1024), update(Some(512), false));
+        assert_eq!(c.streams(), 1);
+        assert_eq!(c.remove_stream(256), update(None, false));
+        assert_eq!(c.add_stream(64), update(Some(128), false));
+    }
+
+    #[test]
+    fn test_largest_wins() {
+        let mut c = LatencyController::new(LatencyPolicy::Largest, 128, 4096);
+        assert_eq!(c.add_stream(256), update(Some(256), false));
+        assert_eq!(c.add_stream(128), update(Some(256), false));
+        assert_eq!(c.add_stream(1024), update(Some(1024), true));
+        assert_eq!(c.add_stream(1024), update(Some(1024), false));
+        assert_eq!(c.remove_stream(1024), update(Some(1024), false));
+        assert_eq!(c.remove_stream(1024), update(Some(256), true));
+        assert_eq!(c.remove_stream(256), update(Some(128), true));
+        assert_eq!(c.remove_stream(128), update(None, false));
+        assert_eq!(c.streams(), 0);
+    }
+
+    #[test]
+    fn test_smallest_wins() {
+        let mut c = LatencyController::new(LatencyPolicy::Smallest, 128, 4096);
+        assert_eq!(c.add_stream(1024), update(Some(1024), false));
+        assert_eq!(c.add_stream(2048), update(Some(1024), false));
+        assert_eq!(c.add_stream(64), update(Some(128), true));
+        // Clamped to the same latency.
+        assert_eq!(c.add_stream(100), update(Some(128), false));
+        assert_eq!(c.remove_stream(64), update(Some(128), false));
+        assert_eq!(c.remove_stream(100), update(Some(1024), true));
+        assert_eq!(c.latency(), Some(1024));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_remove_unknown_stream() {
+        let mut c = LatencyController::new(LatencyPolicy::Smallest, 128, 4096);
+        c.add_stream(256);
+        c.remove_stream(512);
+    }
+}
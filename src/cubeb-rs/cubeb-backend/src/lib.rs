@@ -4,14 +4,30 @@
 // accompanying file LICENSE for details.
 
 extern crate cubeb_core;
+extern crate ringbuf;
 
+#[macro_use]
 pub mod capi;
 #[macro_use]
 pub mod log;
+pub mod byte_order;
+pub mod channel_map;
+pub mod conformance;
+pub mod drift;
+pub mod duplex;
+pub mod latency;
+mod log_queue;
+pub mod mixer;
+pub mod mock;
 mod ops;
+pub mod resampler;
+pub mod rt_check;
 mod traits;
+pub mod unwind;
+pub mod virtual_device;
 
 // Re-export cubeb_core types
 pub use cubeb_core::*;
+pub use log::log_enabled;
 pub use ops::Ops;
 pub use traits::{ContextOps, StreamOps};
@@ -3,13 +3,176 @@
 // This program is made available under an ISC-style license.  See the
 // accompanying file LICENSE for details.
 
+use ffi;
+use log_queue;
+use std::cell::Cell;
+use std::mem;
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use {Error, LogId, LogLevel, Result};
+
 /// Maximum length in bytes for a log message.
-/// Longer messages are silently truncated.  See `write_str`.
+/// Longer messages are truncated and end with `TRUNCATED_MARKER`.
 const LOG_LIMIT: usize = 1024;
 
+const TRUNCATED_MARKER: &str = " [truncated]";
+
+static TRUNCATED_MESSAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of log messages truncated because they did not fit in the log
+/// buffer.
+pub fn truncated_log_messages() -> usize {
+    TRUNCATED_MESSAGES.load(Ordering::Relaxed)
+}
+
+/// Number of asynchronous log messages dropped because the log queue was
+/// full.
+pub fn dropped_log_messages() -> usize {
+    log_queue::dropped()
+}
+
+type LogCallback = unsafe extern "C" fn(*const c_char, ...);
+type LogMessageCallback =
+    unsafe extern "C" fn(ffi::cubeb_log_level, *const c_char, u32, *const c_char);
+
+// Level and callbacks of the Rust logger, the counterparts of the
+// `g_cubeb_log_*` globals of `cubeb_log.cpp`. Callbacks are stored as
+// pointers so that the real-time threads can read them without locking.
+static LOG_LEVEL: AtomicUsize = AtomicUsize::new(ffi::CUBEB_LOG_DISABLED as usize);
+static LOG_CALLBACK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+static LOG_MESSAGE_CALLBACK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Level up to which messages are logged.
+pub fn log_level() -> LogLevel {
+    LogLevel::from(LOG_LEVEL.load(Ordering::Relaxed) as ffi::cubeb_log_level)
+}
+
+/// Whether messages are logged. Shadows `cubeb_core::log_enabled`, which
+/// reads the level of the C logger.
+pub fn log_enabled() -> bool {
+    log_level() != LogLevel::Disabled
+}
+
+pub(crate) fn log_callback() -> ffi::cubeb_log_callback {
+    let callback = LOG_CALLBACK.load(Ordering::Acquire);
+    if callback.is_null() {
+        None
+    } else {
+        Some(unsafe { mem::transmute::<*mut (), LogCallback>(callback) })
+    }
+}
+
+pub(crate) fn log_message_callback() -> ffi::cubeb_log_message_callback {
+    let callback = LOG_MESSAGE_CALLBACK.load(Ordering::Acquire);
+    if callback.is_null() {
+        None
+    } else {
+        Some(unsafe { mem::transmute::<*mut (), LogMessageCallback>(callback) })
+    }
+}
+
+/// Sets the level and callbacks the Rust backends log with. The message
+/// callback, if any, takes precedence over the plain one. `cubeb.c` keeps
+/// them in sync with the C logger through each backend's
+/// `*_rust_set_logging` entry point.
+pub fn set_logging(
+    level: LogLevel,
+    log_callback: ffi::cubeb_log_callback,
+    message_callback: ffi::cubeb_log_message_callback,
+) {
+    // Disabled first and enabled last, so that a callback is never called
+    // once unregistered.
+    LOG_LEVEL.store(ffi::CUBEB_LOG_DISABLED as usize, Ordering::Relaxed);
+    LOG_CALLBACK.store(
+        log_callback.map_or(ptr::null_mut(), |f| f as *mut ()),
+        Ordering::Release,
+    );
+    LOG_MESSAGE_CALLBACK.store(
+        message_callback.map_or(ptr::null_mut(), |f| f as *mut ()),
+        Ordering::Release,
+    );
+    LOG_LEVEL.store(level as usize, Ordering::Relaxed);
+    if level != LogLevel::Disabled && (log_callback.is_some() || message_callback.is_some()) {
+        log_queue::start();
+    }
+}
+
+// Fails like the C setters: a callback is needed to log, and only one can
+// be registered at a time.
+fn check_callback(level: LogLevel, has_callback: bool) -> Result<()> {
+    if !has_callback && level != LogLevel::Disabled {
+        return Err(Error::invalid_parameter());
+    }
+    if has_callback && (log_callback().is_some() || log_message_callback().is_some()) {
+        return Err(Error::not_supported());
+    }
+    Ok(())
+}
+
+/// Rust counterpart of `cubeb_set_log_callback`, for the Rust backends
+/// only.
+pub fn set_log_callback(level: LogLevel, callback: ffi::cubeb_log_callback) -> Result<()> {
+    check_callback(level, callback.is_some())?;
+    set_logging(level, callback, None);
+    Ok(())
+}
+
+/// Rust counterpart of `cubeb_set_log_message_callback`, for the Rust
+/// backends only.
+pub fn set_log_message_callback(
+    level: LogLevel,
+    callback: ffi::cubeb_log_message_callback,
+) -> Result<()> {
+    check_callback(level, callback.is_some())?;
+    set_logging(level, None, callback);
+    Ok(())
+}
+
+/// Counterpart of `cubeb_async_log_reset_threads`, to be called when the
+/// threads logging asynchronously have been replaced. The Rust log queue
+/// accepts messages from any number of threads, so this only starts the
+/// drain thread if logging is enabled and it is not running yet.
+pub fn async_log_reset_threads() {
+    if log_callback().is_some() || log_message_callback().is_some() {
+        log_queue::start();
+    }
+}
+
+thread_local! {
+    static CURRENT_LOG_ID: Cell<Option<LogId>> = const { Cell::new(None) };
+}
+
+/// Identifier attached to messages logged from this thread without an
+/// explicit `id:`.
+pub fn current_log_id() -> Option<LogId> {
+    CURRENT_LOG_ID.with(|id| id.get())
+}
+
+/// Attaches `id` to the messages logged from this thread until the returned
+/// guard is dropped.
+pub fn enter_log_id(id: LogId) -> LogIdGuard {
+    LogIdGuard {
+        previous: CURRENT_LOG_ID.with(|current| current.replace(Some(id))),
+    }
+}
+
+/// Restores the previous log identifier of the thread when dropped.
+#[must_use]
+pub struct LogIdGuard {
+    previous: Option<LogId>,
+}
+
+impl Drop for LogIdGuard {
+    fn drop(&mut self) {
+        CURRENT_LOG_ID.with(|current| current.set(self.previous));
+    }
+}
+
 struct StaticCString<const N: usize> {
     buf: [std::mem::MaybeUninit<u8>; N],
     len: usize,
+    truncated: bool,
 }
 
 impl<const N: usize> StaticCString<N> {
@@ -17,6 +180,7 @@ impl<const N: usize> StaticCString<N> {
         StaticCString {
             buf: unsafe { std::mem::MaybeUninit::uninit().assume_init() },
             len: 0,
+            truncated: false,
         }
     }
 
@@ -24,93 +188,371 @@ impl<const N: usize> StaticCString<N> {
         unsafe {
             std::ffi::CStr::from_bytes_with_nul_unchecked(std::slice::from_raw_parts(
                 self.buf.as_ptr().cast::<u8>(),
-                self.len,
+                self.len + 1,
             ))
         }
     }
-}
 
-impl<const N: usize> std::fmt::Write for StaticCString<N> {
-    fn write_str(&mut self, s: &str) -> std::fmt::Result {
-        use std::convert::TryInto;
-        let s = s.as_bytes();
-        let end = s.len().min(N.checked_sub(1).unwrap() - self.len);
-        debug_assert_eq!(s.len(), end, "message truncated");
+    fn is_char_boundary(&self, pos: usize) -> bool {
+        pos >= self.len || unsafe { self.buf[pos].assume_init() } & 0xc0 != 0x80
+    }
+
+    // Appends as much of `s` as fits, without splitting a character.
+    fn push(&mut self, s: &str) -> bool {
+        let room = N.checked_sub(1).unwrap() - self.len;
+        let mut end = s.len().min(room);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
         unsafe {
             std::ptr::copy_nonoverlapping(
-                s[..end].as_ptr(),
-                self.buf
-                    .as_mut_ptr()
-                    .cast::<u8>()
-                    .offset(self.len.try_into().unwrap()),
+                s.as_ptr(),
+                self.buf.as_mut_ptr().cast::<u8>().add(self.len),
                 end,
             )
         };
         self.len += end;
         self.buf[self.len].write(0);
+        end == s.len()
+    }
+
+    // Appends `suffix`. Truncated messages are cut back to make room for
+    // `TRUNCATED_MARKER` before the suffix. Returns whether the message was
+    // truncated.
+    fn finish(&mut self, suffix: &str) -> bool {
+        if !self.truncated && self.len + suffix.len() < N {
+            self.push(suffix);
+            return false;
+        }
+        let mut len = self
+            .len
+            .min((N - 1).saturating_sub(TRUNCATED_MARKER.len() + suffix.len()));
+        while !self.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.len = len;
+        self.push(TRUNCATED_MARKER);
+        self.push(suffix);
+        true
+    }
+}
+
+impl<const N: usize> std::fmt::Write for StaticCString<N> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        if !self.truncated && !self.push(s) {
+            self.truncated = true;
+        }
         Ok(())
     }
 }
 
-/// Formats `$file:line: $msg\n` into an on-stack buffer of size `LOG_LIMIT`,
-/// then calls `log_callback` with a pointer to the formatted message.
+// Name of the source file, without directories, from a nul-terminated
+// `file!()`.
+fn file_name(file: &'static str) -> &'static str {
+    match file.rfind(['/', '\\']) {
+        Some(pos) => &file[pos + 1..],
+        None => file,
+    }
+}
+
+fn format_message<const N: usize>(
+    buf: &mut StaticCString<N>,
+    location: Option<(&str, u32)>,
+    id: Option<LogId>,
+    msg: std::fmt::Arguments,
+) {
+    use std::fmt::Write;
+    if let Some((file, line)) = location {
+        let _ = write!(buf, "{}:{}: ", file, line);
+    }
+    if let Some(id) = id {
+        let _ = write!(buf, "[{}] ", id);
+    }
+    let _ = buf.write_fmt(msg);
+    let suffix = if location.is_some() { "\n" } else { "" };
+    if buf.finish(suffix) {
+        TRUNCATED_MESSAGES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Logs `msg` through the message callback installed with
+/// `set_log_message_callback` if there is one. Otherwise formats
+/// `$file:line: $msg\n` into an on-stack buffer of size `LOG_LIMIT` for the
+/// plain log callback. `asynchronous` messages are copied into a lock-free
+/// queue without allocating, and handed to the callback by a drain thread.
+/// Messages with an `id` are prefixed with `[name@0x...] `.
+/// `file` must be nul-terminated.
 pub fn cubeb_log_internal_buf_fmt(
-    log_callback: unsafe extern "C" fn(*const i8, ...),
-    file: &str,
+    asynchronous: bool,
+    level: LogLevel,
+    file: &'static str,
     line: u32,
+    id: Option<LogId>,
     msg: std::fmt::Arguments,
 ) {
-    let filename = std::path::Path::new(file)
-        .file_name()
-        .unwrap()
-        .to_str()
-        .unwrap();
-    let mut buf = StaticCString::<LOG_LIMIT>::new();
-    let _ = std::fmt::write(&mut buf, format_args!("{}:{}: {}\n", filename, line, msg));
-    unsafe {
-        log_callback(buf.as_cstr().as_ptr());
+    let message_callback = log_message_callback();
+    let log_callback = log_callback();
+    if message_callback.is_none() && log_callback.is_none() {
+        return;
+    }
+    let file = file_name(file);
+    if asynchronous {
+        // The drain thread adds the location if needed.
+        let mut buf = StaticCString::<{ log_queue::MESSAGE_LIMIT }>::new();
+        format_message(&mut buf, None, id, msg);
+        log_queue::push(level, file, line, buf.as_cstr().to_bytes());
+        return;
+    }
+    let location = if message_callback.is_some() {
+        None
+    } else {
+        Some((&file[..file.len() - 1], line))
     };
+    let fmt = b"%s\0".as_ptr() as *const c_char;
+    let file = file.as_ptr() as *const c_char;
+    unsafe {
+        let mut buf = StaticCString::<LOG_LIMIT>::new();
+        format_message(&mut buf, location, id, msg);
+        if let Some(log_callback) = message_callback {
+            log_callback(level as _, file, line, buf.as_cstr().as_ptr());
+        } else if let Some(log_callback) = log_callback {
+            log_callback(fmt, buf.as_cstr().as_ptr());
+        }
+    }
 }
 
 #[macro_export]
 macro_rules! cubeb_log_internal {
-    ($log_callback: expr, $level: expr, $fmt: expr, $($arg: expr),+) => {
-        #[allow(unused_unsafe)]
-        unsafe {
-            if $level <= $crate::ffi::g_cubeb_log_level.into() {
-                if let Some(log_callback) = $log_callback {
-                    $crate::log::cubeb_log_internal_buf_fmt(log_callback, file!(), line!(), format_args!($fmt, $($arg),+));
-                }
+    ($asynchronous: expr, $level: expr, $id: expr, $fmt: expr, $($arg: expr),+) => {
+        {
+            if $level <= $crate::log::log_level() {
+                $crate::log::cubeb_log_internal_buf_fmt($asynchronous, $level, concat!(file!(), "\0"), line!(), $id, format_args!($fmt, $($arg),+));
             }
         }
     };
-    ($log_callback: expr, $level: expr, $msg: expr) => {
-        cubeb_log_internal!($log_callback, $level, "{}", $msg);
+    ($asynchronous: expr, $level: expr, $id: expr, $msg: expr) => {
+        cubeb_log_internal!($asynchronous, $level, $id, "{}", $msg);
     };
 }
 
+/// Logs a message at normal level. Messages are tagged with the identifier
+/// entered with `log::enter_log_id`, or the one given as `id: ...` first.
 #[macro_export]
 macro_rules! cubeb_log {
-    ($($arg: expr),+) => (cubeb_log_internal!($crate::ffi::g_cubeb_log_callback, $crate::LogLevel::Normal, $($arg),+));
+    (id: $id: expr, $($arg: expr),+) => (cubeb_log_internal!(false, $crate::LogLevel::Normal, Some($id), $($arg),+));
+    ($($arg: expr),+) => (cubeb_log_internal!(false, $crate::LogLevel::Normal, $crate::log::current_log_id(), $($arg),+));
 }
 
 #[macro_export]
 macro_rules! cubeb_logv {
-    ($($arg: expr),+) => (cubeb_log_internal!($crate::ffi::g_cubeb_log_callback, $crate::LogLevel::Verbose, $($arg),+));
+    (id: $id: expr, $($arg: expr),+) => (cubeb_log_internal!(false, $crate::LogLevel::Verbose, Some($id), $($arg),+));
+    ($($arg: expr),+) => (cubeb_log_internal!(false, $crate::LogLevel::Verbose, $crate::log::current_log_id(), $($arg),+));
 }
 
 #[macro_export]
 macro_rules! cubeb_alog {
-    ($($arg: expr),+) => (cubeb_log_internal!($crate::ffi::cubeb_async_log.into(), $crate::LogLevel::Normal, $($arg),+));
+    (id: $id: expr, $($arg: expr),+) => (cubeb_log_internal!(true, $crate::LogLevel::Normal, Some($id), $($arg),+));
+    ($($arg: expr),+) => (cubeb_log_internal!(true, $crate::LogLevel::Normal, $crate::log::current_log_id(), $($arg),+));
 }
 
 #[macro_export]
 macro_rules! cubeb_alogv {
-    ($($arg: expr),+) => (cubeb_log_internal!($crate::ffi::cubeb_async_log.into(), $crate::LogLevel::Verbose, $($arg),+));
+    (id: $id: expr, $($arg: expr),+) => (cubeb_log_internal!(true, $crate::LogLevel::Verbose, Some($id), $($arg),+));
+    ($($arg: expr),+) => (cubeb_log_internal!(true, $crate::LogLevel::Verbose, $crate::log::current_log_id(), $($arg),+));
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use ffi;
+    use std::ffi::CStr;
+    use std::fmt::Write;
+    use std::os::raw::c_char;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+    use {Error, LogId, LogLevel};
+
+    // Level, file, line and text of the messages seen by `capture`.
+    type Message = (LogLevel, String, u32, String);
+
+    static MESSAGES: Mutex<Vec<Message>> = Mutex::new(Vec::new());
+
+    // Held by the tests installing a callback, which must not overlap.
+    static CALLBACK: Mutex<()> = Mutex::new(());
+
+    unsafe extern "C" fn capture(
+        level: ffi::cubeb_log_level,
+        file: *const c_char,
+        line: u32,
+        message: *const c_char,
+    ) {
+        MESSAGES.lock().unwrap().push((
+            LogLevel::from(level),
+            CStr::from_ptr(file).to_string_lossy().into_owned(),
+            line,
+            CStr::from_ptr(message).to_string_lossy().into_owned(),
+        ));
+    }
+
+    // Runs `f` with `capture` installed and returns the captured messages
+    // containing `marker`.
+    fn captured<F: FnOnce()>(marker: &str, f: F) -> Vec<Message> {
+        let _guard = CALLBACK.lock().unwrap();
+        set_log_message_callback(LogLevel::Normal, Some(capture)).unwrap();
+        f();
+        set_log_message_callback(LogLevel::Disabled, None).unwrap();
+        MESSAGES
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| m.3.contains(marker))
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_message_callback() {
+        let line = line!() + 2;
+        let messages = captured("100%", || {
+            cubeb_log!("{} 100% captured", "message");
+            cubeb_logv!("verbose message 100% filtered");
+        });
+        assert_eq!(
+            messages,
+            vec![(
+                LogLevel::Normal,
+                "log.rs".to_owned(),
+                line,
+                "message 100% captured".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_one_callback_at_a_time() {
+        let _guard = CALLBACK.lock().unwrap();
+        assert_eq!(
+            set_log_message_callback(LogLevel::Normal, None),
+            Err(Error::invalid_parameter())
+        );
+        assert!(!log_enabled());
+        set_log_message_callback(LogLevel::Verbose, Some(capture)).unwrap();
+        assert_eq!(log_level(), LogLevel::Verbose);
+        assert_eq!(
+            set_log_message_callback(LogLevel::Normal, Some(capture)),
+            Err(Error::not_supported())
+        );
+        // Unregistering with either setter works.
+        set_log_callback(LogLevel::Disabled, None).unwrap();
+        assert!(!log_enabled());
+        assert!(log_message_callback().is_none());
+    }
+
+    #[test]
+    fn test_log_id() {
+        let stream = LogId::new("stream", 0x10 as *const u8);
+        let context = LogId::new("context", 0x20 as *const u8);
+        let messages = captured("tagged", || {
+            cubeb_log!("untagged");
+            cubeb_log!(id: stream, "{} tagged", "explicitly");
+            let _context = enter_log_id(context);
+            cubeb_log!("implicitly tagged");
+            {
+                let _stream = enter_log_id(stream);
+                cubeb_log!("nested tagged");
+                cubeb_log!(id: context, "overridden tagged");
+            }
+            cubeb_log!("restored tagged");
+        });
+        let messages: Vec<_> = messages.into_iter().map(|m| m.3).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "untagged",
+                "[stream@0x10] explicitly tagged",
+                "[context@0x20] implicitly tagged",
+                "[stream@0x10] nested tagged",
+                "[context@0x20] overridden tagged",
+                "[context@0x20] restored tagged",
+            ]
+        );
+        assert_eq!(current_log_id(), None);
+    }
+
+    #[test]
+    fn test_async_message_callback() {
+        let stream = LogId::new("stream", 0x10 as *const u8);
+        let line = line!() + 2;
+        let messages = captured("queued", || {
+            cubeb_alog!(id: stream, "{} queued", "message");
+            // Wait for the drain thread.
+            for _ in 0..100 {
+                if MESSAGES
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .any(|m| m.3.contains("queued"))
+                {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+        assert_eq!(
+            messages,
+            vec![(
+                LogLevel::Normal,
+                "log.rs".to_owned(),
+                line,
+                "[stream@0x10] message queued".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_truncation() {
+        let mut buf = StaticCString::<16>::new();
+        let _ = buf.write_str("short");
+        assert!(!buf.finish("\n"));
+        assert_eq!(buf.as_cstr().to_str().unwrap(), "short\n");
+
+        let mut buf = StaticCString::<16>::new();
+        let _ = buf.write_str("ééééééééé");
+        assert!(buf.finish(""));
+        assert_eq!(buf.as_cstr().to_str().unwrap(), "é [truncated]");
+
+        let before = truncated_log_messages();
+        let long = "x".repeat(2 * LOG_LIMIT);
+        let messages = captured("overlong", || {
+            cubeb_log!("overlong {}", long);
+        });
+        assert_eq!(messages.len(), 1);
+        let message = &messages[0].3;
+        assert_eq!(message.len(), LOG_LIMIT - 1);
+        assert!(message.ends_with(TRUNCATED_MARKER));
+        assert!(truncated_log_messages() > before);
+    }
+
+    #[test]
+    fn test_panic_hook() {
+        ::unwind::install_panic_hook();
+        let line = line!() + 2;
+        let messages = captured("100% stopped", || {
+            let caught = ::unwind::catch("test_panic_hook", || panic!("{} stopped", "100%"));
+            assert_eq!(caught, None::<()>);
+        });
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0].3,
+            format!(
+                "test_panic_hook panicked at {}:{}: 100% stopped",
+                file!(),
+                line
+            )
+        );
+    }
+
     #[test]
     fn test_normal_logging_sync() {
         cubeb_log!("This is synchronous log output at normal level");
@@ -0,0 +1,330 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Bounded, lock-free queue carrying asynchronous log messages from
+//! real-time threads to the log drain thread.
+//!
+//! Messages are copied into preallocated slots so logging never allocates
+//! or blocks. Any number of threads may push concurrently. When the queue
+//! is full the message is dropped and counted.
+
+use std::cell::UnsafeCell;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
+use std::time::Duration;
+use std::{ptr, thread};
+use {log, LogLevel};
+
+/// Maximum length in bytes, including the nul terminator, of a queued
+/// message. Matches `CUBEB_LOG_MESSAGE_MAX_SIZE` in `cubeb_log.cpp`.
+pub const MESSAGE_LIMIT: usize = 256;
+
+/// Number of messages that can be queued before messages are dropped.
+/// Matches `CUBEB_LOG_MESSAGE_QUEUE_DEPTH` in `cubeb_log.cpp`.
+const QUEUE_DEPTH: usize = 40;
+
+/// Interval at which the drain thread empties the queue.
+const DRAIN_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct Message {
+    pub level: LogLevel,
+    // Nul-terminated.
+    pub file: &'static str,
+    pub line: u32,
+    len: usize,
+    text: [u8; MESSAGE_LIMIT],
+}
+
+impl Message {
+    const EMPTY: Message = Message {
+        level: LogLevel::Normal,
+        file: "\0",
+        line: 0,
+        len: 0,
+        text: [0; MESSAGE_LIMIT],
+    };
+
+    pub fn text(&self) -> &CStr {
+        unsafe { CStr::from_bytes_with_nul_unchecked(&self.text[..self.len + 1]) }
+    }
+}
+
+struct Slot {
+    // Position of the next push (`sequence == pos`) or pop
+    // (`sequence == pos + 1`) allowed to use this slot.
+    sequence: AtomicUsize,
+    message: UnsafeCell<Message>,
+}
+
+pub struct Queue<const N: usize> {
+    slots: [Slot; N],
+    push_pos: AtomicUsize,
+    pop_pos: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+// Slots are only accessed by the thread that claimed them through
+// `push_pos` or `pop_pos`.
+unsafe impl<const N: usize> Sync for Queue<N> {}
+
+impl<const N: usize> Queue<N> {
+    pub const fn new() -> Self {
+        let mut slots = [const {
+            Slot {
+                sequence: AtomicUsize::new(0),
+                message: UnsafeCell::new(Message::EMPTY),
+            }
+        }; N];
+        let mut i = 0;
+        while i < N {
+            slots[i].sequence = AtomicUsize::new(i);
+            i += 1;
+        }
+        Queue {
+            slots,
+            push_pos: AtomicUsize::new(0),
+            pop_pos: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Queues a copy of `text`, truncated to `MESSAGE_LIMIT - 1` bytes.
+    /// Returns false, and counts the message as dropped, if the queue is
+    /// full.
+    pub fn push(&self, level: LogLevel, file: &'static str, line: u32, text: &[u8]) -> bool {
+        let mut pos = self.push_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos % N];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            let diff = sequence.wrapping_sub(pos) as isize;
+            if diff == 0 {
+                match self.push_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        let message = unsafe { &mut *slot.message.get() };
+                        let len = text.len().min(MESSAGE_LIMIT - 1);
+                        message.level = level;
+                        message.file = file;
+                        message.line = line;
+                        message.len = len;
+                        message.text[..len].copy_from_slice(&text[..len]);
+                        message.text[len] = 0;
+                        slot.sequence.store(pos.wrapping_add(1), Ordering::Release);
+                        return true;
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if diff < 0 {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return false;
+            } else {
+                pos = self.push_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Passes the oldest queued message to `f`. Returns false if the queue
+    /// is empty.
+    pub fn pop<F: FnOnce(&Message)>(&self, f: F) -> bool {
+        let mut pos = self.pop_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos % N];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            let diff = sequence.wrapping_sub(pos.wrapping_add(1)) as isize;
+            if diff == 0 {
+                match self.pop_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        f(unsafe { &*slot.message.get() });
+                        slot.sequence.store(pos.wrapping_add(N), Ordering::Release);
+                        return true;
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if diff < 0 {
+                return false;
+            } else {
+                pos = self.pop_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Number of messages dropped because the queue was full.
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+static QUEUE: Queue<QUEUE_DEPTH> = Queue::new();
+static START: Once = Once::new();
+
+/// Queues a message for the drain thread, starting it if needed.
+pub fn push(level: LogLevel, file: &'static str, line: u32, text: &[u8]) {
+    start();
+    QUEUE.push(level, file, line, text);
+}
+
+pub fn dropped() -> usize {
+    QUEUE.dropped()
+}
+
+/// Starts the drain thread. Called when a context is created so that the
+/// first asynchronous message, usually logged from a real-time thread,
+/// does not have to spawn it.
+pub fn start() {
+    START.call_once(|| {
+        let _ = thread::Builder::new()
+            .name("cubeb_log".to_owned())
+            .spawn(|| {
+                let mut reported = 0;
+                loop {
+                    reported = drain(&QUEUE, reported);
+                    thread::sleep(DRAIN_INTERVAL);
+                }
+            });
+    });
+}
+
+// Delivers the queued messages, then reports messages dropped since
+// `reported` were counted. Returns the new dropped count.
+fn drain<const N: usize>(queue: &Queue<N>, reported: usize) -> usize {
+    while queue.pop(deliver) {}
+    let dropped = queue.dropped();
+    if dropped != reported {
+        let text = format!(
+            "{} asynchronous log messages dropped",
+            dropped.wrapping_sub(reported)
+        );
+        let mut message = Message::EMPTY;
+        message.len = text.len().min(MESSAGE_LIMIT - 1);
+        message.text[..message.len].copy_from_slice(&text.as_bytes()[..message.len]);
+        deliver(&message);
+    }
+    dropped
+}
+
+// Hands `message` to the installed log callback, formatted as
+// `file:line: message` for the plain callback.
+fn deliver(message: &Message) {
+    unsafe {
+        if let Some(log_callback) = log::log_message_callback() {
+            let file = if message.line == 0 {
+                ptr::null()
+            } else {
+                message.file.as_ptr() as *const c_char
+            };
+            log_callback(
+                message.level as _,
+                file,
+                message.line,
+                message.text().as_ptr(),
+            );
+        } else if let Some(log_callback) = log::log_callback() {
+            let file = &message.file[..message.file.len() - 1];
+            let text = message.text().to_string_lossy();
+            let formatted = if message.line == 0 {
+                format!("{}\n\0", text)
+            } else {
+                format!("{}:{}: {}\n\0", file, message.line, text)
+            };
+            log_callback(
+                b"%s\0".as_ptr() as *const c_char,
+                formatted.as_ptr() as *const c_char,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn pop_text<const N: usize>(queue: &Queue<N>) -> Option<(u32, String)> {
+        let mut text = None;
+        queue.pop(|m| text = Some((m.line, m.text().to_string_lossy().into_owned())));
+        text
+    }
+
+    #[test]
+    fn test_queue_order_and_wrap() {
+        let queue = Queue::<4>::new();
+        assert_eq!(pop_text(&queue), None);
+        for round in 0..3 {
+            for i in 0..3 {
+                assert!(queue.push(LogLevel::Normal, "log.rs\0", i, b"msg"));
+            }
+            for i in 0..3 {
+                assert_eq!(pop_text(&queue), Some((i, "msg".to_owned())), "{}", round);
+            }
+            assert_eq!(pop_text(&queue), None);
+        }
+        assert_eq!(queue.dropped(), 0);
+    }
+
+    #[test]
+    fn test_queue_full() {
+        let queue = Queue::<2>::new();
+        assert!(queue.push(LogLevel::Normal, "log.rs\0", 1, b"one"));
+        assert!(queue.push(LogLevel::Normal, "log.rs\0", 2, b"two"));
+        assert!(!queue.push(LogLevel::Normal, "log.rs\0", 3, b"three"));
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(pop_text(&queue), Some((1, "one".to_owned())));
+        assert!(queue.push(LogLevel::Normal, "log.rs\0", 4, b"four"));
+        assert_eq!(pop_text(&queue), Some((2, "two".to_owned())));
+        assert_eq!(pop_text(&queue), Some((4, "four".to_owned())));
+    }
+
+    #[test]
+    fn test_queue_truncates() {
+        let queue = Queue::<1>::new();
+        let long = [b'x'; 2 * MESSAGE_LIMIT];
+        assert!(queue.push(LogLevel::Normal, "log.rs\0", 1, &long));
+        let (_, text) = pop_text(&queue).unwrap();
+        assert_eq!(text.len(), MESSAGE_LIMIT - 1);
+    }
+
+    #[test]
+    fn test_queue_concurrent_producers() {
+        const PER_THREAD: u32 = 200;
+        let queue = Arc::new(Queue::<8>::new());
+        let producers: Vec<_> = (0..4)
+            .map(|t| {
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    for i in 0..PER_THREAD {
+                        let text = format!("{}", t);
+                        while !queue.push(LogLevel::Normal, "log.rs\0", i, text.as_bytes()) {
+                            thread::yield_now();
+                        }
+                    }
+                })
+            })
+            .collect();
+        // Messages from each producer arrive in order.
+        let mut next = [0; 4];
+        while next.iter().any(|&n| n < PER_THREAD) {
+            if let Some((line, text)) = pop_text(&*queue) {
+                let t: usize = text.parse().unwrap();
+                assert_eq!(line, next[t]);
+                next[t] += 1;
+            }
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        assert_eq!(pop_text(&*queue), None);
+    }
+}
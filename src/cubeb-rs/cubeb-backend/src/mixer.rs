@@ -0,0 +1,814 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Up and down mixing of interleaved frames between channel layouts.
+//!
+//! By default, the mixing coefficients follow those of `cubeb_mixer.cpp`,
+//! adapted from libswresample: channels missing from the output are folded
+//! into their neighbours, the center and surround channels at -3 dB. A
+//! [`MixMatrix`] changes those levels, drops the LFE channel, or gives the
+//! full matrix. Integer samples are always normalised so mixing full scale
+//! channels does not clip, and can be dithered with `mix_dithered`.
+//!
+//! Channels are described by lists of [`Channel`]s in frame order, which
+//! unlike a `ChannelLayout` can express devices exposing their channels in
+//! any order, or with unused ones (`Channel::Silence`).
+
+use cubeb_core::{ChannelLayout, MixCoefficient, MixMatrix, Quantizer};
+use duplex::Sample;
+use std::marker::PhantomData;
+
+/// A speaker position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    FrontLeft = 0,
+    FrontRight = 1,
+    FrontCenter = 2,
+    LowFrequency = 3,
+    BackLeft = 4,
+    BackRight = 5,
+    FrontLeftOfCenter = 6,
+    FrontRightOfCenter = 7,
+    BackCenter = 8,
+    SideLeft = 9,
+    SideRight = 10,
+    TopCenter = 11,
+    TopFrontLeft = 12,
+    TopFrontCenter = 13,
+    TopFrontRight = 14,
+    TopBackLeft = 15,
+    TopBackCenter = 16,
+    TopBackRight = 17,
+    /// A channel not carrying audio: silent on output, ignored on input.
+    Silence,
+}
+
+impl Channel {
+    /// Number of channel kinds, including `Silence`.
+    pub const fn count() -> usize {
+        CHANNEL_ORDER.len()
+    }
+
+    /// The bit of the channel in a `ChannelLayout`, 0 for `Silence`.
+    pub fn bitmask(self) -> u32 {
+        match self {
+            Channel::Silence => 0,
+            c => 1 << c as u32,
+        }
+    }
+}
+
+/// The channels, in the order of the bits of a `ChannelLayout`.
+pub const CHANNEL_ORDER: [Channel; 19] = [
+    Channel::FrontLeft,
+    Channel::FrontRight,
+    Channel::FrontCenter,
+    Channel::LowFrequency,
+    Channel::BackLeft,
+    Channel::BackRight,
+    Channel::FrontLeftOfCenter,
+    Channel::FrontRightOfCenter,
+    Channel::BackCenter,
+    Channel::SideLeft,
+    Channel::SideRight,
+    Channel::TopCenter,
+    Channel::TopFrontLeft,
+    Channel::TopFrontCenter,
+    Channel::TopFrontRight,
+    Channel::TopBackLeft,
+    Channel::TopBackCenter,
+    Channel::TopBackRight,
+    Channel::Silence,
+];
+
+// Speaker positions, not counting `Silence`.
+const NAMED_CHANNELS: usize = 18;
+
+/// The channels of frames in `layout`, in order.
+pub fn channel_order(layout: ChannelLayout) -> Vec<Channel> {
+    CHANNEL_ORDER[..NAMED_CHANNELS]
+        .iter()
+        .filter(|c| layout.bits() & c.bitmask() != 0)
+        .cloned()
+        .collect()
+}
+
+/// The channels of frames of `channel_count` without a known layout: the
+/// first of `CHANNEL_ORDER`, then silence.
+pub fn default_channel_order(channel_count: usize) -> Vec<Channel> {
+    assert_ne!(channel_count, 0);
+    let mut channels: Vec<_> = CHANNEL_ORDER.iter().take(channel_count).cloned().collect();
+    channels.resize(channel_count, Channel::Silence);
+    channels
+}
+
+/// Whether a speaker position appears more than once in `channels`.
+pub fn non_silent_duplicate_channel_present(channels: &[Channel]) -> bool {
+    let mut bitmap = 0;
+    for channel in channels {
+        if bitmap & channel.bitmask() != 0 {
+            return true;
+        }
+        bitmap |= channel.bitmask();
+    }
+    false
+}
+
+/// Mixes frames of `T` from one list of channels to another.
+#[derive(Clone, Debug)]
+pub struct Mixer<T> {
+    input_channels: Vec<Channel>,
+    output_channels: Vec<Channel>,
+    // Coefficient of each input channel, for each output channel.
+    matrix: Vec<f32>,
+    // Whether each output channel only sums whole multiples of its inputs,
+    // so integer samples need no rounding.
+    exact: Vec<bool>,
+    identity: bool,
+    sample: PhantomData<T>,
+}
+
+impl<T: Sample> Mixer<T> {
+    /// A mixer from frames of `input_count` channels laid out as
+    /// `input_layout` to frames of `output_count` channels, the device
+    /// reports as `output_channels`. When the count and the channels
+    /// disagree, or the channels make no sense, the default channel order
+    /// is assumed.
+    pub fn new(
+        input_count: usize,
+        input_layout: ChannelLayout,
+        output_count: usize,
+        output_channels: Vec<Channel>,
+    ) -> Mixer<T> {
+        Mixer::with_matrix(
+            input_count,
+            input_layout,
+            output_count,
+            output_channels,
+            &MixMatrix::default(),
+        )
+    }
+
+    /// Like `new`, mixing following `matrix` instead of cubeb's default.
+    pub fn with_matrix(
+        input_count: usize,
+        input_layout: ChannelLayout,
+        output_count: usize,
+        mut output_channels: Vec<Channel>,
+        matrix: &MixMatrix,
+    ) -> Mixer<T> {
+        assert!(input_count > 0);
+        assert!(output_count > 0);
+
+        cubeb_log!(
+            "Creating a mixer with input channel count: {}, input layout: {:?}, \
+             output channel count: {}, output channels: {:?}",
+            input_count,
+            input_layout,
+            output_count,
+            output_channels
+        );
+
+        let input_channels = if input_count as u32 != input_layout.bits().count_ones() {
+            cubeb_log!(
+                "Mismatch between input channels and layout. Applying default layout instead"
+            );
+            default_channel_order(input_count)
+        } else {
+            channel_order(input_layout)
+        };
+
+        // When having one or two channel, force mono or stereo. Some devices
+        // (namely, Bose QC35, mark 1 and 2), expose a single channel mapped to
+        // the right for some reason. Some devices (e.g., builtin speaker on
+        // MacBook Pro 2018) map the channel layout to the undefined channels.
+        if output_count == 1 {
+            output_channels = vec![Channel::FrontCenter];
+        } else if output_count == 2 {
+            output_channels = vec![Channel::FrontLeft, Channel::FrontRight];
+        }
+
+        if output_count != output_channels.len()
+            || output_channels.iter().all(|&c| c == Channel::Silence)
+            || non_silent_duplicate_channel_present(&output_channels)
+        {
+            cubeb_log!("Use invalid layout. Apply default layout instead");
+            output_channels = default_channel_order(output_count);
+        }
+
+        Mixer::with_channels_and_matrix(&input_channels, &output_channels, matrix)
+    }
+
+    /// A mixer from frames of `input_channels` to frames of
+    /// `output_channels`.
+    ///
+    /// # Panics
+    ///
+    /// If a speaker position appears twice in either list.
+    pub fn with_channels(input_channels: &[Channel], output_channels: &[Channel]) -> Mixer<T> {
+        Mixer::with_channels_and_matrix(input_channels, output_channels, &MixMatrix::default())
+    }
+
+    fn with_channels_and_matrix(
+        input_channels: &[Channel],
+        output_channels: &[Channel],
+        matrix: &MixMatrix,
+    ) -> Mixer<T> {
+        assert!(!non_silent_duplicate_channel_present(input_channels));
+        assert!(!non_silent_duplicate_channel_present(output_channels));
+        let layout = |channels: &[Channel]| channels.iter().fold(0, |l, c| l | c.bitmask());
+        let named = match matrix.coefficients {
+            Some(ref coefficients) => custom_matrix(coefficients),
+            None => auto_matrix(layout(input_channels), layout(output_channels), matrix),
+        };
+        let normalize = matrix.normalize;
+        let mut matrix = Vec::with_capacity(input_channels.len() * output_channels.len());
+        for &o in output_channels {
+            for &i in input_channels {
+                matrix.push(match (o, i) {
+                    (Channel::Silence, _) | (_, Channel::Silence) => 0.0,
+                    (o, i) => named[o as usize][i as usize],
+                });
+            }
+        }
+        // Rows add up to at most 1, not to clip.
+        let max_sum = matrix
+            .chunks(input_channels.len())
+            .map(|row| row.iter().map(|c| c.abs()).sum::<f64>())
+            .fold(0.0, f64::max);
+        if (T::SATURATES || normalize) && max_sum > 1.0 {
+            for c in &mut matrix {
+                *c /= max_sum;
+            }
+        }
+        let identity = input_channels.len() == output_channels.len()
+            && matrix
+                .chunks(input_channels.len())
+                .enumerate()
+                .all(|(o, row)| {
+                    row.iter()
+                        .enumerate()
+                        .all(|(i, &c)| c == if i == o { 1.0 } else { 0.0 })
+                });
+        let exact = matrix
+            .chunks(input_channels.len())
+            .map(|row| row.iter().all(|c| c.fract() == 0.0))
+            .collect();
+        Mixer {
+            input_channels: input_channels.to_vec(),
+            output_channels: output_channels.to_vec(),
+            matrix: matrix.into_iter().map(|c| c as f32).collect(),
+            exact,
+            identity,
+            sample: PhantomData,
+        }
+    }
+
+    pub fn input_channels(&self) -> &[Channel] {
+        &self.input_channels
+    }
+
+    pub fn output_channels(&self) -> &[Channel] {
+        &self.output_channels
+    }
+
+    /// Mixes the interleaved frames of `input` into those of `output`,
+    /// which must hold the same number of frames.
+    pub fn mix(&self, input: &[T], output: &mut [T]) {
+        self.mix_with(input, output, None)
+    }
+
+    /// As `mix`, quantizing integer samples with `quantizer`, which must be
+    /// for the output channels. Output channels the mix leaves exact, such
+    /// as copies of input channels, are not dithered.
+    pub fn mix_dithered(&self, input: &[T], output: &mut [T], quantizer: &mut Quantizer) {
+        self.mix_with(input, output, Some(quantizer))
+    }
+
+    fn mix_with(&self, input: &[T], output: &mut [T], mut quantizer: Option<&mut Quantizer>) {
+        let (in_channels, out_channels) = (self.input_channels.len(), self.output_channels.len());
+        assert_eq!(input.len() % in_channels, 0);
+        assert_eq!(input.len() / in_channels, output.len() / out_channels);
+        if self.identity {
+            output.copy_from_slice(input);
+            return;
+        }
+        for (in_frame, out_frame) in input
+            .chunks(in_channels)
+            .zip(output.chunks_mut(out_channels))
+        {
+            let rows = self.matrix.chunks(in_channels).zip(&self.exact);
+            for (channel, (out, (row, &exact))) in out_frame.iter_mut().zip(rows).enumerate() {
+                let sum: f32 = in_frame.iter().zip(row).map(|(s, c)| s.to_f32() * c).sum();
+                *out = match quantizer {
+                    Some(ref mut q) if !exact => T::quantize(sum, q, channel),
+                    _ => T::from_f32(sum),
+                };
+            }
+        }
+    }
+}
+
+const FRONT_LEFT: usize = Channel::FrontLeft as usize;
+const FRONT_RIGHT: usize = Channel::FrontRight as usize;
+const FRONT_CENTER: usize = Channel::FrontCenter as usize;
+const LOW_FREQUENCY: usize = Channel::LowFrequency as usize;
+const BACK_LEFT: usize = Channel::BackLeft as usize;
+const BACK_RIGHT: usize = Channel::BackRight as usize;
+const FRONT_LEFT_OF_CENTER: usize = Channel::FrontLeftOfCenter as usize;
+const FRONT_RIGHT_OF_CENTER: usize = Channel::FrontRightOfCenter as usize;
+const BACK_CENTER: usize = Channel::BackCenter as usize;
+const SIDE_LEFT: usize = Channel::SideLeft as usize;
+const SIDE_RIGHT: usize = Channel::SideRight as usize;
+
+const fn bit(channel: usize) -> u32 {
+    1 << channel
+}
+
+const STEREO: u32 = bit(FRONT_LEFT) | bit(FRONT_RIGHT);
+const SQRT1_2: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+// Treats single channels other than front left as mono.
+fn clean_layout(layout: u32) -> u32 {
+    if layout != 0 && layout != bit(FRONT_LEFT) && layout.count_ones() == 1 {
+        bit(FRONT_CENTER)
+    } else {
+        layout
+    }
+}
+
+// At least one front channel, and no lone left or right channel.
+fn sane_layout(layout: u32) -> bool {
+    let even = |pair: u32| (layout & pair).count_ones() != 1;
+    layout & (STEREO | bit(FRONT_CENTER)) != 0
+        && even(STEREO)
+        && even(bit(SIDE_LEFT) | bit(SIDE_RIGHT))
+        && even(bit(BACK_LEFT) | bit(BACK_RIGHT))
+        && even(bit(FRONT_LEFT_OF_CENTER) | bit(FRONT_RIGHT_OF_CENTER))
+}
+
+// Coefficients of the input speakers, for each output speaker.
+type NamedMatrix = [[f64; NAMED_CHANNELS]; NAMED_CHANNELS];
+
+fn custom_matrix(coefficients: &[MixCoefficient]) -> NamedMatrix {
+    let mut m = [[0.0; NAMED_CHANNELS]; NAMED_CHANNELS];
+    let index = |channel: ChannelLayout| channel.bits().trailing_zeros() as usize;
+    for c in coefficients {
+        let (output, input) = (index(c.output), index(c.input));
+        if output < NAMED_CHANNELS && input < NAMED_CHANNELS {
+            m[output][input] = f64::from(c.gain);
+        }
+    }
+    m
+}
+
+// The coefficients derived from the layouts, as in
+// `MixerContext::auto_matrix`. Layouts it does not support only get the
+// channels they share.
+fn auto_matrix(input: u32, output: u32, levels: &MixMatrix) -> NamedMatrix {
+    let center_mix_level = f64::from(levels.center_mix_level);
+    let surround_mix_level = f64::from(levels.surround_mix_level);
+    let lfe_mix_level = f64::from(levels.lfe_mix_level);
+    let mut m = [[0.0; NAMED_CHANNELS]; NAMED_CHANNELS];
+    let (input, output) = (clean_layout(input), clean_layout(output));
+    for (i, row) in m.iter_mut().enumerate() {
+        if input & output & bit(i) != 0 {
+            row[i] = 1.0;
+        }
+    }
+    if !sane_layout(input) || !sane_layout(output) {
+        return m;
+    }
+
+    let has = |layout: u32, channel: usize| layout & bit(channel) != 0;
+    let unaccounted = input & !output;
+
+    if has(unaccounted, FRONT_CENTER) && output & STEREO == STEREO {
+        let level = if input & STEREO != 0 {
+            center_mix_level
+        } else {
+            SQRT1_2
+        };
+        m[FRONT_LEFT][FRONT_CENTER] += level;
+        m[FRONT_RIGHT][FRONT_CENTER] += level;
+    }
+    if unaccounted & STEREO != 0 && has(output, FRONT_CENTER) {
+        m[FRONT_CENTER][FRONT_LEFT] += SQRT1_2;
+        m[FRONT_CENTER][FRONT_RIGHT] += SQRT1_2;
+        if has(input, FRONT_CENTER) {
+            m[FRONT_CENTER][FRONT_CENTER] = center_mix_level * 2f64.sqrt();
+        }
+    }
+
+    if has(unaccounted, BACK_CENTER) {
+        if has(output, BACK_LEFT) {
+            m[BACK_LEFT][BACK_CENTER] += SQRT1_2;
+            m[BACK_RIGHT][BACK_CENTER] += SQRT1_2;
+        } else if has(output, SIDE_LEFT) {
+            m[SIDE_LEFT][BACK_CENTER] += SQRT1_2;
+            m[SIDE_RIGHT][BACK_CENTER] += SQRT1_2;
+        } else if has(output, FRONT_LEFT) {
+            m[FRONT_LEFT][BACK_CENTER] += surround_mix_level * SQRT1_2;
+            m[FRONT_RIGHT][BACK_CENTER] += surround_mix_level * SQRT1_2;
+        } else if has(output, FRONT_CENTER) {
+            m[FRONT_CENTER][BACK_CENTER] += surround_mix_level * SQRT1_2;
+        }
+    }
+    if has(unaccounted, BACK_LEFT) {
+        if has(output, BACK_CENTER) {
+            m[BACK_CENTER][BACK_LEFT] += SQRT1_2;
+            m[BACK_CENTER][BACK_RIGHT] += SQRT1_2;
+        } else if has(output, SIDE_LEFT) {
+            let level = if has(input, SIDE_LEFT) { SQRT1_2 } else { 1.0 };
+            m[SIDE_LEFT][BACK_LEFT] += level;
+            m[SIDE_RIGHT][BACK_RIGHT] += level;
+        } else if has(output, FRONT_LEFT) {
+            m[FRONT_LEFT][BACK_LEFT] += surround_mix_level;
+            m[FRONT_RIGHT][BACK_RIGHT] += surround_mix_level;
+        } else if has(output, FRONT_CENTER) {
+            m[FRONT_CENTER][BACK_LEFT] += surround_mix_level * SQRT1_2;
+            m[FRONT_CENTER][BACK_RIGHT] += surround_mix_level * SQRT1_2;
+        }
+    }
+
+    if has(unaccounted, SIDE_LEFT) {
+        if has(output, BACK_LEFT) {
+            // Side channels are copied to missing back channels, mixed into
+            // existing ones.
+            let level = if has(input, BACK_LEFT) { SQRT1_2 } else { 1.0 };
+            m[BACK_LEFT][SIDE_LEFT] += level;
+            m[BACK_RIGHT][SIDE_RIGHT] += level;
+        } else if has(output, BACK_CENTER) {
+            m[BACK_CENTER][SIDE_LEFT] += SQRT1_2;
+            m[BACK_CENTER][SIDE_RIGHT] += SQRT1_2;
+        } else if has(output, FRONT_LEFT) {
+            m[FRONT_LEFT][SIDE_LEFT] += surround_mix_level;
+            m[FRONT_RIGHT][SIDE_RIGHT] += surround_mix_level;
+        } else if has(output, FRONT_CENTER) {
+            m[FRONT_CENTER][SIDE_LEFT] += surround_mix_level * SQRT1_2;
+            m[FRONT_CENTER][SIDE_RIGHT] += surround_mix_level * SQRT1_2;
+        }
+    }
+
+    if has(unaccounted, FRONT_LEFT_OF_CENTER) {
+        if has(output, FRONT_LEFT) {
+            m[FRONT_LEFT][FRONT_LEFT_OF_CENTER] += 1.0;
+            m[FRONT_RIGHT][FRONT_RIGHT_OF_CENTER] += 1.0;
+        } else if has(output, FRONT_CENTER) {
+            m[FRONT_CENTER][FRONT_LEFT_OF_CENTER] += SQRT1_2;
+            m[FRONT_CENTER][FRONT_RIGHT_OF_CENTER] += SQRT1_2;
+        }
+    }
+    // LFE goes into the front center, or front left and right.
+    if has(unaccounted, LOW_FREQUENCY) {
+        if has(output, FRONT_CENTER) {
+            m[FRONT_CENTER][LOW_FREQUENCY] += lfe_mix_level;
+        } else if has(output, FRONT_LEFT) {
+            m[FRONT_LEFT][LOW_FREQUENCY] += lfe_mix_level * SQRT1_2;
+            m[FRONT_RIGHT][LOW_FREQUENCY] += lfe_mix_level * SQRT1_2;
+        }
+    }
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {ChannelLayout, MixCoefficient, MixMatrix};
+
+    // This test gives a clear channel order of the ChannelLayout passed from
+    // cubeb interface.
+    #[test]
+    fn test_channel_order() {
+        use self::Channel::*;
+        for &(layout, ref channels) in &[
+            (ChannelLayout::MONO, vec![FrontCenter]),
+            (ChannelLayout::MONO_LFE, vec![FrontCenter, LowFrequency]),
+            (ChannelLayout::STEREO, vec![FrontLeft, FrontRight]),
+            (
+                ChannelLayout::STEREO_LFE,
+                vec![FrontLeft, FrontRight, LowFrequency],
+            ),
+            (ChannelLayout::_3F, vec![FrontLeft, FrontRight, FrontCenter]),
+            (
+                ChannelLayout::_3F_LFE,
+                vec![FrontLeft, FrontRight, FrontCenter, LowFrequency],
+            ),
+            (ChannelLayout::_2F1, vec![FrontLeft, FrontRight, BackCenter]),
+            (
+                ChannelLayout::_2F1_LFE,
+                vec![FrontLeft, FrontRight, LowFrequency, BackCenter],
+            ),
+            (
+                ChannelLayout::_3F1,
+                vec![FrontLeft, FrontRight, FrontCenter, BackCenter],
+            ),
+            (
+                ChannelLayout::_3F1_LFE,
+                vec![FrontLeft, FrontRight, FrontCenter, LowFrequency, BackCenter],
+            ),
+            (
+                ChannelLayout::_2F2,
+                vec![FrontLeft, FrontRight, SideLeft, SideRight],
+            ),
+            (
+                ChannelLayout::_2F2_LFE,
+                vec![FrontLeft, FrontRight, LowFrequency, SideLeft, SideRight],
+            ),
+            (
+                ChannelLayout::QUAD,
+                vec![FrontLeft, FrontRight, BackLeft, BackRight],
+            ),
+            (
+                ChannelLayout::QUAD_LFE,
+                vec![FrontLeft, FrontRight, LowFrequency, BackLeft, BackRight],
+            ),
+            (
+                ChannelLayout::_3F2,
+                vec![FrontLeft, FrontRight, FrontCenter, SideLeft, SideRight],
+            ),
+            (
+                ChannelLayout::_3F2_LFE,
+                vec![
+                    FrontLeft,
+                    FrontRight,
+                    FrontCenter,
+                    LowFrequency,
+                    SideLeft,
+                    SideRight,
+                ],
+            ),
+            (
+                ChannelLayout::_3F2_BACK,
+                vec![FrontLeft, FrontRight, FrontCenter, BackLeft, BackRight],
+            ),
+            (
+                ChannelLayout::_3F2_LFE_BACK,
+                vec![
+                    FrontLeft,
+                    FrontRight,
+                    FrontCenter,
+                    LowFrequency,
+                    BackLeft,
+                    BackRight,
+                ],
+            ),
+            (
+                ChannelLayout::_3F3R_LFE,
+                vec![
+                    FrontLeft,
+                    FrontRight,
+                    FrontCenter,
+                    LowFrequency,
+                    BackCenter,
+                    SideLeft,
+                    SideRight,
+                ],
+            ),
+            (
+                ChannelLayout::_3F4_LFE,
+                vec![
+                    FrontLeft,
+                    FrontRight,
+                    FrontCenter,
+                    LowFrequency,
+                    BackLeft,
+                    BackRight,
+                    SideLeft,
+                    SideRight,
+                ],
+            ),
+        ] {
+            assert_eq!(&channel_order(layout), channels, "{:?}", layout);
+        }
+    }
+
+    #[test]
+    fn test_default_channel_order() {
+        for len in 1..CHANNEL_ORDER.len() + 10 {
+            let channels = default_channel_order(len);
+            if len <= CHANNEL_ORDER.len() {
+                assert_eq!(channels, &CHANNEL_ORDER[..len]);
+            } else {
+                let silences = vec![Channel::Silence; len - CHANNEL_ORDER.len()];
+                assert_eq!(channels[..CHANNEL_ORDER.len()], CHANNEL_ORDER);
+                assert_eq!(&channels[CHANNEL_ORDER.len()..], silences.as_slice());
+            }
+        }
+    }
+
+    #[test]
+    fn test_non_silent_duplicate_channels() {
+        use self::Channel::*;
+        let duplicate = [
+            FrontLeft,
+            Silence,
+            FrontRight,
+            FrontCenter,
+            Silence,
+            FrontRight,
+        ];
+        assert!(non_silent_duplicate_channel_present(&duplicate));
+
+        let non_duplicate = [
+            FrontLeft,
+            Silence,
+            FrontRight,
+            FrontCenter,
+            Silence,
+            Silence,
+        ];
+        assert!(!non_silent_duplicate_channel_present(&non_duplicate));
+    }
+
+    fn mix<T: Sample>(mixer: &Mixer<T>, input: &[T]) -> Vec<T> {
+        let frames = input.len() / mixer.input_channels().len();
+        let mut output = vec![T::default(); frames * mixer.output_channels().len()];
+        mixer.mix(input, &mut output);
+        output
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_identity() {
+        let m = Mixer::<i16>::new(2, ChannelLayout::STEREO, 2, Vec::new());
+        assert_eq!(mix(&m, &[1, 2, 3, 4]), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_stereo_mono() {
+        use std::f32::consts::FRAC_1_SQRT_2;
+        // -3 dB for floats, normalised for integers.
+        let m = Mixer::<f32>::new(2, ChannelLayout::STEREO, 1, Vec::new());
+        assert_close(&mix(&m, &[0.5, 0.5]), &[FRAC_1_SQRT_2]);
+        let m = Mixer::<i16>::new(2, ChannelLayout::STEREO, 1, Vec::new());
+        assert_eq!(mix(&m, &[i16::MAX, i16::MAX]), [i16::MAX]);
+        assert_eq!(mix(&m, &[1000, 3000]), [2000]);
+        // Mono is spread over both channels at -3 dB.
+        let m = Mixer::<f32>::new(1, ChannelLayout::MONO, 2, Vec::new());
+        assert_close(&mix(&m, &[1.0]), &[FRAC_1_SQRT_2, FRAC_1_SQRT_2]);
+    }
+
+    #[test]
+    fn test_surround_stereo() {
+        use self::Channel::*;
+        let m = Mixer::<f32>::with_channels(
+            &[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                SideLeft,
+                SideRight,
+            ],
+            &[FrontLeft, FrontRight],
+        );
+        let c = std::f32::consts::FRAC_1_SQRT_2;
+        // Each channel alone, in turn.
+        let mut input = vec![0.0; 36];
+        for i in 0..6 {
+            input[i * 6 + i] = 1.0;
+        }
+        assert_close(
+            &mix(&m, &input),
+            &[1., 0., 0., 1., c, c, c, c, c, 0., 0., c],
+        );
+    }
+
+    #[test]
+    fn test_mix_dithered() {
+        use self::Channel::*;
+        use cubeb_core::Dither;
+        // Halves of odd sums are dithered, and average out.
+        let m = Mixer::<i16>::new(2, ChannelLayout::STEREO, 1, Vec::new());
+        let mut quantizer = Quantizer::new(Dither::Tpdf, 1);
+        let input = [1000, 3001].repeat(1000);
+        let mut output = vec![0; 1000];
+        m.mix_dithered(&input, &mut output, &mut quantizer);
+        assert!(output.iter().all(|s| (1999..=2002).contains(s)));
+        assert!(output.iter().any(|&s| s != output[0]));
+        let average = output.iter().map(|&s| f64::from(s)).sum::<f64>() / 1000.0;
+        assert!((average - 2000.5).abs() < 0.1, "{}", average);
+        // Reordered channels are left alone.
+        let m = Mixer::<i16>::with_channels(
+            &[FrontLeft, FrontRight],
+            &[FrontRight, Silence, FrontLeft],
+        );
+        let mut quantizer = Quantizer::new(Dither::NoiseShaped, 3);
+        let mut output = [0; 6];
+        m.mix_dithered(&[1, 2, 3, 4], &mut output, &mut quantizer);
+        assert_eq!(output, [2, 0, 1, 4, 0, 3]);
+    }
+
+    #[test]
+    fn test_device_channels() {
+        use self::Channel::*;
+        // Frames reordered, and silent channels skipped or left silent.
+        let m = Mixer::<i16>::with_channels(
+            &[FrontLeft, FrontRight],
+            &[FrontRight, Silence, FrontLeft],
+        );
+        assert_eq!(mix(&m, &[1, 2]), [2, 0, 1]);
+        let m = Mixer::<i16>::with_channels(
+            &[FrontLeft, Silence, FrontRight],
+            &[FrontLeft, FrontRight],
+        );
+        assert_eq!(mix(&m, &[1, 9, 2]), [1, 2]);
+    }
+
+    #[test]
+    fn test_invalid_output_channels() {
+        use self::Channel::*;
+        // Devices with one or two channels are taken as mono or stereo.
+        let m = Mixer::<f32>::new(2, ChannelLayout::STEREO, 2, vec![FrontRight, FrontRight]);
+        assert_eq!(m.output_channels(), [FrontLeft, FrontRight]);
+        let m = Mixer::<f32>::new(1, ChannelLayout::MONO, 1, vec![FrontRight]);
+        assert_eq!(m.output_channels(), [FrontCenter]);
+        // Otherwise, invalid channels get the default order.
+        let m = Mixer::<f32>::new(2, ChannelLayout::STEREO, 3, vec![Silence; 3]);
+        assert_eq!(m.output_channels(), [FrontLeft, FrontRight, FrontCenter]);
+        let m = Mixer::<f32>::new(2, ChannelLayout::STEREO, 3, vec![FrontLeft; 3]);
+        assert_eq!(m.output_channels(), [FrontLeft, FrontRight, FrontCenter]);
+        // As are input channels not matching their layout.
+        let m = Mixer::<f32>::new(3, ChannelLayout::STEREO, 2, Vec::new());
+        assert_eq!(m.input_channels(), [FrontLeft, FrontRight, FrontCenter]);
+    }
+
+    #[test]
+    fn test_levels() {
+        use self::Channel::*;
+        let input = [FrontLeft, FrontRight, FrontCenter, LowFrequency];
+        let frames = [1.0, 0., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1.];
+        // ITU-R BS.775 drops the LFE channel.
+        let m = Mixer::<f32>::with_channels_and_matrix(
+            &input,
+            &[FrontLeft, FrontRight],
+            &MixMatrix::itu_bs775(),
+        );
+        let c = std::f32::consts::FRAC_1_SQRT_2;
+        assert_close(&mix(&m, &frames), &[1., 0., c, c, 0., 0.]);
+        // Unless the device has an LFE speaker.
+        let m = Mixer::<f32>::with_channels_and_matrix(
+            &input,
+            &[FrontCenter, LowFrequency],
+            &MixMatrix::itu_bs775(),
+        );
+        assert_close(&mix(&m, &frames), &[c, 0., 1., 0., 0., 1.]);
+        // Other levels.
+        let matrix = MixMatrix {
+            center_mix_level: 0.5,
+            ..MixMatrix::default().without_lfe()
+        };
+        let m = Mixer::<f32>::with_channels_and_matrix(&input, &[FrontLeft, FrontRight], &matrix);
+        assert_close(&mix(&m, &frames), &[1., 0., 0.5, 0.5, 0., 0.]);
+    }
+
+    #[test]
+    fn test_normalized() {
+        use self::Channel::*;
+        // Full scale channels mixed together do not clip.
+        let m = Mixer::<f32>::with_channels_and_matrix(
+            &[FrontLeft, FrontRight, FrontCenter],
+            &[FrontLeft, FrontRight],
+            &MixMatrix::default().normalized(),
+        );
+        let c = std::f32::consts::FRAC_1_SQRT_2;
+        assert_close(&mix(&m, &[1., 1., 1.]), &[1., 1.]);
+        assert_close(&mix(&m, &[1., 0., 0.]), &[1. / (1. + c), 0.]);
+    }
+
+    #[test]
+    fn test_custom() {
+        use self::Channel::*;
+        let matrix = MixMatrix::custom(vec![
+            MixCoefficient::new(ChannelLayout::FRONT_LEFT, ChannelLayout::FRONT_RIGHT, 0.5),
+            MixCoefficient::new(ChannelLayout::FRONT_RIGHT, ChannelLayout::FRONT_LEFT, 0.25),
+            // Not in the layouts.
+            MixCoefficient::new(ChannelLayout::BACK_LEFT, ChannelLayout::FRONT_LEFT, 1.0),
+        ]);
+        let m = Mixer::<f32>::with_channels_and_matrix(
+            &[FrontLeft, FrontRight],
+            &[FrontLeft, FrontRight],
+            &matrix,
+        );
+        assert_close(&mix(&m, &[1., 0.5]), &[0.25, 0.25]);
+        // Normalised for integers only.
+        let matrix = MixMatrix::custom(vec![MixCoefficient::new(
+            ChannelLayout::FRONT_CENTER,
+            ChannelLayout::FRONT_CENTER,
+            2.0,
+        )]);
+        let m = Mixer::<f32>::with_matrix(1, ChannelLayout::MONO, 1, Vec::new(), &matrix);
+        assert_eq!(mix(&m, &[0.5]), [1.]);
+        let m = Mixer::<i16>::with_matrix(1, ChannelLayout::MONO, 1, Vec::new(), &matrix);
+        assert_eq!(mix(&m, &[1000]), [1000]);
+    }
+}
@@ -0,0 +1,400 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Scripted backend driven by a virtual clock, for deterministic tests.
+//!
+//! Nothing happens on its own: `advance` moves the clock of a context
+//! forward and runs the data and state callbacks of its started streams
+//! synchronously, one `Script::period` at a time.
+
+use cubeb_core::{
+    ffi, CallbackResult, Capabilities, Context, ContextRef, DeviceCollectionRef, DeviceId,
+    DeviceType, Error, Result, SampleFormat, Stream, StreamOptionsRef, StreamParams,
+    StreamParamsRef, StreamRef,
+};
+use rt_check::enter_audio_callback;
+use std::ffi::CStr;
+use std::os::raw::{c_long, c_void};
+use std::ptr;
+use std::time::Duration;
+use {ContextOps, Ops, StreamOps};
+
+pub const MOCK_OPS: Ops = capi_new!(
+    MockContext,
+    MockStream,
+    unsupported: [stream_get_current_device, stream_device_destroy]
+);
+
+/// Behaviour of a mock context and its streams.
+#[derive(Clone, Debug)]
+pub struct Script {
+    pub preferred_rate: u32,
+    pub max_channels: u32,
+    pub min_latency: u32,
+    /// Frames passed to each data callback.
+    pub period: u32,
+    pub output_latency: u32,
+    pub input_latency: u32,
+    pub capabilities: Capabilities,
+    /// Frames a stream processes before failing with `CUBEB_STATE_ERROR`.
+    pub error_after: Option<u64>,
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Script {
+            preferred_rate: 48000,
+            max_channels: 8,
+            min_latency: 128,
+            period: 256,
+            output_latency: 512,
+            input_latency: 256,
+            capabilities: Capabilities::STREAM_VOLUME
+                | Capabilities::INPUT_LATENCY
+                | Capabilities::FORMAT_S16LE
+                | Capabilities::FORMAT_S16BE
+                | Capabilities::FORMAT_FLOAT32LE
+                | Capabilities::FORMAT_FLOAT32BE,
+            error_after: None,
+        }
+    }
+}
+
+#[repr(C)]
+pub struct MockContext {
+    _ops: *const Ops,
+    script: Script,
+    // Virtual time elapsed since the context was created.
+    now: Duration,
+    streams: Vec<*mut MockStream>,
+}
+
+impl MockContext {
+    /// Creates a mock context following `script`.
+    pub fn init_with_script(script: Script) -> Result<Context> {
+        let ctx = Box::new(MockContext {
+            _ops: &MOCK_OPS as *const _,
+            script,
+            now: Duration::from_secs(0),
+            streams: Vec::new(),
+        });
+        Ok(unsafe { Context::from_ptr(Box::into_raw(ctx) as *mut _) })
+    }
+
+    fn advance(&mut self, duration: Duration) {
+        self.now += duration;
+        // Callbacks may destroy streams, so look them up one at a time.
+        let mut i = 0;
+        while i < self.streams.len() {
+            let stm = self.streams[i];
+            unsafe { (*stm).run(duration) };
+            if self.streams.get(i) == Some(&stm) {
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Moves the virtual clock of `context`, a mock context, forward by
+/// `duration`, running the callbacks of its started streams.
+pub fn advance(context: &ContextRef, duration: Duration) {
+    let ctx = unsafe { &mut *(context.as_ptr() as *mut MockContext) };
+    assert!(
+        ptr::eq(ctx._ops, &MOCK_OPS),
+        "advance called on a context that is not a mock"
+    );
+    ctx.advance(duration);
+}
+
+/// Volume last set on `stream`, a mock stream.
+pub fn volume(stream: &StreamRef) -> f32 {
+    let stm = unsafe { &*(stream.as_ptr() as *const MockStream) };
+    stm.volume
+}
+
+/// Virtual time elapsed on `context`, a mock context.
+pub fn now(context: &ContextRef) -> Duration {
+    let ctx = unsafe { &*(context.as_ptr() as *const MockContext) };
+    ctx.now
+}
+
+impl ContextOps for MockContext {
+    fn init(_context_name: Option<&CStr>) -> Result<Context> {
+        Self::init_with_script(Script::default())
+    }
+
+    fn backend_id(&mut self) -> &'static CStr {
+        unsafe { CStr::from_ptr(b"mock\0".as_ptr() as *const _) }
+    }
+    fn max_channel_count(&mut self) -> Result<u32> {
+        Ok(self.script.max_channels)
+    }
+    fn min_latency(&mut self, _params: StreamParams) -> Result<u32> {
+        Ok(self.script.min_latency)
+    }
+    fn preferred_sample_rate(&mut self) -> Result<u32> {
+        Ok(self.script.preferred_rate)
+    }
+    fn capabilities(&mut self) -> Result<Capabilities> {
+        Ok(self.script.capabilities)
+    }
+    fn enumerate_devices(
+        &mut self,
+        _devtype: DeviceType,
+        collection: &DeviceCollectionRef,
+    ) -> Result<()> {
+        let coll = unsafe { &mut *collection.as_ptr() };
+        coll.device = ptr::null_mut();
+        coll.count = 0;
+        Ok(())
+    }
+    fn device_collection_destroy(&mut self, collection: &mut DeviceCollectionRef) -> Result<()> {
+        let coll = unsafe { &mut *collection.as_ptr() };
+        coll.device = ptr::null_mut();
+        coll.count = 0;
+        Ok(())
+    }
+    fn stream_init(
+        &mut self,
+        _stream_name: Option<&CStr>,
+        _input_device: DeviceId,
+        input_stream_params: Option<&StreamParamsRef>,
+        _output_device: DeviceId,
+        output_stream_params: Option<&StreamParamsRef>,
+        _latency_frames: u32,
+        data_callback: ffi::cubeb_data_callback,
+        state_callback: ffi::cubeb_state_callback,
+        user_ptr: *mut c_void,
+    ) -> Result<Stream> {
+        let input = input_stream_params.map(Buffer::new);
+        let output = output_stream_params.map(Buffer::new);
+        if let (Some(i), Some(o)) = (&input, &output) {
+            if i.rate != o.rate {
+                return Err(Error::invalid_format());
+            }
+        }
+        let mut stm = Box::new(MockStream {
+            context: self,
+            user_ptr,
+            data_callback,
+            state_callback,
+            period: self.script.period as usize,
+            output_latency: self.script.output_latency,
+            input_latency: self.script.input_latency,
+            error_after: self.script.error_after,
+            rate: output.as_ref().or(input.as_ref()).unwrap().rate,
+            input,
+            output,
+            state: None,
+            running: Duration::from_secs(0),
+            frames: 0,
+            volume: 1.0,
+        });
+        for buffer in stm.input.iter_mut().chain(stm.output.iter_mut()) {
+            buffer.data.resize(stm.period * buffer.frame_size, 0);
+        }
+        let stm = Box::into_raw(stm);
+        self.streams.push(stm);
+        Ok(unsafe { Stream::from_ptr(stm as *mut _) })
+    }
+    // The options only change what the frames carry, which the mock does
+    // not look at.
+    fn stream_init_with_options(
+        &mut self,
+        stream_name: Option<&CStr>,
+        input_device: DeviceId,
+        input_stream_params: Option<&StreamParamsRef>,
+        output_device: DeviceId,
+        output_stream_params: Option<&StreamParamsRef>,
+        _options: &StreamOptionsRef,
+        latency_frames: u32,
+        data_callback: ffi::cubeb_data_callback,
+        state_callback: ffi::cubeb_state_callback,
+        user_ptr: *mut c_void,
+    ) -> Result<Stream> {
+        self.stream_init(
+            stream_name,
+            input_device,
+            input_stream_params,
+            output_device,
+            output_stream_params,
+            latency_frames,
+            data_callback,
+            state_callback,
+            user_ptr,
+        )
+    }
+    fn register_device_collection_changed(
+        &mut self,
+        _devtype: DeviceType,
+        _cb: ffi::cubeb_device_collection_changed_callback,
+        _user_ptr: *mut c_void,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct Buffer {
+    rate: u32,
+    frame_size: usize,
+    data: Vec<u8>,
+}
+
+impl Buffer {
+    fn new(params: &StreamParamsRef) -> Self {
+        let sample_size = match params.format() {
+            SampleFormat::S16LE | SampleFormat::S16BE | SampleFormat::S16NE => 2,
+            SampleFormat::Float32LE | SampleFormat::Float32BE | SampleFormat::Float32NE => 4,
+        };
+        Buffer {
+            rate: params.rate(),
+            frame_size: sample_size * params.channels() as usize,
+            data: Vec::new(),
+        }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut c_void {
+        self.data.as_mut_ptr() as *mut c_void
+    }
+}
+
+// Keeps the layout `cubeb.c` expects of a `cubeb_stream`.
+#[repr(C)]
+pub struct MockStream {
+    context: *mut MockContext,
+    user_ptr: *mut c_void,
+    data_callback: ffi::cubeb_data_callback,
+    state_callback: ffi::cubeb_state_callback,
+    period: usize,
+    output_latency: u32,
+    input_latency: u32,
+    error_after: Option<u64>,
+    rate: u32,
+    input: Option<Buffer>,
+    output: Option<Buffer>,
+    // Last state reported, `None` before the stream is first started.
+    state: Option<ffi::cubeb_state>,
+    // Time spent started, not yet consumed by callbacks.
+    running: Duration,
+    frames: u64,
+    volume: f32,
+}
+
+impl MockStream {
+    fn notify(&mut self, state: ffi::cubeb_state) {
+        self.state = Some(state);
+        unsafe {
+            self.state_callback.unwrap()(self as *mut _ as *mut _, self.user_ptr, state);
+        }
+    }
+
+    fn period_duration(&self) -> Duration {
+        Duration::from_nanos(self.period as u64 * 1_000_000_000 / u64::from(self.rate))
+    }
+
+    fn run(&mut self, duration: Duration) {
+        if self.state != Some(ffi::CUBEB_STATE_STARTED) {
+            return;
+        }
+        self.running += duration;
+        let period = self.period_duration();
+        while self.running >= period && self.state == Some(ffi::CUBEB_STATE_STARTED) {
+            self.running -= period;
+            self.process();
+        }
+    }
+
+    fn process(&mut self) {
+        if let Some(limit) = self.error_after {
+            if self.frames >= limit {
+                self.notify(ffi::CUBEB_STATE_ERROR);
+                return;
+            }
+        }
+        let input = self
+            .input
+            .as_mut()
+            .map_or(ptr::null_mut(), |b| b.as_mut_ptr());
+        let output = self
+            .output
+            .as_mut()
+            .map_or(ptr::null_mut(), |b| b.as_mut_ptr());
+        let _rt = enter_audio_callback("mock data callback");
+        let got = unsafe {
+            self.data_callback.unwrap()(
+                self as *mut _ as *mut _,
+                self.user_ptr,
+                input,
+                output,
+                self.period as c_long,
+            )
+        };
+        match CallbackResult::from_raw(got, self.period as c_long) {
+            CallbackResult::Continue(n) => self.frames += n as u64,
+            CallbackResult::Drain(n) => {
+                self.frames += n as u64;
+                self.notify(ffi::CUBEB_STATE_DRAINED);
+            }
+            CallbackResult::Error => self.notify(ffi::CUBEB_STATE_ERROR),
+        }
+    }
+}
+
+impl Drop for MockStream {
+    fn drop(&mut self) {
+        let stm = self as *mut MockStream;
+        let ctx = unsafe { &mut *self.context };
+        ctx.streams.retain(|&s| s != stm);
+    }
+}
+
+impl StreamOps for MockStream {
+    fn start(&mut self) -> Result<()> {
+        if self.state != Some(ffi::CUBEB_STATE_STARTED) {
+            self.notify(ffi::CUBEB_STATE_STARTED);
+        }
+        Ok(())
+    }
+    fn stop(&mut self) -> Result<()> {
+        if self.state == Some(ffi::CUBEB_STATE_STARTED) {
+            self.running = Duration::from_secs(0);
+            self.notify(ffi::CUBEB_STATE_STOPPED);
+        }
+        Ok(())
+    }
+    fn position(&mut self) -> Result<u64> {
+        Ok(self.frames)
+    }
+    fn latency(&mut self) -> Result<u32> {
+        match self.output {
+            Some(_) => Ok(self.output_latency),
+            None => Err(Error::error()),
+        }
+    }
+    fn input_latency(&mut self) -> Result<u32> {
+        match self.input {
+            Some(_) => Ok(self.input_latency),
+            None => Err(Error::error()),
+        }
+    }
+    fn set_volume(&mut self, volume: f32) -> Result<()> {
+        match self.output {
+            Some(_) => {
+                self.volume = volume;
+                Ok(())
+            }
+            None => Err(Error::error()),
+        }
+    }
+    fn set_name(&mut self, _name: &CStr) -> Result<()> {
+        Ok(())
+    }
+    fn register_device_changed_callback(
+        &mut self,
+        _device_changed_callback: ffi::cubeb_device_changed_callback,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
@@ -94,4 +94,36 @@ pub struct Ops {
             user_ptr: *mut c_void,
         ) -> c_int,
     >,
+    pub get_capabilities: Option<
+        unsafe extern "C" fn(
+            context: *mut ffi::cubeb,
+            capabilities: *mut ffi::cubeb_capabilities,
+        ) -> c_int,
+    >,
+    pub device_collection_get_properties: Option<
+        unsafe extern "C" fn(
+            context: *mut ffi::cubeb,
+            collection: *mut ffi::cubeb_device_collection,
+            index: usize,
+            properties: *mut *const ffi::cubeb_property,
+            count: *mut usize,
+        ) -> c_int,
+    >,
+    #[allow(clippy::type_complexity)]
+    pub stream_init_with_options: Option<
+        unsafe extern "C" fn(
+            context: *mut ffi::cubeb,
+            stream: *mut *mut ffi::cubeb_stream,
+            stream_name: *const c_char,
+            input_device: ffi::cubeb_devid,
+            input_stream_params: *mut ffi::cubeb_stream_params,
+            output_device: ffi::cubeb_devid,
+            output_stream_params: *mut ffi::cubeb_stream_params,
+            options: *const ffi::cubeb_stream_options,
+            latency: c_uint,
+            data_callback: ffi::cubeb_data_callback,
+            state_callback: ffi::cubeb_state_callback,
+            user_ptr: *mut c_void,
+        ) -> c_int,
+    >,
 }
@@ -0,0 +1,1079 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Sample rate conversion between the rate of a stream and the rate of its
+//! devices, without libcubeb.
+//!
+//! [`Resampler`] follows `cubeb_resampler_fill`: it calls the data callback
+//! with frames at the stream rate, and converts the input and output frames
+//! of the devices from and to it. When only one direction of a duplex
+//! stream is resampled, the other is delayed by the same latency to keep
+//! them in sync. [`RawResampler`] wraps it behind the signature of the C
+//! functions, for backends driving a `cubeb_data_callback`.
+//!
+//! The conversion is a windowed sinc filter with the parameters of the
+//! speex resampler used by libcubeb, for each [`ResamplerQuality`]. The
+//! filter coefficients are computed for each phase when there are not too
+//! many of them, otherwise interpolated between oversampled ones as speex
+//! does. Dot products use SSE on x86_64 and NEON on aarch64.
+
+use cubeb_core::{ffi, SampleFormat, StreamParamsRef};
+use duplex::Sample;
+use std::os::raw::{c_long, c_void};
+use std::{cmp, ptr, slice};
+
+/// Trade-off between the quality, latency and CPU use of the resampler.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResamplerQuality {
+    Voip,
+    #[default]
+    Default,
+    Desktop,
+}
+
+impl From<ffi::cubeb_resampler_quality> for ResamplerQuality {
+    fn from(x: ffi::cubeb_resampler_quality) -> Self {
+        match x {
+            ffi::CUBEB_RESAMPLER_QUALITY_VOIP => ResamplerQuality::Voip,
+            ffi::CUBEB_RESAMPLER_QUALITY_DEFAULT => ResamplerQuality::Default,
+            ffi::CUBEB_RESAMPLER_QUALITY_DESKTOP => ResamplerQuality::Desktop,
+            x => panic!("unknown resampler quality: {}", x),
+        }
+    }
+}
+
+// Filter parameters, as `quality_map` of speex.
+struct QualityMapping {
+    base_length: u64,
+    oversample: u64,
+    downsample_bandwidth: f64,
+    upsample_bandwidth: f64,
+    window: &'static [f64; 36],
+}
+
+impl ResamplerQuality {
+    // Speex qualities 3, 4 and 5.
+    fn mapping(self) -> QualityMapping {
+        match self {
+            ResamplerQuality::Voip => QualityMapping {
+                base_length: 48,
+                oversample: 8,
+                downsample_bandwidth: 0.895,
+                upsample_bandwidth: 0.917,
+                window: &KAISER8,
+            },
+            ResamplerQuality::Default => QualityMapping {
+                base_length: 64,
+                oversample: 8,
+                downsample_bandwidth: 0.921,
+                upsample_bandwidth: 0.940,
+                window: &KAISER8,
+            },
+            ResamplerQuality::Desktop => QualityMapping {
+                base_length: 80,
+                oversample: 16,
+                downsample_bandwidth: 0.922,
+                upsample_bandwidth: 0.940,
+                window: &KAISER10,
+            },
+        }
+    }
+}
+
+// Kaiser windows, sampled 32 times over [0, 1], from speex.
+const WINDOW_OVERSAMPLE: f64 = 32.0;
+#[rustfmt::skip]
+const KAISER8: [f64; 36] = [
+    0.99635258, 1.00000000, 0.99635258, 0.98548012, 0.96759014, 0.94302200,
+    0.91223751, 0.87580811, 0.83439927, 0.78875245, 0.73966538, 0.68797126,
+    0.63451750, 0.58014482, 0.52566725, 0.47185369, 0.41941150, 0.36897272,
+    0.32108304, 0.27619388, 0.23465776, 0.19672670, 0.16255380, 0.13219758,
+    0.10562887, 0.08273982, 0.06335451, 0.04724088, 0.03412321, 0.02369490,
+    0.01563093, 0.00959968, 0.00527363, 0.00233883, 0.00050000, 0.00000000,
+];
+#[rustfmt::skip]
+const KAISER10: [f64; 36] = [
+    0.99537781, 1.00000000, 0.99537781, 0.98162644, 0.95908712, 0.92831446,
+    0.89005583, 0.84522401, 0.79486424, 0.74011713, 0.68217934, 0.62226347,
+    0.56155915, 0.50119680, 0.44221549, 0.38553619, 0.33194107, 0.28205962,
+    0.23636152, 0.19515633, 0.15859932, 0.12670280, 0.09935205, 0.07632451,
+    0.05731132, 0.04193980, 0.02979584, 0.02044510, 0.01345224, 0.00839739,
+    0.00488951, 0.00257636, 0.00115101, 0.00035515, 0.00000000, 0.00000000,
+];
+
+/// Most coefficients computed for each phase, beyond which they are
+/// interpolated.
+const MAX_DIRECT_COEFFICIENTS: u64 = 1 << 16;
+
+// Coefficients of the cubic interpolation at `frac` between the second and
+// third of four points.
+fn cubic_coefficients(frac: f64) -> [f64; 4] {
+    let (frac2, frac3) = (frac * frac, frac * frac * frac);
+    let c0 = -frac / 3.0 + 0.5 * frac2 - frac3 / 6.0;
+    let c2 = frac + 0.5 * frac2 - 0.5 * frac3;
+    let c3 = -frac / 6.0 + frac3 / 6.0;
+    [c0, 1.0 - c0 - c2 - c3, c2, c3]
+}
+
+fn window(x: f64, table: &[f64; 36]) -> f64 {
+    let y = x * WINDOW_OVERSAMPLE;
+    let index = y.floor() as usize;
+    let c = cubic_coefficients(y - index as f64);
+    (0..4).map(|i| c[i] * table[index + i]).sum()
+}
+
+fn sinc(cutoff: f64, x: f64, length: u64, table: &[f64; 36]) -> f64 {
+    use std::f64::consts::PI;
+    let half = length as f64 / 2.0;
+    if x.abs() < 1e-6 {
+        cutoff
+    } else if x.abs() > half {
+        0.0
+    } else {
+        let xx = PI * x * cutoff;
+        cutoff * xx.sin() / xx * window((x / half).abs(), table)
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Dot product of the first `a.len()` elements of `a` and `b`.
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let b = &b[..a.len()];
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        // SSE is part of x86_64.
+        use std::arch::x86_64::*;
+        let chunks = a.len() / 8;
+        let (mut acc0, mut acc1) = (_mm_setzero_ps(), _mm_setzero_ps());
+        for i in 0..chunks {
+            let (pa, pb) = (a.as_ptr().add(i * 8), b.as_ptr().add(i * 8));
+            acc0 = _mm_add_ps(acc0, _mm_mul_ps(_mm_loadu_ps(pa), _mm_loadu_ps(pb)));
+            acc1 = _mm_add_ps(
+                acc1,
+                _mm_mul_ps(_mm_loadu_ps(pa.add(4)), _mm_loadu_ps(pb.add(4))),
+            );
+        }
+        let mut lanes = [0f32; 4];
+        _mm_storeu_ps(lanes.as_mut_ptr(), _mm_add_ps(acc0, acc1));
+        let tail = dot_scalar(&a[chunks * 8..], &b[chunks * 8..]);
+        (lanes[0] + lanes[1]) + (lanes[2] + lanes[3]) + tail
+    }
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        // NEON is part of aarch64.
+        use std::arch::aarch64::*;
+        let chunks = a.len() / 4;
+        let mut acc = vdupq_n_f32(0.0);
+        for i in 0..chunks {
+            let (pa, pb) = (a.as_ptr().add(i * 4), b.as_ptr().add(i * 4));
+            acc = vfmaq_f32(acc, vld1q_f32(pa), vld1q_f32(pb));
+        }
+        vaddvq_f32(acc) + dot_scalar(&a[chunks * 4..], &b[chunks * 4..])
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    dot_scalar(a, b)
+}
+
+fn dot_scalar(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// A windowed sinc filter converting interleaved frames between two rates,
+/// as `speex_resampler_process_interleaved_*`.
+#[derive(Debug)]
+struct SincFilter {
+    // Input and output rates, divided by their GCD.
+    num: u64,
+    den: u64,
+    length: usize,
+    // Coefficients of each phase, or `oversample + 3` oversampled phases
+    // from -1, `length` each.
+    coefficients: Vec<f32>,
+    oversample: Option<u64>,
+    // Input frames of each channel, from those the next output frame is
+    // computed from.
+    history: Vec<Vec<f32>>,
+    // Phase of the next output frame, in `1 / den` input frames.
+    phase: u64,
+}
+
+impl SincFilter {
+    fn new(channels: usize, source_rate: u32, target_rate: u32, quality: ResamplerQuality) -> Self {
+        assert!(channels > 0 && source_rate > 0 && target_rate > 0);
+        let divisor = gcd(u64::from(source_rate), u64::from(target_rate));
+        let (num, den) = (
+            u64::from(source_rate) / divisor,
+            u64::from(target_rate) / divisor,
+        );
+        let q = quality.mapping();
+        let (mut length, mut oversample) = (q.base_length, q.oversample);
+        let cutoff = if num > den {
+            // Lower the cutoff below the target Nyquist frequency, with a
+            // longer filter to keep the transition band as steep.
+            length = ((length * num / den - 1) & !7) + 8;
+            for factor in &[2, 4, 8, 16] {
+                if factor * den < num {
+                    oversample >>= 1;
+                }
+            }
+            oversample = cmp::max(oversample, 1);
+            q.downsample_bandwidth * den as f64 / num as f64
+        } else {
+            q.upsample_bandwidth
+        };
+
+        let window = q.window;
+        let row = move |shift: f64| {
+            (0..length).map(move |j| {
+                let x = (j + 1) as f64 - shift - length as f64 / 2.0;
+                sinc(cutoff, x, length, window) as f32
+            })
+        };
+        let (coefficients, oversample) = if den * length <= MAX_DIRECT_COEFFICIENTS {
+            let rows = (0..den).flat_map(|phase| row(phase as f64 / den as f64));
+            (rows.collect(), None)
+        } else {
+            let phases = -1..oversample as i64 + 2;
+            let rows = phases.flat_map(|phase| row(phase as f64 / oversample as f64));
+            (rows.collect(), Some(oversample))
+        };
+
+        SincFilter {
+            num,
+            den,
+            length: length as usize,
+            coefficients,
+            oversample,
+            history: vec![vec![0.0; length as usize - 1]; channels],
+            phase: 0,
+        }
+    }
+
+    /// Latency, in output frames.
+    fn latency(&self) -> usize {
+        (((self.length as u64 / 2) * self.den + self.num / 2) / self.num) as usize
+    }
+
+    /// Input frames to add for `frames` more output frames.
+    fn input_needed(&self, frames: usize) -> usize {
+        if frames == 0 {
+            return 0;
+        }
+        let last = (self.phase + (frames as u64 - 1) * self.num) / self.den;
+        (last as usize + self.length).saturating_sub(self.history[0].len())
+    }
+
+    /// Output frames computed once `frames` more input frames are added.
+    fn output_for_input(&self, frames: usize) -> usize {
+        let end = (self.history[0].len() + frames + 1).saturating_sub(self.length) as u64;
+        let available = end * self.den;
+        if available <= self.phase {
+            return 0;
+        }
+        (available - self.phase).div_ceil(self.num) as usize
+    }
+
+    fn compute(&self, channel: usize) -> f32 {
+        let start = (self.phase / self.den) as usize;
+        let frames = &self.history[channel][start..start + self.length];
+        let phase = self.phase % self.den;
+        match self.oversample {
+            None => {
+                let offset = phase as usize * self.length;
+                dot(frames, &self.coefficients[offset..])
+            }
+            Some(oversample) => {
+                let offset = phase * oversample / self.den;
+                let frac = ((phase * oversample) % self.den) as f64 / self.den as f64;
+                let c = cubic_coefficients(frac);
+                (0..4)
+                    .map(|i| {
+                        // Rows start at the oversampled phase -1.
+                        let row = (offset as usize + i) * self.length;
+                        c[i] * f64::from(dot(frames, &self.coefficients[row..]))
+                    })
+                    .sum::<f64>() as f32
+            }
+        }
+    }
+
+    /// Resamples interleaved `input` into `output`, and returns the number
+    /// of input frames used and of output frames written. Input frames are
+    /// only used as needed for the output frames.
+    fn process<T: Sample>(&mut self, input: &[T], output: &mut [T]) -> (usize, usize) {
+        let channels = self.history.len();
+        let used = cmp::min(
+            self.input_needed(output.len() / channels),
+            input.len() / channels,
+        );
+        for (c, history) in self.history.iter_mut().enumerate() {
+            let samples = input[..used * channels].iter().skip(c).step_by(channels);
+            history.extend(samples.map(|s| s.to_f32()));
+        }
+
+        let mut written = 0;
+        for frame in output.chunks_mut(channels) {
+            if (self.phase / self.den) as usize + self.length > self.history[0].len() {
+                break;
+            }
+            for (c, sample) in frame.iter_mut().enumerate() {
+                *sample = T::from_f32(self.compute(c));
+            }
+            self.phase += self.num;
+            written += 1;
+        }
+
+        let consumed = (self.phase / self.den) as usize;
+        for history in &mut self.history {
+            history.drain(..consumed);
+        }
+        self.phase -= consumed as u64 * self.den;
+        (used, written)
+    }
+}
+
+// When dropping input frames not to build up latency, the number of frames
+// to keep.
+fn min_buffered_frames(rate: u32) -> usize {
+    rate as usize / 20
+}
+
+/// One direction of a resampler, buffering its input.
+#[derive(Debug)]
+struct OneWay<T> {
+    channels: usize,
+    source_rate: u32,
+    filter: SincFilter,
+    // Input frames not resampled yet.
+    input: Vec<T>,
+    // Resampled frames handed out by `output`.
+    output: Vec<T>,
+    leftover: usize,
+}
+
+impl<T: Sample> OneWay<T> {
+    fn new(channels: usize, source_rate: u32, target_rate: u32, quality: ResamplerQuality) -> Self {
+        // As libcubeb, run half a filter of silence through it first,
+        // dropping what is left of it.
+        let mut filter = SincFilter::new(channels, source_rate, target_rate, quality);
+        let length = filter.length;
+        for history in &mut filter.history {
+            history.resize(length - 1 + length / 2, 0.0);
+        }
+        filter.phase += filter.output_for_input(0) as u64 * filter.num;
+        let consumed = (filter.phase / filter.den) as usize;
+        for history in &mut filter.history {
+            history.drain(..consumed);
+            history.truncate(length - 1);
+        }
+        filter.phase -= consumed as u64 * filter.den;
+        OneWay {
+            channels,
+            source_rate,
+            filter,
+            input: Vec::new(),
+            output: Vec::new(),
+            leftover: 0,
+        }
+    }
+
+    fn buffered(&self) -> usize {
+        self.input.len() / self.channels
+    }
+
+    fn latency(&self) -> usize {
+        self.filter.latency()
+    }
+}
+
+/// Delays a stream, when the other direction of a duplex stream is
+/// resampled.
+#[derive(Debug)]
+struct DelayLine<T> {
+    channels: usize,
+    rate: u32,
+    length: usize,
+    buffer: Vec<T>,
+    output: Vec<T>,
+    leftover: usize,
+}
+
+impl<T: Sample> DelayLine<T> {
+    fn new(length: usize, channels: usize, rate: u32) -> Self {
+        DelayLine {
+            channels,
+            rate,
+            length,
+            buffer: vec![T::default(); length * channels],
+            output: Vec::new(),
+            leftover: 0,
+        }
+    }
+}
+
+/// A direction of a stream, as seen by `Resampler`.
+#[derive(Debug)]
+enum Processor<T> {
+    Resample(OneWay<T>),
+    Delay(DelayLine<T>),
+}
+
+impl<T: Sample> Processor<T> {
+    fn latency(&self) -> usize {
+        match self {
+            Processor::Resample(r) => r.latency(),
+            Processor::Delay(d) => d.length,
+        }
+    }
+
+    fn channels(&self) -> usize {
+        match self {
+            Processor::Resample(r) => r.channels,
+            Processor::Delay(d) => d.channels,
+        }
+    }
+
+    fn input(&mut self, frames: &[T]) {
+        match self {
+            Processor::Resample(r) => r.input.extend_from_slice(frames),
+            Processor::Delay(d) => d.buffer.extend_from_slice(frames),
+        }
+    }
+
+    // Output frames available once `frames` more input frames are added.
+    fn output_for_input(&self, frames: usize) -> usize {
+        match self {
+            Processor::Resample(r) => r.filter.output_for_input(r.buffered() + frames),
+            Processor::Delay(_) => frames,
+        }
+    }
+
+    // Input frames to add for `frames` output frames.
+    fn input_needed_for_output(&self, frames: usize) -> usize {
+        match self {
+            Processor::Resample(r) => r.filter.input_needed(frames).saturating_sub(r.buffered()),
+            Processor::Delay(_) => frames,
+        }
+    }
+
+    // Exactly `frames` output frames, padded with silence, and the number
+    // of input frames used.
+    fn output(&mut self, frames: usize) -> (&[T], usize) {
+        let channels = self.channels();
+        let (output, used) = match self {
+            Processor::Resample(r) => {
+                r.output.resize(frames * channels, T::default());
+                let (used, written) = r.filter.process(&r.input, &mut r.output);
+                if written < frames {
+                    cubeb_logv!(
+                        "underrun during resampling: got {} frames, expected {}",
+                        written,
+                        frames
+                    );
+                    for s in &mut r.output[written * channels..] {
+                        *s = T::default();
+                    }
+                }
+                r.input.drain(..used * channels);
+                (&r.output, used)
+            }
+            Processor::Delay(d) => {
+                let available = cmp::min(frames * channels, d.buffer.len());
+                d.output.clear();
+                d.output.extend(d.buffer.drain(..available));
+                d.output.resize(frames * channels, T::default());
+                (&d.output, frames)
+            }
+        };
+        (&output[..], used)
+    }
+
+    // Writes up to `output.len()` output frames, and returns their number.
+    fn output_into(&mut self, output: &mut [T]) -> usize {
+        match self {
+            Processor::Resample(r) => {
+                let (used, written) = r.filter.process(&r.input, output);
+                r.input.drain(..used * r.channels);
+                written
+            }
+            Processor::Delay(d) => {
+                let available = cmp::min(output.len(), d.buffer.len());
+                output[..available].copy_from_slice(&d.buffer[..available]);
+                d.buffer.drain(..available);
+                available / d.channels
+            }
+        }
+    }
+
+    // Room for `frames` input frames, to be confirmed with `written`.
+    fn input_buffer(&mut self, frames: usize) -> &mut [T] {
+        let channels = self.channels();
+        let (buffer, leftover) = match self {
+            Processor::Resample(r) => (&mut r.input, &mut r.leftover),
+            Processor::Delay(d) => (&mut d.buffer, &mut d.leftover),
+        };
+        *leftover = buffer.len();
+        buffer.resize(*leftover + frames * channels, T::default());
+        &mut buffer[*leftover..]
+    }
+
+    fn written(&mut self, frames: usize) {
+        let channels = self.channels();
+        let (buffer, leftover) = match self {
+            Processor::Resample(r) => (&mut r.input, r.leftover),
+            Processor::Delay(d) => (&mut d.buffer, d.leftover),
+        };
+        buffer.truncate(leftover + frames * channels);
+    }
+
+    // Drops the oldest input frames past 50 ms, not to build up latency.
+    fn drop_audio_if_needed(&mut self) {
+        let channels = self.channels();
+        let (buffer, rate) = match self {
+            Processor::Resample(r) => (&mut r.input, r.source_rate),
+            Processor::Delay(d) => (&mut d.buffer, d.rate),
+        };
+        let available = buffer.len() / channels;
+        let to_keep = min_buffered_frames(rate);
+        if available > to_keep {
+            cubeb_logv!("Dropping {} frames", available - to_keep);
+            buffer.drain(..(available - to_keep) * channels);
+        }
+    }
+}
+
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+enum Kind<T> {
+    // The stream and device rates match.
+    Passthrough {
+        rate: u32,
+        // Input frames not handed to the callback yet.
+        input: Vec<T>,
+    },
+    Resampling {
+        input: Option<Processor<T>>,
+        output: Option<Processor<T>>,
+        draining: bool,
+    },
+}
+
+/// Converts the frames of a stream between the rates of its devices and
+/// its own rate, around its data callback.
+#[derive(Debug)]
+pub struct Resampler<T> {
+    input_channels: usize,
+    output_channels: usize,
+    kind: Kind<T>,
+}
+
+impl<T: Sample> Resampler<T> {
+    /// A resampler for a stream with `input_params` and `output_params` at
+    /// the rates of their devices, calling the data callback at
+    /// `target_rate`.
+    pub fn new(
+        input_params: Option<&StreamParamsRef>,
+        output_params: Option<&StreamParamsRef>,
+        target_rate: u32,
+        quality: ResamplerQuality,
+    ) -> Resampler<T> {
+        assert!(input_params.is_some() || output_params.is_some());
+        let input_channels = input_params.map_or(0, |p| p.channels() as usize);
+        let output_channels = output_params.map_or(0, |p| p.channels() as usize);
+        let resampled = |p: Option<&StreamParamsRef>| p.is_some_and(|p| p.rate() != target_rate);
+
+        if !resampled(input_params) && !resampled(output_params) {
+            cubeb_log!(
+                "Input and output sample-rate match, target rate of {}Hz",
+                target_rate
+            );
+            return Resampler {
+                input_channels,
+                output_channels,
+                kind: Kind::Passthrough {
+                    rate: target_rate,
+                    input: Vec::new(),
+                },
+            };
+        }
+
+        let mut input = input_params.filter(|&p| resampled(Some(p))).map(|p| {
+            Processor::Resample(OneWay::new(input_channels, p.rate(), target_rate, quality))
+        });
+        let mut output = output_params.filter(|&p| resampled(Some(p))).map(|p| {
+            Processor::Resample(OneWay::new(output_channels, target_rate, p.rate(), quality))
+        });
+        // Delay the direction not resampled of duplex streams, to keep them
+        // in sync.
+        match (input_params, output_params, &input, &output) {
+            (Some(_), Some(p), Some(resampler), None) => {
+                cubeb_log!(
+                    "Resampling input ({}Hz) to target and output rate of {}Hz",
+                    input_params.unwrap().rate(),
+                    target_rate
+                );
+                output = Some(Processor::Delay(DelayLine::new(
+                    resampler.latency(),
+                    output_channels,
+                    p.rate(),
+                )));
+            }
+            (Some(_), Some(p), None, Some(resampler)) => {
+                cubeb_log!(
+                    "Resampling output ({}Hz) to target and input rate of {}Hz",
+                    p.rate(),
+                    target_rate
+                );
+                input = Some(Processor::Delay(DelayLine::new(
+                    resampler.latency(),
+                    input_channels,
+                    p.rate(),
+                )));
+            }
+            _ => cubeb_log!("Resampling to target rate of {}Hz", target_rate),
+        }
+
+        Resampler {
+            input_channels,
+            output_channels,
+            kind: Kind::Resampling {
+                input,
+                output,
+                draining: false,
+            },
+        }
+    }
+
+    /// Latency added by the resampler, in frames at the target rate.
+    pub fn latency(&self) -> usize {
+        match &self.kind {
+            Kind::Passthrough { .. } => 0,
+            Kind::Resampling { input, output, .. } => {
+                input.as_ref().or(output.as_ref()).unwrap().latency()
+            }
+        }
+    }
+
+    /// As `cubeb_resampler_fill`: takes the interleaved `input` frames from
+    /// the input device, if any, and fills `output` for the output device,
+    /// calling `data_callback` with frames at the target rate, and their
+    /// number. Returns the number of frames written to `output`, or of
+    /// input frames consumed by the callback for input streams. Fewer than
+    /// requested means the stream drains. `input_frames_used` is set to the
+    /// number of input frames used.
+    pub fn fill<F>(
+        &mut self,
+        input: Option<&[T]>,
+        input_frames_used: &mut usize,
+        output: Option<&mut [T]>,
+        mut data_callback: F,
+    ) -> isize
+    where
+        F: FnMut(Option<&[T]>, Option<&mut [T]>, usize) -> isize,
+    {
+        let input_frames = input.map_or(0, |i| i.len() / cmp::max(self.input_channels, 1));
+        *input_frames_used = input_frames;
+        let output_frames = output
+            .as_ref()
+            .map_or(0, |o| o.len() / cmp::max(self.output_channels, 1));
+        let input_channels = self.input_channels;
+
+        match &mut self.kind {
+            Kind::Passthrough {
+                rate,
+                input: buffer,
+            } => {
+                let (input, output) = match (input, output) {
+                    (Some(input), None) => return data_callback(Some(input), None, input_frames),
+                    (None, output) => return data_callback(None, output, output_frames),
+                    (Some(input), Some(output)) => (input, output),
+                };
+                // Hand the callback exactly `output_frames` input frames,
+                // buffering the rest or padding with silence.
+                let needed = output_frames * input_channels;
+                let rv = if buffer.is_empty() && input.len() >= needed {
+                    buffer.extend_from_slice(&input[needed..]);
+                    *input_frames_used = output_frames;
+                    data_callback(Some(&input[..needed]), Some(output), output_frames)
+                } else {
+                    buffer.extend_from_slice(input);
+                    let available = cmp::min(buffer.len(), needed);
+                    *input_frames_used = available / cmp::max(input_channels, 1);
+                    buffer.resize(cmp::max(buffer.len(), needed), T::default());
+                    let rv = data_callback(Some(&buffer[..needed]), Some(output), output_frames);
+                    buffer.drain(..needed);
+                    rv
+                };
+                let available = buffer.len() / cmp::max(input_channels, 1);
+                let to_keep = min_buffered_frames(*rate);
+                if available > to_keep {
+                    cubeb_logv!("Dropping {} frames", available - to_keep);
+                    buffer.drain(..(available - to_keep) * input_channels);
+                }
+                rv
+            }
+            Kind::Resampling {
+                draining,
+                input: input_processor,
+                output: output_processor,
+            } => match (input_processor, output_processor, output) {
+                (Some(input_processor), None, _) => {
+                    let input = input.unwrap();
+                    let resampled = input_processor.output_for_input(input_frames);
+                    input_processor.input(input);
+                    if resampled == 0 {
+                        return input_frames as isize;
+                    }
+                    let (frames, used) = input_processor.output(resampled);
+                    *input_frames_used = used;
+                    let got = data_callback(Some(frames), None, resampled);
+                    used as isize * (got / resampled as isize)
+                }
+                (input_processor, Some(output_processor), Some(output)) => {
+                    if !*draining {
+                        let frames = output_processor.input_needed_for_output(output_frames);
+                        let resampled = match (input_processor.as_mut(), input) {
+                            (Some(processor), Some(input)) => {
+                                processor.input(input);
+                                let (resampled, used) = processor.output(frames);
+                                *input_frames_used = used;
+                                Some(resampled)
+                            }
+                            _ => None,
+                        };
+                        let unprocessed = output_processor.input_buffer(frames);
+                        let got = data_callback(resampled, Some(unprocessed), frames);
+                        if got < frames as isize {
+                            *draining = true;
+                            if got < 0 {
+                                output_processor.written(0);
+                                return got;
+                            }
+                        }
+                        output_processor.written(got as usize);
+                        if let Some(processor) = input_processor.as_mut() {
+                            processor.drop_audio_if_needed();
+                        }
+                    }
+                    let got = output_processor.output_into(output);
+                    if input_processor.is_some() {
+                        output_processor.drop_audio_if_needed();
+                    }
+                    got as isize
+                }
+                _ => panic!("fill called without buffers for the stream directions"),
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+enum TypedResampler {
+    S16(Resampler<i16>),
+    Float32(Resampler<f32>),
+}
+
+/// A [`Resampler`] calling a `cubeb_data_callback`, behind the signature of
+/// the `cubeb_resampler_*` functions.
+#[derive(Debug)]
+pub struct RawResampler {
+    stream: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    data_callback: ffi::cubeb_data_callback,
+    input_channels: usize,
+    output_channels: usize,
+    resampler: TypedResampler,
+}
+
+impl RawResampler {
+    /// As `cubeb_resampler_create`.
+    pub fn new(
+        stream: *mut ffi::cubeb_stream,
+        input_params: Option<&StreamParamsRef>,
+        output_params: Option<&StreamParamsRef>,
+        target_rate: u32,
+        data_callback: ffi::cubeb_data_callback,
+        user_ptr: *mut c_void,
+        quality: ResamplerQuality,
+    ) -> RawResampler {
+        let format = input_params.or(output_params).unwrap().format();
+        let resampler = match format {
+            SampleFormat::S16LE | SampleFormat::S16BE | SampleFormat::S16NE => TypedResampler::S16(
+                Resampler::new(input_params, output_params, target_rate, quality),
+            ),
+            SampleFormat::Float32LE | SampleFormat::Float32BE | SampleFormat::Float32NE => {
+                TypedResampler::Float32(Resampler::new(
+                    input_params,
+                    output_params,
+                    target_rate,
+                    quality,
+                ))
+            }
+        };
+        RawResampler {
+            stream,
+            user_ptr,
+            data_callback,
+            input_channels: input_params.map_or(0, |p| p.channels() as usize),
+            output_channels: output_params.map_or(0, |p| p.channels() as usize),
+            resampler,
+        }
+    }
+
+    /// As `cubeb_resampler_fill`.
+    ///
+    /// # Safety
+    ///
+    /// The buffers must hold the frames given by their counts, and the
+    /// callback must be safe to call with the stream and user pointer.
+    pub unsafe fn fill(
+        &mut self,
+        input_buffer: *mut c_void,
+        input_frame_count: *mut c_long,
+        output_buffer: *mut c_void,
+        output_frames_needed: c_long,
+    ) -> c_long {
+        match &mut self.resampler {
+            TypedResampler::S16(r) => fill(
+                r,
+                (self.stream, self.user_ptr, self.data_callback),
+                (input_buffer, input_frame_count, self.input_channels),
+                (output_buffer, output_frames_needed, self.output_channels),
+            ),
+            TypedResampler::Float32(r) => fill(
+                r,
+                (self.stream, self.user_ptr, self.data_callback),
+                (input_buffer, input_frame_count, self.input_channels),
+                (output_buffer, output_frames_needed, self.output_channels),
+            ),
+        }
+    }
+
+    /// As `cubeb_resampler_latency`.
+    pub fn latency(&self) -> c_long {
+        match &self.resampler {
+            TypedResampler::S16(r) => r.latency() as c_long,
+            TypedResampler::Float32(r) => r.latency() as c_long,
+        }
+    }
+}
+
+type Callback = (
+    *mut ffi::cubeb_stream,
+    *mut c_void,
+    ffi::cubeb_data_callback,
+);
+
+unsafe fn fill<T: Sample>(
+    resampler: &mut Resampler<T>,
+    (stream, user_ptr, data_callback): Callback,
+    (input_buffer, input_frame_count, input_channels): (*mut c_void, *mut c_long, usize),
+    (output_buffer, output_frames, output_channels): (*mut c_void, c_long, usize),
+) -> c_long {
+    let input = if input_buffer.is_null() {
+        None
+    } else {
+        let samples = *input_frame_count as usize * input_channels;
+        Some(slice::from_raw_parts(input_buffer as *const T, samples))
+    };
+    let output = if output_buffer.is_null() {
+        None
+    } else {
+        let samples = output_frames as usize * output_channels;
+        Some(slice::from_raw_parts_mut(output_buffer as *mut T, samples))
+    };
+    let mut input_frames_used = 0;
+    let rv = resampler.fill(
+        input,
+        &mut input_frames_used,
+        output,
+        |input, output, frames| {
+            let input = input.map_or(ptr::null(), |i| i.as_ptr() as *const c_void);
+            let output = output.map_or(ptr::null_mut(), |o| o.as_mut_ptr() as *mut c_void);
+            data_callback.unwrap()(stream, user_ptr, input, output, frames as c_long) as isize
+        },
+    );
+    if !input_frame_count.is_null() {
+        *input_frame_count = input_frames_used as c_long;
+    }
+    rv as c_long
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use StreamParams;
+
+    fn params(channels: u32, rate: u32) -> StreamParams {
+        StreamParams::from(ffi::cubeb_stream_params {
+            format: ffi::CUBEB_SAMPLE_FLOAT32NE,
+            rate,
+            channels,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn dot_matches_scalar() {
+        let a: Vec<f32> = (0..37).map(|i| i as f32 * 0.25 - 3.0).collect();
+        let b: Vec<f32> = (0..40).map(|i| 1.0 / (i as f32 + 1.0)).collect();
+        for len in 0..a.len() {
+            let expected = dot_scalar(&a[..len], &b[..len]);
+            assert!((dot(&a[..len], &b) - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn filter_counts() {
+        // Coefficients are computed per phase, unless there are too many.
+        let cases = [
+            (44100, 48000, false),
+            (48000, 44100, false),
+            (96000, 48000, false),
+            (44100, 48001, true),
+        ];
+        for &(from, to, interpolated) in &cases {
+            let mut filter = SincFilter::new(2, from, to, ResamplerQuality::Default);
+            assert_eq!(filter.oversample.is_some(), interpolated);
+            let input = vec![0.5f32; 2 * 2000];
+            let mut output = vec![0f32; 2 * 512];
+            for _ in 0..10 {
+                let needed = filter.input_needed(512);
+                assert!(filter.output_for_input(needed) >= 512);
+                assert!(filter.output_for_input(needed - 1) < 512);
+                assert_eq!(filter.process(&input, &mut output), (needed, 512));
+            }
+            // Past the latency, DC goes through unchanged.
+            assert!(
+                output.iter().all(|s| (s - 0.5).abs() < 1e-2),
+                "{:?}",
+                output
+            );
+        }
+    }
+
+    #[test]
+    fn latency() {
+        let filter = SincFilter::new(1, 44100, 48000, ResamplerQuality::Default);
+        // Half the filter length, at the output rate.
+        assert_eq!(filter.latency(), (32 * 160 + 147 / 2) / 147);
+        // A click shows up after the latency.
+        let mut filter = SincFilter::new(1, 24000, 48000, ResamplerQuality::Voip);
+        let mut input = vec![0f32; 100];
+        input[0] = 1.0;
+        let mut output = vec![0f32; 200];
+        filter.process(&input, &mut output);
+        let peak = (0..output.len())
+            .max_by(|&a, &b| output[a].partial_cmp(&output[b]).unwrap())
+            .unwrap();
+        assert_eq!(peak, filter.latency());
+    }
+
+    #[test]
+    fn passthrough() {
+        let params = params(1, 48000);
+        let mut r = Resampler::<f32>::new(Some(&params), Some(&params), 48000, Default::default());
+        assert_eq!(r.latency(), 0);
+        let mut output = [0f32; 4];
+        let mut used = 0;
+        // Short input is padded with silence, long input is kept for later.
+        let got = r.fill(Some(&[1., 2.]), &mut used, Some(&mut output), |i, o, n| {
+            assert_eq!(i.unwrap(), [1., 2., 0., 0.]);
+            o.unwrap().copy_from_slice(i.unwrap());
+            n as isize
+        });
+        assert_eq!((got, used), (4, 2));
+        let got = r.fill(
+            Some(&[3., 4., 5., 6., 7., 8.]),
+            &mut used,
+            Some(&mut output),
+            |i, _, n| {
+                assert_eq!(i.unwrap(), [3., 4., 5., 6.]);
+                n as isize
+            },
+        );
+        assert_eq!((got, used), (4, 4));
+        r.fill(Some(&[9., 10.]), &mut used, Some(&mut output), |i, _, n| {
+            assert_eq!(i.unwrap(), [7., 8., 9., 10.]);
+            n as isize
+        });
+    }
+
+    #[test]
+    fn duplex_output_resampled() {
+        let (input, output) = (params(1, 48000), params(2, 44100));
+        let mut r = Resampler::<f32>::new(Some(&input), Some(&output), 48000, Default::default());
+        // The input is delayed as much as the output.
+        let latency = r.latency();
+        assert!(latency > 0);
+        let mut out = vec![0f32; 2 * 441];
+        let mut used = 0;
+        let mut delivered = Vec::new();
+        for i in 0..10 {
+            let input: Vec<f32> = (0..441).map(|j| (i * 441 + j) as f32).collect();
+            let got = r.fill(Some(&input), &mut used, Some(&mut out), |i, o, n| {
+                delivered.extend_from_slice(i.unwrap());
+                assert_eq!(o.unwrap().len(), 2 * n);
+                n as isize
+            });
+            // The output is always filled in full.
+            assert_eq!(got, 441);
+        }
+        assert!(delivered[..latency].iter().all(|&s| s == 0.0));
+        assert_eq!(delivered[latency], 0.0);
+        assert_eq!(delivered[latency + 1], 1.0);
+    }
+
+    #[test]
+    fn drain() {
+        let output = params(1, 44100);
+        let mut r = Resampler::<f32>::new(None, Some(&output), 48000, ResamplerQuality::Voip);
+        let mut out = vec![0f32; 441];
+        let mut used = 0;
+        assert_eq!(
+            r.fill(None, &mut used, Some(&mut out), |_, _, n| n as isize),
+            441
+        );
+        // The callback returning less drains the filter, then stops.
+        let got = r.fill(None, &mut used, Some(&mut out), |_, _, n| n as isize / 2);
+        assert!(got > 0 && got < 441);
+        let mut calls = 0;
+        let mut total = got;
+        while total > 0 {
+            total = r.fill(None, &mut used, Some(&mut out), |_, _, _| {
+                calls += 1;
+                0
+            });
+        }
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn input_only() {
+        let input = params(1, 96000);
+        let mut r = Resampler::<i16>::new(Some(&input), None, 48000, Default::default());
+        let mut used = 0;
+        let mut delivered = 0;
+        for _ in 0..10 {
+            let frames = [1000i16; 960];
+            let got = r.fill(Some(&frames), &mut used, None, |i, o, n| {
+                assert!(o.is_none());
+                assert_eq!(i.unwrap().len(), n);
+                delivered += n;
+                n as isize
+            });
+            assert_eq!(got, used as isize);
+        }
+        // All but the filter's own latency went through.
+        assert!(delivered >= 4800 - r.latency() - 1);
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Checks, in debug builds, that code running in an audio callback does not
+//! allocate, free or block.
+//!
+//! Backends mark the body of their audio callbacks with
+//! `enter_audio_callback`. Heap operations inside such a scope are seen by
+//! `RtCheckAllocator`, which the final binary (or a test harness) installs as
+//! its `#[global_allocator]`, and blocking calls are flagged by going
+//! through `lock` or `note_blocking`. Violations are counted and logged with
+//! `cubeb_alog!` when the scope ends. In release builds the scopes and the
+//! allocator hook do nothing.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+static VIOLATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy, Default)]
+struct Counts {
+    allocations: usize,
+    deallocations: usize,
+    blocking: usize,
+    // First blocking call seen.
+    blocking_what: Option<&'static str>,
+}
+
+impl Counts {
+    fn total(&self) -> usize {
+        self.allocations + self.deallocations + self.blocking
+    }
+}
+
+thread_local! {
+    // Name of the innermost audio callback scope on this thread.
+    static SCOPE: Cell<Option<&'static str>> = const { Cell::new(None) };
+    static COUNTS: Cell<Counts> = const {
+        Cell::new(Counts {
+            allocations: 0,
+            deallocations: 0,
+            blocking: 0,
+            blocking_what: None,
+        })
+    };
+}
+
+fn record<F: FnOnce(&mut Counts)>(f: F) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    // `try_with` since the allocator can run while thread locals are
+    // being destroyed.
+    let in_scope = SCOPE.try_with(|s| s.get().is_some()).unwrap_or(false);
+    if in_scope {
+        let _ = COUNTS.try_with(|c| {
+            let mut counts = c.get();
+            f(&mut counts);
+            c.set(counts);
+        });
+    }
+}
+
+/// Marks the current thread as running an audio callback until the returned
+/// guard is dropped. Scopes nest.
+pub fn enter_audio_callback(name: &'static str) -> AudioCallbackScope {
+    if !cfg!(debug_assertions) {
+        return AudioCallbackScope {
+            name,
+            previous: None,
+            outer: Counts::default(),
+        };
+    }
+    AudioCallbackScope {
+        name,
+        previous: SCOPE.with(|s| s.replace(Some(name))),
+        outer: COUNTS.with(|c| c.replace(Counts::default())),
+    }
+}
+
+/// Whether the current thread is inside an audio callback scope. Always
+/// false in release builds.
+pub fn in_audio_callback() -> bool {
+    cfg!(debug_assertions) && SCOPE.with(|s| s.get().is_some())
+}
+
+/// Flags `what`, a call that may block, if made from an audio callback.
+pub fn note_blocking(what: &'static str) {
+    record(|c| {
+        c.blocking += 1;
+        c.blocking_what.get_or_insert(what);
+    });
+}
+
+/// Locks `mutex`, flagging the acquisition if made from an audio callback.
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    note_blocking("mutex lock");
+    mutex.lock().unwrap()
+}
+
+/// Total number of violations reported since the process started.
+pub fn rt_violations() -> usize {
+    VIOLATIONS.load(Ordering::Relaxed)
+}
+
+/// Restores the enclosing scope, if any, when dropped, and reports the
+/// violations seen in this one.
+#[must_use]
+pub struct AudioCallbackScope {
+    name: &'static str,
+    previous: Option<&'static str>,
+    // Counts of the enclosing scope, set aside while this one is active.
+    outer: Counts,
+}
+
+impl AudioCallbackScope {
+    /// Number of violations seen so far in this scope.
+    pub fn violations(&self) -> usize {
+        if !cfg!(debug_assertions) {
+            return 0;
+        }
+        COUNTS.with(|c| c.get().total())
+    }
+}
+
+impl Drop for AudioCallbackScope {
+    fn drop(&mut self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        let counts = COUNTS.with(|c| c.replace(self.outer));
+        if counts.total() == 0 {
+            SCOPE.with(|s| s.set(self.previous));
+            return;
+        }
+        VIOLATIONS.fetch_add(counts.total(), Ordering::Relaxed);
+        // Logging may start the log drain thread, which allocates. That is
+        // not worth reporting.
+        SCOPE.with(|s| s.set(None));
+        cubeb_alog!(
+            "Real-time violation in audio callback `{}`: {} allocations, {} deallocations, {} blocking calls{}{}",
+            self.name,
+            counts.allocations,
+            counts.deallocations,
+            counts.blocking,
+            if counts.blocking_what.is_some() { ", first: " } else { "" },
+            counts.blocking_what.unwrap_or("")
+        );
+        SCOPE.with(|s| s.set(self.previous));
+    }
+}
+
+/// Global allocator flagging heap operations made from audio callbacks,
+/// forwarding them to `A`.
+///
+/// ```
+/// use cubeb_backend::rt_check::RtCheckAllocator;
+/// use std::alloc::System;
+///
+/// #[global_allocator]
+/// static ALLOC: RtCheckAllocator = RtCheckAllocator::new(System);
+/// # fn main() {}
+/// ```
+pub struct RtCheckAllocator<A = System> {
+    inner: A,
+}
+
+impl<A> RtCheckAllocator<A> {
+    pub const fn new(inner: A) -> Self {
+        RtCheckAllocator { inner }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for RtCheckAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(|c| c.allocations += 1);
+        self.inner.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(|c| c.allocations += 1);
+        self.inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record(|c| c.deallocations += 1);
+        self.inner.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(|c| c.allocations += 1);
+        self.inner.realloc(ptr, layout, new_size)
+    }
+}
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOC: RtCheckAllocator = RtCheckAllocator::new(System);
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+    use std::hint::black_box;
+
+    #[test]
+    fn test_allocation_flagged() {
+        let before = rt_violations();
+        {
+            let scope = enter_audio_callback("test");
+            assert!(in_audio_callback());
+            let v = black_box(Vec::<u8>::with_capacity(16));
+            assert_eq!(scope.violations(), 1);
+            drop(v);
+            assert_eq!(scope.violations(), 2);
+        }
+        assert!(!in_audio_callback());
+        assert!(rt_violations() >= before + 2);
+    }
+
+    #[test]
+    fn test_no_violation() {
+        let mut buf = black_box(vec![0f32; 16]);
+        let scope = enter_audio_callback("test");
+        for (i, s) in buf.iter_mut().enumerate() {
+            *s = i as f32;
+        }
+        buf.truncate(8);
+        assert_eq!(scope.violations(), 0);
+    }
+
+    #[test]
+    fn test_lock_flagged() {
+        let mutex = Mutex::new(0);
+        *lock(&mutex) += 1;
+        let scope = enter_audio_callback("test");
+        *lock(&mutex) += 1;
+        assert_eq!(scope.violations(), 1);
+    }
+
+    #[test]
+    fn test_nested_scopes() {
+        let outer = enter_audio_callback("outer");
+        let _ = black_box(Box::new(1));
+        assert_eq!(outer.violations(), 2);
+        {
+            let inner = enter_audio_callback("inner");
+            assert_eq!(inner.violations(), 0);
+            note_blocking("sleep");
+            assert_eq!(inner.violations(), 1);
+        }
+        assert!(in_audio_callback());
+        assert_eq!(outer.violations(), 2);
+    }
+}
@@ -4,25 +4,53 @@
 // accompanying file LICENSE for details.
 
 use cubeb_core::{
-    Context, DeviceCollectionRef, DeviceId, DeviceRef, DeviceType, Result, Stream, StreamParams,
-    StreamParamsRef,
+    Capabilities, Context, DeviceCollectionRef, DeviceId, DeviceRef, DeviceType, Error, Result,
+    Stream, StreamOptionsRef, StreamParams, StreamParamsRef,
 };
 use ffi;
 use std::ffi::CStr;
 use std::os::raw::c_void;
 
+/// Operations of a backend context.
+///
+/// Methods with a default implementation are optional and fail with
+/// `Error::not_supported()`. Backends relying on a default should name the
+/// matching operation in `capi_new!`'s `unsupported` list so that it is
+/// left out of their `Ops`.
 pub trait ContextOps {
     fn init(context_name: Option<&CStr>) -> Result<Context>;
     fn backend_id(&mut self) -> &CStr;
-    fn max_channel_count(&mut self) -> Result<u32>;
-    fn min_latency(&mut self, params: StreamParams) -> Result<u32>;
-    fn preferred_sample_rate(&mut self) -> Result<u32>;
+    fn max_channel_count(&mut self) -> Result<u32> {
+        Err(Error::not_supported())
+    }
+    fn min_latency(&mut self, _params: StreamParams) -> Result<u32> {
+        Err(Error::not_supported())
+    }
+    fn preferred_sample_rate(&mut self) -> Result<u32> {
+        Err(Error::not_supported())
+    }
+    fn capabilities(&mut self) -> Result<Capabilities> {
+        Err(Error::not_supported())
+    }
     fn enumerate_devices(
         &mut self,
-        devtype: DeviceType,
-        collection: &DeviceCollectionRef,
-    ) -> Result<()>;
-    fn device_collection_destroy(&mut self, collection: &mut DeviceCollectionRef) -> Result<()>;
+        _devtype: DeviceType,
+        _collection: &DeviceCollectionRef,
+    ) -> Result<()> {
+        Err(Error::not_supported())
+    }
+    fn device_collection_destroy(&mut self, _collection: &mut DeviceCollectionRef) -> Result<()> {
+        Err(Error::not_supported())
+    }
+    /// Backend specific properties of the device at `index` of a collection
+    /// returned by `enumerate_devices`. They live as long as the collection.
+    fn device_collection_properties(
+        &mut self,
+        _collection: &DeviceCollectionRef,
+        _index: usize,
+    ) -> Result<&[ffi::cubeb_property]> {
+        Err(Error::not_supported())
+    }
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
     fn stream_init(
         &mut self,
@@ -36,26 +64,62 @@ pub trait ContextOps {
         state_callback: ffi::cubeb_state_callback,
         user_ptr: *mut c_void,
     ) -> Result<Stream>;
+    /// Like `stream_init`, for streams given optional parameters.
+    // Takes the arguments of `cubeb_stream_init_with_options`, one for one.
+    #[allow(clippy::too_many_arguments)]
+    fn stream_init_with_options(
+        &mut self,
+        _stream_name: Option<&CStr>,
+        _input_device: DeviceId,
+        _input_stream_params: Option<&StreamParamsRef>,
+        _output_device: DeviceId,
+        _output_stream_params: Option<&StreamParamsRef>,
+        _options: &StreamOptionsRef,
+        _latency_frames: u32,
+        _data_callback: ffi::cubeb_data_callback,
+        _state_callback: ffi::cubeb_state_callback,
+        _user_ptr: *mut c_void,
+    ) -> Result<Stream> {
+        Err(Error::not_supported())
+    }
     fn register_device_collection_changed(
         &mut self,
-        devtype: DeviceType,
-        cb: ffi::cubeb_device_collection_changed_callback,
-        user_ptr: *mut c_void,
-    ) -> Result<()>;
+        _devtype: DeviceType,
+        _cb: ffi::cubeb_device_collection_changed_callback,
+        _user_ptr: *mut c_void,
+    ) -> Result<()> {
+        Err(Error::not_supported())
+    }
 }
 
+/// Operations of a backend stream. Methods with a default implementation
+/// are optional, see `ContextOps`.
 pub trait StreamOps {
     fn start(&mut self) -> Result<()>;
     fn stop(&mut self) -> Result<()>;
     fn position(&mut self) -> Result<u64>;
-    fn latency(&mut self) -> Result<u32>;
-    fn input_latency(&mut self) -> Result<u32>;
-    fn set_volume(&mut self, volume: f32) -> Result<()>;
-    fn set_name(&mut self, name: &CStr) -> Result<()>;
-    fn current_device(&mut self) -> Result<&DeviceRef>;
-    fn device_destroy(&mut self, device: &DeviceRef) -> Result<()>;
+    fn latency(&mut self) -> Result<u32> {
+        Err(Error::not_supported())
+    }
+    fn input_latency(&mut self) -> Result<u32> {
+        Err(Error::not_supported())
+    }
+    fn set_volume(&mut self, _volume: f32) -> Result<()> {
+        Err(Error::not_supported())
+    }
+    fn set_name(&mut self, _name: &CStr) -> Result<()> {
+        Err(Error::not_supported())
+    }
+    fn current_device(&mut self) -> Result<&DeviceRef> {
+        Err(Error::not_supported())
+    }
+    fn device_destroy(&mut self, _device: &DeviceRef) -> Result<()> {
+        Err(Error::not_supported())
+    }
     fn register_device_changed_callback(
         &mut self,
-        device_changed_callback: ffi::cubeb_device_changed_callback,
-    ) -> Result<()>;
+        _device_changed_callback: ffi::cubeb_device_changed_callback,
+    ) -> Result<()> {
+        Err(Error::not_supported())
+    }
 }
@@ -0,0 +1,62 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Stops panics at the boundary with C code, where unwinding is undefined
+//! behaviour.
+
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+thread_local! {
+    // Name of the innermost `catch` running on this thread.
+    static CATCHING: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Runs `f`, returning `None` if it panics instead of unwinding into the
+/// caller. `what` names the function for the message logged by the hook
+/// `install_panic_hook` sets.
+///
+/// The state `f` touched is not expected to be usable after a panic: callers
+/// report an error, or put the stream in the error state.
+pub fn catch<T, F: FnOnce() -> T>(what: &'static str, f: F) -> Option<T> {
+    let previous = CATCHING.with(|c| c.replace(Some(what)));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.with(|c| c.set(previous));
+    result.ok()
+}
+
+/// Installs, once per process, a panic hook logging the panics `catch`
+/// stops through cubeb logging, with their message and location. The hook
+/// previously installed still runs after, for all panics.
+pub fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if let Some(what) = CATCHING.with(|c| c.get()) {
+                let payload = info.payload();
+                let message = if let Some(s) = payload.downcast_ref::<&str>() {
+                    s
+                } else if let Some(s) = payload.downcast_ref::<String>() {
+                    s.as_str()
+                } else {
+                    "Box<dyn Any>"
+                };
+                match info.location() {
+                    Some(l) => cubeb_log!(
+                        "{} panicked at {}:{}: {}",
+                        what,
+                        l.file(),
+                        l.line(),
+                        message
+                    ),
+                    None => cubeb_log!("{} panicked: {}", what, message),
+                }
+            }
+            previous(info);
+        }));
+    });
+}
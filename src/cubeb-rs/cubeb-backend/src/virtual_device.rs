@@ -0,0 +1,996 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! A context mixing its output streams into a single stream of another
+//! context, as a virtual device.
+//!
+//! Applications opening many short output streams, such as sound effects,
+//! get a single stream on the backend, instead of one each with its own
+//! latency and server side cost. Each logical stream keeps the behaviour of
+//! a cubeb stream: its own format, rate, channel layout and mix matrix,
+//! volume, start, stop and drain state, position and latency. Streams the
+//! mix cannot carry, with an input, or asking for a given output device,
+//! are opened on the underlying context instead.
+//!
+//! The physical stream is opened in `Output`'s float format with the first
+//! mixed stream, and runs while any of them is started. Once they all
+//! drained, it plays silence until one of them is started, stopped or
+//! destroyed.
+//!
+//! The callbacks of mixed streams run one after the other on the audio
+//! thread of the physical stream, with a lock held that the operations on
+//! the logical streams take: as for any cubeb stream, they must not call
+//! into the virtual device.
+
+use byte_order;
+use cubeb_core::{
+    ffi, Capabilities, ChannelLayout, Context, ContextRef, DeviceCollectionRef, DeviceId,
+    DeviceRef, DeviceType, Error, Result, SampleFormat, State, Stream, StreamOptions,
+    StreamOptionsRef, StreamParams, StreamParamsBuilder, StreamParamsRef, StreamPrefs,
+};
+use mixer::{self, Mixer};
+use resampler::{Resampler, ResamplerQuality};
+use rt_check::{self, enter_audio_callback};
+use std::ffi::CStr;
+use std::os::raw::{c_int, c_long, c_void};
+use std::sync::Mutex;
+use std::{ptr, slice};
+use {ContextOps, Ops, StreamOps};
+
+pub const VIRTUAL_DEVICE_OPS: Ops = capi_new!(VirtualDevice, VirtualStream);
+
+/// The physical stream of a virtual device.
+#[derive(Clone, Debug)]
+pub struct Output {
+    /// The preferred rate of the underlying context if `None`.
+    pub rate: Option<u32>,
+    pub channels: u32,
+    pub layout: ChannelLayout,
+    /// The minimum latency of the underlying context if `None`.
+    pub latency_frames: Option<u32>,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Output {
+            rate: None,
+            channels: 2,
+            layout: ChannelLayout::STEREO,
+            latency_frames: None,
+        }
+    }
+}
+
+fn check(rv: c_int) -> Result<()> {
+    match rv {
+        ffi::CUBEB_OK => Ok(()),
+        rv => Err(Error::from_raw(rv)),
+    }
+}
+
+// Converts the samples of `format` a data callback wrote at the start of
+// `raw` to `output`.
+fn to_float(format: SampleFormat, raw: &[f32], output: &mut [f32]) {
+    let native = byte_order::is_native(format);
+    match format {
+        SampleFormat::S16LE | SampleFormat::S16BE | SampleFormat::S16NE => {
+            let samples =
+                unsafe { slice::from_raw_parts(raw.as_ptr() as *const i16, raw.len() * 2) };
+            for (o, &s) in output.iter_mut().zip(samples) {
+                let s = if native { s } else { s.swap_bytes() };
+                *o = f32::from(s) / f32::from(i16::MAX);
+            }
+        }
+        SampleFormat::Float32LE | SampleFormat::Float32BE | SampleFormat::Float32NE => {
+            for (o, &s) in output.iter_mut().zip(raw) {
+                *o = if native {
+                    s
+                } else {
+                    f32::from_bits(s.to_bits().swap_bytes())
+                };
+            }
+        }
+    }
+}
+
+// Resamples the frames of a stream at `rate` to those of `output`.
+fn resampler(output: &StreamParams, rate: u32, channels: u32) -> Resampler<f32> {
+    let resampled = StreamParamsBuilder::new()
+        .format(output.format())
+        .rate(output.rate())
+        .channels(channels)
+        .take();
+    Resampler::new(None, Some(&resampled), rate, ResamplerQuality::Default)
+}
+
+// A logical stream, as mixed on the audio thread.
+struct Voice {
+    stream: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    data_callback: ffi::cubeb_data_callback,
+    state_callback: ffi::cubeb_state_callback,
+    device_changed_callback: ffi::cubeb_device_changed_callback,
+    format: SampleFormat,
+    rate: u32,
+    channels: usize,
+    state: State,
+    volume: f32,
+    resampler: Resampler<f32>,
+    // From the channels of the stream to those of the device, unless they
+    // match.
+    mixer: Option<Mixer<f32>>,
+    // Frames as written by the data callback, aligned for any format.
+    raw: Vec<f32>,
+    resampled: Vec<f32>,
+    mixed: Vec<f32>,
+    // Frames written by the data callback, and mixed at the device rate.
+    rendered: u64,
+    played: u64,
+}
+
+impl Voice {
+    fn notify(&self, state: State) {
+        if let Some(callback) = self.state_callback {
+            unsafe { callback(self.stream, self.user_ptr, state.into()) };
+        }
+    }
+
+    // Adds the next frames of the stream to `output`, at the device rate.
+    fn render(&mut self, output: &mut [f32], device_channels: usize) {
+        let frames = output.len() / device_channels;
+        let channels = self.channels;
+        self.resampled.resize(frames * channels, 0.0);
+        self.mixed.resize(frames * device_channels, 0.0);
+
+        let (stream, user_ptr, callback) = (self.stream, self.user_ptr, self.data_callback);
+        let format = self.format;
+        let raw = &mut self.raw;
+        let rendered = &mut self.rendered;
+        let mut used = 0;
+        let got = self.resampler.fill(
+            None,
+            &mut used,
+            Some(&mut self.resampled),
+            |_, output, frames| {
+                let output = output.unwrap();
+                if raw.len() < output.len() {
+                    raw.resize(output.len(), 0.0);
+                }
+                let got = unsafe {
+                    callback.unwrap()(
+                        stream,
+                        user_ptr,
+                        ptr::null(),
+                        raw.as_mut_ptr() as *mut c_void,
+                        frames as c_long,
+                    )
+                };
+                let written = got.clamp(0, frames as c_long) as usize;
+                to_float(format, raw, &mut output[..written * channels]);
+                *rendered += written as u64;
+                got as isize
+            },
+        );
+        if got < 0 {
+            self.state = State::Error;
+            self.notify(State::Error);
+            return;
+        }
+
+        let got = got as usize;
+        let source = &self.resampled[..got * channels];
+        let mixed = match &self.mixer {
+            Some(mixer) => {
+                let mixed = &mut self.mixed[..got * device_channels];
+                mixer.mix(source, mixed);
+                &*mixed
+            }
+            None => source,
+        };
+        for (o, s) in output.iter_mut().zip(mixed) {
+            *o += s * self.volume;
+        }
+        self.played += got as u64;
+        if got < frames {
+            self.state = State::Drained;
+            self.notify(State::Drained);
+        }
+    }
+
+    // Frames played, not ahead of those the data callback wrote.
+    fn position(&self, device_rate: u32) -> u64 {
+        let played = self.played * u64::from(self.rate) / u64::from(device_rate);
+        played
+            .saturating_sub(self.resampler.latency() as u64)
+            .min(self.rendered)
+    }
+}
+
+struct Mix {
+    channels: usize,
+    voices: Vec<Voice>,
+}
+
+impl Mix {
+    fn voice(&mut self, stream: *mut ffi::cubeb_stream) -> &mut Voice {
+        self.voices.iter_mut().find(|v| v.stream == stream).unwrap()
+    }
+
+    fn render(&mut self, output: &mut [f32]) {
+        for s in output.iter_mut() {
+            *s = 0.0;
+        }
+        let channels = self.channels;
+        for voice in &mut self.voices {
+            if voice.state == State::Started {
+                voice.render(output, channels);
+            }
+        }
+    }
+}
+
+struct Physical {
+    stream: Option<Stream>,
+    running: bool,
+    // Whether the stream reports device changes.
+    device_changed: bool,
+}
+
+// State shared by the context, its streams and the audio thread. The audio
+// thread only takes `mix`, which is taken last.
+struct Shared {
+    mix: Mutex<Mix>,
+    physical: Mutex<Physical>,
+}
+
+impl Shared {
+    // Runs the physical stream while a mixed stream is started.
+    fn update_physical(&self) -> Result<()> {
+        let mut physical = self.physical.lock().unwrap();
+        let started = rt_check::lock(&self.mix)
+            .voices
+            .iter()
+            .any(|v| v.state == State::Started);
+        if started == physical.running {
+            return Ok(());
+        }
+        let stream = physical.stream.as_ref().unwrap();
+        if started {
+            stream.start()?;
+        } else {
+            stream.stop()?;
+        }
+        physical.running = started;
+        Ok(())
+    }
+}
+
+unsafe extern "C" fn physical_data_callback(
+    _stream: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    _input: *const c_void,
+    output: *mut c_void,
+    frames: c_long,
+) -> c_long {
+    let _rt = enter_audio_callback("virtual device data callback");
+    let shared = &*(user_ptr as *const Shared);
+    // Operations on the logical streams hold the lock briefly, without
+    // allocating or calling into the backend. Debug builds still report
+    // taking it.
+    let mut mix = rt_check::lock(&shared.mix);
+    let samples = frames as usize * mix.channels;
+    mix.render(slice::from_raw_parts_mut(output as *mut f32, samples));
+    frames
+}
+
+unsafe extern "C" fn physical_state_callback(
+    _stream: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    state: ffi::cubeb_state,
+) {
+    if state != ffi::CUBEB_STATE_ERROR {
+        return;
+    }
+    let shared = &*(user_ptr as *const Shared);
+    let mut mix = rt_check::lock(&shared.mix);
+    for voice in &mut mix.voices {
+        if voice.state == State::Started {
+            voice.state = State::Error;
+            voice.notify(State::Error);
+        }
+    }
+}
+
+unsafe extern "C" fn physical_device_changed_callback(user_ptr: *mut c_void) {
+    let shared = &*(user_ptr as *const Shared);
+    let mix = rt_check::lock(&shared.mix);
+    for voice in &mix.voices {
+        if let Some(callback) = voice.device_changed_callback {
+            callback(voice.user_ptr);
+        }
+    }
+}
+
+/// A context mixing its output streams into one stream of an underlying
+/// context.
+#[repr(C)]
+pub struct VirtualDevice {
+    _ops: *const Ops,
+    inner: Context,
+    // Float frames in the native byte order.
+    output: StreamParams,
+    latency_frames: u32,
+    shared: Box<Shared>,
+}
+
+impl VirtualDevice {
+    /// Creates a virtual device mixing into an `output` stream of `inner`.
+    pub fn init_with_context(inner: Context, output: Output) -> Result<Context> {
+        let rate = match output.rate {
+            Some(rate) => rate,
+            None => inner.preferred_sample_rate()?,
+        };
+        let params = StreamParamsBuilder::new()
+            .format(byte_order::native(SampleFormat::Float32NE))
+            .rate(rate)
+            .channels(output.channels)
+            .layout(output.layout)
+            .take();
+        let latency_frames = match output.latency_frames {
+            Some(latency) => latency,
+            None => inner.min_latency(&params)?,
+        };
+        let ctx = Box::new(VirtualDevice {
+            _ops: &VIRTUAL_DEVICE_OPS as *const _,
+            inner,
+            output: params,
+            latency_frames,
+            shared: Box::new(Shared {
+                mix: Mutex::new(Mix {
+                    channels: output.channels as usize,
+                    voices: Vec::new(),
+                }),
+                physical: Mutex::new(Physical {
+                    stream: None,
+                    running: false,
+                    device_changed: false,
+                }),
+            }),
+        });
+        Ok(unsafe { Context::from_ptr(Box::into_raw(ctx) as *mut _) })
+    }
+
+    fn open_physical(&mut self) -> Result<()> {
+        let mut physical = self.shared.physical.lock().unwrap();
+        if physical.stream.is_some() {
+            return Ok(());
+        }
+        let name = CStr::from_bytes_with_nul(b"cubeb virtual device\0").unwrap();
+        let stream = unsafe {
+            self.inner.stream_init(
+                Some(name),
+                ptr::null(),
+                None,
+                ptr::null(),
+                Some(&self.output),
+                self.latency_frames,
+                Some(physical_data_callback),
+                Some(physical_state_callback),
+                &*self.shared as *const Shared as *mut c_void,
+            )?
+        };
+        physical.device_changed = stream
+            .register_device_changed_callback(Some(physical_device_changed_callback))
+            .is_ok();
+        physical.stream = Some(stream);
+        Ok(())
+    }
+
+    fn voice(
+        &self,
+        params: &StreamParamsRef,
+        options: &StreamOptionsRef,
+        stream: *mut VirtualStream,
+        user_ptr: *mut c_void,
+        data_callback: ffi::cubeb_data_callback,
+        state_callback: ffi::cubeb_state_callback,
+    ) -> Voice {
+        let channels = params.channels() as usize;
+        let device_channels = self.output.channels() as usize;
+        let matrix = options.mix_matrix();
+        let mixer = if channels == device_channels
+            && matrix.is_none()
+            && (params.layout() == self.output.layout()
+                || params.layout() == ChannelLayout::UNDEFINED)
+        {
+            None
+        } else {
+            Some(Mixer::with_matrix(
+                channels,
+                params.layout(),
+                device_channels,
+                mixer::channel_order(self.output.layout()),
+                &matrix.unwrap_or_default(),
+            ))
+        };
+        let frames = 2 * self.latency_frames as usize;
+        Voice {
+            stream: stream as *mut _,
+            user_ptr,
+            data_callback,
+            state_callback,
+            device_changed_callback: None,
+            format: params.format(),
+            rate: params.rate(),
+            channels,
+            state: State::Stopped,
+            volume: 1.0,
+            resampler: resampler(&self.output, params.rate(), params.channels()),
+            mixer,
+            raw: Vec::with_capacity(frames * channels),
+            resampled: Vec::with_capacity(frames * channels),
+            mixed: Vec::with_capacity(frames * device_channels),
+            rendered: 0,
+            played: 0,
+        }
+    }
+}
+
+/// The context `context`, a virtual device, mixes into.
+pub fn inner(context: &ContextRef) -> &ContextRef {
+    let ctx = unsafe { &*(context.as_ptr() as *const VirtualDevice) };
+    assert!(
+        ptr::eq(ctx._ops, &VIRTUAL_DEVICE_OPS),
+        "inner called on a context that is not a virtual device"
+    );
+    &ctx.inner
+}
+
+impl Drop for VirtualDevice {
+    fn drop(&mut self) {
+        // Before the underlying context.
+        self.shared.physical.lock().unwrap().stream.take();
+    }
+}
+
+impl ContextOps for VirtualDevice {
+    fn init(context_name: Option<&CStr>) -> Result<Context> {
+        let inner = Context::init(context_name, None)?;
+        Self::init_with_context(inner, Output::default())
+    }
+
+    fn backend_id(&mut self) -> &'static CStr {
+        unsafe { CStr::from_ptr(b"virtual-device\0".as_ptr() as *const _) }
+    }
+    fn max_channel_count(&mut self) -> Result<u32> {
+        self.inner.max_channel_count()
+    }
+    fn min_latency(&mut self, params: StreamParams) -> Result<u32> {
+        self.inner.min_latency(&params)
+    }
+    fn preferred_sample_rate(&mut self) -> Result<u32> {
+        // Mixed without resampling.
+        Ok(self.output.rate())
+    }
+    fn capabilities(&mut self) -> Result<Capabilities> {
+        Ok(self.inner.capabilities()?
+            | Capabilities::STREAM_VOLUME
+            | Capabilities::FORMAT_S16LE
+            | Capabilities::FORMAT_S16BE
+            | Capabilities::FORMAT_FLOAT32LE
+            | Capabilities::FORMAT_FLOAT32BE)
+    }
+    fn enumerate_devices(
+        &mut self,
+        devtype: DeviceType,
+        collection: &DeviceCollectionRef,
+    ) -> Result<()> {
+        check(unsafe {
+            ffi::cubeb_enumerate_devices(self.inner.as_ptr(), devtype.bits(), collection.as_ptr())
+        })
+    }
+    fn device_collection_destroy(&mut self, collection: &mut DeviceCollectionRef) -> Result<()> {
+        check(unsafe {
+            ffi::cubeb_device_collection_destroy(self.inner.as_ptr(), collection.as_ptr())
+        })
+    }
+    fn device_collection_properties(
+        &mut self,
+        collection: &DeviceCollectionRef,
+        index: usize,
+    ) -> Result<&[ffi::cubeb_property]> {
+        let mut props = ptr::null();
+        let mut count = 0;
+        check(unsafe {
+            ffi::cubeb_device_collection_get_properties(
+                self.inner.as_ptr(),
+                collection.as_ptr(),
+                index,
+                &mut props,
+                &mut count,
+            )
+        })?;
+        if props.is_null() {
+            return Ok(&[]);
+        }
+        Ok(unsafe { slice::from_raw_parts(props, count) })
+    }
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn stream_init(
+        &mut self,
+        stream_name: Option<&CStr>,
+        input_device: DeviceId,
+        input_stream_params: Option<&StreamParamsRef>,
+        output_device: DeviceId,
+        output_stream_params: Option<&StreamParamsRef>,
+        latency_frames: u32,
+        data_callback: ffi::cubeb_data_callback,
+        state_callback: ffi::cubeb_state_callback,
+        user_ptr: *mut c_void,
+    ) -> Result<Stream> {
+        self.stream_init_with_options(
+            stream_name,
+            input_device,
+            input_stream_params,
+            output_device,
+            output_stream_params,
+            &StreamOptions::default(),
+            latency_frames,
+            data_callback,
+            state_callback,
+            user_ptr,
+        )
+    }
+    // The devices, options and callbacks are passed on to the underlying
+    // context. Mixed streams share the physical stream, whose properties
+    // are the context's own, and only take the mix matrix.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn stream_init_with_options(
+        &mut self,
+        stream_name: Option<&CStr>,
+        input_device: DeviceId,
+        input_stream_params: Option<&StreamParamsRef>,
+        output_device: DeviceId,
+        output_stream_params: Option<&StreamParamsRef>,
+        options: &StreamOptionsRef,
+        latency_frames: u32,
+        data_callback: ffi::cubeb_data_callback,
+        state_callback: ffi::cubeb_state_callback,
+        user_ptr: *mut c_void,
+    ) -> Result<Stream> {
+        let mut stm = Box::new(VirtualStream {
+            context: self,
+            user_ptr,
+            data_callback,
+            state_callback,
+            device_changed_callback: None,
+            kind: Kind::Mixed,
+        });
+        let stm_ptr = &mut *stm as *mut VirtualStream;
+        let params = match output_stream_params {
+            Some(params)
+                if input_stream_params.is_none()
+                    && output_device.is_null()
+                    && !params.prefs().contains(StreamPrefs::LOOPBACK) =>
+            {
+                params
+            }
+            _ => {
+                // Opened on the underlying context, with the callbacks of
+                // the stream called with this one.
+                let stream = unsafe {
+                    self.inner.stream_init_with_options(
+                        stream_name,
+                        input_device,
+                        input_stream_params,
+                        output_device,
+                        output_stream_params,
+                        options,
+                        latency_frames,
+                        Some(direct_data_callback),
+                        Some(direct_state_callback),
+                        stm_ptr as *mut c_void,
+                    )?
+                };
+                stm.kind = Kind::Direct(stream);
+                return Ok(unsafe { Stream::from_ptr(Box::into_raw(stm) as *mut _) });
+            }
+        };
+
+        self.open_physical()?;
+        let voice = self.voice(
+            params,
+            options,
+            stm_ptr,
+            user_ptr,
+            data_callback,
+            state_callback,
+        );
+        rt_check::lock(&self.shared.mix).voices.push(voice);
+        Ok(unsafe { Stream::from_ptr(Box::into_raw(stm) as *mut _) })
+    }
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn register_device_collection_changed(
+        &mut self,
+        devtype: DeviceType,
+        cb: ffi::cubeb_device_collection_changed_callback,
+        user_ptr: *mut c_void,
+    ) -> Result<()> {
+        unsafe {
+            self.inner
+                .register_device_collection_changed(devtype, cb, user_ptr)
+        }
+    }
+}
+
+unsafe extern "C" fn direct_data_callback(
+    _stream: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    input: *const c_void,
+    output: *mut c_void,
+    frames: c_long,
+) -> c_long {
+    let stm = &*(user_ptr as *const VirtualStream);
+    stm.data_callback.unwrap()(user_ptr as *mut _, stm.user_ptr, input, output, frames)
+}
+
+unsafe extern "C" fn direct_state_callback(
+    _stream: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    state: ffi::cubeb_state,
+) {
+    let stm = &*(user_ptr as *const VirtualStream);
+    stm.state_callback.unwrap()(user_ptr as *mut _, stm.user_ptr, state)
+}
+
+unsafe extern "C" fn direct_device_changed_callback(user_ptr: *mut c_void) {
+    let stm = &*(user_ptr as *const VirtualStream);
+    if let Some(callback) = stm.device_changed_callback {
+        callback(stm.user_ptr);
+    }
+}
+
+enum Kind {
+    // Mixed, as the voice with the pointer of the stream.
+    Mixed,
+    // Opened on the underlying context.
+    Direct(Stream),
+}
+
+// Keeps the layout `cubeb.c` expects of a `cubeb_stream`.
+#[repr(C)]
+pub struct VirtualStream {
+    context: *mut VirtualDevice,
+    user_ptr: *mut c_void,
+    data_callback: ffi::cubeb_data_callback,
+    state_callback: ffi::cubeb_state_callback,
+    device_changed_callback: ffi::cubeb_device_changed_callback,
+    kind: Kind,
+}
+
+impl VirtualStream {
+    fn shared(&self) -> &Shared {
+        unsafe { &(*self.context).shared }
+    }
+
+    fn output(&self) -> &StreamParams {
+        unsafe { &(*self.context).output }
+    }
+
+    // Runs `f` on the voice of a mixed stream.
+    fn with_voice<R, F: FnOnce(&mut Voice) -> R>(&self, f: F) -> R {
+        let stream = self as *const _ as *mut ffi::cubeb_stream;
+        f(rt_check::lock(&self.shared().mix).voice(stream))
+    }
+
+    // The physical stream, opened with the first mixed stream.
+    fn physical_ptr(&self) -> *mut ffi::cubeb_stream {
+        let physical = self.shared().physical.lock().unwrap();
+        physical.stream.as_ref().unwrap().as_ptr()
+    }
+}
+
+impl Drop for VirtualStream {
+    fn drop(&mut self) {
+        if let Kind::Mixed = self.kind {
+            let stream = self as *mut _ as *mut ffi::cubeb_stream;
+            let shared = self.shared();
+            // None if the stream failed to open.
+            let voice = {
+                let mut mix = rt_check::lock(&shared.mix);
+                match mix.voices.iter().position(|v| v.stream == stream) {
+                    Some(i) => mix.voices.swap_remove(i),
+                    None => return,
+                }
+            };
+            // Freed without the lock held.
+            drop(voice);
+            let _ = shared.update_physical();
+        }
+    }
+}
+
+impl StreamOps for VirtualStream {
+    fn start(&mut self) -> Result<()> {
+        if let Kind::Direct(stream) = &self.kind {
+            return stream.start();
+        }
+        let state = self.with_voice(|v| v.state);
+        if state == State::Started {
+            return Ok(());
+        }
+        // Drained and failed streams start over.
+        let resampler = if state == State::Drained || state == State::Error {
+            let (rate, channels) = self.with_voice(|v| (v.rate, v.channels as u32));
+            Some(resampler(self.output(), rate, channels))
+        } else {
+            None
+        };
+        self.with_voice(|v| {
+            if let Some(resampler) = resampler {
+                v.resampler = resampler;
+            }
+            v.notify(State::Started);
+            v.state = State::Started;
+        });
+        self.shared().update_physical()
+    }
+    fn stop(&mut self) -> Result<()> {
+        if let Kind::Direct(stream) = &self.kind {
+            return stream.stop();
+        }
+        self.with_voice(|v| {
+            if v.state == State::Started {
+                v.state = State::Stopped;
+                v.notify(State::Stopped);
+            }
+        });
+        self.shared().update_physical()
+    }
+    fn position(&mut self) -> Result<u64> {
+        match &self.kind {
+            Kind::Mixed => {
+                let rate = self.output().rate();
+                Ok(self.with_voice(|v| v.position(rate)))
+            }
+            Kind::Direct(stream) => stream.position(),
+        }
+    }
+    fn latency(&mut self) -> Result<u32> {
+        if let Kind::Direct(stream) = &self.kind {
+            return stream.latency();
+        }
+        let mut latency = 0;
+        check(unsafe { ffi::cubeb_stream_get_latency(self.physical_ptr(), &mut latency) })?;
+        let device_rate = u64::from(self.output().rate());
+        Ok(self.with_voice(|v| {
+            let latency = u64::from(latency) * u64::from(v.rate) / device_rate;
+            latency as u32 + v.resampler.latency() as u32
+        }))
+    }
+    fn input_latency(&mut self) -> Result<u32> {
+        match &self.kind {
+            Kind::Mixed => Err(Error::error()),
+            Kind::Direct(stream) => stream.input_latency(),
+        }
+    }
+    fn set_volume(&mut self, volume: f32) -> Result<()> {
+        match &self.kind {
+            Kind::Mixed => {
+                self.with_voice(|v| v.volume = volume);
+                Ok(())
+            }
+            Kind::Direct(stream) => stream.set_volume(volume),
+        }
+    }
+    fn set_name(&mut self, name: &CStr) -> Result<()> {
+        match &self.kind {
+            Kind::Mixed => Err(Error::not_supported()),
+            Kind::Direct(stream) => stream.set_name(name),
+        }
+    }
+    fn current_device(&mut self) -> Result<&DeviceRef> {
+        let stream = match &self.kind {
+            Kind::Mixed => self.physical_ptr(),
+            Kind::Direct(stream) => stream.as_ptr(),
+        };
+        let mut device = ptr::null_mut();
+        check(unsafe { ffi::cubeb_stream_get_current_device(stream, &mut device) })?;
+        Ok(unsafe { DeviceRef::from_ptr(device) })
+    }
+    fn device_destroy(&mut self, device: &DeviceRef) -> Result<()> {
+        let stream = match &self.kind {
+            Kind::Mixed => self.physical_ptr(),
+            Kind::Direct(stream) => stream.as_ptr(),
+        };
+        check(unsafe { ffi::cubeb_stream_device_destroy(stream, device.as_ptr()) })
+    }
+    fn register_device_changed_callback(
+        &mut self,
+        device_changed_callback: ffi::cubeb_device_changed_callback,
+    ) -> Result<()> {
+        match &self.kind {
+            Kind::Mixed => {
+                if !self.shared().physical.lock().unwrap().device_changed {
+                    return Err(Error::not_supported());
+                }
+                self.with_voice(|v| v.device_changed_callback = device_changed_callback);
+                Ok(())
+            }
+            Kind::Direct(stream) => {
+                stream.register_device_changed_callback(
+                    device_changed_callback.and(Some(direct_device_changed_callback)),
+                )?;
+                self.device_changed_callback = device_changed_callback;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::{MockContext, Script};
+
+    #[test]
+    fn test_to_float() {
+        let mut raw = [0f32; 2];
+        let samples = unsafe { slice::from_raw_parts_mut(raw.as_mut_ptr() as *mut i16, 4) };
+        samples.copy_from_slice(&[i16::MAX, -16384, 0x0100, 0]);
+        let mut output = [0f32; 3];
+        to_float(SampleFormat::S16NE, &raw, &mut output);
+        assert_eq!(output[0], 1.0);
+        assert!((output[1] + 0.5).abs() < 1e-4);
+        let swapped = byte_order::native(SampleFormat::S16NE) != SampleFormat::S16LE;
+        let other = if swapped {
+            SampleFormat::S16LE
+        } else {
+            SampleFormat::S16BE
+        };
+        to_float(other, &raw, &mut output);
+        assert_eq!(output[2], 1.0 / f32::from(i16::MAX));
+
+        let raw = [f32::from_bits(0.5f32.to_bits().swap_bytes())];
+        let mut output = [0f32];
+        let other = if swapped {
+            SampleFormat::Float32LE
+        } else {
+            SampleFormat::Float32BE
+        };
+        to_float(other, &raw, &mut output);
+        assert_eq!(output, [0.5]);
+    }
+
+    // Writes `value` to every sample, in `format`.
+    struct Source {
+        format: SampleFormat,
+        channels: usize,
+        value: f32,
+    }
+
+    unsafe extern "C" fn data_cb(
+        _: *mut ffi::cubeb_stream,
+        user_ptr: *mut c_void,
+        _: *const c_void,
+        output: *mut c_void,
+        nframes: c_long,
+    ) -> c_long {
+        let source = &*(user_ptr as *const Source);
+        let samples = nframes as usize * source.channels;
+        match source.format {
+            SampleFormat::S16NE => {
+                let output = slice::from_raw_parts_mut(output as *mut i16, samples);
+                for s in output {
+                    *s = (source.value * f32::from(i16::MAX)) as i16;
+                }
+            }
+            _ => {
+                let output = slice::from_raw_parts_mut(output as *mut u32, samples);
+                for s in output {
+                    *s = source.value.to_bits().swap_bytes();
+                }
+            }
+        }
+        nframes
+    }
+
+    unsafe extern "C" fn state_cb(_: *mut ffi::cubeb_stream, _: *mut c_void, _: ffi::cubeb_state) {}
+
+    fn stream_init(context: &Context, source: &Source, rate: u32, layout: ChannelLayout) -> Stream {
+        let params = StreamParamsBuilder::new()
+            .format(source.format)
+            .rate(rate)
+            .channels(source.channels as u32)
+            .layout(layout)
+            .take();
+        unsafe {
+            context.stream_init(
+                None,
+                ptr::null(),
+                None,
+                ptr::null(),
+                Some(&params),
+                4096,
+                Some(data_cb),
+                Some(state_cb),
+                source as *const _ as *mut c_void,
+            )
+        }
+        .unwrap()
+    }
+
+    // Renders `frames` stereo frames of the mix of `context`.
+    fn render(context: &Context, frames: usize) -> Vec<f32> {
+        let ctx = unsafe { &*(context.as_ptr() as *const VirtualDevice) };
+        let mut output = vec![1.0; frames * 2];
+        ctx.shared.mix.lock().unwrap().render(&mut output);
+        output
+    }
+
+    #[test]
+    fn test_mix_streams() {
+        let inner = MockContext::init_with_script(Script::default()).unwrap();
+        let context = VirtualDevice::init_with_context(inner, Output::default()).unwrap();
+        let a = Source {
+            format: SampleFormat::S16NE,
+            channels: 2,
+            value: 0.25,
+        };
+        let other_float = if byte_order::native(SampleFormat::Float32NE) == SampleFormat::Float32LE
+        {
+            SampleFormat::Float32BE
+        } else {
+            SampleFormat::Float32LE
+        };
+        let b = Source {
+            format: other_float,
+            channels: 2,
+            value: 0.5,
+        };
+        let stream_a = stream_init(&context, &a, 48000, ChannelLayout::STEREO);
+        let stream_b = stream_init(&context, &b, 48000, ChannelLayout::STEREO);
+        assert!(render(&context, 16).iter().all(|&s| s == 0.0));
+
+        stream_a.start().unwrap();
+        stream_b.start().unwrap();
+        stream_b.set_volume(0.5).unwrap();
+        for s in render(&context, 16) {
+            assert!((s - 0.5).abs() < 1e-4, "{}", s);
+        }
+        stream_a.stop().unwrap();
+        for s in render(&context, 16) {
+            assert!((s - 0.25).abs() < 1e-4, "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_mix_converts_rate_and_channels() {
+        let inner = MockContext::init_with_script(Script::default()).unwrap();
+        let context = VirtualDevice::init_with_context(inner, Output::default()).unwrap();
+        let source = Source {
+            format: SampleFormat::S16NE,
+            channels: 1,
+            value: 0.5,
+        };
+        let stream = stream_init(&context, &source, 44100, ChannelLayout::MONO);
+        stream.start().unwrap();
+        let mut output = Vec::new();
+        for _ in 0..20 {
+            output.extend(render(&context, 480));
+        }
+        // Past the filters filling up, the level is kept on both channels.
+        let tail = &output[output.len() - 960..];
+        let (left, right) = (tail[0], tail[1]);
+        assert_eq!(left, right);
+        assert!(left > 0.3 && left < 0.6, "{}", left);
+        for frame in tail.chunks(2) {
+            assert!((frame[0] - left).abs() < 1e-2, "{:?}", frame);
+        }
+        // 9600 frames at 48kHz are 8820 at 44.1kHz, less the latency.
+        let position = stream.position().unwrap();
+        assert!(position <= 8820 && position > 8400, "{}", position);
+    }
+}
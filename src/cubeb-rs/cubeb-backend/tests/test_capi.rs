@@ -9,8 +9,8 @@
 extern crate cubeb_backend;
 
 use cubeb_backend::{
-    ffi, Context, ContextOps, DeviceCollectionRef, DeviceId, DeviceRef, DeviceType, Ops, Result,
-    Stream, StreamOps, StreamParams, StreamParamsRef,
+    ffi, Capabilities, Context, ContextOps, DeviceCollectionRef, DeviceId, DeviceRef, DeviceType,
+    Ops, Result, Stream, StreamOps, StreamParams, StreamParamsRef,
 };
 use std::ffi::CStr;
 use std::os::raw::c_void;
@@ -43,6 +43,9 @@ impl ContextOps for TestContext {
     fn preferred_sample_rate(&mut self) -> Result<u32> {
         Ok(0u32)
     }
+    fn capabilities(&mut self) -> Result<Capabilities> {
+        Ok(Capabilities::LOOPBACK | Capabilities::FORMAT_FLOAT32LE)
+    }
     fn enumerate_devices(
         &mut self,
         _devtype: DeviceType,
@@ -61,6 +64,16 @@ impl ContextOps for TestContext {
         coll.count = 0;
         Ok(())
     }
+    fn device_collection_properties(
+        &mut self,
+        collection: &DeviceCollectionRef,
+        index: usize,
+    ) -> Result<&[ffi::cubeb_property]> {
+        let coll = unsafe { &*collection.as_ptr() };
+        assert_eq!(coll.device, 0xDEAD_BEEF as *mut _);
+        assert_eq!(index, 1);
+        Ok(&[])
+    }
     fn stream_init(
         &mut self,
         _stream_name: Option<&CStr>,
@@ -170,6 +183,20 @@ fn test_ops_context_preferred_sample_rate() {
     assert_eq!(rate, 0);
 }
 
+#[test]
+fn test_ops_context_capabilities() {
+    let c: *mut ffi::cubeb = ptr::null_mut();
+    let mut capabilities = ffi::CUBEB_CAPABILITY_NONE;
+    assert_eq!(
+        unsafe { OPS.get_capabilities.unwrap()(c, &mut capabilities) },
+        ffi::CUBEB_OK
+    );
+    assert_eq!(
+        capabilities,
+        ffi::CUBEB_CAPABILITY_LOOPBACK | ffi::CUBEB_CAPABILITY_FORMAT_FLOAT32LE
+    );
+}
+
 #[test]
 fn test_ops_context_enumerate_devices() {
     let c: *mut ffi::cubeb = ptr::null_mut();
@@ -200,6 +227,25 @@ fn test_ops_context_device_collection_destroy() {
     assert_eq!(coll.count, 0);
 }
 
+#[test]
+fn test_ops_context_device_collection_get_properties() {
+    let c: *mut ffi::cubeb = ptr::null_mut();
+    let mut coll = ffi::cubeb_device_collection {
+        device: 0xDEAD_BEEF as *mut _,
+        count: 2,
+    };
+    let mut props = 0xDEAD_BEEF as *const _;
+    let mut count = usize::MAX;
+    assert_eq!(
+        unsafe {
+            OPS.device_collection_get_properties.unwrap()(c, &mut coll, 1, &mut props, &mut count)
+        },
+        ffi::CUBEB_OK
+    );
+    assert!(props.is_null());
+    assert_eq!(count, 0);
+}
+
 // stream_init: Some($crate::capi::capi_stream_init::<$ctx>),
 // stream_destroy: Some($crate::capi::capi_stream_destroy::<$stm>),
 // stream_start: Some($crate::capi::capi_stream_start::<$stm>),
@@ -251,3 +297,137 @@ fn test_ops_stream_device_destroy() {
         OPS.stream_device_destroy.unwrap()(s, 0xDEAD_BEEF as *mut _);
     }
 }
+
+// Backend implementing only the operations `cubeb_init` requires.
+pub const MINIMAL_OPS: Ops = capi_new!(
+    MinimalContext,
+    MinimalStream,
+    unsupported: [
+        get_max_channel_count,
+        stream_set_name,
+        stream_register_device_changed_callback,
+    ]
+);
+
+struct MinimalContext {}
+
+impl ContextOps for MinimalContext {
+    fn init(_context_name: Option<&CStr>) -> Result<Context> {
+        unimplemented!()
+    }
+    fn backend_id(&mut self) -> &'static CStr {
+        unsafe { CStr::from_ptr(b"minimal\0".as_ptr() as *const _) }
+    }
+    fn stream_init(
+        &mut self,
+        _stream_name: Option<&CStr>,
+        _input_device: DeviceId,
+        _input_stream_params: Option<&StreamParamsRef>,
+        _output_device: DeviceId,
+        _output_stream_params: Option<&StreamParamsRef>,
+        _latency_frame: u32,
+        _data_callback: ffi::cubeb_data_callback,
+        _state_callback: ffi::cubeb_state_callback,
+        _user_ptr: *mut c_void,
+    ) -> Result<Stream> {
+        unimplemented!()
+    }
+}
+
+struct MinimalStream {}
+
+impl StreamOps for MinimalStream {
+    fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn stop(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn position(&mut self) -> Result<u64> {
+        Ok(0u64)
+    }
+}
+
+#[test]
+fn test_ops_unsupported_are_none() {
+    assert!(MINIMAL_OPS.get_max_channel_count.is_none());
+    assert!(MINIMAL_OPS.stream_set_name.is_none());
+    assert!(MINIMAL_OPS
+        .stream_register_device_changed_callback
+        .is_none());
+    assert!(MINIMAL_OPS.stream_start.is_some());
+    assert!(MINIMAL_OPS.get_min_latency.is_some());
+}
+
+#[test]
+fn test_ops_default_not_supported() {
+    let mut c = MinimalContext {};
+    let s: *mut ffi::cubeb_stream = &mut MinimalStream {} as *mut _ as *mut _;
+    let mut latency = u32::MAX;
+    assert_eq!(
+        unsafe { MINIMAL_OPS.stream_get_latency.unwrap()(s, &mut latency) },
+        ffi::CUBEB_ERROR_NOT_SUPPORTED
+    );
+    assert_eq!(latency, u32::MAX);
+    let mut rate = u32::MAX;
+    assert_eq!(
+        unsafe {
+            MINIMAL_OPS.get_preferred_sample_rate.unwrap()(&mut c as *mut _ as *mut _, &mut rate)
+        },
+        ffi::CUBEB_ERROR_NOT_SUPPORTED
+    );
+    let options = ffi::cubeb_stream_options::default();
+    let mut stm: *mut ffi::cubeb_stream = ptr::null_mut();
+    assert_eq!(
+        unsafe {
+            MINIMAL_OPS.stream_init_with_options.unwrap()(
+                &mut c as *mut _ as *mut _,
+                &mut stm,
+                ptr::null(),
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null(),
+                ptr::null_mut(),
+                &options,
+                0,
+                None,
+                None,
+                ptr::null_mut(),
+            )
+        },
+        ffi::CUBEB_ERROR_NOT_SUPPORTED
+    );
+    assert!(stm.is_null());
+}
+
+#[test]
+fn test_ops_panic_is_error() {
+    // `MinimalContext::init` and `stream_init` panic.
+    let mut c: *mut ffi::cubeb = ptr::null_mut();
+    assert_eq!(
+        unsafe { MINIMAL_OPS.init.unwrap()(&mut c, ptr::null()) },
+        ffi::CUBEB_ERROR
+    );
+    assert!(c.is_null());
+    let mut ctx = MinimalContext {};
+    let mut s: *mut ffi::cubeb_stream = ptr::null_mut();
+    assert_eq!(
+        unsafe {
+            MINIMAL_OPS.stream_init.unwrap()(
+                &mut ctx as *mut _ as *mut _,
+                &mut s,
+                ptr::null(),
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null(),
+                ptr::null_mut(),
+                0,
+                None,
+                None,
+                ptr::null_mut(),
+            )
+        },
+        ffi::CUBEB_ERROR
+    );
+    assert!(s.is_null());
+}
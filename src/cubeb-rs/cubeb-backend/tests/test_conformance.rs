@@ -0,0 +1,125 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details
+
+extern crate cubeb_backend;
+
+use cubeb_backend::conformance::{self, Config};
+use cubeb_backend::mock::{self, MockContext, Script};
+use cubeb_backend::{
+    ffi, Capabilities, ChannelLayout, Context, SampleFormat, State, StreamParamsBuilder,
+};
+use std::os::raw::{c_long, c_void};
+use std::ptr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+fn mock_config() -> Config {
+    Config {
+        duplex: true,
+        wait: mock::advance,
+        timeout: Duration::from_secs(1),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn test_mock_conformance() {
+    if let Err(failures) = conformance::run::<MockContext>(&mock_config()) {
+        panic!("{:#?}", failures);
+    }
+}
+
+#[derive(Default)]
+struct Calls {
+    callbacks: usize,
+    states: Vec<State>,
+}
+
+unsafe extern "C" fn data_cb(
+    _: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    _: *const c_void,
+    _: *mut c_void,
+    nframes: c_long,
+) -> c_long {
+    let calls = &*(user_ptr as *const Mutex<Calls>);
+    calls.lock().unwrap().callbacks += 1;
+    nframes
+}
+
+unsafe extern "C" fn state_cb(
+    _: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    state: ffi::cubeb_state,
+) {
+    let calls = &*(user_ptr as *const Mutex<Calls>);
+    calls.lock().unwrap().states.push(State::from(state));
+}
+
+fn run_script(script: Script, duration: Duration) -> Calls {
+    let context: Context = MockContext::init_with_script(script).unwrap();
+    let params = StreamParamsBuilder::new()
+        .format(SampleFormat::Float32NE)
+        .rate(48000)
+        .channels(2)
+        .layout(ChannelLayout::STEREO)
+        .take();
+    let calls = Mutex::new(Calls::default());
+    let stream = unsafe {
+        context.stream_init(
+            None,
+            ptr::null(),
+            None,
+            ptr::null(),
+            Some(&params),
+            4096,
+            Some(data_cb),
+            Some(state_cb),
+            &calls as *const _ as *mut c_void,
+        )
+    }
+    .unwrap();
+    stream.start().unwrap();
+    mock::advance(&context, duration);
+    assert_eq!(mock::now(&context), duration);
+    stream.set_volume(0.25).unwrap();
+    assert_eq!(mock::volume(&stream), 0.25);
+    drop(stream);
+    calls.into_inner().unwrap()
+}
+
+#[test]
+fn test_mock_callbacks_follow_clock() {
+    // 48000 / 256 = 187.5 periods in a second.
+    let calls = run_script(Script::default(), Duration::from_secs(1));
+    assert_eq!(calls.callbacks, 187);
+    // Dropping a `Stream` stops it first.
+    assert_eq!(calls.states, [State::Started, State::Stopped]);
+}
+
+#[test]
+fn test_mock_scripted_error() {
+    let script = Script {
+        error_after: Some(1024),
+        ..Script::default()
+    };
+    let calls = run_script(script, Duration::from_secs(1));
+    assert_eq!(calls.callbacks, 4);
+    assert_eq!(calls.states, [State::Started, State::Error]);
+}
+
+#[test]
+fn test_mock_capabilities() {
+    let script = Script {
+        capabilities: Capabilities::LOOPBACK | Capabilities::FORMAT_S16LE,
+        ..Script::default()
+    };
+    let context = MockContext::init_with_script(script).unwrap();
+    let capabilities = context.capabilities().unwrap();
+    assert!(capabilities.contains(Capabilities::LOOPBACK));
+    assert!(!capabilities.contains(Capabilities::STREAM_VOLUME));
+    assert!(capabilities.supports_format(SampleFormat::S16LE));
+    assert!(!capabilities.supports_format(SampleFormat::Float32NE));
+}
@@ -0,0 +1,393 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details
+
+//! Compares `cubeb_backend::resampler` with the resampler of libcubeb.
+
+extern crate cubeb_backend;
+
+use cubeb_backend::resampler::{RawResampler, Resampler, ResamplerQuality};
+use cubeb_backend::{ffi, StreamParams};
+use std::f64::consts::PI;
+use std::os::raw::{c_long, c_void};
+use std::ptr;
+use std::time::{Duration, Instant};
+
+const QUALITIES: [ffi::cubeb_resampler_quality; 3] = [
+    ffi::CUBEB_RESAMPLER_QUALITY_VOIP,
+    ffi::CUBEB_RESAMPLER_QUALITY_DEFAULT,
+    ffi::CUBEB_RESAMPLER_QUALITY_DESKTOP,
+];
+
+fn params(channels: u32, rate: u32) -> StreamParams {
+    StreamParams::from(ffi::cubeb_stream_params {
+        format: ffi::CUBEB_SAMPLE_FLOAT32NE,
+        rate,
+        channels,
+        ..Default::default()
+    })
+}
+
+// A 1 kHz sine on every channel.
+struct Sine {
+    rate: u32,
+    channels: usize,
+    frame: u64,
+}
+
+impl Sine {
+    fn fill(&mut self, output: &mut [f32]) {
+        for frame in output.chunks_mut(self.channels) {
+            let t = self.frame as f64 / f64::from(self.rate);
+            let sample = (0.5 * (2.0 * PI * 1000.0 * t).sin()) as f32;
+            for s in frame {
+                *s = sample;
+            }
+            self.frame += 1;
+        }
+    }
+}
+
+unsafe extern "C" fn sine_callback(
+    _stream: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    _input: *const c_void,
+    output: *mut c_void,
+    frames: c_long,
+) -> c_long {
+    let sine = &mut *(user_ptr as *mut Sine);
+    let samples = frames as usize * sine.channels;
+    sine.fill(std::slice::from_raw_parts_mut(output as *mut f32, samples));
+    frames
+}
+
+unsafe extern "C" fn copy_callback(
+    _stream: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    input: *const c_void,
+    output: *mut c_void,
+    frames: c_long,
+) -> c_long {
+    let channels = *(user_ptr as *const usize);
+    ptr::copy_nonoverlapping(
+        input as *const f32,
+        output as *mut f32,
+        frames as usize * channels,
+    );
+    frames
+}
+
+struct CResampler(*mut ffi::cubeb_resampler);
+
+impl CResampler {
+    fn new(
+        input: Option<&StreamParams>,
+        output: Option<&StreamParams>,
+        target_rate: u32,
+        callback: ffi::cubeb_data_callback,
+        user_ptr: *mut c_void,
+        quality: ffi::cubeb_resampler_quality,
+    ) -> CResampler {
+        let raw = |p: Option<&StreamParams>| p.map_or(ptr::null_mut(), |p| p.as_ptr());
+        let resampler = unsafe {
+            ffi::cubeb_resampler_create(
+                ptr::null_mut(),
+                raw(input),
+                raw(output),
+                target_rate,
+                callback,
+                user_ptr,
+                quality,
+                ffi::CUBEB_RESAMPLER_RECLOCK_NONE,
+            )
+        };
+        assert!(!resampler.is_null());
+        CResampler(resampler)
+    }
+
+    fn fill_output(&mut self, output: &mut [f32], frames: usize) -> c_long {
+        unsafe {
+            ffi::cubeb_resampler_fill(
+                self.0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                output.as_mut_ptr() as *mut c_void,
+                frames as c_long,
+            )
+        }
+    }
+
+    fn latency(&self) -> c_long {
+        unsafe { ffi::cubeb_resampler_latency(self.0) }
+    }
+}
+
+impl Drop for CResampler {
+    fn drop(&mut self) {
+        unsafe { ffi::cubeb_resampler_destroy(self.0) }
+    }
+}
+
+// Signal to noise ratio of `actual` against `expected`, in dB.
+fn snr(expected: &[f32], actual: &[f32]) -> f64 {
+    let (signal, noise) =
+        expected
+            .iter()
+            .zip(actual)
+            .fold((0f64, 0f64), |(signal, noise), (&e, &a)| {
+                let (e, a) = (f64::from(e), f64::from(a));
+                (signal + e * e, noise + (e - a) * (e - a))
+            });
+    10.0 * (signal / noise).log10()
+}
+
+// Resamples a second of sine at `from` to `to`, with both resamplers.
+fn resample_sine(
+    from: u32,
+    to: u32,
+    quality: ffi::cubeb_resampler_quality,
+) -> (Vec<f32>, Vec<f32>, c_long) {
+    const CHANNELS: usize = 2;
+    let output_params = params(CHANNELS as u32, to);
+    let buffer_frames = to as usize / 100;
+
+    let mut sine = Sine {
+        rate: from,
+        channels: CHANNELS,
+        frame: 0,
+    };
+    let mut c = CResampler::new(
+        None,
+        Some(&output_params),
+        from,
+        Some(sine_callback),
+        &mut sine as *mut Sine as *mut c_void,
+        quality,
+    );
+    // libcubeb estimates the frames it needs from the callback, and can
+    // come a frame short.
+    let mut expected = Vec::new();
+    let mut buffer = vec![0f32; buffer_frames * CHANNELS];
+    while expected.len() < to as usize * CHANNELS {
+        let got = c.fill_output(&mut buffer, buffer_frames) as usize;
+        assert!(got + 1 >= buffer_frames);
+        expected.extend_from_slice(&buffer[..got * CHANNELS]);
+    }
+    expected.truncate(to as usize * CHANNELS);
+
+    let mut sine = Sine {
+        rate: from,
+        channels: CHANNELS,
+        frame: 0,
+    };
+    let mut r = Resampler::new(None, Some(&output_params), from, quality.into());
+    assert_eq!(r.latency() as c_long, c.latency());
+    let mut actual = vec![0f32; to as usize * CHANNELS];
+    for buffer in actual.chunks_mut(buffer_frames * CHANNELS) {
+        let mut used = 0;
+        let got = r.fill(None, &mut used, Some(buffer), |_, output, frames| {
+            sine.fill(output.unwrap());
+            frames as isize
+        });
+        assert_eq!(got, buffer_frames as isize);
+    }
+    (expected, actual, c.latency())
+}
+
+#[test]
+fn test_resampler_matches_libcubeb() {
+    let rates = [
+        (44100, 48000),
+        (48000, 44100),
+        (16000, 48000),
+        (96000, 44100),
+    ];
+    for &quality in &QUALITIES {
+        for &(from, to) in &rates {
+            let (expected, actual, latency) = resample_sine(from, to, quality);
+            // Skip the filters filling up.
+            let start = 2 * 2 * latency as usize;
+            let snr = snr(&expected[start..], &actual[start..]);
+            assert!(
+                snr > 90.0,
+                "{} to {}Hz at quality {}: {}dB",
+                from,
+                to,
+                quality,
+                snr
+            );
+        }
+    }
+}
+
+#[test]
+fn test_resampler_duplex_matches_libcubeb() {
+    // Resample either direction of a mono duplex stream, copying the input
+    // to the output.
+    for &(input_rate, output_rate) in &[(44100, 48000), (48000, 44100)] {
+        let (input, output) = (params(1, input_rate), params(1, output_rate));
+        let mut channels = 1usize;
+        let c = CResampler::new(
+            Some(&input),
+            Some(&output),
+            48000,
+            Some(copy_callback),
+            &mut channels as *mut usize as *mut c_void,
+            ffi::CUBEB_RESAMPLER_QUALITY_DEFAULT,
+        );
+        let mut r = Resampler::<f32>::new(
+            Some(&input),
+            Some(&output),
+            48000,
+            ResamplerQuality::Default,
+        );
+        assert_eq!(r.latency() as c_long, c.latency());
+
+        // The devices run at the same pace, in 10ms buffers.
+        let (input_frames, output_frames) = (input_rate as usize / 100, output_rate as usize / 100);
+        let mut sine = Sine {
+            rate: input_rate,
+            channels: 1,
+            frame: 0,
+        };
+        let (mut expected, mut actual) = (Vec::new(), Vec::new());
+        for _ in 0..100 {
+            let mut input = vec![0f32; input_frames];
+            sine.fill(&mut input);
+
+            let mut c_output = vec![0f32; output_frames];
+            let mut frames = input_frames as c_long;
+            let got = unsafe {
+                ffi::cubeb_resampler_fill(
+                    c.0,
+                    input.as_mut_ptr() as *mut c_void,
+                    &mut frames,
+                    c_output.as_mut_ptr() as *mut c_void,
+                    output_frames as c_long,
+                )
+            };
+            assert_eq!(got, output_frames as c_long);
+            expected.extend_from_slice(&c_output);
+
+            let mut output = vec![0f32; output_frames];
+            let mut used = 0;
+            let got = r.fill(
+                Some(&input),
+                &mut used,
+                Some(&mut output),
+                |i, o, frames| {
+                    o.unwrap().copy_from_slice(i.unwrap());
+                    frames as isize
+                },
+            );
+            assert_eq!(got, output_frames as isize);
+            assert!(used <= input_frames);
+            actual.extend_from_slice(&output);
+        }
+        let start = output_rate as usize / 10;
+        let snr = snr(&expected[start..], &actual[start..]);
+        assert!(snr > 90.0, "{} to {}Hz: {}dB", input_rate, output_rate, snr);
+    }
+}
+
+#[test]
+fn test_raw_resampler() {
+    let output = params(2, 44100);
+    let mut sine = Sine {
+        rate: 48000,
+        channels: 2,
+        frame: 0,
+    };
+    let mut raw = RawResampler::new(
+        ptr::null_mut(),
+        None,
+        Some(&output),
+        48000,
+        Some(sine_callback),
+        &mut sine as *mut Sine as *mut c_void,
+        ResamplerQuality::Voip,
+    );
+    let c = CResampler::new(
+        None,
+        Some(&output),
+        48000,
+        None,
+        ptr::null_mut(),
+        ffi::CUBEB_RESAMPLER_QUALITY_VOIP,
+    );
+    assert_eq!(raw.latency(), c.latency());
+    let mut buffer = vec![0f32; 2 * 441];
+    let mut frames = 0;
+    let got = unsafe {
+        raw.fill(
+            ptr::null_mut(),
+            &mut frames,
+            buffer.as_mut_ptr() as *mut _,
+            441,
+        )
+    };
+    assert_eq!(got, 441);
+    assert!(buffer.iter().any(|&s| s != 0.0));
+}
+
+// Times one resampler against the other: `cargo test -- --ignored`.
+#[test]
+#[ignore]
+fn bench_resampler() {
+    fn time(f: &mut dyn FnMut()) -> Duration {
+        let start = Instant::now();
+        f();
+        start.elapsed()
+    }
+
+    const CHANNELS: usize = 2;
+    for &quality in &QUALITIES {
+        for &(from, to) in &[(44100, 48000), (48000, 44100), (96000, 48000)] {
+            let output_params = params(CHANNELS as u32, to);
+            let frames = to as usize / 100;
+            let mut buffer = vec![0f32; frames * CHANNELS];
+            // A minute of audio, in 10ms buffers.
+            let buffers = 6000;
+
+            let mut sine = Sine {
+                rate: from,
+                channels: CHANNELS,
+                frame: 0,
+            };
+            let mut c = CResampler::new(
+                None,
+                Some(&output_params),
+                from,
+                Some(sine_callback),
+                &mut sine as *mut Sine as *mut c_void,
+                quality,
+            );
+            let c_time = time(&mut || {
+                for _ in 0..buffers {
+                    c.fill_output(&mut buffer, frames);
+                }
+            });
+
+            let mut sine = Sine {
+                rate: from,
+                channels: CHANNELS,
+                frame: 0,
+            };
+            let mut r = Resampler::new(None, Some(&output_params), from, quality.into());
+            let rust_time = time(&mut || {
+                for _ in 0..buffers {
+                    let mut used = 0;
+                    r.fill(None, &mut used, Some(&mut buffer), |_, output, frames| {
+                        sine.fill(output.unwrap());
+                        frames as isize
+                    });
+                }
+            });
+            println!(
+                "{} to {}Hz, quality {}: libcubeb {:?}, rust {:?}",
+                from, to, quality, c_time, rust_time
+            );
+        }
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details
+
+extern crate cubeb_backend;
+
+use cubeb_backend::conformance::{self, Config};
+use cubeb_backend::mock::{self, MockContext, Script};
+use cubeb_backend::virtual_device::{self, Output, VirtualDevice};
+use cubeb_backend::{
+    ffi, ChannelLayout, Context, ContextRef, SampleFormat, State, Stream, StreamParamsBuilder,
+};
+use std::os::raw::{c_long, c_void};
+use std::ptr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+fn init() -> Context {
+    let inner = MockContext::init_with_script(Script::default()).unwrap();
+    VirtualDevice::init_with_context(inner, Output::default()).unwrap()
+}
+
+fn advance(context: &ContextRef, duration: Duration) {
+    mock::advance(virtual_device::inner(context), duration);
+}
+
+#[test]
+fn test_virtual_device_conformance() {
+    let config = Config {
+        duplex: true,
+        wait: advance,
+        timeout: Duration::from_secs(1),
+        ..Config::default()
+    };
+    if let Err(failures) = conformance::run_with_context(&init(), &config) {
+        panic!("{:#?}", failures);
+    }
+}
+
+#[derive(Default)]
+struct Calls {
+    frames: u64,
+    // Frames after which the stream drains.
+    drain_after: Option<u64>,
+    states: Vec<State>,
+}
+
+unsafe extern "C" fn data_cb(
+    _: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    _: *const c_void,
+    _: *mut c_void,
+    nframes: c_long,
+) -> c_long {
+    let mut calls = (*(user_ptr as *const Mutex<Calls>)).lock().unwrap();
+    let frames = match calls.drain_after {
+        Some(limit) => (limit - calls.frames).min(nframes as u64) as c_long,
+        None => nframes,
+    };
+    calls.frames += frames as u64;
+    frames
+}
+
+unsafe extern "C" fn state_cb(
+    _: *mut ffi::cubeb_stream,
+    user_ptr: *mut c_void,
+    state: ffi::cubeb_state,
+) {
+    let calls = &*(user_ptr as *const Mutex<Calls>);
+    calls.lock().unwrap().states.push(State::from(state));
+}
+
+fn stream_init(context: &Context, rate: u32, calls: &Mutex<Calls>) -> Stream {
+    let params = StreamParamsBuilder::new()
+        .format(SampleFormat::S16NE)
+        .rate(rate)
+        .channels(1)
+        .layout(ChannelLayout::MONO)
+        .take();
+    unsafe {
+        context.stream_init(
+            None,
+            ptr::null(),
+            None,
+            ptr::null(),
+            Some(&params),
+            4096,
+            Some(data_cb),
+            Some(state_cb),
+            calls as *const _ as *mut c_void,
+        )
+    }
+    .unwrap()
+}
+
+#[test]
+fn test_virtual_device_streams_are_independent() {
+    let context = init();
+    assert_eq!(context.backend_id(), "virtual-device");
+    let (a, b) = (Mutex::new(Calls::default()), Mutex::new(Calls::default()));
+    b.lock().unwrap().drain_after = Some(4800);
+    let stream_a = stream_init(&context, 44100, &a);
+    let stream_b = stream_init(&context, 48000, &b);
+
+    stream_a.start().unwrap();
+    advance(&context, Duration::from_millis(100));
+    assert_eq!(b.lock().unwrap().frames, 0);
+    assert_eq!(stream_b.position().unwrap(), 0);
+
+    stream_b.start().unwrap();
+    advance(&context, Duration::from_millis(200));
+    assert_eq!(b.lock().unwrap().states, [State::Started, State::Drained]);
+    assert_eq!(b.lock().unwrap().frames, 4800);
+    assert_eq!(stream_b.position().unwrap(), 4800);
+
+    // A plays on, at its own rate.
+    assert_eq!(a.lock().unwrap().states, [State::Started]);
+    let position = stream_a.position().unwrap();
+    assert!(position > 44100 / 4 && position <= a.lock().unwrap().frames);
+    stream_a.stop().unwrap();
+    let frames = a.lock().unwrap().frames;
+    advance(&context, Duration::from_millis(100));
+    assert_eq!(a.lock().unwrap().frames, frames);
+    assert_eq!(a.lock().unwrap().states, [State::Started, State::Stopped]);
+
+    // A drained stream starts over.
+    b.lock().unwrap().drain_after = Some(9600);
+    stream_b.start().unwrap();
+    advance(&context, Duration::from_millis(200));
+    assert_eq!(
+        b.lock().unwrap().states,
+        [
+            State::Started,
+            State::Drained,
+            State::Started,
+            State::Drained
+        ]
+    );
+    drop(stream_a);
+    drop(stream_b);
+}
@@ -8,7 +8,10 @@ use std::ffi::CStr;
 use std::os::raw::c_void;
 use std::{ptr, str};
 use util::opt_bytes;
-use {DeviceCollection, DeviceId, DeviceType, Result, Stream, StreamParamsRef};
+use {
+    DeviceCollection, DeviceId, DeviceType, Result, SampleFormat, Stream, StreamOptionsRef,
+    StreamParamsRef,
+};
 
 macro_rules! as_ptr {
     ($e:expr) => {
@@ -16,6 +19,52 @@ macro_rules! as_ptr {
     };
 }
 
+bitflags! {
+    /// Optional features of a backend.
+    pub struct Capabilities: ffi::cubeb_capabilities {
+        const NONE = ffi::CUBEB_CAPABILITY_NONE;
+        /// Loopback streams, see `StreamPrefs::LOOPBACK`.
+        const LOOPBACK = ffi::CUBEB_CAPABILITY_LOOPBACK;
+        /// Streams on the default device follow it when it changes.
+        const DEVICE_SWITCHING = ffi::CUBEB_CAPABILITY_DEVICE_SWITCHING;
+        /// `StreamRef::set_volume`.
+        const STREAM_VOLUME = ffi::CUBEB_CAPABILITY_STREAM_VOLUME;
+        /// `StreamRef::input_latency`.
+        const INPUT_LATENCY = ffi::CUBEB_CAPABILITY_INPUT_LATENCY;
+        /// `StreamRef::register_device_changed_callback`.
+        const DEVICE_CHANGED_CALLBACK = ffi::CUBEB_CAPABILITY_DEVICE_CHANGED_CALLBACK;
+        /// `StreamPrefs::VOICE` enables platform voice processing, such as
+        /// echo cancellation.
+        const VOICE_PROCESSING = ffi::CUBEB_CAPABILITY_VOICE_PROCESSING;
+        /// Input streams capture the device channels given by
+        /// `StreamOptionsRef::input_channel_map`.
+        const INPUT_CHANNEL_MAP = ffi::CUBEB_CAPABILITY_INPUT_CHANNEL_MAP;
+        /// `StreamOptionsRef::mix_matrix`.
+        const MIX_MATRIX = ffi::CUBEB_CAPABILITY_MIX_MATRIX;
+        const FORMAT_S16LE = ffi::CUBEB_CAPABILITY_FORMAT_S16LE;
+        const FORMAT_S16BE = ffi::CUBEB_CAPABILITY_FORMAT_S16BE;
+        const FORMAT_FLOAT32LE = ffi::CUBEB_CAPABILITY_FORMAT_FLOAT32LE;
+        const FORMAT_FLOAT32BE = ffi::CUBEB_CAPABILITY_FORMAT_FLOAT32BE;
+    }
+}
+
+impl Capabilities {
+    /// The flag for streams of `format`.
+    pub fn format(format: SampleFormat) -> Capabilities {
+        match ffi::cubeb_sample_format::from(format) {
+            ffi::CUBEB_SAMPLE_S16LE => Capabilities::FORMAT_S16LE,
+            ffi::CUBEB_SAMPLE_S16BE => Capabilities::FORMAT_S16BE,
+            ffi::CUBEB_SAMPLE_FLOAT32LE => Capabilities::FORMAT_FLOAT32LE,
+            _ => Capabilities::FORMAT_FLOAT32BE,
+        }
+    }
+
+    /// Whether streams of `format` are supported.
+    pub fn supports_format(self, format: SampleFormat) -> bool {
+        self.contains(Capabilities::format(format))
+    }
+}
+
 ffi_type_heap! {
     type CType = ffi::cubeb;
     fn drop = ffi::cubeb_destroy;
@@ -78,6 +127,18 @@ impl ContextRef {
         Ok(rate)
     }
 
+    /// Optional features the backend supports.
+    pub fn capabilities(&self) -> Result<Capabilities> {
+        let mut capabilities: ffi::cubeb_capabilities = 0;
+        unsafe {
+            call!(ffi::cubeb_get_capabilities(
+                self.as_ptr(),
+                &mut capabilities
+            ))?;
+        }
+        Ok(Capabilities::from_bits_truncate(capabilities))
+    }
+
     /// # Safety
     ///
     /// This function is unsafe because it dereferences the given `data_callback`, `state_callback`, and `user_ptr` pointers.
@@ -117,6 +178,50 @@ impl ContextRef {
         Ok(Stream::from_ptr(stm))
     }
 
+    /// Like `stream_init`, with the optional parameters in `options`.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because it dereferences the given `data_callback`, `state_callback`, and `user_ptr` pointers.
+    /// The caller should ensure those pointers are valid.
+    // Takes the arguments of `cubeb_stream_init_with_options`, one for one.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn stream_init_with_options(
+        &self,
+        stream_name: Option<&CStr>,
+        input_device: DeviceId,
+        input_stream_params: Option<&StreamParamsRef>,
+        output_device: DeviceId,
+        output_stream_params: Option<&StreamParamsRef>,
+        options: &StreamOptionsRef,
+        latency_frames: u32,
+        data_callback: ffi::cubeb_data_callback,
+        state_callback: ffi::cubeb_state_callback,
+        user_ptr: *mut c_void,
+    ) -> Result<Stream> {
+        let mut stm: *mut ffi::cubeb_stream = ptr::null_mut();
+
+        let stream_name = as_ptr!(stream_name);
+        let input_stream_params = as_ptr!(input_stream_params);
+        let output_stream_params = as_ptr!(output_stream_params);
+
+        call!(ffi::cubeb_stream_init_with_options(
+            self.as_ptr(),
+            &mut stm,
+            stream_name,
+            input_device,
+            input_stream_params,
+            output_device,
+            output_stream_params,
+            options.as_ptr(),
+            latency_frames,
+            data_callback,
+            state_callback,
+            user_ptr
+        ))?;
+        Ok(Stream::from_ptr(stm))
+    }
+
     pub fn enumerate_devices(&self, devtype: DeviceType) -> Result<DeviceCollection> {
         let mut coll = ffi::cubeb_device_collection::default();
         unsafe {
@@ -149,3 +254,24 @@ impl ContextRef {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {Capabilities, SampleFormat};
+
+    #[test]
+    fn capabilities_format() {
+        let caps = Capabilities::STREAM_VOLUME | Capabilities::FORMAT_FLOAT32LE;
+        assert!(caps.supports_format(SampleFormat::Float32LE));
+        assert!(!caps.supports_format(SampleFormat::Float32BE));
+        assert!(!caps.supports_format(SampleFormat::S16LE));
+        assert_eq!(
+            Capabilities::format(SampleFormat::S16NE),
+            if cfg!(target_endian = "big") {
+                Capabilities::FORMAT_S16BE
+            } else {
+                Capabilities::FORMAT_S16LE
+            }
+        );
+    }
+}
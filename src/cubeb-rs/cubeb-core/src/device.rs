@@ -91,6 +91,21 @@ impl DeviceRef {
     }
 }
 
+/// Form factor of the device, e.g. "headset", "speaker" or "hdmi".
+pub const DEVICE_PROPERTY_FORM_FACTOR: &str = ffi::CUBEB_DEVICE_PROPERTY_FORM_FACTOR;
+/// Bus the device is attached to, e.g. "usb", "bluetooth" or "pci".
+pub const DEVICE_PROPERTY_BUS: &str = ffi::CUBEB_DEVICE_PROPERTY_BUS;
+/// Name of an icon suitable to represent the device in a UI.
+pub const DEVICE_PROPERTY_ICON_NAME: &str = ffi::CUBEB_DEVICE_PROPERTY_ICON_NAME;
+/// Name of the port currently in use on the device.
+pub const DEVICE_PROPERTY_ACTIVE_PORT: &str = ffi::CUBEB_DEVICE_PROPERTY_ACTIVE_PORT;
+/// Human readable description of the port currently in use.
+pub const DEVICE_PROPERTY_ACTIVE_PORT_DESCRIPTION: &str =
+    ffi::CUBEB_DEVICE_PROPERTY_ACTIVE_PORT_DESCRIPTION;
+/// Prefix of the keys listing the available ports of the device. The rest of
+/// the key is the port name, the value is its description.
+pub const DEVICE_PROPERTY_PORT_PREFIX: &str = ffi::CUBEB_DEVICE_PROPERTY_PORT_PREFIX;
+
 ffi_type_stack! {
     /// This structure holds the characteristics of an input or output
     /// audio device. It is obtained using `enumerate_devices`, which
@@ -5,8 +5,8 @@
 
 use ffi;
 use ffi_types;
-use std::{ops, slice};
-use {ContextRef, DeviceInfo};
+use std::{ops, ptr, slice};
+use {ContextRef, DeviceInfo, Properties, Result};
 
 /// A collection of `DeviceInfo` used by libcubeb
 type CType = ffi::cubeb_device_collection;
@@ -22,6 +22,23 @@ impl<'ctx> DeviceCollection<'ctx> {
     pub fn as_ptr(&self) -> *mut CType {
         &self.0 as *const CType as *mut CType
     }
+
+    /// Backend specific properties of the device at `index`. See the
+    /// `DEVICE_PROPERTY_*` constants for the well-known keys.
+    pub fn properties(&self, index: usize) -> Result<Properties<'_>> {
+        let mut props = ptr::null();
+        let mut count = 0;
+        unsafe {
+            call!(ffi::cubeb_device_collection_get_properties(
+                self.1.as_ptr(),
+                self.as_ptr(),
+                index,
+                &mut props,
+                &mut count
+            ))?;
+            Ok(Properties::from_raw(props, count))
+        }
+    }
 }
 
 impl<'ctx> Drop for DeviceCollection<'ctx> {
@@ -0,0 +1,222 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+//! Conversion of float samples to 16 bits.
+
+use ffi;
+
+/// How float samples are quantized to 16 bits.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Dither {
+    /// Round to the nearest value.
+    #[default]
+    None,
+    /// Add triangular noise of ±1 LSB before rounding, so the error is
+    /// noise rather than distortion following the signal.
+    Tpdf,
+    /// TPDF dither, with the error fed back through a second order filter
+    /// that moves the noise away from low and mid frequencies, towards the
+    /// Nyquist frequency.
+    NoiseShaped,
+}
+
+impl From<ffi::cubeb_dither> for Dither {
+    fn from(x: ffi::cubeb_dither) -> Self {
+        match x {
+            ffi::CUBEB_DITHER_TPDF => Dither::Tpdf,
+            ffi::CUBEB_DITHER_NOISE_SHAPED => Dither::NoiseShaped,
+            _ => Dither::None,
+        }
+    }
+}
+
+impl From<Dither> for ffi::cubeb_dither {
+    fn from(x: Dither) -> Self {
+        match x {
+            Dither::None => ffi::CUBEB_DITHER_NONE,
+            Dither::Tpdf => ffi::CUBEB_DITHER_TPDF,
+            Dither::NoiseShaped => ffi::CUBEB_DITHER_NOISE_SHAPED,
+        }
+    }
+}
+
+/// Converts `value`, at full scale between -1 and 1, to 16 bits. Values out
+/// of range saturate, and NaN is silence.
+pub fn float_to_i16(value: f32) -> i16 {
+    if value.is_nan() {
+        return 0;
+    }
+    (value.clamp(-1.0, 1.0) * f32::from(i16::MAX)).round() as i16
+}
+
+/// Quantizes float samples to 16 bits with some `Dither`, keeping the state
+/// of each channel between calls. Does not allocate once created.
+#[derive(Clone, Debug)]
+pub struct Quantizer {
+    dither: Dither,
+    // xorshift32 state, never 0.
+    seed: u32,
+    // Last two errors of each channel, the latest first.
+    errors: Vec<[f32; 2]>,
+}
+
+impl Quantizer {
+    pub fn new(dither: Dither, channels: usize) -> Quantizer {
+        Quantizer {
+            dither,
+            seed: 0x9e37_79b9,
+            errors: vec![[0.0; 2]; channels],
+        }
+    }
+
+    pub fn dither(&self) -> Dither {
+        self.dither
+    }
+
+    // Triangular noise between -1 and 1, from two uniform values.
+    fn tpdf(&mut self) -> f32 {
+        let mut x = self.seed;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.seed = x;
+        let (a, b) = (x >> 16, x & 0xffff);
+        (a as f32 - b as f32) / 65536.0
+    }
+
+    /// Quantizes `value` of `channel`, in units of the least significant
+    /// bit of 16-bit samples.
+    pub fn quantize(&mut self, channel: usize, value: f32) -> i16 {
+        if value.is_nan() {
+            return 0;
+        }
+        let (min, max) = (f32::from(i16::MIN), f32::from(i16::MAX));
+        match self.dither {
+            Dither::None => value.round().clamp(min, max) as i16,
+            Dither::Tpdf => (value + self.tpdf()).round().clamp(min, max) as i16,
+            Dither::NoiseShaped => {
+                let [e1, e2] = self.errors[channel];
+                // Error filtered by (1 - z^-1)^2.
+                let wanted = value - 2.0 * e1 + e2;
+                let rounded = (wanted + self.tpdf()).round();
+                // The error before clipping is at most 1.5, keeping the
+                // loop stable.
+                self.errors[channel] = [rounded - wanted, e1];
+                rounded.clamp(min, max) as i16
+            }
+        }
+    }
+
+    /// Quantizes `value` of `channel`, at full scale between -1 and 1 as
+    /// for `float_to_i16`.
+    pub fn quantize_float(&mut self, channel: usize, value: f32) -> i16 {
+        let value = if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(-1.0, 1.0)
+        };
+        self.quantize(channel, value * f32::from(i16::MAX))
+    }
+
+    /// Converts the interleaved frames of `input`, at full scale between -1
+    /// and 1, to those of `output`.
+    pub fn convert(&mut self, input: &[f32], output: &mut [i16]) {
+        assert_eq!(input.len(), output.len());
+        let channels = self.errors.len();
+        assert_eq!(input.len() % channels, 0);
+        for (i, (&x, out)) in input.iter().zip(output).enumerate() {
+            *out = self.quantize_float(i % channels, x);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn float_to_i16_saturates() {
+        assert_eq!(float_to_i16(0.0), 0);
+        assert_eq!(float_to_i16(1.0), i16::MAX);
+        assert_eq!(float_to_i16(-1.0), -i16::MAX);
+        assert_eq!(float_to_i16(0.5), 16384);
+        assert_eq!(float_to_i16(-0.5), -16384);
+        assert_eq!(float_to_i16(1.5), i16::MAX);
+        assert_eq!(float_to_i16(-1.5), -i16::MAX);
+        assert_eq!(float_to_i16(f32::INFINITY), i16::MAX);
+        assert_eq!(float_to_i16(f32::NEG_INFINITY), -i16::MAX);
+        assert_eq!(float_to_i16(f32::NAN), 0);
+    }
+
+    #[test]
+    fn quantize_without_dither_rounds() {
+        let mut q = Quantizer::new(Dither::None, 1);
+        assert_eq!(q.quantize(0, 1.4), 1);
+        assert_eq!(q.quantize(0, -1.6), -2);
+        assert_eq!(q.quantize(0, 40000.0), i16::MAX);
+        assert_eq!(q.quantize(0, -40000.0), i16::MIN);
+        let mut output = [0; 4];
+        q.convert(&[0.5, -0.5, 2.0, f32::NAN], &mut output);
+        assert_eq!(output, [16384, -16384, i16::MAX, 0]);
+    }
+
+    #[test]
+    fn dither_is_triangular() {
+        let mut q = Quantizer::new(Dither::Tpdf, 1);
+        let noise: Vec<f32> = (0..100_000).map(|_| q.tpdf()).collect();
+        assert!(noise.iter().all(|n| n.abs() < 1.0));
+        let mean = noise.iter().sum::<f32>() / noise.len() as f32;
+        let variance = noise.iter().map(|n| n * n).sum::<f32>() / noise.len() as f32;
+        assert!(mean.abs() < 0.01, "{}", mean);
+        // 1/6 for a triangular distribution between -1 and 1.
+        assert!((variance - 1.0 / 6.0).abs() < 0.01, "{}", variance);
+        // Within a step of the value, near it in average.
+        let sum: i64 = (0..100_000)
+            .map(|_| {
+                let s = q.quantize(0, 10.25);
+                assert!((9..=12).contains(&s), "{}", s);
+                i64::from(s)
+            })
+            .sum();
+        assert!((sum as f32 / 100_000.0 - 10.25).abs() < 0.01);
+    }
+
+    // Power of the error of quantizing a quiet sine, below `cutoff` of the
+    // sample rate, by four one pole lowpass filters.
+    fn low_frequency_error(dither: Dither, cutoff: f32) -> f32 {
+        let mut q = Quantizer::new(dither, 2);
+        let alpha = 1.0 - (-2.0 * PI * cutoff).exp();
+        let (mut filtered, mut power) = ([0.0; 4], 0.0);
+        let frames = 48_000;
+        for n in 0..frames {
+            let value = 3.3 * (2.0 * PI * 440.0 * n as f32 / 48_000.0).sin();
+            // The other channel is left alone.
+            q.quantize(1, 1000.0);
+            let error = f32::from(q.quantize(0, value)) - value;
+            let mut x = error;
+            for f in &mut filtered {
+                *f += alpha * (x - *f);
+                x = *f;
+            }
+            power += x * x;
+        }
+        power / frames as f32
+    }
+
+    #[test]
+    fn noise_shaping_lowers_low_frequency_noise() {
+        let tpdf = low_frequency_error(Dither::Tpdf, 0.02);
+        let shaped = low_frequency_error(Dither::NoiseShaped, 0.02);
+        assert!(shaped * 10.0 < tpdf, "{} {}", shaped, tpdf);
+        // Full scale stays stable.
+        let mut q = Quantizer::new(Dither::NoiseShaped, 1);
+        for n in 0..10_000 {
+            let value = 40000.0 * (n as f32 / 10.0).sin();
+            let s = q.quantize(0, value);
+            assert!((f32::from(s) - value.clamp(-32768.0, 32767.0)).abs() <= 5.0);
+        }
+    }
+}
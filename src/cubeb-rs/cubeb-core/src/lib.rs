@@ -6,6 +6,10 @@
 #[macro_use]
 extern crate bitflags;
 extern crate cubeb_sys;
+#[cfg(feature = "log")]
+extern crate log as log_facade;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 
 #[macro_use]
 mod ffi_types;
@@ -17,9 +21,11 @@ mod channel;
 mod context;
 mod device;
 mod device_collection;
+mod dither;
 mod error;
 mod format;
 mod log;
+mod property;
 mod stream;
 mod util;
 
@@ -28,9 +34,11 @@ pub use channel::*;
 pub use context::*;
 pub use device::*;
 pub use device_collection::*;
+pub use dither::*;
 pub use error::*;
 pub use format::*;
 pub use log::*;
+pub use property::*;
 pub use stream::*;
 
 pub mod ffi {
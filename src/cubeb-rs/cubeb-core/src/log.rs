@@ -4,6 +4,17 @@
 // accompanying file LICENSE for details.
 
 use ffi;
+#[cfg(any(feature = "log", feature = "tracing"))]
+use std::ffi::CStr;
+use std::fmt;
+#[cfg(any(feature = "log", feature = "tracing"))]
+use std::os::raw::c_char;
+#[cfg(any(feature = "log", feature = "tracing"))]
+use std::str;
+#[cfg(any(feature = "log", feature = "tracing"))]
+use util::opt_bytes;
+#[cfg(any(feature = "log", feature = "tracing"))]
+use Result;
 
 /// Level (verbosity) of logging for a particular cubeb context.
 #[derive(PartialEq, Eq, Clone, Debug, Copy, PartialOrd, Ord)]
@@ -31,6 +42,177 @@ pub fn log_enabled() -> bool {
     unsafe { ffi::g_cubeb_log_level != LogLevel::Disabled as _ }
 }
 
+/// Identifies the stream or context a log message is about. Messages
+/// carrying one start with `[name@0x...] `.
+#[derive(PartialEq, Eq, Clone, Debug, Copy)]
+pub struct LogId {
+    name: &'static str,
+    ptr: usize,
+}
+
+impl LogId {
+    pub fn new<T>(name: &'static str, ptr: *const T) -> Self {
+        LogId {
+            name,
+            ptr: ptr as usize,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn ptr(&self) -> usize {
+        self.ptr
+    }
+}
+
+impl fmt::Display for LogId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{:#x}", self.name, self.ptr)
+    }
+}
+
+/// Splits the `LogId` prefix, if any, off a formatted message.
+pub fn split_log_id(message: &str) -> (Option<&str>, &str) {
+    if message.starts_with('[') {
+        if let Some(end) = message.find("] ") {
+            let id = &message[1..end];
+            if id.contains("@0x") {
+                return (Some(id), &message[end + 2..]);
+            }
+        }
+    }
+    (None, message)
+}
+
+// Installs `callback` for messages up to `level`, replacing the message
+// callback set by a previous call.
+#[cfg(any(feature = "log", feature = "tracing"))]
+fn set_log_message_callback(
+    level: LogLevel,
+    callback: ffi::cubeb_log_message_callback,
+) -> Result<()> {
+    let callback = if level == LogLevel::Disabled {
+        None
+    } else {
+        callback
+    };
+    unsafe {
+        if ffi::g_cubeb_log_message_callback.is_some() {
+            call!(ffi::cubeb_set_log_message_callback(
+                ffi::CUBEB_LOG_DISABLED,
+                None
+            ))?;
+        }
+        call!(ffi::cubeb_set_log_message_callback(level as _, callback))
+    }
+}
+
+// File and line of a message, when cubeb knows them.
+#[cfg(any(feature = "log", feature = "tracing"))]
+unsafe fn message_location<'a>(file: *const c_char, line: u32) -> (Option<&'a str>, Option<u32>) {
+    let file = opt_bytes(file).and_then(|f| str::from_utf8(f).ok());
+    (file, if line == 0 { None } else { Some(line) })
+}
+
+/// Level of the `log` crate cubeb messages logged at `level` are reported
+/// with.
+#[cfg(feature = "log")]
+pub fn log_crate_level(level: LogLevel) -> log_facade::Level {
+    match level {
+        LogLevel::Verbose => log_facade::Level::Trace,
+        _ => log_facade::Level::Debug,
+    }
+}
+
+#[cfg(feature = "log")]
+impl From<log_facade::LevelFilter> for LogLevel {
+    /// Most verbose cubeb level whose messages pass `filter`.
+    fn from(filter: log_facade::LevelFilter) -> Self {
+        if filter >= log_crate_level(LogLevel::Verbose) {
+            LogLevel::Verbose
+        } else if filter >= log_crate_level(LogLevel::Normal) {
+            LogLevel::Normal
+        } else {
+            LogLevel::Disabled
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+unsafe extern "C" fn log_crate_callback(
+    level: ffi::cubeb_log_level,
+    file: *const c_char,
+    line: u32,
+    message: *const c_char,
+) {
+    let level = log_crate_level(LogLevel::from(level));
+    if level > log_facade::max_level() {
+        return;
+    }
+    let (file, line) = message_location(file, line);
+    let message = CStr::from_ptr(message).to_string_lossy();
+    log_facade::logger().log(
+        &log_facade::Record::builder()
+            .level(level)
+            .target("cubeb")
+            .file(file)
+            .line(line)
+            .args(format_args!("{}", message))
+            .build(),
+    );
+}
+
+/// Forwards cubeb log messages, including the ones queued by the
+/// asynchronous logger, to the `log` crate under the `cubeb` target.
+/// `LogLevel::Disabled` stops forwarding. `log::max_level().into()` picks
+/// the level matching the logger configuration.
+#[cfg(feature = "log")]
+pub fn forward_to_log(level: LogLevel) -> Result<()> {
+    set_log_message_callback(level, Some(log_crate_callback))
+}
+
+#[cfg(feature = "tracing")]
+impl From<tracing::level_filters::LevelFilter> for LogLevel {
+    /// Most verbose cubeb level whose events pass `filter`.
+    fn from(filter: tracing::level_filters::LevelFilter) -> Self {
+        if filter >= tracing::Level::TRACE {
+            LogLevel::Verbose
+        } else if filter >= tracing::Level::DEBUG {
+            LogLevel::Normal
+        } else {
+            LogLevel::Disabled
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+unsafe extern "C" fn tracing_callback(
+    level: ffi::cubeb_log_level,
+    file: *const c_char,
+    line: u32,
+    message: *const c_char,
+) {
+    let (file, line) = message_location(file, line);
+    let message = CStr::from_ptr(message).to_string_lossy();
+    let (id, message) = split_log_id(&message);
+    // Event levels have to be constant.
+    match LogLevel::from(level) {
+        LogLevel::Verbose => tracing::trace!(target: "cubeb", id, file, line, "{}", message),
+        _ => tracing::debug!(target: "cubeb", id, file, line, "{}", message),
+    }
+}
+
+/// Forwards cubeb log messages, including the ones queued by the
+/// asynchronous logger, to `tracing` as events with the `cubeb` target and
+/// `id`/`file`/`line` fields. Normal messages are `DEBUG` events, verbose ones
+/// `TRACE`. `LogLevel::Disabled` stops forwarding.
+#[cfg(feature = "tracing")]
+pub fn forward_to_tracing(level: LogLevel) -> Result<()> {
+    set_log_message_callback(level, Some(tracing_callback))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -39,4 +221,183 @@ mod tests {
     fn test_logging_disabled_by_default() {
         assert!(!log_enabled());
     }
+
+    #[test]
+    fn test_log_id() {
+        let id = LogId::new("stream", 0x1234 as *const u8);
+        assert_eq!(id.name(), "stream");
+        assert_eq!(id.ptr(), 0x1234);
+        assert_eq!(id.to_string(), "stream@0x1234");
+        let message = format!("[{}] started", id);
+        assert_eq!(split_log_id(&message), (Some("stream@0x1234"), "started"));
+        assert_eq!(split_log_id("[not an id] msg"), (None, "[not an id] msg"));
+        assert_eq!(split_log_id("plain"), (None, "plain"));
+    }
+
+    #[cfg(feature = "log")]
+    mod log_crate {
+        use super::super::*;
+        use log_facade::{self, Level, LevelFilter, Log, Metadata, Record};
+        use std::ptr;
+        use std::sync::{Mutex, Once};
+
+        // Level, target, file, line and message of a record.
+        type Entry = (Level, String, Option<String>, Option<u32>, String);
+
+        struct Capture(Mutex<Vec<Entry>>);
+
+        impl Log for Capture {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+            fn log(&self, record: &Record) {
+                self.0.lock().unwrap().push((
+                    record.level(),
+                    record.target().to_owned(),
+                    record.file().map(str::to_owned),
+                    record.line(),
+                    record.args().to_string(),
+                ));
+            }
+            fn flush(&self) {}
+        }
+
+        static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+        static INIT: Once = Once::new();
+
+        #[test]
+        fn test_log_levels() {
+            assert_eq!(log_crate_level(LogLevel::Normal), Level::Debug);
+            assert_eq!(log_crate_level(LogLevel::Verbose), Level::Trace);
+            assert_eq!(LogLevel::from(LevelFilter::Trace), LogLevel::Verbose);
+            assert_eq!(LogLevel::from(LevelFilter::Debug), LogLevel::Normal);
+            assert_eq!(LogLevel::from(LevelFilter::Info), LogLevel::Disabled);
+            assert_eq!(LogLevel::from(LevelFilter::Off), LogLevel::Disabled);
+        }
+
+        #[test]
+        fn test_log_crate_callback() {
+            INIT.call_once(|| {
+                log_facade::set_logger(&CAPTURE).unwrap();
+                log_facade::set_max_level(LevelFilter::Trace);
+            });
+            unsafe {
+                log_crate_callback(
+                    ffi::CUBEB_LOG_VERBOSE,
+                    b"cubeb.c\0".as_ptr() as *const _,
+                    42,
+                    b"verbose message\0".as_ptr() as *const _,
+                );
+                log_crate_callback(
+                    ffi::CUBEB_LOG_NORMAL,
+                    ptr::null(),
+                    0,
+                    b"async message\0".as_ptr() as *const _,
+                );
+            }
+            let records = CAPTURE.0.lock().unwrap();
+            assert_eq!(
+                *records,
+                vec![
+                    (
+                        Level::Trace,
+                        "cubeb".to_owned(),
+                        Some("cubeb.c".to_owned()),
+                        Some(42),
+                        "verbose message".to_owned()
+                    ),
+                    (
+                        Level::Debug,
+                        "cubeb".to_owned(),
+                        None,
+                        None,
+                        "async message".to_owned()
+                    ),
+                ]
+            );
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    mod tracing_events {
+        use super::super::*;
+        use std::fmt;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{subscriber, Event, Level, Metadata, Subscriber};
+
+        #[derive(Default)]
+        struct Fields(Vec<(String, String)>);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                self.0
+                    .push((field.name().to_owned(), format!("{:?}", value)));
+            }
+        }
+
+        // Level, target and fields of an event.
+        type Entry = (Level, String, Vec<(String, String)>);
+
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<Entry>>>);
+
+        impl Subscriber for Capture {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+            fn new_span(&self, _: &Attributes) -> Id {
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event) {
+                let mut fields = Fields::default();
+                event.record(&mut fields);
+                self.0.lock().unwrap().push((
+                    *event.metadata().level(),
+                    event.metadata().target().to_owned(),
+                    fields.0,
+                ));
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        #[test]
+        fn test_tracing_levels() {
+            use tracing::level_filters::LevelFilter;
+            assert_eq!(LogLevel::from(LevelFilter::TRACE), LogLevel::Verbose);
+            assert_eq!(LogLevel::from(LevelFilter::DEBUG), LogLevel::Normal);
+            assert_eq!(LogLevel::from(LevelFilter::WARN), LogLevel::Disabled);
+        }
+
+        #[test]
+        fn test_tracing_callback() {
+            let capture = Capture::default();
+            subscriber::with_default(capture.clone(), || unsafe {
+                tracing_callback(
+                    ffi::CUBEB_LOG_NORMAL,
+                    b"cubeb_pulse.c\0".as_ptr() as *const _,
+                    7,
+                    b"[stream@0x10] normal message\0".as_ptr() as *const _,
+                );
+            });
+            let events = capture.0.lock().unwrap();
+            assert_eq!(events.len(), 1);
+            let (level, target, fields) = &events[0];
+            assert_eq!(*level, Level::DEBUG);
+            assert_eq!(target, "cubeb");
+            assert_eq!(
+                *fields,
+                vec![
+                    ("message".to_owned(), "normal message".to_owned()),
+                    ("id".to_owned(), "\"stream@0x10\"".to_owned()),
+                    ("file".to_owned(), "\"cubeb_pulse.c\"".to_owned()),
+                    ("line".to_owned(), "7".to_owned()),
+                ]
+            );
+        }
+    }
 }
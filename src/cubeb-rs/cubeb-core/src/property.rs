@@ -0,0 +1,137 @@
+// Copyright © 2026 Mozilla Foundation
+//
+// This program is made available under an ISC-style license.  See the
+// accompanying file LICENSE for details.
+
+use ffi;
+use std::{slice, str};
+use util::opt_bytes;
+
+/// Iterator over a list of key/value properties, as `(key, value)` pairs.
+/// Entries with a null key or value, or one that is not UTF-8, are skipped.
+#[derive(Clone, Debug)]
+pub struct Properties<'a> {
+    iter: slice::Iter<'a, ffi::cubeb_property>,
+}
+
+impl<'a> Properties<'a> {
+    /// # Safety
+    ///
+    /// `props` must be null or point to `count` valid entries that outlive
+    /// `'a`.
+    pub(crate) unsafe fn from_raw(props: *const ffi::cubeb_property, count: usize) -> Self {
+        let props = if props.is_null() {
+            &[]
+        } else {
+            slice::from_raw_parts(props, count)
+        };
+        Properties { iter: props.iter() }
+    }
+
+    /// Value of the property named `key`, if present.
+    pub fn get(self, key: &str) -> Option<&'a str> {
+        let mut props = self;
+        props.find(|&(k, _)| k == key).map(|(_, v)| v)
+    }
+}
+
+impl<'a> Iterator for Properties<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for prop in &mut self.iter {
+            let key = unsafe { opt_bytes(prop.key) };
+            let value = unsafe { opt_bytes(prop.value) };
+            if let (Some(k), Some(v)) = (key, value) {
+                if let (Ok(k), Ok(v)) = (str::from_utf8(k), str::from_utf8(v)) {
+                    return Some((k, v));
+                }
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Properties;
+    use ffi::cubeb_property;
+    use std::ffi::CString;
+    use std::ptr;
+    use {DEVICE_PROPERTY_BUS, DEVICE_PROPERTY_FORM_FACTOR};
+
+    #[test]
+    fn properties_empty() {
+        let props = unsafe { Properties::from_raw(ptr::null(), 0) };
+        assert_eq!(props.clone().count(), 0);
+        assert_eq!(props.get(DEVICE_PROPERTY_BUS), None);
+    }
+
+    #[test]
+    fn properties_skip_null_entries() {
+        let bus = CString::new(DEVICE_PROPERTY_BUS).unwrap();
+        let usb = CString::new("usb").unwrap();
+        let form_factor = CString::new(DEVICE_PROPERTY_FORM_FACTOR).unwrap();
+        let headset = CString::new("headset").unwrap();
+        let raw = [
+            cubeb_property {
+                key: bus.as_ptr(),
+                value: usb.as_ptr(),
+            },
+            cubeb_property {
+                key: form_factor.as_ptr(),
+                value: ptr::null(),
+            },
+            cubeb_property {
+                key: form_factor.as_ptr(),
+                value: headset.as_ptr(),
+            },
+        ];
+        let props = unsafe { Properties::from_raw(raw.as_ptr(), raw.len()) };
+
+        assert_eq!(
+            props.clone().collect::<Vec<_>>(),
+            vec![
+                (DEVICE_PROPERTY_BUS, "usb"),
+                (DEVICE_PROPERTY_FORM_FACTOR, "headset")
+            ]
+        );
+        assert_eq!(props.clone().get(DEVICE_PROPERTY_BUS), Some("usb"));
+        assert_eq!(
+            props.clone().get(DEVICE_PROPERTY_FORM_FACTOR),
+            Some("headset")
+        );
+        assert_eq!(props.get("unknown"), None);
+    }
+
+    #[test]
+    fn properties_skip_non_utf8_entries() {
+        let bus = CString::new(DEVICE_PROPERTY_BUS).unwrap();
+        let latin1 = CString::new(vec![b'u', 0xe9]).unwrap();
+        let usb = CString::new("usb").unwrap();
+        let raw = [
+            cubeb_property {
+                key: bus.as_ptr(),
+                value: latin1.as_ptr(),
+            },
+            cubeb_property {
+                key: latin1.as_ptr(),
+                value: usb.as_ptr(),
+            },
+            cubeb_property {
+                key: bus.as_ptr(),
+                value: usb.as_ptr(),
+            },
+        ];
+        let props = unsafe { Properties::from_raw(raw.as_ptr(), raw.len()) };
+
+        assert_eq!(
+            props.collect::<Vec<_>>(),
+            vec![(DEVICE_PROPERTY_BUS, "usb")]
+        );
+    }
+}
@@ -5,9 +5,9 @@
 
 use ffi;
 use std::ffi::CStr;
-use std::os::raw::c_void;
-use std::ptr;
-use {ChannelLayout, DeviceRef, Result, SampleFormat};
+use std::os::raw::{c_long, c_void};
+use std::{ptr, slice};
+use {ChannelLayout, DeviceRef, Dither, Properties, Result, SampleFormat};
 
 /// Stream states signaled via `state_callback`.
 #[derive(PartialEq, Eq, Clone, Debug, Copy)]
@@ -46,6 +46,38 @@ impl From<State> for ffi::cubeb_state {
     }
 }
 
+/// What a data callback did with the buffers it was given, and how the
+/// stream should proceed.
+///
+/// The raw callback return value is a frame count: the number of frames
+/// requested to continue, fewer to drain, and a negative value on error.
+#[derive(PartialEq, Eq, Clone, Debug, Copy)]
+pub enum CallbackResult {
+    /// The given number of frames were read or written. Output frames past
+    /// that count are silence. The stream keeps running.
+    Continue(usize),
+    /// The given number of frames were read or written, and are the last
+    /// ones. Output streams play them out then report `State::Drained`;
+    /// input-only streams stop and report `State::Drained` right away.
+    Drain(usize),
+    /// The stream reports `State::Error` and stops.
+    Error,
+}
+
+impl CallbackResult {
+    /// Interprets `ret`, the value returned by a data callback asked for
+    /// `nframes` frames.
+    pub fn from_raw(ret: c_long, nframes: c_long) -> CallbackResult {
+        if ret < 0 {
+            CallbackResult::Error
+        } else if ret < nframes {
+            CallbackResult::Drain(ret as usize)
+        } else {
+            CallbackResult::Continue(nframes as usize)
+        }
+    }
+}
+
 bitflags! {
     /// Miscellaneous stream preferences.
     pub struct StreamPrefs: ffi::cubeb_stream_prefs {
@@ -53,6 +85,161 @@ bitflags! {
         const LOOPBACK = ffi::CUBEB_STREAM_PREF_LOOPBACK;
         const DISABLE_DEVICE_SWITCHING = ffi::CUBEB_STREAM_PREF_DISABLE_DEVICE_SWITCHING;
         const VOICE = ffi::CUBEB_STREAM_PREF_VOICE;
+        const DRIFT_CORRECTION = ffi::CUBEB_STREAM_PREF_DRIFT_CORRECTION;
+    }
+}
+
+/// Routes a channel of an input device to a channel of an input stream,
+/// see `StreamOptionsRef::input_channel_map`.
+#[derive(PartialEq, Clone, Debug, Copy)]
+pub struct ChannelRoute {
+    /// Index of the channel in the device frames.
+    pub device_channel: u32,
+    /// Index of the channel in the stream frames.
+    pub stream_channel: u32,
+    /// Linear gain applied to the device channel.
+    pub gain: f32,
+}
+
+impl ChannelRoute {
+    /// Routes `device_channel` to `stream_channel` unchanged.
+    pub fn new(device_channel: u32, stream_channel: u32) -> ChannelRoute {
+        ChannelRoute {
+            device_channel,
+            stream_channel,
+            gain: 1.0,
+        }
+    }
+}
+
+impl From<ffi::cubeb_channel_route> for ChannelRoute {
+    fn from(x: ffi::cubeb_channel_route) -> Self {
+        ChannelRoute {
+            device_channel: x.device_channel,
+            stream_channel: x.stream_channel,
+            gain: x.gain,
+        }
+    }
+}
+
+impl From<ChannelRoute> for ffi::cubeb_channel_route {
+    fn from(x: ChannelRoute) -> Self {
+        ffi::cubeb_channel_route {
+            device_channel: x.device_channel,
+            stream_channel: x.stream_channel,
+            gain: x.gain,
+        }
+    }
+}
+
+/// Gain of a speaker of an output stream in a speaker of its device, see
+/// `MixMatrix::custom`.
+#[derive(PartialEq, Clone, Debug, Copy)]
+pub struct MixCoefficient {
+    /// Speaker of the output device, a single channel.
+    pub output: ChannelLayout,
+    /// Speaker of the stream layout, a single channel.
+    pub input: ChannelLayout,
+    /// Linear gain.
+    pub gain: f32,
+}
+
+impl MixCoefficient {
+    pub fn new(output: ChannelLayout, input: ChannelLayout, gain: f32) -> MixCoefficient {
+        MixCoefficient {
+            output,
+            input,
+            gain,
+        }
+    }
+}
+
+impl From<ffi::cubeb_mix_coefficient> for MixCoefficient {
+    fn from(x: ffi::cubeb_mix_coefficient) -> Self {
+        MixCoefficient {
+            output: ChannelLayout::from(x.output),
+            input: ChannelLayout::from(x.input),
+            gain: x.gain,
+        }
+    }
+}
+
+impl From<MixCoefficient> for ffi::cubeb_mix_coefficient {
+    fn from(x: MixCoefficient) -> Self {
+        ffi::cubeb_mix_coefficient {
+            output: x.output.into(),
+            input: x.input.into(),
+            gain: x.gain,
+        }
+    }
+}
+
+/// How an output stream is mixed to the speakers of its device, when they
+/// differ from its layout, see `StreamOptionsRef::mix_matrix`.
+///
+/// Speakers missing from the device are folded into their neighbours, at
+/// the levels given here, unless `coefficients` gives the full matrix.
+#[derive(PartialEq, Clone, Debug)]
+pub struct MixMatrix {
+    /// Gain of the front center channel in the front left and right
+    /// speakers.
+    pub center_mix_level: f32,
+    /// Gain of the surround channels in the front speakers.
+    pub surround_mix_level: f32,
+    /// Gain of the LFE channel in the front center speaker, 3 dB lower in
+    /// the front left and right speakers. 0 drops it on devices without LFE
+    /// speaker.
+    pub lfe_mix_level: f32,
+    /// Scale the matrix for no speaker to clip, whatever the sample format.
+    /// Integer formats always are.
+    pub normalize: bool,
+    /// Custom matrix, replacing the levels. Pairs of speakers not listed
+    /// are not mixed.
+    pub coefficients: Option<Vec<MixCoefficient>>,
+}
+
+impl MixMatrix {
+    /// The downmixing of ITU-R BS.775: center and surround channels at
+    /// -3 dB, without LFE.
+    pub fn itu_bs775() -> MixMatrix {
+        MixMatrix::default().without_lfe()
+    }
+
+    /// A matrix mixing each pair of speakers of `coefficients`.
+    pub fn custom(coefficients: Vec<MixCoefficient>) -> MixMatrix {
+        MixMatrix {
+            coefficients: Some(coefficients),
+            ..MixMatrix::default()
+        }
+    }
+
+    /// The same matrix, dropping the LFE channel.
+    pub fn without_lfe(self) -> MixMatrix {
+        MixMatrix {
+            lfe_mix_level: 0.0,
+            ..self
+        }
+    }
+
+    /// The same matrix, scaled for no speaker to clip.
+    pub fn normalized(self) -> MixMatrix {
+        MixMatrix {
+            normalize: true,
+            ..self
+        }
+    }
+}
+
+impl Default for MixMatrix {
+    /// Center and surround channels at -3 dB, LFE at 0 dB.
+    fn default() -> MixMatrix {
+        MixMatrix {
+            center_mix_level: std::f32::consts::FRAC_1_SQRT_2,
+            surround_mix_level: std::f32::consts::FRAC_1_SQRT_2,
+            lfe_mix_level: 1.0,
+            normalize: false,
+            coefficients: None,
+        }
     }
 }
 
@@ -96,6 +283,71 @@ impl StreamParamsRef {
     }
 }
 
+ffi_type_stack! {
+    /// Optional stream initialization parameters, see
+    /// `ContextRef::stream_init_with_options`.
+    type CType = ffi::cubeb_stream_options;
+    #[derive(Debug)]
+    pub struct StreamOptions;
+    pub struct StreamOptionsRef;
+}
+
+impl StreamOptionsRef {
+    fn get_ref(&self) -> &ffi::cubeb_stream_options {
+        unsafe { &*self.as_ptr() }
+    }
+
+    /// Caller supplied properties. Keys are backend specific.
+    pub fn properties(&self) -> Properties<'_> {
+        let options = self.get_ref();
+        unsafe { Properties::from_raw(options.properties, options.property_count) }
+    }
+
+    /// Value of the property named `key`, if the caller provided it.
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties().get(key)
+    }
+
+    /// Caller supplied routing of the input device channels, empty for the
+    /// backend's default. Stream channels fed by several device channels
+    /// get the average of their contributions.
+    pub fn input_channel_map(&self) -> Vec<ChannelRoute> {
+        let options = self.get_ref();
+        if options.input_channel_map.is_null() {
+            return Vec::new();
+        }
+        let routes = unsafe {
+            slice::from_raw_parts(options.input_channel_map, options.input_channel_map_count)
+        };
+        routes.iter().map(|&r| ChannelRoute::from(r)).collect()
+    }
+
+    /// Caller supplied mixing to the speakers of the output device, `None`
+    /// for the backend's default.
+    pub fn mix_matrix(&self) -> Option<MixMatrix> {
+        let matrix = unsafe { self.get_ref().mix_matrix.as_ref()? };
+        let coefficients = if matrix.coefficients.is_null() {
+            None
+        } else {
+            let coefficients =
+                unsafe { slice::from_raw_parts(matrix.coefficients, matrix.coefficient_count) };
+            Some(coefficients.iter().map(|&c| c.into()).collect())
+        };
+        Some(MixMatrix {
+            center_mix_level: matrix.center_mix_level,
+            surround_mix_level: matrix.surround_mix_level,
+            lfe_mix_level: matrix.lfe_mix_level,
+            normalize: matrix.normalize != 0,
+            coefficients,
+        })
+    }
+
+    /// Dither applied when the backend requantizes samples to 16 bits.
+    pub fn dither(&self) -> Dither {
+        Dither::from(self.get_ref().dither)
+    }
+}
+
 unsafe fn wrapped_cubeb_stream_destroy(stream: *mut ffi::cubeb_stream) {
     ffi::cubeb_stream_stop(stream);
     ffi::cubeb_stream_destroy(stream);
@@ -205,8 +457,30 @@ impl StreamRef {
 
 #[cfg(test)]
 mod tests {
-    use std::mem;
-    use {StreamParams, StreamParamsRef, StreamPrefs};
+    use std::{mem, ptr};
+    use {
+        CallbackResult, ChannelLayout, ChannelRoute, Dither, MixCoefficient, MixMatrix,
+        StreamOptionsRef, StreamParams, StreamParamsRef, StreamPrefs,
+    };
+
+    #[test]
+    fn callback_result_from_raw() {
+        assert_eq!(
+            CallbackResult::from_raw(256, 256),
+            CallbackResult::Continue(256)
+        );
+        // Returning more than requested is clamped.
+        assert_eq!(
+            CallbackResult::from_raw(300, 256),
+            CallbackResult::Continue(256)
+        );
+        assert_eq!(
+            CallbackResult::from_raw(100, 256),
+            CallbackResult::Drain(100)
+        );
+        assert_eq!(CallbackResult::from_raw(0, 256), CallbackResult::Drain(0));
+        assert_eq!(CallbackResult::from_raw(-1, 256), CallbackResult::Error);
+    }
 
     #[test]
     fn stream_params_default() {
@@ -319,6 +593,132 @@ mod tests {
         assert_eq!(params.prefs(), StreamPrefs::LOOPBACK);
     }
 
+    #[test]
+    fn stream_options_raw_properties() {
+        let mut raw = super::ffi::cubeb_stream_options::default();
+        {
+            let options = unsafe { StreamOptionsRef::from_ptr(&mut raw) };
+            assert_eq!(options.properties().count(), 0);
+            assert_eq!(options.property("media.role"), None);
+        }
+
+        let props = [
+            super::ffi::cubeb_property {
+                key: b"media.role\0".as_ptr() as *const _,
+                value: b"phone\0".as_ptr() as *const _,
+            },
+            super::ffi::cubeb_property {
+                key: b"application.icon_name\0".as_ptr() as *const _,
+                value: ptr::null(),
+            },
+        ];
+        raw.properties = props.as_ptr();
+        raw.property_count = props.len();
+        let options = unsafe { StreamOptionsRef::from_ptr(&mut raw) };
+        assert_eq!(
+            options.properties().collect::<Vec<_>>(),
+            vec![("media.role", "phone")]
+        );
+        assert_eq!(options.property("media.role"), Some("phone"));
+        assert_eq!(options.property("application.icon_name"), None);
+    }
+
+    #[test]
+    fn stream_options_raw_input_channel_map() {
+        let mut raw = super::ffi::cubeb_stream_options::default();
+        {
+            let options = unsafe { StreamOptionsRef::from_ptr(&mut raw) };
+            assert!(options.input_channel_map().is_empty());
+        }
+
+        let map = [
+            super::ffi::cubeb_channel_route {
+                device_channel: 2,
+                stream_channel: 0,
+                gain: 1.0,
+            },
+            super::ffi::cubeb_channel_route {
+                device_channel: 3,
+                stream_channel: 1,
+                gain: 0.5,
+            },
+        ];
+        raw.input_channel_map = map.as_ptr();
+        raw.input_channel_map_count = map.len();
+        let options = unsafe { StreamOptionsRef::from_ptr(&mut raw) };
+        assert_eq!(
+            options.input_channel_map(),
+            [
+                ChannelRoute::new(2, 0),
+                ChannelRoute {
+                    device_channel: 3,
+                    stream_channel: 1,
+                    gain: 0.5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn stream_options_raw_dither() {
+        let mut raw = super::ffi::cubeb_stream_options::default();
+        {
+            let options = unsafe { StreamOptionsRef::from_ptr(&mut raw) };
+            assert_eq!(options.dither(), Dither::None);
+        }
+        raw.dither = super::ffi::CUBEB_DITHER_NOISE_SHAPED;
+        let options = unsafe { StreamOptionsRef::from_ptr(&mut raw) };
+        assert_eq!(options.dither(), Dither::NoiseShaped);
+    }
+
+    #[test]
+    fn stream_options_raw_mix_matrix() {
+        let mut raw = super::ffi::cubeb_stream_options::default();
+        {
+            let options = unsafe { StreamOptionsRef::from_ptr(&mut raw) };
+            assert_eq!(options.mix_matrix(), None);
+        }
+
+        let mut matrix = super::ffi::cubeb_mix_matrix {
+            center_mix_level: 0.5,
+            surround_mix_level: 0.25,
+            lfe_mix_level: 0.0,
+            normalize: 1,
+            coefficients: ptr::null(),
+            coefficient_count: 0,
+        };
+        raw.mix_matrix = &matrix;
+        {
+            let options = unsafe { StreamOptionsRef::from_ptr(&mut raw) };
+            assert_eq!(
+                options.mix_matrix(),
+                Some(MixMatrix {
+                    center_mix_level: 0.5,
+                    surround_mix_level: 0.25,
+                    ..MixMatrix::itu_bs775().normalized()
+                })
+            );
+        }
+
+        let coefficients = [super::ffi::cubeb_mix_coefficient {
+            output: super::ffi::CHANNEL_FRONT_CENTER,
+            input: super::ffi::CHANNEL_FRONT_LEFT,
+            gain: 0.5,
+        }];
+        matrix.coefficients = coefficients.as_ptr();
+        matrix.coefficient_count = coefficients.len();
+        raw.mix_matrix = &matrix;
+        let options = unsafe { StreamOptionsRef::from_ptr(&mut raw) };
+        assert_eq!(
+            options.mix_matrix().unwrap().coefficients,
+            Some(vec![MixCoefficient::new(
+                ChannelLayout::FRONT_CENTER,
+                ChannelLayout::FRONT_LEFT,
+                0.5
+            )])
+        );
+    }
+
     #[test]
     fn stream_params_stream_params_ref_ptr_match() {
         let params = StreamParams::default();
@@ -6,10 +6,28 @@
 use callbacks::{cubeb_data_callback, cubeb_state_callback};
 use device::cubeb_devid;
 use std::os::raw::{c_char, c_int, c_uint, c_void};
-use stream::{cubeb_stream, cubeb_stream_params};
+use stream::{cubeb_stream, cubeb_stream_options, cubeb_stream_params};
 
 pub enum cubeb {}
 
+cubeb_enum! {
+    pub enum cubeb_capabilities {
+        CUBEB_CAPABILITY_NONE = 0x00,
+        CUBEB_CAPABILITY_LOOPBACK = 0x01,
+        CUBEB_CAPABILITY_DEVICE_SWITCHING = 0x02,
+        CUBEB_CAPABILITY_STREAM_VOLUME = 0x04,
+        CUBEB_CAPABILITY_INPUT_LATENCY = 0x08,
+        CUBEB_CAPABILITY_DEVICE_CHANGED_CALLBACK = 0x10,
+        CUBEB_CAPABILITY_VOICE_PROCESSING = 0x20,
+        CUBEB_CAPABILITY_INPUT_CHANNEL_MAP = 0x40,
+        CUBEB_CAPABILITY_MIX_MATRIX = 0x80,
+        CUBEB_CAPABILITY_FORMAT_S16LE = 0x100,
+        CUBEB_CAPABILITY_FORMAT_S16BE = 0x200,
+        CUBEB_CAPABILITY_FORMAT_FLOAT32LE = 0x400,
+        CUBEB_CAPABILITY_FORMAT_FLOAT32BE = 0x800,
+    }
+}
+
 extern "C" {
     pub fn cubeb_init(
         context: *mut *mut cubeb,
@@ -24,6 +42,10 @@ extern "C" {
         latency_frames: *mut c_uint,
     ) -> c_int;
     pub fn cubeb_get_preferred_sample_rate(context: *mut cubeb, rate: *mut c_uint) -> c_int;
+    pub fn cubeb_get_capabilities(
+        context: *mut cubeb,
+        capabilities: *mut cubeb_capabilities,
+    ) -> c_int;
     pub fn cubeb_destroy(context: *mut cubeb);
     pub fn cubeb_stream_init(
         context: *mut cubeb,
@@ -38,4 +60,18 @@ extern "C" {
         state_callback: cubeb_state_callback,
         user_ptr: *mut c_void,
     ) -> c_int;
+    pub fn cubeb_stream_init_with_options(
+        context: *mut cubeb,
+        stream: *mut *mut cubeb_stream,
+        stream_name: *const c_char,
+        input_device: cubeb_devid,
+        input_stream_params: *mut cubeb_stream_params,
+        output_device: cubeb_devid,
+        output_stream_params: *mut cubeb_stream_params,
+        options: *const cubeb_stream_options,
+        latency_frames: c_uint,
+        data_callback: cubeb_data_callback,
+        state_callback: cubeb_state_callback,
+        user_ptr: *mut c_void,
+    ) -> c_int;
 }
@@ -7,6 +7,7 @@ use callbacks::cubeb_device_collection_changed_callback;
 use context::cubeb;
 use std::os::raw::{c_char, c_int, c_uint, c_void};
 use std::{fmt, mem};
+use stream::cubeb_property;
 
 cubeb_enum! {
     pub enum cubeb_device_fmt {
@@ -103,6 +104,13 @@ impl fmt::Debug for cubeb_device_collection {
     }
 }
 
+pub const CUBEB_DEVICE_PROPERTY_FORM_FACTOR: &str = "form_factor";
+pub const CUBEB_DEVICE_PROPERTY_BUS: &str = "bus";
+pub const CUBEB_DEVICE_PROPERTY_ICON_NAME: &str = "icon_name";
+pub const CUBEB_DEVICE_PROPERTY_ACTIVE_PORT: &str = "active_port";
+pub const CUBEB_DEVICE_PROPERTY_ACTIVE_PORT_DESCRIPTION: &str = "active_port_description";
+pub const CUBEB_DEVICE_PROPERTY_PORT_PREFIX: &str = "port.";
+
 #[repr(C)]
 pub struct cubeb_device_info {
     pub devid: cubeb_devid,
@@ -165,6 +173,13 @@ extern "C" {
         context: *mut cubeb,
         collection: *mut cubeb_device_collection,
     ) -> c_int;
+    pub fn cubeb_device_collection_get_properties(
+        context: *mut cubeb,
+        collection: *mut cubeb_device_collection,
+        index: usize,
+        properties: *mut *const cubeb_property,
+        count: *mut usize,
+    ) -> c_int;
     pub fn cubeb_register_device_collection_changed(
         context: *mut cubeb,
         devtype: cubeb_device_type,
@@ -14,15 +14,28 @@ cubeb_enum! {
 }
 
 pub type cubeb_log_callback = Option<unsafe extern "C" fn(*const c_char, ...)>;
+pub type cubeb_log_message_callback = Option<
+    unsafe extern "C" fn(
+        level: cubeb_log_level,
+        file: *const c_char,
+        line: u32,
+        message: *const c_char,
+    ),
+>;
 
 extern "C" {
     pub fn cubeb_set_log_callback(
         log_level: cubeb_log_level,
         log_callback: cubeb_log_callback,
     ) -> c_int;
+    pub fn cubeb_set_log_message_callback(
+        log_level: cubeb_log_level,
+        log_callback: cubeb_log_message_callback,
+    ) -> c_int;
 
     pub static g_cubeb_log_level: cubeb_log_level;
     pub static g_cubeb_log_callback: cubeb_log_callback;
+    pub static g_cubeb_log_message_callback: cubeb_log_message_callback;
 
     pub fn cubeb_async_log_reset_threads(_: c_void);
     pub fn cubeb_async_log(msg: *const c_char, ...);
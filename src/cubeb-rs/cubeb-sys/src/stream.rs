@@ -4,7 +4,7 @@
 // accompanying file LICENSE for details.
 
 use callbacks::cubeb_device_changed_callback;
-use channel::cubeb_channel_layout;
+use channel::{cubeb_channel, cubeb_channel_layout};
 use device::cubeb_device;
 use format::cubeb_sample_format;
 use std::os::raw::{c_char, c_float, c_int, c_uint, c_void};
@@ -16,6 +16,15 @@ cubeb_enum! {
         CUBEB_STREAM_PREF_LOOPBACK = 0x01,
         CUBEB_STREAM_PREF_DISABLE_DEVICE_SWITCHING = 0x02,
         CUBEB_STREAM_PREF_VOICE = 0x04,
+        CUBEB_STREAM_PREF_DRIFT_CORRECTION = 0x40,
+    }
+}
+
+cubeb_enum! {
+    pub enum cubeb_dither {
+        CUBEB_DITHER_NONE,
+        CUBEB_DITHER_TPDF,
+        CUBEB_DITHER_NOISE_SHAPED,
     }
 }
 
@@ -30,6 +39,40 @@ cubeb_enum! {
 
 pub enum cubeb_stream {}
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct cubeb_property {
+    pub key: *const c_char,
+    pub value: *const c_char,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct cubeb_channel_route {
+    pub device_channel: c_uint,
+    pub stream_channel: c_uint,
+    pub gain: c_float,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct cubeb_mix_coefficient {
+    pub output: cubeb_channel,
+    pub input: cubeb_channel,
+    pub gain: c_float,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct cubeb_mix_matrix {
+    pub center_mix_level: c_float,
+    pub surround_mix_level: c_float,
+    pub lfe_mix_level: c_float,
+    pub normalize: c_int,
+    pub coefficients: *const cubeb_mix_coefficient,
+    pub coefficient_count: usize,
+}
+
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct cubeb_stream_params {
@@ -59,6 +102,42 @@ impl fmt::Debug for cubeb_stream_params {
     }
 }
 
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct cubeb_stream_options {
+    pub size: usize,
+    pub properties: *const cubeb_property,
+    pub property_count: usize,
+    pub input_channel_map: *const cubeb_channel_route,
+    pub input_channel_map_count: usize,
+    pub mix_matrix: *const cubeb_mix_matrix,
+    pub dither: cubeb_dither,
+}
+
+impl Default for cubeb_stream_options {
+    fn default() -> Self {
+        cubeb_stream_options {
+            size: mem::size_of::<cubeb_stream_options>(),
+            ..unsafe { mem::zeroed() }
+        }
+    }
+}
+
+// Explicit Debug impl to work around bug in ctest
+impl fmt::Debug for cubeb_stream_options {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("cubeb_stream_options")
+            .field("size", &self.size)
+            .field("properties", &self.properties)
+            .field("property_count", &self.property_count)
+            .field("input_channel_map", &self.input_channel_map)
+            .field("input_channel_map_count", &self.input_channel_map_count)
+            .field("mix_matrix", &self.mix_matrix)
+            .field("dither", &self.dither)
+            .finish()
+    }
+}
+
 extern "C" {
     pub fn cubeb_stream_destroy(stream: *mut cubeb_stream);
     pub fn cubeb_stream_start(stream: *mut cubeb_stream) -> c_int;
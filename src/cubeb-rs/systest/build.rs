@@ -36,6 +36,10 @@ fn main() {
     // g_cubeb_log_* globals aren't visible via cubeb.h, skip them.
     cfg.skip_static(|s| s.starts_with("g_cubeb_log_"));
 
+    // Device property keys are string macros, which can't be compared as
+    // constants.
+    cfg.skip_const(|s| s.starts_with("CUBEB_DEVICE_PROPERTY_"));
+
     // Generate the tests, passing the path to the `*-sys` library as well as
     // the module to generate.
     cfg.generate("../cubeb-sys/src/lib.rs", "all.rs");
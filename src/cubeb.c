@@ -8,6 +8,7 @@
 #include "cubeb/cubeb.h"
 #include "cubeb-internal.h"
 #include <assert.h>
+#include <math.h>
 #include <stddef.h>
 #include <stdlib.h>
 #include <string.h>
@@ -34,6 +35,10 @@ pulse_init(cubeb ** context, char const * context_name);
 #if defined(USE_PULSE_RUST)
 int
 pulse_rust_init(cubeb ** contet, char const * context_name);
+void
+pulse_rust_set_logging(cubeb_log_level log_level,
+                       cubeb_log_callback log_callback,
+                       cubeb_log_message_callback message_callback);
 #endif
 #if defined(USE_JACK)
 int
@@ -50,6 +55,10 @@ audiounit_init(cubeb ** context, char const * context_name);
 #if defined(USE_AUDIOUNIT_RUST)
 int
 audiounit_rust_init(cubeb ** contet, char const * context_name);
+void
+audiounit_rust_set_logging(cubeb_log_level log_level,
+                           cubeb_log_callback log_callback,
+                           cubeb_log_message_callback message_callback);
 #endif
 #if defined(USE_WINMM)
 int
@@ -132,6 +141,120 @@ validate_stream_params(cubeb_stream_params * input_stream_params,
   return CUBEB_ERROR_INVALID_FORMAT;
 }
 
+static int
+validate_channel_map(cubeb * context, cubeb_stream_params * input_stream_params,
+                     cubeb_stream_options const * options)
+{
+  if (!options->input_channel_map) {
+    return CUBEB_OK;
+  }
+  if (!input_stream_params || options->input_channel_map_count == 0) {
+    return CUBEB_ERROR_INVALID_PARAMETER;
+  }
+  for (size_t i = 0; i < options->input_channel_map_count; i++) {
+    if (options->input_channel_map[i].stream_channel >=
+            input_stream_params->channels ||
+        !isfinite(options->input_channel_map[i].gain)) {
+      return CUBEB_ERROR_INVALID_PARAMETER;
+    }
+  }
+  cubeb_capabilities capabilities = CUBEB_CAPABILITY_NONE;
+  if (!context->ops->get_capabilities ||
+      context->ops->get_capabilities(context, &capabilities) != CUBEB_OK ||
+      !(capabilities & CUBEB_CAPABILITY_INPUT_CHANNEL_MAP)) {
+    return CUBEB_ERROR_NOT_SUPPORTED;
+  }
+  return CUBEB_OK;
+}
+
+static int
+valid_mix_level(float level)
+{
+  return isfinite(level) && level >= 0;
+}
+
+static int
+valid_speaker(cubeb_channel channel)
+{
+  return channel != CHANNEL_UNKNOWN && channel <= CHANNEL_TOP_BACK_RIGHT &&
+         (channel & (channel - 1)) == 0;
+}
+
+static int
+validate_mix_matrix(cubeb * context, cubeb_stream_params * output_stream_params,
+                    cubeb_stream_options const * options)
+{
+  cubeb_mix_matrix const * matrix = options->mix_matrix;
+  if (!matrix) {
+    return CUBEB_OK;
+  }
+  if (!output_stream_params) {
+    return CUBEB_ERROR_INVALID_PARAMETER;
+  }
+  if (!valid_mix_level(matrix->center_mix_level) ||
+      !valid_mix_level(matrix->surround_mix_level) ||
+      !valid_mix_level(matrix->lfe_mix_level)) {
+    return CUBEB_ERROR_INVALID_PARAMETER;
+  }
+  if (matrix->coefficients) {
+    if (matrix->coefficient_count == 0) {
+      return CUBEB_ERROR_INVALID_PARAMETER;
+    }
+    for (size_t i = 0; i < matrix->coefficient_count; i++) {
+      if (!valid_speaker(matrix->coefficients[i].output) ||
+          !valid_speaker(matrix->coefficients[i].input) ||
+          !isfinite(matrix->coefficients[i].gain)) {
+        return CUBEB_ERROR_INVALID_PARAMETER;
+      }
+    }
+  }
+  cubeb_capabilities capabilities = CUBEB_CAPABILITY_NONE;
+  if (!context->ops->get_capabilities ||
+      context->ops->get_capabilities(context, &capabilities) != CUBEB_OK ||
+      !(capabilities & CUBEB_CAPABILITY_MIX_MATRIX)) {
+    return CUBEB_ERROR_NOT_SUPPORTED;
+  }
+  return CUBEB_OK;
+}
+
+static int
+validate_dither(cubeb_stream_options const * options)
+{
+  switch (options->dither) {
+  case CUBEB_DITHER_NONE:
+  case CUBEB_DITHER_TPDF:
+  case CUBEB_DITHER_NOISE_SHAPED:
+    return CUBEB_OK;
+  }
+  return CUBEB_ERROR_INVALID_PARAMETER;
+}
+
+// Copies `options`, of any version, into `copy`. The fields the caller does
+// not know of are left zeroed, that is unset.
+static int
+copy_stream_options(cubeb_stream_options const * options,
+                    cubeb_stream_options * copy)
+{
+  memset(copy, 0, sizeof(*copy));
+  if (options) {
+    if (options->size < sizeof(options->size)) {
+      return CUBEB_ERROR_INVALID_PARAMETER;
+    }
+    memcpy(copy, options,
+           options->size < sizeof(*copy) ? options->size : sizeof(*copy));
+  }
+  copy->size = sizeof(*copy);
+  return CUBEB_OK;
+}
+
+static int
+has_stream_options(cubeb_stream_options const * options)
+{
+  return (options->properties && options->property_count) ||
+         options->input_channel_map || options->mix_matrix ||
+         options->dither != CUBEB_DITHER_NONE;
+}
+
 static int
 validate_latency(int latency)
 {
@@ -343,6 +466,20 @@ cubeb_get_preferred_sample_rate(cubeb * context, uint32_t * rate)
   return context->ops->get_preferred_sample_rate(context, rate);
 }
 
+int
+cubeb_get_capabilities(cubeb * context, cubeb_capabilities * capabilities)
+{
+  if (!context || !capabilities) {
+    return CUBEB_ERROR_INVALID_PARAMETER;
+  }
+
+  if (!context->ops->get_capabilities) {
+    return CUBEB_ERROR_NOT_SUPPORTED;
+  }
+
+  return context->ops->get_capabilities(context, capabilities);
+}
+
 void
 cubeb_destroy(cubeb * context)
 {
@@ -361,23 +498,55 @@ cubeb_stream_init(cubeb * context, cubeb_stream ** stream,
                   cubeb_stream_params * output_stream_params,
                   unsigned int latency, cubeb_data_callback data_callback,
                   cubeb_state_callback state_callback, void * user_ptr)
+{
+  return cubeb_stream_init_with_options(
+      context, stream, stream_name, input_device, input_stream_params,
+      output_device, output_stream_params, NULL, latency, data_callback,
+      state_callback, user_ptr);
+}
+
+int
+cubeb_stream_init_with_options(
+    cubeb * context, cubeb_stream ** stream, char const * stream_name,
+    cubeb_devid input_device, cubeb_stream_params * input_stream_params,
+    cubeb_devid output_device, cubeb_stream_params * output_stream_params,
+    cubeb_stream_options const * options, unsigned int latency,
+    cubeb_data_callback data_callback, cubeb_state_callback state_callback,
+    void * user_ptr)
 {
   int r;
+  cubeb_stream_options stream_options;
 
   if (!context || !stream || !data_callback || !state_callback) {
     return CUBEB_ERROR_INVALID_PARAMETER;
   }
 
-  if ((r = validate_stream_params(input_stream_params, output_stream_params)) !=
+  if ((r = copy_stream_options(options, &stream_options)) != CUBEB_OK ||
+      (r = validate_stream_params(input_stream_params, output_stream_params)) !=
           CUBEB_OK ||
+      (r = validate_channel_map(context, input_stream_params,
+                                &stream_options)) != CUBEB_OK ||
+      (r = validate_mix_matrix(context, output_stream_params,
+                               &stream_options)) != CUBEB_OK ||
+      (r = validate_dither(&stream_options)) != CUBEB_OK ||
       (r = validate_latency(latency)) != CUBEB_OK) {
     return r;
   }
 
-  r = context->ops->stream_init(context, stream, stream_name, input_device,
-                                input_stream_params, output_device,
-                                output_stream_params, latency, data_callback,
-                                state_callback, user_ptr);
+  if (has_stream_options(&stream_options)) {
+    if (!context->ops->stream_init_with_options) {
+      return CUBEB_ERROR_NOT_SUPPORTED;
+    }
+    r = context->ops->stream_init_with_options(
+        context, stream, stream_name, input_device, input_stream_params,
+        output_device, output_stream_params, &stream_options, latency,
+        data_callback, state_callback, user_ptr);
+  } else {
+    r = context->ops->stream_init(context, stream, stream_name, input_device,
+                                  input_stream_params, output_device,
+                                  output_stream_params, latency, data_callback,
+                                  state_callback, user_ptr);
+  }
 
   if (r == CUBEB_ERROR_INVALID_FORMAT) {
     LOG("Invalid format, %p %p %d %d", output_stream_params,
@@ -625,6 +794,23 @@ log_device(cubeb_device_info * device_info)
       device_info->latency_hi);
 }
 
+static void
+log_device_properties(cubeb * context, cubeb_device_collection * collection,
+                      size_t index)
+{
+  cubeb_property const * properties = NULL;
+  size_t count = 0;
+
+  if (cubeb_device_collection_get_properties(context, collection, index,
+                                             &properties, &count) != CUBEB_OK) {
+    return;
+  }
+
+  for (size_t i = 0; i < count; i++) {
+    LOG("\t%s:\t\"%s\"", properties[i].key, properties[i].value);
+  }
+}
+
 int
 cubeb_enumerate_devices(cubeb * context, cubeb_device_type devtype,
                         cubeb_device_collection * collection)
@@ -642,6 +828,7 @@ cubeb_enumerate_devices(cubeb * context, cubeb_device_type devtype,
   if (g_cubeb_log_callback) {
     for (size_t i = 0; i < collection->count; i++) {
       log_device(&collection->device[i]);
+      log_device_properties(context, collection, i);
     }
   }
 
@@ -672,6 +859,24 @@ cubeb_device_collection_destroy(cubeb * context,
   return r;
 }
 
+int
+cubeb_device_collection_get_properties(cubeb * context,
+                                       cubeb_device_collection * collection,
+                                       size_t index,
+                                       cubeb_property const ** properties,
+                                       size_t * count)
+{
+  if (context == NULL || collection == NULL || properties == NULL ||
+      count == NULL || index >= collection->count)
+    return CUBEB_ERROR_INVALID_PARAMETER;
+
+  if (!context->ops->device_collection_get_properties)
+    return CUBEB_ERROR_NOT_SUPPORTED;
+
+  return context->ops->device_collection_get_properties(
+      context, collection, index, properties, count);
+}
+
 int
 cubeb_register_device_collection_changed(
     cubeb * context, cubeb_device_type devtype,
@@ -689,6 +894,20 @@ cubeb_register_device_collection_changed(
                                                           callback, user_ptr);
 }
 
+/* The Rust backends have their own logger, which follows the C one. */
+static void
+set_rust_logging(void)
+{
+#if defined(USE_PULSE_RUST)
+  pulse_rust_set_logging(g_cubeb_log_level, g_cubeb_log_callback,
+                         g_cubeb_log_message_callback);
+#endif
+#if defined(USE_AUDIOUNIT_RUST)
+  audiounit_rust_set_logging(g_cubeb_log_level, g_cubeb_log_callback,
+                             g_cubeb_log_message_callback);
+#endif
+}
+
 int
 cubeb_set_log_callback(cubeb_log_level log_level,
                        cubeb_log_callback log_callback)
@@ -705,8 +924,10 @@ cubeb_set_log_callback(cubeb_log_level log_level,
     return CUBEB_ERROR_NOT_SUPPORTED;
   }
 
+  g_cubeb_log_message_callback = NULL;
   g_cubeb_log_callback = log_callback;
   g_cubeb_log_level = log_level;
+  set_rust_logging();
 
   // Logging a message here allows to initialize the asynchronous logger from a
   // thread that is not the audio rendering thread, and especially to not
@@ -719,3 +940,32 @@ cubeb_set_log_callback(cubeb_log_level log_level,
 
   return CUBEB_OK;
 }
+
+int
+cubeb_set_log_message_callback(cubeb_log_level log_level,
+                               cubeb_log_message_callback log_callback)
+{
+  if (log_level < CUBEB_LOG_DISABLED || log_level > CUBEB_LOG_VERBOSE) {
+    return CUBEB_ERROR_INVALID_FORMAT;
+  }
+
+  if (!log_callback && log_level != CUBEB_LOG_DISABLED) {
+    return CUBEB_ERROR_INVALID_PARAMETER;
+  }
+
+  if (g_cubeb_log_callback && log_callback) {
+    return CUBEB_ERROR_NOT_SUPPORTED;
+  }
+
+  g_cubeb_log_message_callback = log_callback;
+  g_cubeb_log_callback = log_callback ? cubeb_log_message_forward : NULL;
+  g_cubeb_log_level = log_level;
+  set_rust_logging();
+
+  // See cubeb_set_log_callback.
+  if (log_level >= CUBEB_LOG_VERBOSE) {
+    ALOGV("Starting cubeb log");
+  }
+
+  return CUBEB_OK;
+}
@@ -18,6 +18,7 @@
 
 cubeb_log_level g_cubeb_log_level;
 cubeb_log_callback g_cubeb_log_callback;
+cubeb_log_message_callback g_cubeb_log_message_callback;
 
 /** The maximum size of a log message, after having been formatted. */
 const size_t CUBEB_LOG_MESSAGE_MAX_SIZE = 256;
@@ -26,6 +27,9 @@ const size_t CUBEB_LOG_MESSAGE_MAX_SIZE = 256;
 const size_t CUBEB_LOG_MESSAGE_QUEUE_DEPTH = 40;
 /** Number of milliseconds to wait before dequeuing log messages. */
 #define CUBEB_LOG_BATCH_PRINT_INTERVAL_MS 10
+/** The maximum size of a synchronous log message, after having been
+ * formatted. */
+const size_t CUBEB_LOG_SYNC_MESSAGE_MAX_SIZE = 1024;
 
 /**
  * This wraps an inline buffer, that represents a log message, that must be
@@ -35,7 +39,9 @@ const size_t CUBEB_LOG_MESSAGE_QUEUE_DEPTH = 40;
 class cubeb_log_message {
 public:
   cubeb_log_message() { *storage = '\0'; }
-  cubeb_log_message(char const str[CUBEB_LOG_MESSAGE_MAX_SIZE])
+  cubeb_log_message(cubeb_log_level level, char const * file, uint32_t line,
+                    char const str[CUBEB_LOG_MESSAGE_MAX_SIZE])
+      : level(level), file(file), line(line)
   {
     size_t length = strlen(str);
     /* paranoia against malformed message */
@@ -48,6 +54,11 @@ public:
   }
   char const * get() { return storage; }
 
+  cubeb_log_level level = CUBEB_LOG_NORMAL;
+  /* Static string from `__FILENAME__`, or NULL. */
+  char const * file = nullptr;
+  uint32_t line = 0;
+
 private:
   char storage[CUBEB_LOG_MESSAGE_MAX_SIZE];
 };
@@ -62,9 +73,10 @@ public:
     static cubeb_async_logger instance;
     return instance;
   }
-  void push(char const str[CUBEB_LOG_MESSAGE_MAX_SIZE])
+  void push(cubeb_log_level level, char const * file, uint32_t line,
+            char const str[CUBEB_LOG_MESSAGE_MAX_SIZE])
   {
-    cubeb_log_message msg(str);
+    cubeb_log_message msg(level, file, line, str);
     msg_queue.enqueue(msg);
   }
   void run()
@@ -74,7 +86,13 @@ public:
       while (true) {
         cubeb_log_message msg;
         while (msg_queue.dequeue(&msg, 1)) {
-          LOG_INTERNAL_NO_FORMAT(CUBEB_LOG_NORMAL, "%s", msg.get());
+          cubeb_log_message_callback message_callback =
+              g_cubeb_log_message_callback;
+          if (message_callback) {
+            message_callback(msg.level, msg.file, msg.line, msg.get());
+          } else {
+            LOG_INTERNAL_NO_FORMAT(CUBEB_LOG_NORMAL, "%s", msg.get());
+          }
         }
 #ifdef _WIN32
         Sleep(CUBEB_LOG_BATCH_PRINT_INTERVAL_MS);
@@ -121,7 +139,44 @@ cubeb_async_log(char const * fmt, ...)
   va_start(args, fmt);
   char msg[CUBEB_LOG_MESSAGE_MAX_SIZE];
   vsnprintf(msg, CUBEB_LOG_MESSAGE_MAX_SIZE, fmt, args);
-  cubeb_async_logger::get().push(msg);
+  cubeb_async_logger::get().push(CUBEB_LOG_NORMAL, nullptr, 0, msg);
+  va_end(args);
+}
+
+static void
+cubeb_log_va(cubeb_log_level level, char const * file, uint32_t line,
+             char const * fmt, va_list args)
+{
+  cubeb_log_message_callback message_callback = g_cubeb_log_message_callback;
+  if (!message_callback) {
+    return;
+  }
+  char msg[CUBEB_LOG_SYNC_MESSAGE_MAX_SIZE];
+  vsnprintf(msg, CUBEB_LOG_SYNC_MESSAGE_MAX_SIZE, fmt, args);
+  // Messages passed straight to `g_cubeb_log_callback` end with a newline.
+  size_t len = strlen(msg);
+  if (len && msg[len - 1] == '\n') {
+    msg[len - 1] = '\0';
+  }
+  message_callback(level, file, line, msg);
+}
+
+void
+cubeb_log_internal(cubeb_log_level level, char const * file, uint32_t line,
+                   char const * fmt, ...)
+{
+  va_list args;
+  va_start(args, fmt);
+  cubeb_log_va(level, file, line, fmt, args);
+  va_end(args);
+}
+
+void
+cubeb_log_message_forward(char const * fmt, ...)
+{
+  va_list args;
+  va_start(args, fmt);
+  cubeb_log_va(CUBEB_LOG_NORMAL, nullptr, 0, fmt, args);
   va_end(args);
 }
 
@@ -32,6 +32,15 @@ extern "C" {
 
 extern cubeb_log_level g_cubeb_log_level;
 extern cubeb_log_callback g_cubeb_log_callback PRINTF_FORMAT(1, 2);
+/* Set by `cubeb_set_log_message_callback`. `g_cubeb_log_callback` then points
+ * to `cubeb_log_message_forward`, so checking it is enough to know whether
+ * logging is enabled. */
+extern cubeb_log_message_callback g_cubeb_log_message_callback;
+void
+cubeb_log_message_forward(char const * fmt, ...) PRINTF_FORMAT(1, 2);
+void
+cubeb_log_internal(cubeb_log_level level, char const * file, uint32_t line,
+                   char const * fmt, ...) PRINTF_FORMAT(4, 5);
 void
 cubeb_async_log(const char * fmt, ...);
 void
@@ -54,8 +63,13 @@ cubeb_async_log_reset_threads(void);
 #define LOG_INTERNAL(level, fmt, ...)                                          \
   do {                                                                         \
     if (g_cubeb_log_callback && level <= g_cubeb_log_level) {                  \
-      g_cubeb_log_callback("%s:%d: " fmt "\n", __FILENAME__, __LINE__,         \
+      if (g_cubeb_log_message_callback) {                                      \
+        cubeb_log_internal(level, __FILENAME__, __LINE__, fmt,                 \
                            ##__VA_ARGS__);                                     \
+      } else {                                                                 \
+        g_cubeb_log_callback("%s:%d: " fmt "\n", __FILENAME__, __LINE__,       \
+                             ##__VA_ARGS__);                                   \
+      }                                                                        \
     }                                                                          \
   } while (0)
 
@@ -615,7 +615,7 @@ sndio_enumerate_devices(cubeb * context, cubeb_device_type type,
   static char dev[] = SIO_DEVANY;
   cubeb_device_info * device;
 
-  device = malloc(sizeof(cubeb_device_info));
+  device = calloc(1, sizeof(cubeb_device_info));
   if (device == NULL)
     return CUBEB_ERROR;
 